@@ -146,6 +146,10 @@ struct Connection {
     status: String,
     account_count: usize,
     last_sync: Option<String>,
+    #[serde(default)]
+    failure_streak: usize,
+    #[serde(default)]
+    last_error: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
@@ -160,6 +160,9 @@ fn build_menu(
     append_disabled(&menu, &runtime.last_cycle_text)?;
     append_disabled(&menu, &runtime.next_cycle_text)?;
     append_disabled(&menu, &runtime.last_summary)?;
+    if let Some(overview) = overview {
+        append_disabled_lines(&menu, &failing_connection_lines(overview))?;
+    }
     append_separator(&menu)?;
 
     let (history_lines, breakdown_lines, spending_lines, transaction_lines) =
@@ -256,6 +259,25 @@ fn overview_breakdown_lines(overview: &Overview) -> Vec<String> {
     lines
 }
 
+fn failing_connection_lines(overview: &Overview) -> Vec<String> {
+    overview
+        .connections
+        .iter()
+        .filter(|connection| connection.failure_streak > 0)
+        .map(|connection| {
+            let runs = if connection.failure_streak == 1 {
+                "sync"
+            } else {
+                "syncs"
+            };
+            format!(
+                "{}: {} failed {runs}",
+                connection.name, connection.failure_streak
+            )
+        })
+        .collect()
+}
+
 fn fallback_line(lines: &[String], fallback: &str) -> Vec<String> {
     if lines.is_empty() {
         vec![fallback.to_string()]
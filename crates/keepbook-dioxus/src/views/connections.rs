@@ -130,10 +130,19 @@ pub(super) fn ConnectionsView(
                         let sync_name = connection.name.clone();
                         let prices_target = target.clone();
                         let prices_name = connection.name.clone();
+                        let status_label = if connection.failure_streak > 0 {
+                            format!("{} ({} failed)", connection.status, connection.failure_streak)
+                        } else {
+                            connection.status.clone()
+                        };
                         rsx! {
                     div { class: "table-row",
                         strong { "{connection.name}" }
-                        span { class: "status", "{connection.status}" }
+                        span {
+                            class: "status",
+                            title: "{connection.last_error.clone().unwrap_or_default()}",
+                            "{status_label}"
+                        }
                         span { "{connection.account_count}" }
                         small {
                             "{connection.last_sync.clone().unwrap_or_else(|| \"Never\".to_string())}"
@@ -7,20 +7,33 @@ use std::time::Duration;
 
 use anyhow::{Context, Result};
 #[cfg(feature = "http")]
+use axum::extract::Request;
+#[cfg(feature = "http")]
 use axum::extract::{Path as AxumPath, Query, State};
 #[cfg(feature = "http")]
-use axum::http::StatusCode;
+use axum::http::{Method, StatusCode};
+#[cfg(feature = "http")]
+use axum::middleware::{self, Next};
+#[cfg(feature = "http")]
+use axum::response::sse::{Event, KeepAlive, Sse};
 #[cfg(feature = "http")]
 use axum::response::{IntoResponse, Response};
 #[cfg(feature = "http")]
 use axum::routing::{get, post};
 #[cfg(feature = "http")]
 use axum::{Json, Router};
-use chrono::{Local, Utc};
+use chrono::{Local, NaiveDate, Utc};
 use keepbook::config::{default_config_path, ResolvedConfig};
 use keepbook::format::{currency_symbol, format_base_currency_display};
 use keepbook::models::Asset;
-use keepbook::storage::{JsonFileStorage, Storage};
+#[cfg(feature = "http")]
+use keepbook::storage::{
+    prepare_for_write, DataDirBusy, DataDirLock, LockMode, NewerFormatVersion, DEFAULT_LOCK_TIMEOUT,
+};
+use keepbook::storage::{
+    storage_event_channel, watch_data_dir, DataDirWatcher, JsonFileStorage, ObservableStorage,
+    Storage, StorageEventSender,
+};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
@@ -39,12 +52,37 @@ pub use ai_rules::{
 #[derive(Clone)]
 pub struct ApiState {
     inner: Arc<RwLock<ApiStateInner>>,
+    /// Storage change feed; outlives reloads so `/api/events` subscribers
+    /// keep receiving after the config changes.
+    events: StorageEventSender,
 }
 
 struct ApiStateInner {
     config_path: PathBuf,
     config: ResolvedConfig,
     storage: Arc<dyn Storage>,
+    _watcher: Option<DataDirWatcher>,
+}
+
+/// Storage for `config`'s data dir that reports its own writes, and those of
+/// other processes, into `events`.
+fn observed_storage(
+    config: &ResolvedConfig,
+    events: &StorageEventSender,
+) -> (Arc<dyn Storage>, Option<DataDirWatcher>) {
+    let storage =
+        ObservableStorage::with_sender(JsonFileStorage::new(&config.data_dir), events.clone());
+    let watcher = match watch_data_dir(&config.data_dir, events.clone()) {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            tracing::warn!(
+                error = format!("{err:#}"),
+                "unable to watch data directory; /api/events only reports this server's writes"
+            );
+            None
+        }
+    };
+    (Arc::new(storage), watcher)
 }
 
 #[derive(Clone)]
@@ -59,14 +97,17 @@ impl ApiState {
         let config_path = config_path.as_ref().to_path_buf();
         let config = ResolvedConfig::load_or_default(&config_path)
             .with_context(|| format!("failed to load config from {}", config_path.display()))?;
-        let storage = Arc::new(JsonFileStorage::new(&config.data_dir));
+        let events = storage_event_channel();
+        let (storage, watcher) = observed_storage(&config, &events);
 
         Ok(Self {
             inner: Arc::new(RwLock::new(ApiStateInner {
                 config_path,
                 config,
                 storage,
+                _watcher: watcher,
             })),
+            events,
         })
     }
 
@@ -86,10 +127,11 @@ impl ApiState {
         };
         let config = ResolvedConfig::load_or_default(&config_path)
             .with_context(|| format!("failed to reload config from {}", config_path.display()))?;
-        let storage = Arc::new(JsonFileStorage::new(&config.data_dir));
+        let (storage, watcher) = observed_storage(&config, &self.events);
         let mut inner = self.inner.write().await;
         inner.config = config;
         inner.storage = storage;
+        inner._watcher = watcher;
         Ok(())
     }
 
@@ -223,6 +265,18 @@ impl ApiState {
         json_value(keepbook::app::list_connections(state.storage.as_ref()).await?)
     }
 
+    pub async fn sync_history(&self, query: SyncHistoryQuery) -> Result<serde_json::Value> {
+        let state = self.snapshot().await;
+        json_value(
+            keepbook::app::sync_history(
+                state.storage.as_ref(),
+                query.connection.as_deref(),
+                query.limit,
+            )
+            .await?,
+        )
+    }
+
     pub async fn accounts(&self) -> Result<serde_json::Value> {
         let state = self.snapshot().await;
         json_value(keepbook::app::list_accounts(state.storage.as_ref()).await?)
@@ -234,12 +288,26 @@ impl ApiState {
     }
 
     pub async fn transactions(&self, query: TransactionQuery) -> Result<serde_json::Value> {
+        let (start, end) = transaction_date_bounds(&query)?;
         let state = self.snapshot().await;
+        if let Some(q) = query.q.as_deref().filter(|q| !q.trim().is_empty()) {
+            let search = format!("{q} date:>={start} date:<={end}");
+            return json_value(
+                keepbook::app::search_transactions(
+                    state.storage.as_ref(),
+                    &search,
+                    query.sort_by_amount,
+                    !query.include_ignored,
+                    &state.config,
+                )
+                .await?,
+            );
+        }
         json_value(
             keepbook::app::list_transactions(
                 state.storage.as_ref(),
-                query.start,
-                query.end,
+                Some(start.to_string()),
+                Some(end.to_string()),
                 query.sort_by_amount,
                 !query.include_ignored,
                 &state.config,
@@ -742,7 +810,12 @@ pub struct TraySnapshotOutput {
 
 #[derive(Debug, Deserialize)]
 pub struct TransactionQuery {
+    /// A `keepbook search` query, narrowed to `start`..=`end`.
+    pub q: Option<String>,
+    /// `YYYY-MM-DD`; defaults to 30 days before `end`, as in
+    /// `list transactions`.
     pub start: Option<String>,
+    /// `YYYY-MM-DD`; defaults to today.
     pub end: Option<String>,
     #[serde(default)]
     pub sort_by_amount: bool,
@@ -789,6 +862,12 @@ pub struct ProposedTransactionEditsQuery {
     pub include_decided: bool,
 }
 
+#[derive(Debug, Deserialize, Default)]
+pub struct SyncHistoryQuery {
+    pub connection: Option<String>,
+    pub limit: Option<usize>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct HistoryQuery {
     pub currency: Option<String>,
@@ -812,6 +891,40 @@ pub struct OverviewQuery {
     pub include_history: bool,
 }
 
+/// A request parameter the API can't use; reported as 400.
+#[derive(Debug)]
+pub struct InvalidQuery(String);
+
+impl std::fmt::Display for InvalidQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidQuery {}
+
+fn parse_query_date(name: &str, value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        InvalidQuery(format!(
+            "invalid {name} date {value:?}; expected YYYY-MM-DD"
+        ))
+        .into()
+    })
+}
+
+/// Resolves `start`/`end` with the same defaults as `list transactions`.
+fn transaction_date_bounds(query: &TransactionQuery) -> Result<(NaiveDate, NaiveDate)> {
+    let end = match &query.end {
+        Some(end) => parse_query_date("end", end)?,
+        None => Utc::now().date_naive(),
+    };
+    let start = match &query.start {
+        Some(start) => parse_query_date("start", start)?,
+        None => end - chrono::Duration::days(30),
+    };
+    Ok((start, end))
+}
+
 #[cfg(feature = "http")]
 #[derive(Debug, Serialize)]
 struct ErrorOutput {
@@ -831,10 +944,19 @@ impl From<anyhow::Error> for ApiError {
 #[cfg(feature = "http")]
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
+        let status = if self.0.downcast_ref::<DataDirBusy>().is_some() {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else if self.0.downcast_ref::<NewerFormatVersion>().is_some() {
+            StatusCode::CONFLICT
+        } else if self.0.downcast_ref::<InvalidQuery>().is_some() {
+            StatusCode::BAD_REQUEST
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
         let body = Json(ErrorOutput {
             error: self.0.to_string(),
         });
-        (StatusCode::INTERNAL_SERVER_ERROR, body).into_response()
+        (status, body).into_response()
     }
 }
 
@@ -1071,16 +1193,15 @@ async fn tray_transaction_lines(storage: Arc<dyn Storage>, config: &ResolvedConf
         let cutoff = chrono::Utc::now() - chrono::Duration::days(30);
         let mut rows = Vec::new();
         for account in &accounts {
-            let txns = storage.get_transactions(&account.id).await?;
+            let txns = storage
+                .get_transactions_in_range(&account.id, Some(cutoff), None)
+                .await?;
             let source = account_conn_name
                 .get(&account.id.to_string())
                 .cloned()
                 .unwrap_or_else(|| "Unknown".to_string());
 
             for tx in txns {
-                if tx.timestamp < cutoff {
-                    continue;
-                }
                 rows.push(TxRow {
                     timestamp: tx.timestamp,
                     source: source.clone(),
@@ -1201,12 +1322,39 @@ pub fn router(state: ApiState) -> Router {
         .route("/api/git/sync", post(sync_git_repo))
         .route("/api/sync/connections", post(sync_connections))
         .route("/api/sync/prices", post(sync_prices))
+        .route("/api/sync/history", get(sync_history))
         .route("/api/ai/rules/suggest", post(suggest_ai_rules))
+        .layer(middleware::from_fn_with_state(state.clone(), data_dir_lock))
+        // Long-lived and reads nothing, so it must not hold the data-dir lock.
+        .route("/api/events", get(storage_events))
         .layer(CorsLayer::permissive())
         .layer(TraceLayer::new_for_http())
         .with_state(state)
 }
 
+/// Hold the data-dir lock for the whole request: shared for reads,
+/// exclusive for anything that may write. Writes are refused if the data
+/// dir is in a newer on-disk format.
+#[cfg(feature = "http")]
+async fn data_dir_lock(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let mode = if matches!(*request.method(), Method::GET | Method::HEAD) {
+        LockMode::Shared
+    } else {
+        LockMode::Exclusive
+    };
+    let data_dir = state.snapshot().await.config.data_dir;
+    let _lock = match DataDirLock::acquire(&data_dir, mode, DEFAULT_LOCK_TIMEOUT).await {
+        Ok(lock) => lock,
+        Err(err) => return ApiError(err).into_response(),
+    };
+    if mode == LockMode::Exclusive {
+        if let Err(err) = prepare_for_write(&data_dir) {
+            return ApiError(err).into_response();
+        }
+    }
+    next.run(request).await
+}
+
 #[cfg(feature = "http")]
 pub async fn serve(config_path: impl AsRef<Path>, addr: SocketAddr) -> Result<()> {
     let state = ApiState::load(config_path)?;
@@ -1223,6 +1371,28 @@ async fn shutdown_signal() {
     let _ = tokio::signal::ctrl_c().await;
 }
 
+/// Server-sent `storage` events, one JSON `StorageEvent` each. A `lagged`
+/// event (data: how many were dropped) means the client fell behind and
+/// should refetch whatever it shows.
+#[cfg(feature = "http")]
+async fn storage_events(
+    State(state): State<ApiState>,
+) -> Sse<impl futures::Stream<Item = Result<Event, axum::Error>>> {
+    use tokio::sync::broadcast::error::RecvError;
+
+    let stream = futures::stream::unfold(state.events.subscribe(), |mut receiver| async move {
+        let event = match receiver.recv().await {
+            Ok(event) => Event::default().event("storage").json_data(&event),
+            Err(RecvError::Lagged(skipped)) => {
+                Ok(Event::default().event("lagged").data(skipped.to_string()))
+            }
+            Err(RecvError::Closed) => return None,
+        };
+        Some((event, receiver))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
 #[cfg(feature = "http")]
 async fn health() -> Json<HealthOutput> {
     Json(HealthOutput { ok: true })
@@ -1246,6 +1416,14 @@ async fn connections(State(state): State<ApiState>) -> Result<Json<serde_json::V
     Ok(Json(state.connections().await?))
 }
 
+#[cfg(feature = "http")]
+async fn sync_history(
+    State(state): State<ApiState>,
+    Query(query): Query<SyncHistoryQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    Ok(Json(state.sync_history(query).await?))
+}
+
 #[cfg(feature = "http")]
 async fn accounts(State(state): State<ApiState>) -> Result<Json<serde_json::Value>, ApiError> {
     Ok(Json(state.accounts().await?))
@@ -1811,6 +1989,32 @@ mod tests {
             .expect("nested path should be valid");
     }
 
+    fn transaction_query(start: Option<&str>, end: Option<&str>) -> TransactionQuery {
+        TransactionQuery {
+            q: Some("merchant:coffee".to_string()),
+            start: start.map(str::to_string),
+            end: end.map(str::to_string),
+            sort_by_amount: false,
+            include_ignored: false,
+        }
+    }
+
+    #[test]
+    fn transaction_date_bounds_rejects_non_dates() {
+        let error =
+            transaction_date_bounds(&transaction_query(Some("2026-01-01 -category:x"), None))
+                .expect_err("start should be rejected");
+        assert!(error.downcast_ref::<InvalidQuery>().is_some());
+    }
+
+    #[test]
+    fn transaction_date_bounds_default_to_the_last_30_days() -> Result<()> {
+        let (start, end) = transaction_date_bounds(&transaction_query(None, Some("2026-03-31")))?;
+        assert_eq!(start, NaiveDate::from_ymd_opt(2026, 3, 1).unwrap());
+        assert_eq!(end, NaiveDate::from_ymd_opt(2026, 3, 31).unwrap());
+        Ok(())
+    }
+
     #[test]
     fn load_git_remote_settings_ignores_non_table_git_sync() -> Result<()> {
         let config_path = unique_test_config_path("load-git-non-table");
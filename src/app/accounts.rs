@@ -0,0 +1,265 @@
+use anyhow::{Context, Result};
+
+use crate::clock::{Clock, SystemClock};
+use crate::config::ResolvedConfig;
+use crate::models::{Account, AssetBalance, BalanceSnapshot};
+use crate::storage::{find_account, find_connection, AccountHistoryStorage, Storage};
+
+use super::maybe_auto_commit;
+
+async fn require_account(storage: &dyn Storage, id_or_name: &str) -> Result<Account> {
+    find_account(storage, id_or_name)
+        .await?
+        .with_context(|| format!("Account not found: {id_or_name}"))
+}
+
+/// Drop the account from its connection's account list. Saving the
+/// connection also refreshes its account symlinks, which is needed even when
+/// the account was only linked by `connection_id`.
+async fn unlink_from_connection(storage: &dyn Storage, account: &Account) -> Result<()> {
+    if let Some(mut connection) = storage.get_connection(&account.connection_id).await? {
+        connection.state.account_ids.retain(|id| *id != account.id);
+        storage.save_connection(&connection).await?;
+    }
+    Ok(())
+}
+
+/// Fold `from` into `into`: move its balances, transactions and annotations
+/// across (keeping `into`'s versions of shared transactions), then delete it.
+///
+/// Meant for a provider that started reporting an existing account under a
+/// new id, which leaves the old account inactive with the older history.
+pub async fn merge_accounts<S>(
+    storage: &S,
+    config: &ResolvedConfig,
+    from: &str,
+    into: &str,
+) -> Result<serde_json::Value>
+where
+    S: Storage + AccountHistoryStorage,
+{
+    let from_account = require_account(storage, from).await?;
+    let mut into_account = require_account(storage, into).await?;
+    if from_account.id == into_account.id {
+        anyhow::bail!("Cannot merge account {} into itself", from_account.id);
+    }
+
+    let moved = storage
+        .merge_account_history(&from_account.id, &into_account.id)
+        .await?;
+
+    let mut tags_added = Vec::new();
+    for tag in &from_account.tags {
+        if !into_account.tags.contains(tag) {
+            into_account.tags.push(tag.clone());
+            tags_added.push(tag.clone());
+        }
+    }
+    if !tags_added.is_empty() {
+        storage.save_account(&into_account).await?;
+    }
+    if storage.get_account_config(&into_account.id)?.is_none() {
+        if let Some(account_config) = storage.get_account_config(&from_account.id)? {
+            storage
+                .save_account_config(&into_account.id, &account_config)
+                .await?;
+        }
+    }
+
+    storage.delete_account(&from_account.id).await?;
+    unlink_from_connection(storage, &from_account).await?;
+
+    let result = serde_json::json!({
+        "success": true,
+        "from": {
+            "id": from_account.id.to_string(),
+            "name": from_account.name,
+        },
+        "into": {
+            "id": into_account.id.to_string(),
+            "name": into_account.name,
+        },
+        "moved": moved,
+        "tags_added": tags_added,
+    });
+
+    maybe_auto_commit(
+        config,
+        &format!("merge account {} into {}", from_account.id, into_account.id),
+    );
+
+    Ok(result)
+}
+
+/// Reassign an account to another connection.
+pub async fn move_account(
+    storage: &dyn Storage,
+    config: &ResolvedConfig,
+    account_id_or_name: &str,
+    connection_id_or_name: &str,
+) -> Result<serde_json::Value> {
+    let mut account = require_account(storage, account_id_or_name).await?;
+    let mut connection = find_connection(storage, connection_id_or_name)
+        .await?
+        .with_context(|| format!("Connection not found: {connection_id_or_name}"))?;
+
+    let from_connection_id = account.connection_id.clone();
+    if from_connection_id != *connection.id() {
+        let previous = account.clone();
+        account.connection_id = connection.id().clone();
+        storage.save_account(&account).await?;
+        unlink_from_connection(storage, &previous).await?;
+        if !connection.state.account_ids.contains(&account.id) {
+            connection.state.account_ids.push(account.id.clone());
+        }
+        storage.save_connection(&connection).await?;
+    }
+
+    let result = serde_json::json!({
+        "success": true,
+        "account": {
+            "id": account.id.to_string(),
+            "name": account.name,
+        },
+        "from_connection_id": from_connection_id.to_string(),
+        "connection": {
+            "id": connection.id().to_string(),
+            "name": connection.config.name,
+        },
+    });
+
+    maybe_auto_commit(
+        config,
+        &format!("move account {} to {}", account.id, connection.id()),
+    );
+
+    Ok(result)
+}
+
+pub async fn close_account(
+    storage: &dyn Storage,
+    config: &ResolvedConfig,
+    account_id_or_name: &str,
+) -> Result<serde_json::Value> {
+    close_account_with(storage, config, account_id_or_name, &SystemClock).await
+}
+
+/// Record an explicit zero balance for every asset the account last held and
+/// mark it inactive, so portfolio history drops it from the close onwards
+/// instead of carrying its last balance forward.
+pub async fn close_account_with(
+    storage: &dyn Storage,
+    config: &ResolvedConfig,
+    account_id_or_name: &str,
+    clock: &dyn Clock,
+) -> Result<serde_json::Value> {
+    let mut account = require_account(storage, account_id_or_name).await?;
+
+    let zeroed: Vec<AssetBalance> = storage
+        .get_latest_balance_snapshot(&account.id)
+        .await?
+        .map(|snapshot| {
+            snapshot
+                .balances
+                .into_iter()
+                .map(|balance| AssetBalance::new(balance.asset, "0"))
+                .collect()
+        })
+        .unwrap_or_default();
+    let snapshot = BalanceSnapshot::new(clock.now(), zeroed);
+    storage
+        .append_balance_snapshot(&account.id, &snapshot)
+        .await?;
+
+    account.active = false;
+    storage.save_account(&account).await?;
+
+    let result = serde_json::json!({
+        "success": true,
+        "account": {
+            "id": account.id.to_string(),
+            "name": account.name,
+            "active": account.active,
+        },
+        "closing_snapshot": snapshot,
+    });
+
+    maybe_auto_commit(config, &format!("close account {}", account.id));
+
+    Ok(result)
+}
+
+/// Delete an account and all its data, and drop it from its connection.
+pub async fn remove_account(
+    storage: &dyn Storage,
+    config: &ResolvedConfig,
+    account_id_or_name: &str,
+) -> Result<serde_json::Value> {
+    let account = require_account(storage, account_id_or_name).await?;
+
+    storage.delete_account(&account.id).await?;
+    unlink_from_connection(storage, &account).await?;
+
+    let result = serde_json::json!({
+        "success": true,
+        "account": {
+            "id": account.id.to_string(),
+            "name": account.name,
+            "connection_id": account.connection_id.to_string(),
+        },
+    });
+
+    maybe_auto_commit(config, &format!("remove account {}", account.id));
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::models::{Asset, Connection, ConnectionConfig};
+    use crate::storage::MemoryStorage;
+    use chrono::{TimeZone, Utc};
+
+    #[tokio::test]
+    async fn close_account_zeroes_last_held_assets() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = ResolvedConfig::load_or_default(&dir.path().join("keepbook.toml"))?;
+        let storage = MemoryStorage::new();
+        let connection = Connection::new(ConnectionConfig {
+            name: "Bank".to_string(),
+            synchronizer: "manual".to_string(),
+            credentials: None,
+            balance_staleness: None,
+        });
+        storage.save_connection(&connection).await?;
+        let account = Account::new("Brokerage", connection.id().clone());
+        storage.save_account(&account).await?;
+        storage
+            .append_balance_snapshot(
+                &account.id,
+                &BalanceSnapshot::new(
+                    Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap(),
+                    vec![
+                        AssetBalance::new(Asset::currency("USD"), "12.50"),
+                        AssetBalance::new(Asset::equity("VTI"), "3"),
+                    ],
+                ),
+            )
+            .await?;
+
+        let clock = FixedClock::new(Utc.with_ymd_and_hms(2026, 2, 1, 0, 0, 0).unwrap());
+        close_account_with(&storage, &config, account.id.as_str(), &clock).await?;
+
+        let latest = storage
+            .get_latest_balance_snapshot(&account.id)
+            .await?
+            .expect("closing snapshot");
+        assert_eq!(latest.timestamp, clock.now());
+        assert_eq!(latest.balances.len(), 2);
+        assert!(latest.balances.iter().all(|balance| balance.amount == "0"));
+        assert!(!storage.get_account(&account.id).await?.unwrap().active);
+        Ok(())
+    }
+}
@@ -0,0 +1,769 @@
+//! `keepbook doctor`: integrity checks over the on-disk data dir layout.
+//!
+//! The storage layer reads files lazily and fails on the first malformed
+//! line it meets, so problems tend to surface far from their cause. Doctor
+//! walks every file it knows about instead, reports each problem, and with
+//! `fix` repairs the cases that can't lose data.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::Datelike;
+use serde::de::DeserializeOwned;
+
+use crate::config::ResolvedConfig;
+use crate::market_data::{AssetRegistryEntry, FxRatePoint, PricePoint};
+use crate::models::{
+    Account, BalanceSnapshot, ConnectionConfig, ConnectionState, ProposedTransactionEdit,
+    SyncBatch, SyncRun, Transaction, TransactionAnnotationPatch,
+};
+use crate::storage::{
+    format_version, write_atomic_sync, EncryptionConfig, JsonFileStorage, CURRENT_FORMAT_VERSION,
+    FORMAT_VERSION_FILE,
+};
+
+use super::{maybe_auto_commit, DoctorIssue, DoctorOutput, DoctorSeverity};
+
+/// Check the data dir and, if `fix` is set, repair what can be repaired
+/// safely:
+///
+/// - malformed JSONL lines are moved to `<file>.rejected`
+/// - `account_ids` entries for missing accounts are dropped from `connection.json`
+/// - price and FX points filed under the wrong year are moved to the right file
+/// - stale or missing symlinks are rebuilt
+///
+/// Everything else (orphaned account dirs, annotations for unknown
+/// transactions, ...) is only reported, since fixing it means deleting data.
+pub async fn doctor(config: &ResolvedConfig, fix: bool) -> Result<DoctorOutput> {
+    if EncryptionConfig::load(&config.data_dir)?.is_some() {
+        anyhow::bail!(
+            "keepbook doctor can't check an encrypted data dir; run `keepbook encryption disable` first"
+        );
+    }
+
+    let mut doctor = Doctor {
+        data_dir: config.data_dir.clone(),
+        fix,
+        issues: Vec::new(),
+    };
+
+    doctor.check_format_version()?;
+    let connection_ids = doctor.check_connections()?;
+    doctor.check_accounts(&connection_ids)?;
+    doctor.scan_jsonl::<ProposedTransactionEdit>(
+        &doctor.data_dir.join("proposed_transaction_edits.jsonl"),
+    )?;
+    doctor.check_market_data()?;
+
+    let symlinks_stale = doctor
+        .issues
+        .iter()
+        .any(|issue| issue.code == STALE_SYMLINK || issue.code == MISSING_SYMLINK);
+    if fix && symlinks_stale {
+        let storage = JsonFileStorage::new(&config.data_dir);
+        match storage.rebuild_all_symlinks().await {
+            Ok(_) => {
+                for issue in doctor.issues.iter_mut() {
+                    if issue.code == STALE_SYMLINK || issue.code == MISSING_SYMLINK {
+                        issue.fixed = true;
+                    }
+                }
+            }
+            Err(error) => tracing::warn!("Failed to rebuild symlinks: {error:#}"),
+        }
+    }
+
+    let output = doctor.into_output();
+    if output.fixed > 0 {
+        maybe_auto_commit(config, "doctor --fix");
+    }
+    Ok(output)
+}
+
+const STALE_SYMLINK: &str = "stale_symlink";
+const MISSING_SYMLINK: &str = "missing_symlink";
+
+/// A successfully parsed JSONL line.
+struct JsonlLine<T> {
+    number: usize,
+    raw: String,
+    value: T,
+}
+
+struct Doctor {
+    data_dir: PathBuf,
+    fix: bool,
+    issues: Vec<DoctorIssue>,
+}
+
+impl Doctor {
+    fn report(
+        &mut self,
+        severity: DoctorSeverity,
+        code: &str,
+        path: &Path,
+        line: Option<usize>,
+        message: String,
+        fixed: bool,
+    ) {
+        let path = path
+            .strip_prefix(&self.data_dir)
+            .unwrap_or(path)
+            .display()
+            .to_string();
+        self.issues.push(DoctorIssue {
+            severity,
+            code: code.to_string(),
+            path,
+            line,
+            message,
+            fixed,
+        });
+    }
+
+    fn into_output(self) -> DoctorOutput {
+        let count = |severity: DoctorSeverity| {
+            self.issues
+                .iter()
+                .filter(|issue| issue.severity == severity && !issue.fixed)
+                .count()
+        };
+        DoctorOutput {
+            data_dir: self.data_dir.display().to_string(),
+            errors: count(DoctorSeverity::Error),
+            warnings: count(DoctorSeverity::Warning),
+            fixed: self.issues.iter().filter(|issue| issue.fixed).count(),
+            issues: self.issues,
+        }
+    }
+
+    fn check_format_version(&mut self) -> Result<()> {
+        let version = format_version(&self.data_dir)?;
+        let path = self.data_dir.join(FORMAT_VERSION_FILE);
+        if version > CURRENT_FORMAT_VERSION {
+            self.report(
+                DoctorSeverity::Error,
+                "format_too_new",
+                &path,
+                None,
+                format!(
+                    "Data dir is at format version {version}, newer than this keepbook supports ({CURRENT_FORMAT_VERSION})"
+                ),
+                false,
+            );
+        } else if version < CURRENT_FORMAT_VERSION {
+            self.report(
+                DoctorSeverity::Warning,
+                "format_outdated",
+                &path,
+                None,
+                format!(
+                    "Data dir is at format version {version} (current: {CURRENT_FORMAT_VERSION}); run `keepbook migrate`"
+                ),
+                false,
+            );
+        }
+        Ok(())
+    }
+
+    /// Parse every line of a JSONL file, reporting (and with `fix`,
+    /// quarantining) the ones that don't parse. Returns the good lines.
+    fn scan_jsonl<T: DeserializeOwned>(&mut self, path: &Path) -> Result<Vec<JsonlLine<T>>> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+
+        let mut good = Vec::new();
+        let mut rejected = Vec::new();
+        for (index, raw) in content.lines().enumerate() {
+            if raw.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<T>(raw) {
+                Ok(value) => good.push(JsonlLine {
+                    number: index + 1,
+                    raw: raw.to_string(),
+                    value,
+                }),
+                Err(error) => {
+                    self.report(
+                        DoctorSeverity::Error,
+                        "malformed_line",
+                        path,
+                        Some(index + 1),
+                        format!("Line does not parse: {error}"),
+                        self.fix,
+                    );
+                    rejected.push(raw.to_string());
+                }
+            }
+        }
+
+        if self.fix && !rejected.is_empty() {
+            append_lines(&rejected_path(path), &rejected)?;
+            rewrite_lines(path, good.iter().map(|line| line.raw.as_str()))?;
+        }
+        Ok(good)
+    }
+
+    /// Check connection dirs, their `account_ids` and their symlinks.
+    /// Returns the ids of all connection dirs.
+    fn check_connections(&mut self) -> Result<HashSet<String>> {
+        let connections_dir = self.data_dir.join("connections");
+        let mut ids = HashSet::new();
+        let mut names = BTreeMap::new();
+
+        for dir in list_dirs(&connections_dir)? {
+            let Some(id) = file_name(&dir) else { continue };
+            if id == "by-name" {
+                continue;
+            }
+            ids.insert(id.clone());
+
+            let config_path = dir.join("connection.toml");
+            match std::fs::read_to_string(&config_path) {
+                Ok(content) => match toml::from_str::<ConnectionConfig>(&content) {
+                    Ok(config) => {
+                        if let Some(name) = JsonFileStorage::sanitize_name(&config.name) {
+                            names
+                                .entry(name.to_lowercase())
+                                .or_insert((name, id.clone()));
+                        }
+                    }
+                    Err(error) => self.report(
+                        DoctorSeverity::Error,
+                        "invalid_connection_config",
+                        &config_path,
+                        None,
+                        format!("connection.toml does not parse: {error}"),
+                        false,
+                    ),
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => self.report(
+                    DoctorSeverity::Error,
+                    "missing_connection_config",
+                    &dir,
+                    None,
+                    "Connection dir has no connection.toml".to_string(),
+                    false,
+                ),
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to read {}", config_path.display()))
+                }
+            }
+
+            self.check_connection_state(&dir.join("connection.json"))?;
+            self.scan_jsonl::<SyncRun>(&dir.join("sync_runs.jsonl"))?;
+            self.scan_jsonl::<SyncBatch>(&dir.join("sync_batches.jsonl"))?;
+            self.check_symlinks(&dir.join("accounts"))?;
+        }
+
+        let by_name_dir = connections_dir.join("by-name");
+        self.check_symlinks(&by_name_dir)?;
+        for (name, id) in names.into_values() {
+            let link = by_name_dir.join(&name);
+            if std::fs::symlink_metadata(&link).is_err() {
+                self.report(
+                    DoctorSeverity::Info,
+                    MISSING_SYMLINK,
+                    &link,
+                    None,
+                    format!("No by-name symlink for connection {id}"),
+                    false,
+                );
+            }
+        }
+
+        Ok(ids)
+    }
+
+    fn check_connection_state(&mut self, path: &Path) -> Result<()> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        if let Err(error) = serde_json::from_str::<ConnectionState>(&content) {
+            self.report(
+                DoctorSeverity::Error,
+                "invalid_connection_state",
+                path,
+                None,
+                format!("connection.json does not parse: {error}"),
+                false,
+            );
+            return Ok(());
+        }
+
+        // Edit the raw JSON so a fix doesn't drop fields this version of
+        // keepbook doesn't know about.
+        let mut state: serde_json::Value = serde_json::from_str(&content)?;
+        let Some(account_ids) = state
+            .get_mut("account_ids")
+            .and_then(serde_json::Value::as_array_mut)
+        else {
+            return Ok(());
+        };
+        let accounts_dir = self.data_dir.join("accounts");
+        let mut missing = Vec::new();
+        account_ids.retain(|id| {
+            let id = id.as_str().unwrap_or_default();
+            let exists = accounts_dir.join(id).join("account.json").is_file();
+            if !exists {
+                missing.push(id.to_string());
+            }
+            exists
+        });
+        for id in &missing {
+            self.report(
+                DoctorSeverity::Warning,
+                "missing_account_reference",
+                path,
+                None,
+                format!("account_ids lists account {id}, which does not exist"),
+                self.fix,
+            );
+        }
+        if self.fix && !missing.is_empty() {
+            let mut json = serde_json::to_string_pretty(&state)?;
+            json.push('\n');
+            write_atomic_sync(path, json.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Report symlinks in `dir` whose target no longer exists.
+    fn check_symlinks(&mut self, dir: &Path) -> Result<()> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            let is_symlink = std::fs::symlink_metadata(&path)
+                .map(|meta| meta.file_type().is_symlink())
+                .unwrap_or(false);
+            // `exists` follows the link, so it is false for a dangling one.
+            if is_symlink && !path.exists() {
+                let target = std::fs::read_link(&path)
+                    .map(|target| target.display().to_string())
+                    .unwrap_or_default();
+                self.report(
+                    DoctorSeverity::Warning,
+                    STALE_SYMLINK,
+                    &path,
+                    None,
+                    format!("Symlink points at missing {target}"),
+                    false,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn check_accounts(&mut self, connection_ids: &HashSet<String>) -> Result<()> {
+        for dir in list_dirs(&self.data_dir.join("accounts"))? {
+            let Some(id) = file_name(&dir) else { continue };
+
+            let account_path = dir.join("account.json");
+            match std::fs::read_to_string(&account_path) {
+                Ok(content) => match serde_json::from_str::<Account>(&content) {
+                    Ok(account) => {
+                        if account.id.as_str() != id {
+                            self.report(
+                                DoctorSeverity::Warning,
+                                "account_id_mismatch",
+                                &account_path,
+                                None,
+                                format!("account.json has id {}, but lives in {id}", account.id),
+                                false,
+                            );
+                        }
+                        if !connection_ids.contains(account.connection_id.as_str()) {
+                            self.report(
+                                DoctorSeverity::Warning,
+                                "account_connection_missing",
+                                &account_path,
+                                None,
+                                format!(
+                                    "Account belongs to connection {}, which does not exist",
+                                    account.connection_id
+                                ),
+                                false,
+                            );
+                        }
+                    }
+                    Err(error) => self.report(
+                        DoctorSeverity::Error,
+                        "invalid_account",
+                        &account_path,
+                        None,
+                        format!("account.json does not parse: {error}"),
+                        false,
+                    ),
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => self.report(
+                    DoctorSeverity::Error,
+                    "orphaned_account_dir",
+                    &dir,
+                    None,
+                    "Account dir has no account.json".to_string(),
+                    false,
+                ),
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to read {}", account_path.display()))
+                }
+            }
+
+            self.scan_jsonl::<BalanceSnapshot>(&dir.join("balances.jsonl"))?;
+            let transaction_ids: HashSet<String> = self
+                .scan_jsonl::<Transaction>(&dir.join("transactions.jsonl"))?
+                .into_iter()
+                .map(|line| line.value.id.to_string())
+                .collect();
+            let annotations_path = dir.join("transaction_annotations.jsonl");
+            for line in self.scan_jsonl::<TransactionAnnotationPatch>(&annotations_path)? {
+                if !transaction_ids.contains(line.value.transaction_id.as_str()) {
+                    self.report(
+                        DoctorSeverity::Warning,
+                        "unknown_annotation_target",
+                        &annotations_path,
+                        Some(line.number),
+                        format!(
+                            "Annotation for transaction {}, which this account does not have",
+                            line.value.transaction_id
+                        ),
+                        false,
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_market_data(&mut self) -> Result<()> {
+        self.scan_jsonl::<AssetRegistryEntry>(&self.data_dir.join("assets").join("index.jsonl"))?;
+
+        // Asset ids contain slashes (`equity/AAPL`), so asset dirs nest.
+        let prices_dir = self.data_dir.join("prices");
+        for (year, path) in year_files(&prices_dir)? {
+            let asset_dir = path
+                .parent()
+                .and_then(|dir| dir.strip_prefix(&prices_dir).ok())
+                .map(|dir| dir.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            let prices = self.scan_jsonl::<PricePoint>(&path)?;
+            for line in &prices {
+                if line.value.asset_id.to_string() != asset_dir {
+                    self.report(
+                        DoctorSeverity::Warning,
+                        "price_asset_mismatch",
+                        &path,
+                        Some(line.number),
+                        format!(
+                            "Price for asset {} filed under {asset_dir}",
+                            line.value.asset_id
+                        ),
+                        false,
+                    );
+                }
+            }
+            let dated = prices
+                .into_iter()
+                .map(|line| (line.value.as_of_date.year(), line))
+                .collect();
+            self.check_years(&path, year, dated, "price_wrong_year")?;
+        }
+
+        for (year, path) in year_files(&self.data_dir.join("fx"))? {
+            let dated = self
+                .scan_jsonl::<FxRatePoint>(&path)?
+                .into_iter()
+                .map(|line| (line.value.as_of_date.year(), line))
+                .collect();
+            self.check_years(&path, year, dated, "fx_wrong_year")?;
+        }
+
+        for path in jsonl_files(&self.data_dir.join("intraday"))? {
+            self.scan_jsonl::<PricePoint>(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Report lines of a `{YYYY}.jsonl` file dated in another year and, with
+    /// `fix`, move them to the file for their year.
+    fn check_years<T>(
+        &mut self,
+        path: &Path,
+        year: i32,
+        lines: Vec<(i32, JsonlLine<T>)>,
+        code: &str,
+    ) -> Result<()> {
+        let mut keep = Vec::new();
+        let mut moves: BTreeMap<i32, Vec<String>> = BTreeMap::new();
+        for (line_year, line) in lines {
+            if line_year == year {
+                keep.push(line.raw);
+                continue;
+            }
+            self.report(
+                DoctorSeverity::Warning,
+                code,
+                path,
+                Some(line.number),
+                format!("Dated {line_year}, but filed under {year}"),
+                self.fix,
+            );
+            moves.entry(line_year).or_default().push(line.raw);
+        }
+
+        if self.fix && !moves.is_empty() {
+            for (line_year, raws) in &moves {
+                append_lines(&path.with_file_name(format!("{line_year:04}.jsonl")), raws)?;
+            }
+            rewrite_lines(path, keep.iter().map(String::as_str))?;
+        }
+        Ok(())
+    }
+}
+
+fn rejected_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".rejected");
+    path.with_file_name(name)
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+/// Directories (not symlinks) directly under `dir`, sorted.
+fn list_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+    let mut dirs = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// `.jsonl` files anywhere under `dir` (not following symlinks), sorted.
+fn jsonl_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for sub in list_dirs(dir)? {
+        files.extend(jsonl_files(&sub)?);
+    }
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_file() && path.extension().is_some_and(|ext| ext == "jsonl") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// `{YYYY}.jsonl` files anywhere under `dir`, with their year.
+fn year_files(dir: &Path) -> Result<Vec<(i32, PathBuf)>> {
+    Ok(jsonl_files(dir)?
+        .into_iter()
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_str()?;
+            let year = (stem.len() == 4).then(|| stem.parse::<i32>().ok())??;
+            Some((year, path))
+        })
+        .collect())
+}
+
+fn rewrite_lines<'a>(path: &Path, lines: impl Iterator<Item = &'a str>) -> Result<()> {
+    let mut content = String::new();
+    for line in lines {
+        content.push_str(line);
+        content.push('\n');
+    }
+    write_atomic_sync(path, content.as_bytes())
+}
+
+fn append_lines(path: &Path, lines: &[String]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    // Don't glue the first line onto a file missing its trailing newline.
+    let needs_newline = std::fs::read(path)
+        .map(|existing| existing.last().is_some_and(|&byte| byte != b'\n'))
+        .unwrap_or(false);
+
+    let mut content = String::new();
+    if needs_newline {
+        content.push('\n');
+    }
+    for line in lines {
+        content.push_str(line);
+        content.push('\n');
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(content.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    file.sync_data()
+        .with_context(|| format!("Failed to sync {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_data::{AssetId, PriceKind};
+    use crate::models::{Asset, AssetBalance, Connection};
+    use crate::storage::Storage;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    fn codes(output: &DoctorOutput) -> Vec<(&str, bool)> {
+        let mut codes: Vec<_> = output
+            .issues
+            .iter()
+            .map(|issue| (issue.code.as_str(), issue.fixed))
+            .collect();
+        codes.sort();
+        codes
+    }
+
+    #[tokio::test]
+    async fn reports_then_fixes_safe_issues() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = ResolvedConfig::load_or_default(&dir.path().join("keepbook.toml"))?;
+        let storage = JsonFileStorage::new(dir.path());
+        crate::storage::write_format_version(dir.path(), CURRENT_FORMAT_VERSION)?;
+
+        let connection = Connection::new(ConnectionConfig {
+            name: "Bank".to_string(),
+            synchronizer: "manual".to_string(),
+            credentials: None,
+            balance_staleness: None,
+        });
+        storage
+            .save_connection_config(connection.id(), &connection.config)
+            .await?;
+        storage.save_connection(&connection).await?;
+        let account = Account::new("Checking", connection.id().clone());
+        storage.save_account(&account).await?;
+        storage
+            .append_balance_snapshot(
+                &account.id,
+                &BalanceSnapshot::new(
+                    Utc::now(),
+                    vec![AssetBalance::new(Asset::currency("USD"), "1")],
+                ),
+            )
+            .await?;
+        storage.rebuild_all_symlinks().await?;
+
+        let account_dir = dir.path().join("accounts").join(account.id.as_str());
+        let balances = account_dir.join("balances.jsonl");
+        append_lines(&balances, &["{not json".to_string()])?;
+        append_lines(
+            &account_dir.join("transaction_annotations.jsonl"),
+            &[r#"{"transaction_id":"gone","timestamp":"2024-01-01T00:00:00Z"}"#.to_string()],
+        )?;
+
+        let state_path = dir
+            .path()
+            .join("connections")
+            .join(connection.id().as_str())
+            .join("connection.json");
+        let mut state: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&state_path)?)?;
+        state["account_ids"] = serde_json::json!([account.id.as_str(), "ghost"]);
+        std::fs::write(&state_path, serde_json::to_string(&state)?)?;
+
+        std::fs::create_dir_all(dir.path().join("accounts").join("orphan"))?;
+
+        let asset = Asset::equity("AAPL");
+        let price = PricePoint {
+            asset_id: AssetId::from_asset(&asset),
+            as_of_date: NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap(),
+            price: "185.64".to_string(),
+            quote_currency: "USD".to_string(),
+            kind: PriceKind::Close,
+            source: "test".to_string(),
+        };
+        let prices_dir = dir.path().join("prices").join(price.asset_id.to_string());
+        append_lines(
+            &prices_dir.join("2023.jsonl"),
+            &[serde_json::to_string(&price)?],
+        )?;
+
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(
+            "../missing",
+            dir.path()
+                .join("connections")
+                .join("by-name")
+                .join("Old Bank"),
+        )?;
+
+        let report = doctor(&config, false).await?;
+        let mut expected = vec![
+            ("malformed_line", false),
+            ("missing_account_reference", false),
+            ("orphaned_account_dir", false),
+            ("price_wrong_year", false),
+            ("unknown_annotation_target", false),
+        ];
+        #[cfg(unix)]
+        expected.push((STALE_SYMLINK, false));
+        expected.sort();
+        assert_eq!(codes(&report), expected);
+        assert_eq!(report.errors, 2);
+        assert_eq!(report.fixed, 0);
+        assert!(!prices_dir.join("2024.jsonl").exists());
+
+        let fixed = doctor(&config, true).await?;
+        assert_eq!(fixed.errors, 1);
+        assert_eq!(fixed.warnings, 1);
+        assert_eq!(
+            std::fs::read_to_string(account_dir.join("balances.jsonl.rejected"))?,
+            "{not json\n"
+        );
+        assert_eq!(storage.get_balance_snapshots(&account.id).await?.len(), 1);
+        assert!(!std::fs::read_to_string(&state_path)?.contains("ghost"));
+        assert_eq!(std::fs::read_to_string(prices_dir.join("2023.jsonl"))?, "");
+        assert!(prices_dir.join("2024.jsonl").exists());
+
+        let after = doctor(&config, false).await?;
+        assert_eq!(
+            codes(&after),
+            vec![
+                ("orphaned_account_dir", false),
+                ("unknown_annotation_target", false)
+            ]
+        );
+        Ok(())
+    }
+}
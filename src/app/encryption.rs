@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::config::ResolvedConfig;
+use crate::storage::{
+    decrypt_data_dir, encrypt_data_dir, format_version, write_atomic_sync, AgeIdentities,
+    DataCipher, EncryptionConfig, JsonFileStorage, CURRENT_FORMAT_VERSION, ENCRYPTION_CONFIG_FILE,
+};
+
+use super::maybe_auto_commit;
+
+/// Git diff driver that shows encrypted data files decrypted, once
+/// `diff.keepbook-age.textconv` is set to `keepbook encryption textconv`.
+const GITATTRIBUTES_LINES: &[&str] = &[
+    "*.json.age diff=keepbook-age",
+    "*.jsonl.age diff=keepbook-age",
+    "*.toml.age diff=keepbook-age",
+];
+
+/// Add the textconv diff driver to the data dir's `.gitattributes`.
+/// Returns whether the file changed.
+fn ensure_gitattributes(data_dir: &Path) -> Result<bool> {
+    let path = data_dir.join(".gitattributes");
+    let mut content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let missing: Vec<&str> = GITATTRIBUTES_LINES
+        .iter()
+        .copied()
+        .filter(|line| !content.lines().any(|existing| existing.trim() == *line))
+        .collect();
+    if missing.is_empty() {
+        return Ok(false);
+    }
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    for line in missing {
+        content.push_str(line);
+        content.push('\n');
+    }
+    write_atomic_sync(&path, content.as_bytes())?;
+    Ok(true)
+}
+
+/// Encrypt the data dir at rest.
+///
+/// Writes `encryption.toml` from `recipients`/`recipients_file` (or uses the
+/// existing one), then encrypts every plaintext data file to `<name>.age`.
+/// Refuses recipients the local identity can't decrypt for, since the data
+/// would be unreadable right after.
+pub async fn enable_encryption(
+    config: &ResolvedConfig,
+    recipients: Vec<String>,
+    recipients_file: Option<PathBuf>,
+) -> Result<serde_json::Value> {
+    let data_dir = &config.data_dir;
+    let version = format_version(data_dir)?;
+    if version != CURRENT_FORMAT_VERSION {
+        bail!(
+            "Data dir is at format version {version} (current: {CURRENT_FORMAT_VERSION}); run `keepbook migrate` first"
+        );
+    }
+
+    let existing = EncryptionConfig::load(data_dir)?;
+    let encryption = match existing {
+        Some(_) if !recipients.is_empty() || recipients_file.is_some() => bail!(
+            "Data dir is already encrypted; to change recipients, run `keepbook encryption disable` and enable it again"
+        ),
+        Some(existing) => existing,
+        None if recipients.is_empty() && recipients_file.is_none() => bail!(
+            "No recipients: pass --recipient or --recipients-file, or write {ENCRYPTION_CONFIG_FILE}"
+        ),
+        None => EncryptionConfig {
+            recipients,
+            recipients_file,
+        },
+    };
+
+    let cipher = DataCipher::from_config(&encryption, data_dir)?;
+    cipher.decrypt(&cipher.encrypt(b"keepbook")?).context(
+        "None of the recipients matches a local identity, so the data couldn't be read back",
+    )?;
+
+    std::fs::create_dir_all(data_dir)
+        .with_context(|| format!("Failed to create data dir {}", data_dir.display()))?;
+    encryption.save(data_dir)?;
+    let encrypted = encrypt_data_dir(data_dir, &cipher)?;
+    // The search and transaction indexes hold plaintext and would otherwise
+    // stay on disk next to the encrypted files.
+    let cache_dir = JsonFileStorage::local_cache_dir_for(data_dir);
+    if cache_dir.exists() {
+        std::fs::remove_dir_all(&cache_dir)
+            .with_context(|| format!("Failed to remove {}", cache_dir.display()))?;
+    }
+    let gitattributes_updated = ensure_gitattributes(data_dir)?;
+
+    let result = serde_json::json!({
+        "success": true,
+        "recipients": encryption.recipient_keys(data_dir)?.len(),
+        "encrypted": encrypted,
+        "gitattributes_updated": gitattributes_updated,
+    });
+
+    maybe_auto_commit(config, "enable encryption");
+
+    Ok(result)
+}
+
+/// Decrypt every data file back to plaintext and remove `encryption.toml`.
+pub async fn disable_encryption(config: &ResolvedConfig) -> Result<serde_json::Value> {
+    let data_dir = &config.data_dir;
+    if EncryptionConfig::load(data_dir)?.is_none() {
+        bail!("Data dir is not encrypted");
+    }
+
+    let decrypted = decrypt_data_dir(data_dir, &AgeIdentities::new())?;
+    let config_path = data_dir.join(ENCRYPTION_CONFIG_FILE);
+    std::fs::remove_file(&config_path)
+        .with_context(|| format!("Failed to remove {}", config_path.display()))?;
+
+    let result = serde_json::json!({
+        "success": true,
+        "decrypted": decrypted,
+    });
+
+    maybe_auto_commit(config, "disable encryption");
+
+    Ok(result)
+}
+
+/// The plaintext of a data file, for `git diff`'s textconv. Files that
+/// aren't age-encrypted pass through unchanged.
+pub fn encryption_textconv(path: &Path) -> Result<Vec<u8>> {
+    let content =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if !content.starts_with(b"age-encryption.org/") {
+        return Ok(content);
+    }
+    AgeIdentities::new()
+        .decrypt(&content)
+        .with_context(|| format!("Failed to decrypt {}", path.display()))
+}
@@ -3,20 +3,26 @@ use std::path::Path;
 use std::str::FromStr;
 
 use anyhow::{Context, Result};
-use chrono::{NaiveDate, Utc};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use rust_decimal::Decimal;
 
 use crate::config::{DisplayConfig, ResolvedConfig};
 use crate::format::{currency_symbol, format_base_currency_display};
-use crate::market_data::{MarketDataServiceBuilder, PriceSourceRegistry};
-use crate::models::{Asset, Id, TransactionAnnotation};
-use crate::storage::Storage;
+use crate::market_data::{
+    MarketDataServiceBuilder, PriceSourceRegistry, SourceHealthTracker, SourceUnavailable,
+};
+use crate::models::{
+    failure_streak, Account, Asset, Connection, Id, Transaction, TransactionAnnotation,
+    TransactionAnnotationPatch,
+};
+use crate::storage::{find_account, find_connection, sync_batch_times, written_at, Storage};
 
 use super::ignore_rules::{TransactionIgnoreInput, TransactionIgnoreMatcher};
 use super::value::value_in_reporting_currency_best_effort;
 use super::{
-    AccountOutput, AllOutput, BalanceOutput, ConnectionOutput, PriceSourceOutput,
-    TransactionAnnotationOutput, TransactionOutput,
+    AccountOutput, AllOutput, BalanceOutput, ConnectionOutput, PriceSourceOutput, SyncRunOutput,
+    TransactionAnnotationOutput, TransactionAnnotationPatchOutput, TransactionHistoryEntryOutput,
+    TransactionHistoryOutput, TransactionOutput,
 };
 
 const SPENDING_IGNORE_TAGS: [&str; 3] = ["ignore_spending", "ignore-spending", "ignore:spending"];
@@ -34,6 +40,20 @@ fn annotation_ignores_spending(annotation: &TransactionAnnotation) -> bool {
         .unwrap_or(false)
 }
 
+fn annotation_output(ann: &TransactionAnnotation) -> Option<TransactionAnnotationOutput> {
+    if ann.is_empty() {
+        return None;
+    }
+    Some(TransactionAnnotationOutput {
+        description: ann.description.clone(),
+        note: ann.note.clone(),
+        category: ann.category.clone(),
+        subcategory: ann.subcategory.clone(),
+        tags: ann.tags.clone(),
+        effective_date: ann.effective_date.map(|d| d.to_string()),
+    })
+}
+
 pub async fn list_connections(storage: &dyn Storage) -> Result<Vec<ConnectionOutput>> {
     let connections = storage.list_connections().await?;
     let accounts = storage.list_accounts().await?;
@@ -62,6 +82,7 @@ pub async fn list_connections(storage: &dyn Storage) -> Result<Vec<ConnectionOut
             account_ids.insert(account_id);
         }
 
+        let streak = failure_streak(&storage.get_sync_runs(c.id()).await?);
         output.push(ConnectionOutput {
             id: c.id().to_string(),
             name: c.config.name.clone(),
@@ -69,12 +90,62 @@ pub async fn list_connections(storage: &dyn Storage) -> Result<Vec<ConnectionOut
             status: c.state.status.to_string(),
             account_count: account_ids.len(),
             last_sync: c.state.last_sync.as_ref().map(|ls| ls.at.to_rfc3339()),
+            failure_streak: streak.as_ref().map(|s| s.count).unwrap_or(0),
+            failing_since: streak.as_ref().map(|s| s.since.to_rfc3339()),
+            last_error: streak.and_then(|s| s.last_error),
         });
     }
 
     Ok(output)
 }
 
+/// Sync run journal entries, newest first, optionally for a single connection.
+pub async fn sync_history(
+    storage: &dyn Storage,
+    connection: Option<&str>,
+    limit: Option<usize>,
+) -> Result<Vec<SyncRunOutput>> {
+    let connections = match connection {
+        Some(id_or_name) => vec![find_connection(storage, id_or_name)
+            .await?
+            .with_context(|| format!("Connection not found: {id_or_name}"))?],
+        None => storage.list_connections().await?,
+    };
+
+    let mut runs = Vec::new();
+    for c in connections {
+        // Newest first within a connection so ties keep journal order.
+        for run in storage.get_sync_runs(c.id()).await?.into_iter().rev() {
+            runs.push((c.config.name.clone(), run));
+        }
+    }
+    runs.sort_by_key(|(_, run)| std::cmp::Reverse(run.started_at));
+
+    let mut output: Vec<SyncRunOutput> = runs
+        .into_iter()
+        .map(|(connection_name, run)| SyncRunOutput {
+            connection_id: run.connection_id.to_string(),
+            connection_name,
+            started_at: run.started_at.to_rfc3339(),
+            finished_at: run.finished_at.to_rfc3339(),
+            duration_ms: run.duration_ms(),
+            outcome: run.outcome,
+            errors: run.errors,
+            batch_id: run.batch_id.map(|id| id.to_string()),
+            accounts_added: run.accounts_added,
+            accounts_changed: run.accounts_changed,
+            balances_recorded: run.balances_recorded,
+            transactions_added: run.transactions_added,
+            transactions_changed: run.transactions_changed,
+            prices_refreshed: run.prices_refreshed,
+        })
+        .collect();
+    if let Some(limit) = limit {
+        output.truncate(limit);
+    }
+    Ok(output)
+}
+
 pub async fn list_accounts(storage: &dyn Storage) -> Result<Vec<AccountOutput>> {
     let accounts = storage.list_accounts().await?;
     let mut output = Vec::new();
@@ -101,15 +172,33 @@ pub async fn list_accounts(storage: &dyn Storage) -> Result<Vec<AccountOutput>>
 pub fn list_price_sources(data_dir: &Path) -> Result<Vec<PriceSourceOutput>> {
     let mut registry = PriceSourceRegistry::new(data_dir);
     registry.load()?;
+    let health = SourceHealthTracker::load(data_dir).with_quotas(registry.daily_quotas());
 
     let mut output = Vec::new();
     for s in registry.sources() {
+        let report = health.report(s.config.source_type.provider_name());
+        let status = match report.unavailable {
+            None => "ok",
+            Some(SourceUnavailable::CoolingDown { .. }) => "cooling_down",
+            Some(SourceUnavailable::QuotaExhausted { .. }) => "quota_exhausted",
+        };
         output.push(PriceSourceOutput {
             name: s.name.clone(),
             source_type: format!("{:?}", s.config.source_type).to_lowercase(),
             enabled: s.config.enabled,
             priority: s.config.priority,
             has_credentials: s.config.credentials.is_some(),
+            health: status.to_string(),
+            consecutive_failures: report.health.consecutive_failures,
+            last_error: report.health.last_error,
+            last_success: report.health.last_success_at.map(|at| at.to_rfc3339()),
+            cooldown_until: report
+                .health
+                .cooldown_until
+                .filter(|_| status == "cooling_down")
+                .map(|at| at.to_rfc3339()),
+            daily_quota: report.daily_quota,
+            remaining_quota: report.remaining_quota,
         });
     }
 
@@ -145,6 +234,7 @@ pub async fn list_balances(
 ) -> Result<Vec<BalanceOutput>> {
     let market_data = MarketDataServiceBuilder::for_data_dir(&config.data_dir)
         .with_quote_staleness(config.refresh.price_staleness)
+        .with_fx_pivots(config.refresh.fx_pivots.clone())
         .build()
         .await;
 
@@ -246,6 +336,183 @@ pub async fn list_balances(
     Ok(output)
 }
 
+/// Turns transactions into `TransactionOutput`s, dropping what the ignore
+/// rules exclude when `skip_ignored` is set. Shared by `list transactions`
+/// and search.
+pub(super) struct TransactionOutputs {
+    skip_ignored: bool,
+    ignore_matcher: Option<TransactionIgnoreMatcher>,
+    ignored_account_tags: HashSet<String>,
+    connections_by_id: HashMap<String, Connection>,
+}
+
+impl TransactionOutputs {
+    pub(super) async fn new(
+        storage: &dyn Storage,
+        config: &ResolvedConfig,
+        skip_ignored: bool,
+    ) -> Result<Self> {
+        let ignore_matcher = if skip_ignored {
+            Some(TransactionIgnoreMatcher::from_configs(
+                &config.ignore,
+                &config.spending,
+            )?)
+        } else {
+            None
+        };
+        let ignored_account_tags: HashSet<String> = if skip_ignored {
+            config
+                .spending
+                .ignore_tags
+                .iter()
+                .filter_map(|tag| {
+                    let trimmed = tag.trim();
+                    if trimmed.is_empty() {
+                        None
+                    } else {
+                        Some(trimmed.to_lowercase())
+                    }
+                })
+                .collect()
+        } else {
+            HashSet::new()
+        };
+        let connections_by_id = storage
+            .list_connections()
+            .await?
+            .into_iter()
+            .map(|c| (c.id().to_string(), c))
+            .collect();
+        Ok(Self {
+            skip_ignored,
+            ignore_matcher,
+            ignored_account_tags,
+            connections_by_id,
+        })
+    }
+
+    pub(super) fn connection(&self, account: &Account) -> Option<&Connection> {
+        self.connections_by_id
+            .get(&account.connection_id.to_string())
+    }
+
+    /// Whether the account's tags exclude all of its transactions.
+    pub(super) fn skips_account(&self, account: &Account) -> bool {
+        self.skip_ignored
+            && !self.ignored_account_tags.is_empty()
+            && account.tags.iter().any(|tag| {
+                let trimmed = tag.trim();
+                !trimmed.is_empty() && self.ignored_account_tags.contains(&trimmed.to_lowercase())
+            })
+    }
+
+    /// `tx` as output, or `None` if the ignore rules exclude it.
+    pub(super) fn output(
+        &self,
+        account: &Account,
+        tx: &Transaction,
+        ann: Option<&TransactionAnnotation>,
+    ) -> Option<TransactionOutput> {
+        let status = format!("{:?}", tx.status).to_lowercase();
+
+        if self.skip_ignored {
+            if tx
+                .standardized_metadata
+                .as_ref()
+                .and_then(|md| md.is_internal_transfer_hint)
+                .unwrap_or(false)
+            {
+                return None;
+            }
+            let connection = self.connection(account);
+            if self.ignore_matcher.as_ref().is_some_and(|matcher| {
+                matcher.is_match(&TransactionIgnoreInput {
+                    account_id: account.id.as_str(),
+                    account_name: &account.name,
+                    connection_id: account.connection_id.as_str(),
+                    connection_name: connection
+                        .map(|c| c.config.name.as_str())
+                        .unwrap_or_default(),
+                    synchronizer: connection
+                        .map(|c| c.config.synchronizer.as_str())
+                        .unwrap_or_default(),
+                    description: &tx.description,
+                    status: &status,
+                    amount: &tx.amount,
+                })
+            }) {
+                return None;
+            }
+            if ann.is_some_and(annotation_ignores_spending) {
+                return None;
+            }
+        }
+
+        Some(TransactionOutput {
+            id: tx.id.to_string(),
+            account_id: account.id.to_string(),
+            account_name: account.name.clone(),
+            timestamp: tx.timestamp.to_rfc3339(),
+            description: tx.description.clone(),
+            amount: tx.amount.clone(),
+            asset: serde_json::to_value(&tx.asset).unwrap_or_default(),
+            status,
+            category: effective_category(tx, ann).map(str::to_string),
+            subcategory: ann.and_then(|ann| ann.subcategory.clone()),
+            annotation: ann.and_then(annotation_output),
+            standardized_metadata: tx.standardized_metadata.clone(),
+        })
+    }
+}
+
+/// The annotated category, else the provider's merchant category.
+pub(super) fn effective_category<'a>(
+    tx: &'a Transaction,
+    ann: Option<&'a TransactionAnnotation>,
+) -> Option<&'a str> {
+    ann.and_then(|ann| ann.category.as_deref()).or_else(|| {
+        tx.standardized_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.merchant_category_label.as_deref())
+    })
+}
+
+/// The date a transaction is reported on: its annotated effective date, else
+/// the date of its timestamp.
+pub(super) fn transaction_date(tx: &Transaction, ann: Option<&TransactionAnnotation>) -> NaiveDate {
+    ann.and_then(|annotation| annotation.effective_date)
+        .unwrap_or_else(|| tx.timestamp.date_naive())
+}
+
+/// Materialize last-write-wins annotation state per transaction id.
+pub(super) fn materialize_annotations(
+    patches: Vec<TransactionAnnotationPatch>,
+) -> HashMap<Id, TransactionAnnotation> {
+    let mut annotations_by_tx: HashMap<Id, TransactionAnnotation> = HashMap::new();
+    for patch in patches {
+        let tx_id = patch.transaction_id.clone();
+        let ann = annotations_by_tx
+            .entry(tx_id.clone())
+            .or_insert_with(|| TransactionAnnotation::new(tx_id));
+        patch.apply_to(ann);
+    }
+    annotations_by_tx
+}
+
+/// Sort by amount, ascending; unparseable amounts go last.
+pub(super) fn sort_outputs_by_amount(output: &mut [TransactionOutput]) {
+    output.sort_by(|a, b| {
+        let left = Decimal::from_str(&a.amount);
+        let right = Decimal::from_str(&b.amount);
+        match (left, right) {
+            (Ok(la), Ok(rb)) => la.cmp(&rb),
+            (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+            (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+            (Err(_), Err(_)) => a.amount.cmp(&b.amount),
+        }
+    });
+}
+
 pub async fn list_transactions(
     storage: &dyn Storage,
     start: Option<String>,
@@ -265,175 +532,194 @@ pub async fn list_transactions(
         None => end_date - chrono::Duration::days(30),
     };
 
-    let ignore_matcher = if skip_ignored {
-        Some(TransactionIgnoreMatcher::from_configs(
-            &config.ignore,
-            &config.spending,
-        )?)
-    } else {
-        None
-    };
-    let accounts = storage.list_accounts().await?;
-    let connections = storage.list_connections().await?;
-    let connections_by_id: HashMap<String, crate::models::Connection> = connections
-        .into_iter()
-        .map(|c| (c.id().to_string(), c))
-        .collect();
+    let outputs = TransactionOutputs::new(storage, config, skip_ignored).await?;
     let mut output = Vec::new();
-    let ignored_account_tags: HashSet<String> = if skip_ignored {
-        config
-            .spending
-            .ignore_tags
-            .iter()
-            .filter_map(|tag| {
-                let trimmed = tag.trim();
-                if trimmed.is_empty() {
-                    None
-                } else {
-                    Some(trimmed.to_lowercase())
-                }
-            })
-            .collect()
-    } else {
-        HashSet::new()
-    };
-
-    for account in accounts {
-        if skip_ignored
-            && !ignored_account_tags.is_empty()
-            && account.tags.iter().any(|tag| {
-                let trimmed = tag.trim();
-                !trimmed.is_empty() && ignored_account_tags.contains(&trimmed.to_lowercase())
-            })
-        {
+    for account in storage.list_accounts().await? {
+        if outputs.skips_account(&account) {
             continue;
         }
 
-        let connection = connections_by_id.get(&account.connection_id.to_string());
-        let connection_id = account.connection_id.to_string();
-        let connection_name = connection
-            .map(|c| c.config.name.as_str())
-            .unwrap_or_default();
-        let synchronizer = connection
-            .map(|c| c.config.synchronizer.as_str())
-            .unwrap_or_default();
-
-        let transactions = storage.get_transactions(&account.id).await?;
-        let patches = storage
-            .get_transaction_annotation_patches(&account.id)
-            .await?;
-
-        // Materialize last-write-wins annotation state per transaction id.
-        let mut annotations_by_tx: HashMap<Id, TransactionAnnotation> = HashMap::new();
-        for patch in patches {
-            let tx_id = patch.transaction_id.clone();
-            let ann = annotations_by_tx
-                .entry(tx_id.clone())
-                .or_insert_with(|| TransactionAnnotation::new(tx_id));
-            patch.apply_to(ann);
-        }
+        let annotations_by_tx = materialize_annotations(
+            storage
+                .get_transaction_annotation_patches(&account.id)
+                .await?,
+        );
+        let transactions = transactions_for_dates(
+            storage,
+            &account.id,
+            Some(start_date),
+            Some(end_date),
+            &annotations_by_tx,
+        )
+        .await?;
 
         for tx in transactions {
             let ann = annotations_by_tx.get(&tx.id);
-            let tx_date = ann
-                .and_then(|annotation| annotation.effective_date)
-                .unwrap_or_else(|| tx.timestamp.date_naive());
+            let tx_date = transaction_date(&tx, ann);
             if tx_date < start_date || tx_date > end_date {
                 continue;
             }
-            let status = format!("{:?}", tx.status).to_lowercase();
-
-            if skip_ignored {
-                if tx
-                    .standardized_metadata
-                    .as_ref()
-                    .and_then(|md| md.is_internal_transfer_hint)
-                    .unwrap_or(false)
-                {
-                    continue;
-                }
-                if ignore_matcher.as_ref().is_some_and(|matcher| {
-                    matcher.is_match(&TransactionIgnoreInput {
-                        account_id: account.id.as_str(),
-                        account_name: &account.name,
-                        connection_id: &connection_id,
-                        connection_name,
-                        synchronizer,
-                        description: &tx.description,
-                        status: &status,
-                        amount: &tx.amount,
-                    })
-                }) {
-                    continue;
-                }
-            }
+            output.extend(outputs.output(&account, &tx, ann));
+        }
+    }
 
-            let annotation = annotations_by_tx.get(&tx.id).and_then(|ann| {
-                if ann.is_empty() {
-                    None
-                } else {
-                    Some(TransactionAnnotationOutput {
-                        description: ann.description.clone(),
-                        note: ann.note.clone(),
-                        category: ann.category.clone(),
-                        subcategory: ann.subcategory.clone(),
-                        tags: ann.tags.clone(),
-                        effective_date: ann.effective_date.map(|d| d.to_string()),
-                    })
-                }
-            });
-            let category = annotations_by_tx
-                .get(&tx.id)
-                .and_then(|ann| ann.category.clone())
-                .or_else(|| {
-                    tx.standardized_metadata
-                        .as_ref()
-                        .and_then(|metadata| metadata.merchant_category_label.clone())
-                });
-            let subcategory = annotations_by_tx
-                .get(&tx.id)
-                .and_then(|ann| ann.subcategory.clone());
-            if skip_ignored
-                && annotations_by_tx
-                    .get(&tx.id)
-                    .is_some_and(annotation_ignores_spending)
-            {
+    if sort_by_amount {
+        sort_outputs_by_amount(&mut output);
+    }
+
+    Ok(output)
+}
+
+fn annotation_patch_output(patch: &TransactionAnnotationPatch) -> TransactionAnnotationPatchOutput {
+    TransactionAnnotationPatchOutput {
+        description: patch.description.clone(),
+        note: patch.note.clone(),
+        category: patch.category.clone(),
+        subcategory: patch.subcategory.clone(),
+        tags: patch.tags.clone(),
+        effective_date: patch
+            .effective_date
+            .map(|value| value.map(|date| date.to_string())),
+    }
+}
+
+/// Every stored version of a transaction and every annotation patch to it,
+/// oldest first. Transaction ids are only unique per account, so without
+/// `account` each account holding the id gets its own history.
+///
+/// Entries are ordered by the earliest moment `--as-of` shows them: a
+/// patch's write time, or a version's write time as `AsOfStorage` judges it
+/// (see [`written_at`]).
+pub async fn list_transaction_history(
+    storage: &dyn Storage,
+    transaction_id: &str,
+    account: Option<&str>,
+) -> Result<Vec<TransactionHistoryOutput>> {
+    let accounts = match account {
+        Some(id_or_name) => vec![find_account(storage, id_or_name)
+            .await?
+            .with_context(|| format!("Account not found: {id_or_name}"))?],
+        None => storage.list_accounts().await?,
+    };
+    let tx_id = Id::from_string(transaction_id);
+
+    let mut output = Vec::new();
+    for account in accounts {
+        let batch_times = sync_batch_times(storage, &account.id).await?;
+        let mut entries: Vec<(DateTime<Utc>, TransactionHistoryEntryOutput)> = Vec::new();
+
+        let versions = written_at(
+            storage.get_transactions_raw(&account.id).await?,
+            &batch_times,
+            |tx| (tx.timestamp, tx.sync_batch.as_ref()),
+        );
+        for (written, tx) in versions {
+            if tx.id != tx_id {
                 continue;
             }
-
-            output.push(TransactionOutput {
-                id: tx.id.to_string(),
-                account_id: account.id.to_string(),
-                account_name: account.name.clone(),
-                timestamp: tx.timestamp.to_rfc3339(),
-                description: tx.description.clone(),
-                amount: tx.amount.clone(),
-                asset: serde_json::to_value(&tx.asset).unwrap_or_default(),
-                status,
-                category,
-                subcategory,
-                annotation,
-                standardized_metadata: tx.standardized_metadata.clone(),
-            });
+            let recorded_at = tx
+                .sync_batch
+                .as_ref()
+                .and_then(|batch| batch_times.get(batch))
+                .copied();
+            entries.push((
+                written,
+                TransactionHistoryEntryOutput::Version {
+                    recorded_at: recorded_at.map(|time| time.to_rfc3339()),
+                    sync_batch: tx.sync_batch.as_ref().map(|batch| batch.to_string()),
+                    timestamp: tx.timestamp.to_rfc3339(),
+                    description: tx.description.clone(),
+                    amount: tx.amount.clone(),
+                    asset: serde_json::to_value(&tx.asset).unwrap_or_default(),
+                    status: format!("{:?}", tx.status).to_lowercase(),
+                },
+            ));
         }
-    }
+        let has_versions = !entries.is_empty();
 
-    if sort_by_amount {
-        output.sort_by(|a, b| {
-            let left = Decimal::from_str(&a.amount);
-            let right = Decimal::from_str(&b.amount);
-            match (left, right) {
-                (Ok(la), Ok(rb)) => la.cmp(&rb),
-                (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
-                (Ok(_), Err(_)) => std::cmp::Ordering::Less,
-                (Err(_), Err(_)) => a.amount.cmp(&b.amount),
+        // Patches apply in log order, as everywhere else.
+        let mut annotation = TransactionAnnotation::new(tx_id.clone());
+        let mut patches = Vec::new();
+        for patch in storage
+            .get_transaction_annotation_patches(&account.id)
+            .await?
+        {
+            if patch.transaction_id != tx_id {
+                continue;
             }
+            patch.apply_to(&mut annotation);
+            patches.push((
+                patch.timestamp,
+                TransactionHistoryEntryOutput::AnnotationPatch {
+                    recorded_at: patch.timestamp.to_rfc3339(),
+                    patch: annotation_patch_output(&patch),
+                    annotation: annotation_output(&annotation),
+                },
+            ));
+        }
+        if !has_versions && patches.is_empty() {
+            continue;
+        }
+        // Stable, so versions stay ahead of patches written at the same time.
+        entries.extend(patches);
+        entries.sort_by_key(|(time, _)| *time);
+
+        output.push(TransactionHistoryOutput {
+            transaction_id: tx_id.to_string(),
+            account_id: account.id.to_string(),
+            account_name: account.name.clone(),
+            entries: entries.into_iter().map(|(_, entry)| entry).collect(),
         });
     }
 
+    if output.is_empty() {
+        anyhow::bail!("Transaction not found: {transaction_id}");
+    }
     Ok(output)
 }
 
+/// An account's deduped transactions that can fall on `start..=end`: by
+/// annotated effective date, or else by the timestamp's date in any time
+/// zone. Callers still filter on the exact date.
+pub(super) async fn transactions_for_dates(
+    storage: &dyn Storage,
+    account_id: &Id,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+    annotations_by_tx: &HashMap<Id, TransactionAnnotation>,
+) -> Result<Vec<Transaction>> {
+    if start.is_none() && end.is_none() {
+        return storage.get_transactions(account_id).await;
+    }
+
+    // A day of slack either side covers every UTC offset.
+    let bound = |date: NaiveDate, days: i64| {
+        (date + chrono::Duration::days(days))
+            .and_time(NaiveTime::MIN)
+            .and_utc()
+    };
+    let txns = storage
+        .get_transactions_in_range(
+            account_id,
+            start.map(|date| bound(date, -1)),
+            end.map(|date| bound(date, 2)),
+        )
+        .await?;
+
+    // An effective date can pull in a transaction from anywhere in the
+    // history; those are rare enough to just load everything.
+    let found: HashSet<&Id> = txns.iter().map(|tx| &tx.id).collect();
+    let moved_in = annotations_by_tx.iter().any(|(tx_id, ann)| {
+        ann.effective_date.is_some_and(|date| {
+            start.is_none_or(|start| date >= start) && end.is_none_or(|end| date <= end)
+        }) && !found.contains(tx_id)
+    });
+    if moved_in {
+        return storage.get_transactions(account_id).await;
+    }
+    Ok(txns)
+}
+
 pub async fn list_all(storage: &dyn Storage, config: &ResolvedConfig) -> Result<AllOutput> {
     Ok(AllOutput {
         connections: list_connections(storage).await?,
@@ -532,6 +818,7 @@ mod tests {
                 ignore: crate::config::IgnoreConfig::default(),
                 ai: crate::config::AiConfig::default(),
                 git: crate::config::GitConfig::default(),
+                profiles: Default::default(),
             },
         )
         .await?;
@@ -608,6 +895,7 @@ mod tests {
                 ignore: crate::config::IgnoreConfig::default(),
                 ai: crate::config::AiConfig::default(),
                 git: crate::config::GitConfig::default(),
+                profiles: Default::default(),
             },
         )
         .await?;
@@ -684,6 +972,7 @@ mod tests {
                 ignore: crate::config::IgnoreConfig::default(),
                 ai: crate::config::AiConfig::default(),
                 git: crate::config::GitConfig::default(),
+                profiles: Default::default(),
             },
         )
         .await?;
@@ -707,6 +996,7 @@ mod tests {
                 ignore: crate::config::IgnoreConfig::default(),
                 ai: crate::config::AiConfig::default(),
                 git: crate::config::GitConfig::default(),
+                profiles: Default::default(),
             },
         )
         .await?;
@@ -761,6 +1051,7 @@ mod tests {
                 ignore: crate::config::IgnoreConfig::default(),
                 ai: crate::config::AiConfig::default(),
                 git: crate::config::GitConfig::default(),
+                profiles: Default::default(),
             },
         )
         .await?;
@@ -828,6 +1119,7 @@ mod tests {
             },
             ai: crate::config::AiConfig::default(),
             git: crate::config::GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let skipped = list_transactions(
@@ -895,6 +1187,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: crate::config::GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let skipped = list_transactions(
@@ -962,6 +1255,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: crate::config::GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let skipped = list_transactions(
@@ -1030,6 +1324,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: crate::config::GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let skipped = list_transactions(
@@ -0,0 +1,640 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use tracing::{info, warn};
+
+use crate::config::ResolvedConfig;
+use crate::market_data::{
+    expects_fx_close, missing_ranges, plan_range_fetches, AssetCategory, AssetId, BackfillCall,
+    BackfillJournal, BackfillStatus, BackfillTarget, DateRange, JsonlMarketDataStore,
+    MarketDataService, MarketDataServiceBuilder, MarketDataStore, PriceKind, PriceSourceRegistry,
+    RateLimitConfig, TradingCalendar,
+};
+use crate::models::Asset;
+use crate::storage::Storage;
+
+use super::portfolio::resolve_price_history_scope;
+use super::{
+    maybe_auto_commit, AssetGapOutput, BackfillOutput, FxGapOutput, MarketDataGapRangeOutput,
+    MarketDataGapsOutput,
+};
+
+pub const DEFAULT_BACKFILL_MERGE_DAYS: u32 = 7;
+
+pub struct MarketDataGapsRequest<'a> {
+    pub storage: &'a dyn Storage,
+    pub config: &'a ResolvedConfig,
+    pub account: Option<&'a str>,
+    pub connection: Option<&'a str>,
+    pub start: Option<&'a str>,
+    pub end: Option<&'a str>,
+    pub currency: Option<String>,
+    pub include_fx: bool,
+    /// Plan and execute range fetches for the detected gaps.
+    pub fill: bool,
+    /// Gaps separated by at most this many days are fetched with one call.
+    pub merge_days: u32,
+}
+
+/// Days on which each price asset / FX pair was held. FX days are restricted
+/// to days a close is expected; price days are filtered per asset once its
+/// trading calendar is known.
+#[derive(Default)]
+struct HeldDays {
+    prices: BTreeMap<AssetId, (Asset, BTreeSet<NaiveDate>)>,
+    fx: BTreeMap<(String, String), BTreeSet<NaiveDate>>,
+}
+
+fn is_zero_amount(amount: &str) -> bool {
+    Decimal::from_str(amount.trim())
+        .map(|value| value.is_zero())
+        .unwrap_or(false)
+}
+
+pub async fn market_data_gaps(request: MarketDataGapsRequest<'_>) -> Result<MarketDataGapsOutput> {
+    let MarketDataGapsRequest {
+        storage,
+        config,
+        account,
+        connection,
+        start,
+        end,
+        currency,
+        include_fx,
+        fill,
+        merge_days,
+    } = request;
+
+    let (scope, accounts) = resolve_price_history_scope(storage, account, connection).await?;
+
+    let mut account_snapshots = Vec::new();
+    let mut earliest_balance_date: Option<NaiveDate> = None;
+    for account in &accounts {
+        let mut snapshots = storage.get_balance_snapshots(&account.id).await?;
+        snapshots.sort_by_key(|s| s.timestamp);
+        if let Some(first) = snapshots.first() {
+            let date = first.timestamp.date_naive();
+            earliest_balance_date = Some(earliest_balance_date.map_or(date, |d| d.min(date)));
+        }
+        account_snapshots.push(snapshots);
+    }
+
+    let start_date = match start {
+        Some(value) => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .with_context(|| format!("Invalid start date: {value}"))?,
+        None => earliest_balance_date.context("No balances found for selected scope")?,
+    };
+    let end_date = match end {
+        Some(value) => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .with_context(|| format!("Invalid end date: {value}"))?,
+        None => Utc::now().date_naive(),
+    };
+    if start_date > end_date {
+        anyhow::bail!("Start date must be on or before end date");
+    }
+
+    let target_currency = currency.unwrap_or_else(|| config.reporting_currency.clone());
+    let target_currency_upper = target_currency.trim().to_uppercase();
+    let window = DateRange::new(start_date, end_date);
+
+    let mut held = HeldDays::default();
+    for snapshots in &account_snapshots {
+        for (idx, snapshot) in snapshots.iter().enumerate() {
+            let from = snapshot.timestamp.date_naive().max(window.start);
+            let to = match snapshots.get(idx + 1) {
+                Some(next) => match next.timestamp.date_naive().pred_opt() {
+                    Some(day) => day.min(window.end),
+                    None => continue,
+                },
+                None => window.end,
+            };
+            if from > to {
+                continue;
+            }
+            let held_range = DateRange::new(from, to);
+
+            for balance in &snapshot.balances {
+                if is_zero_amount(&balance.amount) {
+                    continue;
+                }
+                let asset = balance.asset.normalized();
+                match &asset {
+                    Asset::Currency { iso_code } => {
+                        let base = iso_code.trim().to_uppercase();
+                        if !include_fx || base == target_currency_upper {
+                            continue;
+                        }
+                        held.fx
+                            .entry((base, target_currency_upper.clone()))
+                            .or_default()
+                            .extend(held_range.dates().filter(|d| expects_fx_close(*d)));
+                    }
+                    Asset::Equity { .. } | Asset::Crypto { .. } => {
+                        let asset_id = AssetId::from_asset(&asset);
+                        held.prices
+                            .entry(asset_id)
+                            .or_insert_with(|| (asset.clone(), BTreeSet::new()))
+                            .1
+                            .extend(held_range.dates());
+                    }
+                }
+            }
+        }
+    }
+
+    let store: Arc<dyn MarketDataStore> = Arc::new(JsonlMarketDataStore::new(&config.data_dir));
+
+    let mut asset_gaps: Vec<(BackfillTarget, Vec<DateRange>)> = Vec::new();
+    let mut assets_output = Vec::new();
+    let mut missing_total = 0usize;
+
+    for (asset_id, (asset, held_days)) in &held.prices {
+        let tz = store
+            .get_asset_entry(asset_id)
+            .await?
+            .and_then(|entry| entry.tz);
+        let calendar = TradingCalendar::for_asset(asset, tz.as_deref());
+        let expected: BTreeSet<NaiveDate> = held_days
+            .iter()
+            .copied()
+            .filter(|d| calendar.is_trading_day(*d))
+            .collect();
+        let prices = store.get_all_prices(asset_id).await?;
+        if include_fx {
+            // Valuing the asset also needs its quote currency converted.
+            let quote_currencies: BTreeSet<String> = prices
+                .iter()
+                .map(|p| p.quote_currency.trim().to_uppercase())
+                .filter(|c| *c != target_currency_upper)
+                .collect();
+            for quote in quote_currencies {
+                held.fx
+                    .entry((quote, target_currency_upper.clone()))
+                    .or_default()
+                    .extend(expected.iter().filter(|d| expects_fx_close(**d)));
+            }
+        }
+        let covered: BTreeSet<NaiveDate> = prices
+            .into_iter()
+            .filter(|p| p.kind != PriceKind::Quote)
+            .map(|p| p.as_of_date)
+            .filter(|d| expected.contains(d))
+            .collect();
+        let expected: Vec<NaiveDate> = expected.iter().copied().collect();
+        let ranges = missing_ranges(&expected, &covered);
+        let missing_days = expected.len() - covered.len();
+        missing_total += missing_days;
+
+        assets_output.push(AssetGapOutput {
+            asset: asset.clone(),
+            asset_id: asset_id.to_string(),
+            expected_days: expected.len(),
+            covered_days: covered.len(),
+            missing_days,
+            ranges: ranges_output(&ranges, &expected),
+        });
+        asset_gaps.push((
+            BackfillTarget::Price {
+                asset: asset.clone(),
+                asset_id: asset_id.clone(),
+            },
+            ranges,
+        ));
+    }
+
+    let mut fx_output = Vec::new();
+    for ((base, quote), expected) in &held.fx {
+        let covered: BTreeSet<NaiveDate> = store
+            .get_all_fx_rates(base, quote)
+            .await?
+            .into_iter()
+            .map(|r| r.as_of_date)
+            .filter(|d| expected.contains(d))
+            .collect();
+        let expected: Vec<NaiveDate> = expected.iter().copied().collect();
+        let ranges = missing_ranges(&expected, &covered);
+        let missing_days = expected.len() - covered.len();
+        missing_total += missing_days;
+
+        fx_output.push(FxGapOutput {
+            base: base.clone(),
+            quote: quote.clone(),
+            expected_days: expected.len(),
+            covered_days: covered.len(),
+            missing_days,
+            ranges: ranges_output(&ranges, &expected),
+        });
+        asset_gaps.push((
+            BackfillTarget::Fx {
+                base: base.clone(),
+                quote: quote.clone(),
+            },
+            ranges,
+        ));
+    }
+
+    let fill_output = if fill {
+        let key = format!(
+            "{}|{start_date}|{end_date}|{target_currency_upper}|fx={include_fx}|merge={merge_days}",
+            serde_json::to_string(&scope)?
+        );
+        let output = run_backfill(config, store, &key, &asset_gaps, merge_days).await?;
+        maybe_auto_commit(config, "market data backfill");
+        Some(output)
+    } else {
+        None
+    };
+
+    Ok(MarketDataGapsOutput {
+        scope,
+        currency: target_currency,
+        start_date: start_date.to_string(),
+        end_date: end_date.to_string(),
+        assets: assets_output,
+        fx: include_fx.then_some(fx_output),
+        missing_days: missing_total,
+        fill: fill_output,
+    })
+}
+
+fn ranges_output(ranges: &[DateRange], expected: &[NaiveDate]) -> Vec<MarketDataGapRangeOutput> {
+    ranges
+        .iter()
+        .map(|range| MarketDataGapRangeOutput {
+            start_date: range.start.to_string(),
+            end_date: range.end.to_string(),
+            missing_days: expected
+                .iter()
+                .filter(|d| **d >= range.start && **d <= range.end)
+                .count(),
+        })
+        .collect()
+}
+
+fn target_category(target: &BackfillTarget) -> AssetCategory {
+    match target {
+        BackfillTarget::Price {
+            asset: Asset::Crypto { .. },
+            ..
+        } => AssetCategory::Crypto,
+        BackfillTarget::Price { .. } => AssetCategory::Equity,
+        BackfillTarget::Fx { .. } => AssetCategory::Fx,
+    }
+}
+
+async fn run_backfill(
+    config: &ResolvedConfig,
+    store: Arc<dyn MarketDataStore>,
+    key: &str,
+    gaps: &[(BackfillTarget, Vec<DateRange>)],
+    merge_days: u32,
+) -> Result<BackfillOutput> {
+    let data_dir = &config.data_dir;
+    let (mut journal, resumed) = match BackfillJournal::load_matching(data_dir, key)? {
+        Some(journal) if journal.pending() > 0 => {
+            info!(
+                pending = journal.pending(),
+                total = journal.calls.len(),
+                "resuming interrupted market data backfill"
+            );
+            (journal, true)
+        }
+        _ => {
+            let calls = gaps
+                .iter()
+                .flat_map(|(target, ranges)| {
+                    plan_range_fetches(ranges, merge_days)
+                        .into_iter()
+                        .map(|range| BackfillCall {
+                            target: target.clone(),
+                            range,
+                            status: BackfillStatus::Pending,
+                        })
+                })
+                .collect();
+            (
+                BackfillJournal {
+                    key: key.to_string(),
+                    calls,
+                },
+                false,
+            )
+        }
+    };
+
+    let mut registry = PriceSourceRegistry::new(data_dir);
+    if let Err(e) = registry.load() {
+        warn!(error = %e, "failed to load price sources; backfill will not be rate limited");
+    }
+    let rate_limits: HashMap<AssetCategory, RateLimitConfig> = [
+        AssetCategory::Equity,
+        AssetCategory::Crypto,
+        AssetCategory::Fx,
+    ]
+    .into_iter()
+    .filter_map(|category| {
+        registry
+            .rate_limit_for(category)
+            .map(|limit| (category, limit))
+    })
+    .collect();
+
+    let market_data = MarketDataServiceBuilder::new(store, data_dir.clone())
+        .build()
+        .await;
+
+    if !journal.calls.is_empty() {
+        journal.save(data_dir)?;
+    }
+
+    let total = journal.calls.len();
+    let mut last_call: HashMap<AssetCategory, Instant> = HashMap::new();
+    let mut fetched_points = 0usize;
+
+    for idx in 0..total {
+        if journal.calls[idx].status != BackfillStatus::Pending {
+            continue;
+        }
+        let call = journal.calls[idx].clone();
+        let category = target_category(&call.target);
+
+        if let (Some(limit), Some(last)) = (rate_limits.get(&category), last_call.get(&category)) {
+            let wait = limit.min_interval().saturating_sub(last.elapsed());
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+        }
+        last_call.insert(category, Instant::now());
+
+        let result = execute_backfill_call(&market_data, &call).await;
+        journal.calls[idx].status = match result {
+            Ok(fetched) => {
+                fetched_points += fetched;
+                BackfillStatus::Done { fetched }
+            }
+            Err(e) => BackfillStatus::Failed {
+                error: e.to_string(),
+            },
+        };
+        journal.save(data_dir)?;
+
+        info!(
+            call = idx + 1,
+            total,
+            target = ?call.target,
+            start = %call.range.start,
+            end = %call.range.end,
+            status = ?journal.calls[idx].status,
+            "market data backfill progress"
+        );
+    }
+
+    BackfillJournal::remove(data_dir)?;
+
+    let completed_calls = journal
+        .calls
+        .iter()
+        .filter(|c| matches!(c.status, BackfillStatus::Done { .. }))
+        .count();
+    let failed_calls = journal
+        .calls
+        .iter()
+        .filter(|c| matches!(c.status, BackfillStatus::Failed { .. }))
+        .count();
+
+    Ok(BackfillOutput {
+        resumed,
+        planned_calls: total,
+        completed_calls,
+        failed_calls,
+        fetched_points,
+        calls: journal.calls,
+    })
+}
+
+async fn execute_backfill_call(
+    market_data: &MarketDataService,
+    call: &BackfillCall,
+) -> Result<usize> {
+    let DateRange { start, end } = call.range;
+    match &call.target {
+        BackfillTarget::Price { asset, .. } => Ok(market_data
+            .price_closes_range(asset, start, end)
+            .await?
+            .len()),
+        BackfillTarget::Fx { base, quote } => Ok(market_data
+            .fx_closes_range(base, quote, start, end)
+            .await?
+            .len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{
+        DisplayConfig, GitConfig, HistoryConfig, RefreshConfig, SpendingConfig, TrayConfig,
+    };
+    use crate::market_data::{FxRateKind, FxRatePoint, PricePoint};
+    use crate::models::{Account, AssetBalance, BalanceSnapshot, Connection, ConnectionConfig};
+    use crate::storage::MemoryStorage;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn test_config(dir: &TempDir) -> ResolvedConfig {
+        ResolvedConfig {
+            data_dir: dir.path().to_path_buf(),
+            reporting_currency: "USD".to_string(),
+            display: DisplayConfig::default(),
+            refresh: RefreshConfig::default(),
+            history: HistoryConfig::default(),
+            tray: TrayConfig::default(),
+            spending: SpendingConfig::default(),
+            portfolio: crate::config::PortfolioConfig::default(),
+            ignore: crate::config::IgnoreConfig::default(),
+            ai: crate::config::AiConfig::default(),
+            git: GitConfig::default(),
+            profiles: Default::default(),
+        }
+    }
+
+    fn d(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn close(asset: &Asset, date: &str) -> PricePoint {
+        PricePoint {
+            asset_id: AssetId::from_asset(asset),
+            as_of_date: d(date),
+            timestamp: Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap(),
+            price: "100".to_string(),
+            quote_currency: "USD".to_string(),
+            kind: PriceKind::Close,
+            source: "test".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn market_data_gaps_reports_missing_trading_day_ranges() -> Result<()> {
+        let dir = TempDir::new()?;
+        let config = test_config(&dir);
+        let storage = MemoryStorage::new();
+
+        let connection = Connection::new(ConnectionConfig {
+            name: "Brokerage".to_string(),
+            synchronizer: "manual".to_string(),
+            credentials: None,
+            balance_staleness: None,
+        });
+        storage.save_connection(&connection).await?;
+        let account = Account::new("Main", connection.id().clone());
+        storage.save_account(&account).await?;
+
+        let aapl = Asset::equity("AAPL");
+        // Held AAPL and EUR from Mon 2024-01-08; EUR sold from Mon 2024-01-15.
+        storage
+            .append_balance_snapshot(
+                &account.id,
+                &BalanceSnapshot::new(
+                    Utc.with_ymd_and_hms(2024, 1, 8, 12, 0, 0).unwrap(),
+                    vec![
+                        AssetBalance::new(aapl.clone(), "10"),
+                        AssetBalance::new(Asset::currency("EUR"), "50"),
+                    ],
+                ),
+            )
+            .await?;
+        storage
+            .append_balance_snapshot(
+                &account.id,
+                &BalanceSnapshot::new(
+                    Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap(),
+                    vec![
+                        AssetBalance::new(aapl.clone(), "10"),
+                        AssetBalance::new(Asset::currency("EUR"), "0"),
+                    ],
+                ),
+            )
+            .await?;
+
+        let store = JsonlMarketDataStore::new(&config.data_dir);
+        store
+            .put_prices(&[
+                close(&aapl, "2024-01-08"),
+                close(&aapl, "2024-01-09"),
+                close(&aapl, "2024-01-12"),
+            ])
+            .await?;
+        store
+            .put_fx_rates(&[FxRatePoint {
+                base: "EUR".to_string(),
+                quote: "USD".to_string(),
+                as_of_date: d("2024-01-10"),
+                timestamp: Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap(),
+                rate: "1.1".to_string(),
+                kind: FxRateKind::Close,
+                source: "test".to_string(),
+            }])
+            .await?;
+
+        let output = market_data_gaps(MarketDataGapsRequest {
+            storage: &storage,
+            config: &config,
+            account: None,
+            connection: None,
+            start: None,
+            end: Some("2024-01-19"),
+            currency: None,
+            include_fx: true,
+            fill: false,
+            merge_days: DEFAULT_BACKFILL_MERGE_DAYS,
+        })
+        .await?;
+
+        assert_eq!(output.start_date, "2024-01-08");
+        assert_eq!(output.assets.len(), 1);
+        let aapl_gaps = &output.assets[0];
+        assert_eq!(aapl_gaps.expected_days, 10);
+        assert_eq!(aapl_gaps.covered_days, 3);
+        let ranges: Vec<_> = aapl_gaps
+            .ranges
+            .iter()
+            .map(|r| (r.start_date.as_str(), r.end_date.as_str(), r.missing_days))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                ("2024-01-10", "2024-01-11", 2),
+                ("2024-01-15", "2024-01-19", 5)
+            ]
+        );
+
+        let fx = output.fx.expect("fx should be reported");
+        assert_eq!(fx.len(), 1);
+        assert_eq!((fx[0].base.as_str(), fx[0].quote.as_str()), ("EUR", "USD"));
+        assert_eq!(fx[0].expected_days, 5);
+        assert_eq!(fx[0].missing_days, 4);
+        assert_eq!(output.missing_days, 11);
+        assert!(output.fill.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn market_data_gaps_fill_without_sources_records_empty_calls() -> Result<()> {
+        let dir = TempDir::new()?;
+        let config = test_config(&dir);
+        let storage = MemoryStorage::new();
+
+        let connection = Connection::new(ConnectionConfig {
+            name: "Wallet".to_string(),
+            synchronizer: "manual".to_string(),
+            credentials: None,
+            balance_staleness: None,
+        });
+        storage.save_connection(&connection).await?;
+        let account = Account::new("Cold", connection.id().clone());
+        storage.save_account(&account).await?;
+        storage
+            .append_balance_snapshot(
+                &account.id,
+                &BalanceSnapshot::new(
+                    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap(),
+                    vec![AssetBalance::new(Asset::crypto("BTC"), "1")],
+                ),
+            )
+            .await?;
+
+        let output = market_data_gaps(MarketDataGapsRequest {
+            storage: &storage,
+            config: &config,
+            account: None,
+            connection: None,
+            start: None,
+            end: Some("2024-01-05"),
+            currency: None,
+            include_fx: false,
+            fill: true,
+            merge_days: DEFAULT_BACKFILL_MERGE_DAYS,
+        })
+        .await?;
+
+        let fill = output.fill.expect("fill output");
+        assert!(!fill.resumed);
+        assert_eq!(fill.planned_calls, 1);
+        assert_eq!(fill.completed_calls, 1);
+        assert_eq!(fill.fetched_points, 0);
+        assert_eq!(
+            fill.calls[0].range,
+            DateRange::new(d("2024-01-01"), d("2024-01-05"))
+        );
+        assert!(!BackfillJournal::path(&config.data_dir).exists());
+
+        Ok(())
+    }
+}
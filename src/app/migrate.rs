@@ -0,0 +1,15 @@
+use anyhow::Result;
+
+use crate::config::ResolvedConfig;
+use crate::storage::MigrationReport;
+
+use super::maybe_auto_commit;
+
+/// Run any pending on-disk format migrations.
+pub async fn migrate(config: &ResolvedConfig) -> Result<MigrationReport> {
+    let report = crate::storage::migrate(&config.data_dir).await?;
+    if !report.applied.is_empty() {
+        maybe_auto_commit(config, "migrate");
+    }
+    Ok(report)
+}
@@ -1,12 +1,20 @@
+mod accounts;
 mod config;
+mod doctor;
+mod encryption;
 mod graph;
 mod ignore_rules;
 #[cfg(feature = "sync")]
 mod import;
 mod list;
+mod market_data;
+mod migrate;
 mod mutations;
 mod portfolio;
 mod preflight;
+mod profiles;
+mod reconcile;
+mod search;
 mod spending;
 #[cfg(feature = "sync")]
 mod sync;
@@ -15,13 +23,21 @@ mod value;
 
 use crate::config::ResolvedConfig;
 
+pub use accounts::{
+    close_account, close_account_with, merge_accounts, move_account, remove_account,
+};
 pub use config::config_output;
+pub use doctor::doctor;
+pub use encryption::{disable_encryption, enable_encryption, encryption_textconv};
 pub use graph::{portfolio_graph, PortfolioGraphOptions, PortfolioGraphOutput};
 #[cfg(feature = "sync")]
 pub use import::import_schwab_transactions;
 pub use list::{
-    list_accounts, list_all, list_balances, list_connections, list_price_sources, list_transactions,
+    list_accounts, list_all, list_balances, list_connections, list_price_sources,
+    list_transaction_history, list_transactions, sync_history,
 };
+pub use market_data::{market_data_gaps, MarketDataGapsRequest, DEFAULT_BACKFILL_MERGE_DAYS};
+pub use migrate::migrate;
 pub use mutations::{
     add_account, add_account_with, add_connection, add_connection_with,
     approve_proposed_transaction_edit, list_proposed_transaction_edits, parse_asset,
@@ -39,20 +55,27 @@ pub use portfolio::{
     DEFAULT_PORTFOLIO_HISTORY_GRANULARITY, DEFAULT_PORTFOLIO_INCLUDE_PRICES,
 };
 pub use preflight::{run_preflight, PreflightOptions};
+pub use profiles::{lock_profile_data_dirs, profile_storage};
+pub use reconcile::reconcile;
+pub use search::{search_transactions, SearchQuery};
 pub use spending::{spending_report, SpendingReportOptions};
 #[cfg(feature = "sync")]
 pub use sync::{
-    chase_login, schwab_login, sync_all, sync_all_if_stale, sync_backfill_metadata,
-    sync_connection, sync_connection_if_stale, sync_prices, sync_recompact, sync_symlinks,
+    auth_sessions_clear, auth_sessions_list, chase_login, schwab_login, sync_all,
+    sync_all_if_stale, sync_backfill_metadata, sync_connection, sync_connection_dry_run,
+    sync_connection_if_stale, sync_prices, sync_recompact, sync_revert, sync_symlinks,
     SyncPricesScopeArg,
 };
 pub use types::{
-    AccountOutput, AllOutput, AssetInfoOutput, BalanceOutput, ChangePointsOutput, ConnectionOutput,
-    HistoryOutput, HistoryPoint, HistorySummary, PriceHistoryFailure, PriceHistoryOutput,
-    PriceHistoryScopeOutput, PriceHistoryStats, PriceSourceOutput, ProposedTransactionEditOutput,
+    AccountOutput, AllOutput, AssetGapOutput, AssetInfoOutput, BackfillOutput, BalanceOutput,
+    ChangePointsOutput, ConnectionOutput, DoctorIssue, DoctorOutput, DoctorSeverity, FxGapOutput,
+    HistoryOutput, HistoryPoint, HistorySummary, MarketDataGapRangeOutput, MarketDataGapsOutput,
+    PriceHistoryFailure, PriceHistoryOutput, PriceHistoryScopeOutput, PriceHistoryStats,
+    PriceSourceOutput, ProposedTransactionEditOutput, ReconcileOutput, SessionOutput,
     SpendingBreakdownEntryOutput, SpendingOutput, SpendingPeriodOutput, SpendingScopeOutput,
-    TaxImpactGraphOutput, TaxImpactOutput, TaxImpactPoint, TransactionAnnotationOutput,
-    TransactionAnnotationPatchOutput, TransactionOutput,
+    SyncRunOutput, TaxImpactGraphOutput, TaxImpactOutput, TaxImpactPoint,
+    TransactionAnnotationOutput, TransactionAnnotationPatchOutput, TransactionHistoryEntryOutput,
+    TransactionHistoryOutput, TransactionOutput,
 };
 
 fn maybe_auto_commit(config: &ResolvedConfig, action: &str) {
@@ -1,25 +1,25 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
-use chrono::{Datelike, Duration, NaiveDate, Utc};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
 use rust_decimal::Decimal;
 use tracing::warn;
 
 use crate::config::ResolvedConfig;
 use crate::format::format_base_currency_value;
 use crate::market_data::{
-    AssetId, FxRateKind, FxRatePoint, JsonlMarketDataStore, MarketDataService,
-    MarketDataServiceBuilder, MarketDataStore, PricePoint,
+    AssetId, FxRateKind, FxRatePoint, IntradayQuoteStore, JsonlMarketDataStore, MarketDataService,
+    MarketDataServiceBuilder, MarketDataStore, PricePoint, TradingCalendar,
 };
 use crate::models::{Account, Asset, Id};
 use crate::portfolio::{
     collect_change_points, filter_by_date_range, filter_by_granularity, AccountSummary,
-    CoalesceStrategy, CollectOptions, EquityValuationAdjustment, Granularity, Grouping,
-    PortfolioQuery, PortfolioService,
+    ChangePoint, ChangeTrigger, CoalesceStrategy, CollectOptions, EquityValuationAdjustment,
+    Granularity, Grouping, PortfolioQuery, PortfolioService,
 };
 use crate::staleness::{
     check_balance_staleness, check_price_staleness, log_balance_staleness, log_price_staleness,
@@ -547,6 +547,8 @@ struct HistoryPointInput<'a> {
     value_mode: HistoryValueMode,
     cost_basis_backfill: &'a HashMap<String, HistoryCostBasisBackfill>,
     account_ids: &'a [Id],
+    /// Value with intraday quotes recorded at or before this instant.
+    intraday_at: Option<DateTime<Utc>>,
 }
 
 async fn build_history_point_for_date(
@@ -566,7 +568,10 @@ async fn build_history_point_for_date(
         account_ids: input.account_ids.to_vec(),
     };
 
-    let snapshot = service.calculate(&query).await?;
+    let snapshot = match input.intraday_at {
+        Some(at) => service.calculate_at(&query, at).await?,
+        None => service.calculate(&query).await?,
+    };
     let history_point_value = history_total_value_from_snapshot(
         &snapshot,
         config,
@@ -761,6 +766,7 @@ pub async fn fetch_historical_prices(
     let store: Arc<dyn MarketDataStore> = Arc::new(JsonlMarketDataStore::new(&config.data_dir));
     let market_data = MarketDataServiceBuilder::new(store.clone(), config.data_dir.clone())
         .with_lookback_days(lookback_days)
+        .with_fx_pivots(config.refresh.fx_pivots.clone())
         .build()
         .await;
 
@@ -1152,7 +1158,7 @@ fn history_spec_dates(anchor_date: NaiveDate, history_spec: &[String]) -> Result
     Ok(dates)
 }
 
-async fn resolve_price_history_scope(
+pub(super) async fn resolve_price_history_scope(
     storage: &dyn Storage,
     account: Option<&str>,
     connection: Option<&str>,
@@ -1545,9 +1551,16 @@ pub async fn portfolio_snapshot(
                                     .then_with(|| a.timestamp.cmp(&b.timestamp))
                             });
 
+                        let tz = store
+                            .get_asset_entry(&asset_id)
+                            .await?
+                            .and_then(|entry| entry.tz);
+                        let calendar =
+                            TradingCalendar::for_asset(&asset_balance.asset, tz.as_deref());
                         let check = check_price_staleness(
                             cached_price.as_ref(),
                             config.refresh.price_staleness,
+                            &calendar,
                         );
                         log_price_staleness(&asset_key, &check);
                     }
@@ -1581,6 +1594,7 @@ pub async fn portfolio_snapshot(
         Arc::new(
             MarketDataServiceBuilder::new(store.clone(), config.data_dir.clone())
                 .with_quote_staleness(config.refresh.price_staleness)
+                .with_fx_pivots(config.refresh.fx_pivots.clone())
                 .build()
                 .await,
         )
@@ -1588,6 +1602,7 @@ pub async fn portfolio_snapshot(
         Arc::new(
             MarketDataServiceBuilder::new(store.clone(), config.data_dir.clone())
                 .with_quote_staleness(config.refresh.price_staleness)
+                .with_fx_pivots(config.refresh.fx_pivots.clone())
                 .offline_only()
                 .build()
                 .await,
@@ -1643,6 +1658,7 @@ pub async fn portfolio_tax_impact(
             config.data_dir.clone(),
         )
         .with_quote_staleness(config.refresh.price_staleness)
+        .with_fx_pivots(config.refresh.fx_pivots.clone())
         .offline_only()
         .build()
         .await,
@@ -2106,6 +2122,55 @@ pub async fn latent_capital_gains_tax_history(
     .await
 }
 
+/// Merge recorded intraday quotes into `points` as price change points, for
+/// assets the collected points already refer to.
+async fn add_intraday_change_points(
+    points: Vec<ChangePoint>,
+    intraday: &IntradayQuoteStore,
+) -> Result<Vec<ChangePoint>> {
+    let Some(first) = points.first().map(|point| point.timestamp) else {
+        return Ok(points);
+    };
+    let relevant: HashSet<AssetId> = points
+        .iter()
+        .flat_map(|point| point.triggers.iter())
+        .filter_map(|trigger| match trigger {
+            ChangeTrigger::Balance { asset, .. } => Some(AssetId::from_asset(asset)),
+            ChangeTrigger::Price { asset_id } => Some(asset_id.clone()),
+            ChangeTrigger::FxRate { .. } => None,
+        })
+        .collect();
+
+    let mut merged: BTreeMap<DateTime<Utc>, Vec<ChangeTrigger>> = points
+        .into_iter()
+        .map(|point| (point.timestamp, point.triggers))
+        .collect();
+    for asset_id in intraday.asset_ids().await? {
+        if !relevant.contains(&asset_id) {
+            continue;
+        }
+        for quote in intraday.quotes(&asset_id).await? {
+            if quote.timestamp < first {
+                continue;
+            }
+            merged
+                .entry(quote.timestamp)
+                .or_default()
+                .push(ChangeTrigger::Price {
+                    asset_id: asset_id.clone(),
+                });
+        }
+    }
+
+    Ok(merged
+        .into_iter()
+        .map(|(timestamp, triggers)| ChangePoint {
+            timestamp,
+            triggers,
+        })
+        .collect())
+}
+
 async fn portfolio_history_scoped(
     storage: Arc<dyn Storage>,
     config: &ResolvedConfig,
@@ -2155,7 +2220,16 @@ async fn portfolio_history_scoped(
         target_currency: currency.clone(),
     };
 
-    let change_points = collect_change_points(&storage_arc, &store, &options).await?;
+    let mut change_points = collect_change_points(&storage_arc, &store, &options).await?;
+
+    // Hourly history uses recorded intraday quotes as additional price changes.
+    let use_intraday =
+        matches!(granularity_enum, Granularity::Hourly) && config.refresh.intraday.enabled;
+    if use_intraday && include_prices {
+        change_points =
+            add_intraday_change_points(change_points, &IntradayQuoteStore::new(&config.data_dir))
+                .await?;
+    }
 
     // Filter by date range
     let filtered_by_date = filter_by_date_range(change_points, start_date, end_date);
@@ -2176,14 +2250,14 @@ async fn portfolio_history_scoped(
     }
 
     // Setup market data service (offline mode - use cached data only)
-    let market_data = Arc::new(configure_history_market_data(
-        MarketDataServiceBuilder::new(store, config.data_dir.clone())
-            .with_quote_staleness(config.refresh.price_staleness)
-            .offline_only()
-            .build()
-            .await,
-        config,
-    ));
+    let mut builder = MarketDataServiceBuilder::new(store, config.data_dir.clone())
+        .with_quote_staleness(config.refresh.price_staleness)
+        .with_fx_pivots(config.refresh.fx_pivots.clone())
+        .offline_only();
+    if use_intraday {
+        builder = builder.with_intraday(config.refresh.intraday.interval);
+    }
+    let market_data = Arc::new(configure_history_market_data(builder.build().await, config));
 
     let cost_basis_backfill =
         collect_history_cost_basis_backfill(storage_arc.as_ref(), &account_ids).await?;
@@ -2254,6 +2328,7 @@ async fn portfolio_history_scoped(
                 value_mode,
                 cost_basis_backfill: &cost_basis_backfill,
                 account_ids: &account_ids,
+                intraday_at: use_intraday.then_some(change_point.timestamp),
             },
             &mut carry_forward_unit_values,
         )
@@ -2310,6 +2385,7 @@ pub async fn portfolio_recent_history(
     let market_data = Arc::new(configure_history_market_data(
         MarketDataServiceBuilder::new(store, config.data_dir.clone())
             .with_quote_staleness(config.refresh.price_staleness)
+            .with_fx_pivots(config.refresh.fx_pivots.clone())
             .offline_only()
             .build()
             .await,
@@ -2348,6 +2424,7 @@ pub async fn portfolio_recent_history(
                 },
                 cost_basis_backfill: &cost_basis_backfill,
                 account_ids: &account_ids,
+                intraday_at: None,
             },
             &mut carry_forward_unit_values,
         )
@@ -2623,6 +2700,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let storage = Arc::new(MemoryStorage::new());
@@ -2711,6 +2789,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let storage = Arc::new(MemoryStorage::new());
@@ -2798,6 +2877,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let storage = Arc::new(MemoryStorage::new());
@@ -2869,6 +2949,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let storage = Arc::new(MemoryStorage::new());
@@ -2964,6 +3045,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let storage = Arc::new(MemoryStorage::new());
@@ -3040,6 +3122,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let storage = Arc::new(MemoryStorage::new());
@@ -3112,6 +3195,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let storage = Arc::new(MemoryStorage::new());
@@ -3207,6 +3291,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let storage = Arc::new(MemoryStorage::new());
@@ -3301,6 +3386,79 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn hourly_portfolio_history_uses_intraday_quotes() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let mut refresh = RefreshConfig::default();
+        refresh.intraday.enabled = true;
+        let config = ResolvedConfig {
+            data_dir: dir.path().to_path_buf(),
+            reporting_currency: "USD".to_string(),
+            display: DisplayConfig::default(),
+            refresh,
+            history: HistoryConfig::default(),
+            tray: TrayConfig::default(),
+            spending: SpendingConfig::default(),
+            portfolio: crate::config::PortfolioConfig::default(),
+            ignore: crate::config::IgnoreConfig::default(),
+            ai: crate::config::AiConfig::default(),
+            git: GitConfig::default(),
+            profiles: Default::default(),
+        };
+
+        let storage = Arc::new(MemoryStorage::new());
+        let connection = Connection::new(connection_config("Test Broker"));
+        storage.save_connection(&connection).await?;
+        let account = Account::new("Trading", connection.id().clone());
+        storage.save_account(&account).await?;
+        storage
+            .append_balance_snapshot(
+                &account.id,
+                &BalanceSnapshot::new(
+                    Utc.with_ymd_and_hms(2024, 6, 3, 14, 0, 0).unwrap(),
+                    vec![AssetBalance::new(Asset::equity("AAPL"), "10")],
+                ),
+            )
+            .await?;
+
+        let intraday = IntradayQuoteStore::new(&config.data_dir);
+        for (hour, price) in [(14, "150"), (15, "160")] {
+            let quote = PricePoint {
+                asset_id: AssetId::from_asset(&Asset::equity("AAPL")),
+                as_of_date: NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+                timestamp: Utc.with_ymd_and_hms(2024, 6, 3, hour, 30, 0).unwrap(),
+                price: price.to_string(),
+                quote_currency: "USD".to_string(),
+                kind: PriceKind::Quote,
+                source: "test".to_string(),
+            };
+            intraday
+                .record(&quote, config.refresh.intraday.interval)
+                .await?;
+        }
+
+        let output = portfolio_history(
+            storage,
+            &config,
+            None,
+            Some("2024-06-03".to_string()),
+            Some("2024-06-03".to_string()),
+            "hourly".to_string(),
+            true,
+        )
+        .await?;
+
+        let totals: Vec<&str> = output
+            .points
+            .iter()
+            .map(|point| point.total_value.as_str())
+            .collect();
+        assert!(totals.contains(&"1500"), "totals: {totals:?}");
+        assert_eq!(totals.last(), Some(&"1600"));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn portfolio_change_points_includes_prices_when_enabled() -> anyhow::Result<()> {
         let dir = TempDir::new()?;
@@ -3316,6 +3474,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let storage = Arc::new(MemoryStorage::new());
@@ -3501,6 +3660,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         add_connection(&storage, &config, "Duplicate", "manual").await?;
@@ -3529,6 +3689,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let ids = FixedIdGenerator::new([Id::from_string("conn-id"), Id::from_string("acct-id")]);
@@ -3582,6 +3743,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let result = add_connection(&storage, &config, "Test Bank", "manual").await?;
@@ -3619,6 +3781,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let account = Account::new("Checking", Id::new());
@@ -3658,6 +3821,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let account = Account::new("Checking", Id::new());
@@ -3704,6 +3868,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let account = Account::new("Checking", Id::new());
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+
+use crate::config::ResolvedConfig;
+use crate::storage::{CompositeStorage, DataDirLock, JsonFileStorage, LockMode};
+
+/// Read-only storage over the profiles named by `selection` (comma-separated
+/// names, or `all`), with ids namespaced by profile and joint accounts scaled
+/// to the configured ownership.
+pub fn profile_storage(config: &ResolvedConfig, selection: &str) -> Result<CompositeStorage> {
+    let mut storage = CompositeStorage::new();
+    for (name, profile) in config.select_profiles(selection)? {
+        let mut ownership = HashMap::new();
+        for (account, percent) in &profile.ownership {
+            if !(*percent > 0.0 && *percent <= 100.0) {
+                anyhow::bail!(
+                    "Invalid ownership {percent} for {account} in profile {name}: must be in (0, 100]"
+                );
+            }
+            let percent = Decimal::try_from(*percent).with_context(|| {
+                format!("Invalid ownership {percent} for {account} in profile {name}")
+            })?;
+            ownership.insert(account.clone(), percent / Decimal::ONE_HUNDRED);
+        }
+        storage = storage.with_profile(
+            name,
+            Arc::new(JsonFileStorage::new(&profile.data_dir)),
+            ownership,
+        );
+    }
+    Ok(storage)
+}
+
+/// Take shared locks on the data dirs of the profiles named by `selection`.
+///
+/// Each dir is locked once, and the main data dir is skipped: the caller
+/// already holds its lock, possibly exclusively, and a second lock on it from
+/// this process would wait for that one.
+pub async fn lock_profile_data_dirs(
+    config: &ResolvedConfig,
+    selection: &str,
+    timeout: Duration,
+) -> Result<Vec<DataDirLock>> {
+    let mut locked = vec![canonical(&config.data_dir)];
+    let mut locks = Vec::new();
+    for (_, profile) in config.select_profiles(selection)? {
+        let data_dir = canonical(&profile.data_dir);
+        if locked.contains(&data_dir) {
+            continue;
+        }
+        locks.push(DataDirLock::acquire(&data_dir, LockMode::Shared, timeout).await?);
+        locked.push(data_dir);
+    }
+    Ok(locks)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -0,0 +1,42 @@
+use anyhow::{Context, Result};
+
+use crate::reconcile::reconcile_account;
+use crate::storage::{find_account, Storage};
+
+use super::ReconcileOutput;
+
+/// Reconcile balances against transactions for one account, or for every
+/// active account with at least one checkable window.
+pub async fn reconcile(
+    storage: &dyn Storage,
+    account: Option<&str>,
+) -> Result<Vec<ReconcileOutput>> {
+    let explicit = account.is_some();
+    let accounts = match account {
+        Some(id_or_name) => vec![find_account(storage, id_or_name)
+            .await?
+            .with_context(|| format!("Account not found: {id_or_name}"))?],
+        None => storage
+            .list_accounts()
+            .await?
+            .into_iter()
+            .filter(|account| account.active)
+            .collect(),
+    };
+
+    let mut output = Vec::new();
+    for account in accounts {
+        let reconciliation = reconcile_account(storage, &account.id).await?;
+        // Accounts without transactions (e.g. brokerages) have nothing to check.
+        if !explicit && reconciliation.windows_checked == 0 {
+            continue;
+        }
+        output.push(ReconcileOutput {
+            account_id: account.id.to_string(),
+            account_name: account.name,
+            windows_checked: reconciliation.windows_checked,
+            discrepancies: reconciliation.discrepancies,
+        });
+    }
+    Ok(output)
+}
@@ -0,0 +1,511 @@
+//! `keepbook search`: a small query language over transactions.
+//!
+//! A query is a list of terms, all of which must match. A term is a word or
+//! a `"quoted phrase"`, optionally prefixed with a field (`category:dining`,
+//! `account:"Chase Sapphire"`) and negated with a leading `-`. Text matches
+//! word by word: each word of the term must start a word of the field,
+//! ignoring case.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::config::ResolvedConfig;
+use crate::models::{Account, Connection, Id, Transaction, TransactionAnnotation};
+use crate::storage::{search_words, Storage};
+
+use super::list::{
+    effective_category, materialize_annotations, sort_outputs_by_amount, transaction_date,
+    transactions_for_dates, TransactionOutputs,
+};
+use super::TransactionOutput;
+
+const FIELDS: &[&str] = &[
+    "description",
+    "desc",
+    "note",
+    "merchant",
+    "category",
+    "subcategory",
+    "tag",
+    "status",
+    "amount",
+    "date",
+    "after",
+    "before",
+    "account",
+    "connection",
+];
+
+const STATUSES: &[&str] = &["pending", "posted", "reversed", "canceled", "failed"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+impl Comparison {
+    /// Split a leading `<`, `<=`, `=`, `>=` or `>` off `value`.
+    fn parse(value: &str) -> (Self, &str) {
+        for (prefix, comparison) in [
+            ("<=", Self::Le),
+            (">=", Self::Ge),
+            ("<", Self::Lt),
+            (">", Self::Gt),
+            ("=", Self::Eq),
+        ] {
+            if let Some(rest) = value.strip_prefix(prefix) {
+                return (comparison, rest);
+            }
+        }
+        (Self::Eq, value)
+    }
+
+    fn holds<T: Ord>(self, left: &T, right: &T) -> bool {
+        match self {
+            Self::Lt => left < right,
+            Self::Le => left <= right,
+            Self::Eq => left == right,
+            Self::Ge => left >= right,
+            Self::Gt => left > right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    /// Words anywhere in the description, note, merchant, category,
+    /// subcategory or tags.
+    Text(Vec<String>),
+    Description(Vec<String>),
+    Note(Vec<String>),
+    /// The merchant name, or the description when there is none.
+    Merchant(Vec<String>),
+    Category(Vec<String>),
+    Subcategory(Vec<String>),
+    Tag(String),
+    Status(String),
+    Amount(Comparison, Decimal),
+    Date(Comparison, NaiveDate),
+    Account(String),
+    Connection(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    negated: bool,
+    filter: Filter,
+}
+
+/// A parsed search query.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SearchQuery {
+    terms: Vec<Term>,
+}
+
+/// One term before its value is interpreted.
+struct RawTerm {
+    negated: bool,
+    field: Option<String>,
+    value: String,
+}
+
+/// Split a query into terms on unquoted whitespace.
+fn lex(query: &str) -> Result<Vec<RawTerm>> {
+    let mut terms = Vec::new();
+    let mut chars = query.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            break;
+        };
+        let mut negated = false;
+        if first == '-' {
+            chars.next();
+            if chars.peek().is_none_or(|c| c.is_whitespace()) {
+                bail!("Dangling '-' in search query");
+            }
+            negated = true;
+        }
+
+        let mut text = String::new();
+        let mut field_end = None;
+        let mut quoted = false;
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            match c {
+                '"' => {
+                    quoted = true;
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some(c) => text.push(c),
+                            None => bail!("Unterminated quote in search query"),
+                        }
+                    }
+                }
+                ':' if field_end.is_none() && !quoted => {
+                    field_end = Some(text.len());
+                    text.push(c);
+                }
+                c => text.push(c),
+            }
+        }
+
+        let (field, value) = match field_end {
+            Some(end) => {
+                let field = text[..end].to_lowercase();
+                if !FIELDS.contains(&field.as_str()) {
+                    bail!(
+                        "Unknown search field '{field}' (expected one of: {})",
+                        FIELDS.join(", ")
+                    );
+                }
+                (Some(field), text[end + 1..].to_string())
+            }
+            None => (None, text),
+        };
+        terms.push(RawTerm {
+            negated,
+            field,
+            value,
+        });
+    }
+    Ok(terms)
+}
+
+fn parse_words(field: &str, value: &str) -> Result<Vec<String>> {
+    let words: Vec<String> = search_words(value).collect();
+    if words.is_empty() {
+        bail!("Search term '{field}{value}' has no words to match");
+    }
+    Ok(words)
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date in search query: {value} (expected YYYY-MM-DD)"))
+}
+
+impl FromStr for SearchQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(query: &str) -> Result<Self> {
+        let mut terms = Vec::new();
+        for RawTerm {
+            negated,
+            field,
+            value,
+        } in lex(query)?
+        {
+            let filter = match field.as_deref() {
+                None => Filter::Text(parse_words("", &value)?),
+                Some(field) => {
+                    let prefix = format!("{field}:");
+                    let value = value.trim();
+                    match field {
+                        "description" | "desc" => Filter::Description(parse_words(&prefix, value)?),
+                        "note" => Filter::Note(parse_words(&prefix, value)?),
+                        "merchant" => Filter::Merchant(parse_words(&prefix, value)?),
+                        "category" => Filter::Category(parse_words(&prefix, value)?),
+                        "subcategory" => Filter::Subcategory(parse_words(&prefix, value)?),
+                        "tag" => {
+                            parse_words(&prefix, value)?;
+                            Filter::Tag(value.to_lowercase())
+                        }
+                        "status" => {
+                            let status = value.to_lowercase();
+                            if !STATUSES.contains(&status.as_str()) {
+                                bail!(
+                                    "Invalid status in search query: {value} (expected one of: {})",
+                                    STATUSES.join(", ")
+                                );
+                            }
+                            Filter::Status(status)
+                        }
+                        "amount" => {
+                            let (comparison, amount) = Comparison::parse(value);
+                            let amount = Decimal::from_str(amount.trim()).with_context(|| {
+                                format!("Invalid amount in search query: {value}")
+                            })?;
+                            Filter::Amount(comparison, amount)
+                        }
+                        "date" => {
+                            let (comparison, date) = Comparison::parse(value);
+                            Filter::Date(comparison, parse_date(date.trim())?)
+                        }
+                        "after" => Filter::Date(Comparison::Ge, parse_date(value)?),
+                        "before" => Filter::Date(Comparison::Lt, parse_date(value)?),
+                        "account" => Filter::Account(value.to_string()),
+                        "connection" => Filter::Connection(value.to_string()),
+                        _ => unreachable!("lex only accepts known fields"),
+                    }
+                }
+            };
+            terms.push(Term { negated, filter });
+        }
+        Ok(Self { terms })
+    }
+}
+
+/// Whether every word in `words` starts a word of one of `texts`.
+fn words_match<'a>(words: &[String], texts: impl IntoIterator<Item = &'a str>) -> bool {
+    let text_words: Vec<String> = texts.into_iter().flat_map(search_words).collect();
+    words.iter().all(|word| {
+        text_words
+            .iter()
+            .any(|text_word| text_word.starts_with(word.as_str()))
+    })
+}
+
+/// An account or connection by exact id, or by the words of its name.
+fn names(value: &str, id: &Id, name: &str) -> bool {
+    if id.as_str() == value {
+        return true;
+    }
+    let words: Vec<String> = search_words(value).collect();
+    !words.is_empty() && words_match(&words, [name])
+}
+
+impl SearchQuery {
+    /// Word prefixes every match must contain somewhere in its text, for
+    /// narrowing the search with the index.
+    fn required_words(&self) -> Vec<String> {
+        let mut required = Vec::new();
+        for term in self.terms.iter().filter(|term| !term.negated) {
+            match &term.filter {
+                Filter::Text(words)
+                | Filter::Description(words)
+                | Filter::Note(words)
+                | Filter::Merchant(words)
+                | Filter::Category(words)
+                | Filter::Subcategory(words) => required.extend(words.iter().cloned()),
+                Filter::Tag(tag) => required.extend(search_words(tag)),
+                _ => {}
+            }
+        }
+        required.sort();
+        required.dedup();
+        required
+    }
+
+    /// The tightest date range the query allows.
+    fn date_bounds(&self) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        let (mut start, mut end): (Option<NaiveDate>, Option<NaiveDate>) = (None, None);
+        for term in self.terms.iter().filter(|term| !term.negated) {
+            let Filter::Date(comparison, date) = term.filter else {
+                continue;
+            };
+            let (lower, upper) = match comparison {
+                Comparison::Lt => (None, date.pred_opt()),
+                Comparison::Le => (None, Some(date)),
+                Comparison::Eq => (Some(date), Some(date)),
+                Comparison::Ge => (Some(date), None),
+                Comparison::Gt => (date.succ_opt(), None),
+            };
+            start = start.max(lower);
+            end = match (end, upper) {
+                (Some(end), Some(upper)) => Some(end.min(upper)),
+                (end, upper) => end.or(upper),
+            };
+        }
+        (start, end)
+    }
+
+    /// Whether the query can match anything in the account, going by the
+    /// account and connection terms alone.
+    fn may_match_account(&self, account: &Account, connection: Option<&Connection>) -> bool {
+        self.terms.iter().all(|term| {
+            let matched = match &term.filter {
+                Filter::Account(value) => names(value, &account.id, &account.name),
+                Filter::Connection(value) => connection.is_some_and(|connection| {
+                    names(value, connection.id(), &connection.config.name)
+                }),
+                _ => return true,
+            };
+            matched != term.negated
+        })
+    }
+
+    fn matches(&self, tx: &Transaction, ann: Option<&TransactionAnnotation>) -> bool {
+        let merchant = tx
+            .standardized_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.merchant_name.as_deref());
+        let note = ann.and_then(|ann| ann.note.as_deref());
+        let category = effective_category(tx, ann);
+        let subcategory = ann.and_then(|ann| ann.subcategory.as_deref());
+        let tags = ann.and_then(|ann| ann.tags.as_deref()).unwrap_or_default();
+        let descriptions = [
+            Some(tx.description.as_str()),
+            ann.and_then(|ann| ann.description.as_deref()),
+        ];
+
+        self.terms.iter().all(|term| {
+            let matched = match &term.filter {
+                Filter::Text(words) => words_match(
+                    words,
+                    descriptions
+                        .into_iter()
+                        .chain([note, merchant, category, subcategory])
+                        .flatten()
+                        .chain(tags.iter().map(String::as_str)),
+                ),
+                Filter::Description(words) => {
+                    words_match(words, descriptions.into_iter().flatten())
+                }
+                Filter::Note(words) => words_match(words, note),
+                Filter::Merchant(words) => {
+                    words_match(words, [merchant.unwrap_or(&tx.description)])
+                }
+                Filter::Category(words) => words_match(words, category),
+                Filter::Subcategory(words) => words_match(words, subcategory),
+                Filter::Tag(tag) => tags.iter().any(|t| t.trim().to_lowercase() == *tag),
+                Filter::Status(status) => format!("{:?}", tx.status).to_lowercase() == *status,
+                Filter::Amount(comparison, amount) => Decimal::from_str(&tx.amount)
+                    .is_ok_and(|tx_amount| comparison.holds(&tx_amount, amount)),
+                Filter::Date(comparison, date) => {
+                    comparison.holds(&transaction_date(tx, ann), date)
+                }
+                // Checked per account.
+                Filter::Account(_) | Filter::Connection(_) => return true,
+            };
+            matched != term.negated
+        })
+    }
+}
+
+/// Transactions matching `query`, oldest first, across the whole history.
+/// With `skip_ignored`, the ignore rules apply as in `list transactions`.
+pub async fn search_transactions(
+    storage: &dyn Storage,
+    query: &str,
+    sort_by_amount: bool,
+    skip_ignored: bool,
+    config: &ResolvedConfig,
+) -> Result<Vec<TransactionOutput>> {
+    let query: SearchQuery = query.parse()?;
+    let required_words = query.required_words();
+    let (start, end) = query.date_bounds();
+
+    let outputs = TransactionOutputs::new(storage, config, skip_ignored).await?;
+    let mut output = Vec::new();
+    for account in storage.list_accounts().await? {
+        if outputs.skips_account(&account)
+            || !query.may_match_account(&account, outputs.connection(&account))
+        {
+            continue;
+        }
+
+        let annotations_by_tx: HashMap<Id, TransactionAnnotation> = materialize_annotations(
+            storage
+                .get_transaction_annotation_patches(&account.id)
+                .await?,
+        );
+        let transactions = if required_words.is_empty() {
+            transactions_for_dates(storage, &account.id, start, end, &annotations_by_tx).await?
+        } else {
+            storage
+                .find_transactions(&account.id, &required_words)
+                .await?
+        };
+
+        for tx in transactions {
+            let ann = annotations_by_tx.get(&tx.id);
+            if query.matches(&tx, ann) {
+                output.extend(outputs.output(&account, &tx, ann));
+            }
+        }
+    }
+
+    output.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    if sort_by_amount {
+        sort_outputs_by_amount(&mut output);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(s: &str) -> Vec<Term> {
+        s.parse::<SearchQuery>().unwrap().terms
+    }
+
+    fn term(filter: Filter) -> Term {
+        Term {
+            negated: false,
+            filter,
+        }
+    }
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_fields_phrases_and_negation() {
+        assert_eq!(
+            query(
+                r#"merchant:starbucks amount:<-20 -tag:travel after:2026-01-01 account:"Chase Sapphire" "blue bottle""#
+            ),
+            vec![
+                term(Filter::Merchant(words(&["starbucks"]))),
+                term(Filter::Amount(Comparison::Lt, Decimal::new(-20, 0))),
+                Term {
+                    negated: true,
+                    filter: Filter::Tag("travel".to_string()),
+                },
+                term(Filter::Date(
+                    Comparison::Ge,
+                    NaiveDate::from_ymd_opt(2026, 1, 1).unwrap()
+                )),
+                term(Filter::Account("Chase Sapphire".to_string())),
+                term(Filter::Text(words(&["blue", "bottle"]))),
+            ]
+        );
+        assert_eq!(
+            query("Status:Pending amount:>=10.5"),
+            vec![
+                term(Filter::Status("pending".to_string())),
+                term(Filter::Amount(Comparison::Ge, Decimal::new(105, 1))),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_queries() {
+        for bad in [
+            "vendor:acme",
+            "amount:lots",
+            "after:yesterday",
+            "status:settled",
+            "\"open",
+            "coffee -",
+            "category:",
+        ] {
+            assert!(bad.parse::<SearchQuery>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn date_bounds_intersect() {
+        let q: SearchQuery = "after:2026-01-01 before:2026-02-01 date:<=2026-01-20"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            q.date_bounds(),
+            (
+                NaiveDate::from_ymd_opt(2026, 1, 1),
+                NaiveDate::from_ymd_opt(2026, 1, 20)
+            )
+        );
+    }
+}
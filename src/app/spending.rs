@@ -13,6 +13,7 @@ use crate::models::{Account, Asset, Id, TransactionAnnotation, TransactionStatus
 use crate::storage::{find_account, find_connection, Storage};
 
 use super::ignore_rules::{TransactionIgnoreInput, TransactionIgnoreMatcher};
+use super::list::transactions_for_dates;
 use super::types::{
     SpendingBreakdownEntryOutput, SpendingOutput, SpendingPeriodOutput, SpendingScopeOutput,
 };
@@ -581,6 +582,7 @@ async fn spending_report_with_store(
     // Setup market data service (store-only).
     let market_data = MarketDataServiceBuilder::new(store, config.data_dir.clone())
         .with_quote_staleness(config.refresh.price_staleness)
+        .with_fx_pivots(config.refresh.fx_pivots.clone())
         .with_lookback_days(opts.lookback_days)
         .offline_only()
         .build()
@@ -629,7 +631,6 @@ async fn spending_report_with_store(
             .map(|c| c.config.synchronizer.as_str())
             .unwrap_or_default();
 
-        let transactions = storage.get_transactions(account_id).await?;
         let patches = storage
             .get_transaction_annotation_patches(account_id)
             .await?;
@@ -643,6 +644,21 @@ async fn spending_report_with_store(
                 .or_insert_with(|| TransactionAnnotation::new(tx_id));
             patch.apply_to(ann);
         }
+        // Without a start date the range begins at the earliest transaction,
+        // so the whole history is needed.
+        let transactions = match start_date_opt {
+            Some(start) => {
+                transactions_for_dates(
+                    storage,
+                    account_id,
+                    Some(start),
+                    end_date_opt,
+                    &annotations_by_tx,
+                )
+                .await?
+            }
+            None => storage.get_transactions(account_id).await?,
+        };
 
         for tx in transactions {
             let status = format!("{:?}", tx.status).to_lowercase();
@@ -997,6 +1013,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: crate::config::GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let out = spending_report_with_store(
@@ -1085,6 +1102,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: crate::config::GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let out = spending_report_with_store(
@@ -1190,6 +1208,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: crate::config::GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let out = spending_report_with_store(
@@ -1291,6 +1310,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: crate::config::GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let out = spending_report_with_store(
@@ -1421,6 +1441,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: crate::config::GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let out = spending_report_with_store(
@@ -1517,6 +1538,7 @@ mod tests {
             },
             ai: crate::config::AiConfig::default(),
             git: crate::config::GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let out = spending_report_with_store(
@@ -1602,6 +1624,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: crate::config::GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let out = spending_report_with_store(
@@ -1688,6 +1711,7 @@ mod tests {
             ignore: crate::config::IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: crate::config::GitConfig::default(),
+            profiles: Default::default(),
         };
 
         let out = spending_report_with_store(
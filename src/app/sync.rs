@@ -2,18 +2,23 @@ use std::collections::HashMap;
 use std::io::{self, Write};
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 
+use crate::clock::SystemClock;
 use crate::config::ResolvedConfig;
-use crate::market_data::{JsonlMarketDataStore, MarketDataServiceBuilder};
+use crate::credentials::SessionCache;
+use crate::market_data::{IntradayQuoteStore, JsonlMarketDataStore, MarketDataServiceBuilder};
 use crate::models::{Connection, Id};
-use crate::storage::{CompactionStorage, MetadataBackfillStorage, Storage, SymlinkStorage};
+use crate::storage::{
+    find_connection, CompactionStorage, MetadataBackfillStorage, Storage, SymlinkStorage,
+};
 use crate::sync::{
-    AuthPrompter, DefaultSynchronizerFactory, FixedAuthPrompter, GitAutoCommitter, SyncContext,
-    SyncOptions, SyncOutcome, SyncService, TransactionSyncMode,
+    revert_sync_batch, AuthPrompter, DefaultSynchronizerFactory, FixedAuthPrompter,
+    GitAutoCommitter, SyncConcurrency, SyncContext, SyncOptions, SyncOutcome, SyncPreview,
+    SyncRevertResult, SyncService, TransactionSyncMode,
 };
 
-use super::maybe_auto_commit;
+use super::{maybe_auto_commit, SessionOutput};
 
 struct StdinPrompter;
 
@@ -54,11 +59,15 @@ async fn build_sync_service_with_quote_staleness(
     config: &ResolvedConfig,
     quote_staleness_override: Option<std::time::Duration>,
 ) -> SyncService {
-    let quote_staleness = quote_staleness_override.unwrap_or(config.refresh.price_staleness);
-    let market_data = MarketDataServiceBuilder::for_data_dir(&config.data_dir)
-        .with_quote_staleness(quote_staleness)
-        .build()
-        .await;
+    let mut quote_staleness = quote_staleness_override.unwrap_or(config.refresh.price_staleness);
+    let mut builder = MarketDataServiceBuilder::for_data_dir(&config.data_dir);
+    let intraday = &config.refresh.intraday;
+    if intraday.enabled {
+        // Refresh quotes at least once per interval so each sync records one.
+        quote_staleness = quote_staleness.min(intraday.interval);
+        builder = builder.with_intraday(intraday.interval);
+    }
+    let market_data = builder.with_quote_staleness(quote_staleness).build().await;
     let auth_prompter: Arc<dyn AuthPrompter> = if env_enabled("KEEPBOOK_AUTO_LOGIN") {
         Arc::new(FixedAuthPrompter::allow())
     } else if env_enabled("KEEPBOOK_NONINTERACTIVE") {
@@ -70,6 +79,11 @@ async fn build_sync_service_with_quote_staleness(
     let auto_push = config.git.auto_push && !env_disabled("KEEPBOOK_DISABLE_AUTO_PUSH");
     let context = SyncContext::new(storage, market_data, config.reporting_currency.clone())
         .with_auth_prompter(auth_prompter)
+        .with_concurrency(SyncConcurrency {
+            max: config.refresh.sync_concurrency,
+            per_synchronizer: config.refresh.synchronizer_concurrency.clone(),
+        })
+        .with_reconcile_after_sync(config.refresh.reconcile_after_sync)
         .with_auto_committer(Arc::new(GitAutoCommitter::new(
             config.data_dir.clone(),
             auto_commit,
@@ -103,6 +117,17 @@ fn sync_outcome_to_json(outcome: SyncOutcome) -> serde_json::Value {
             if connection.config.synchronizer == "chase" {
                 output["downloaded"] = connection.state.synchronizer_data.clone();
             }
+            if !report.reconcile_warnings.is_empty() {
+                output["reconcile_warnings"] = report
+                    .reconcile_warnings
+                    .iter()
+                    .map(|(account_id, discrepancy)| {
+                        let mut warning = serde_json::json!(discrepancy);
+                        warning["account_id"] = serde_json::json!(account_id);
+                        warning
+                    })
+                    .collect();
+            }
             output
         }
         SyncOutcome::SkippedManual { connection } => serde_json::json!({
@@ -306,6 +331,19 @@ pub async fn sync_connection(
     Ok(sync_outcome_to_json(outcome))
 }
 
+pub async fn sync_connection_dry_run(
+    storage: Arc<dyn Storage>,
+    config: &ResolvedConfig,
+    id_or_name: &str,
+    transactions: TransactionSyncMode,
+) -> Result<SyncPreview> {
+    let service = build_sync_service(storage, config).await;
+    let options = SyncOptions { transactions };
+    service
+        .preview_connection_with_options(id_or_name, &options)
+        .await
+}
+
 pub async fn sync_connection_if_stale(
     storage: Arc<dyn Storage>,
     config: &ResolvedConfig,
@@ -441,6 +479,23 @@ pub async fn sync_prices(
         _ => anyhow::bail!("Invalid sync prices scope"),
     };
 
+    let intraday_json = if config.refresh.intraday.enabled {
+        let stats = IntradayQuoteStore::new(&config.data_dir)
+            .compact(
+                &JsonlMarketDataStore::new(&config.data_dir),
+                config.refresh.intraday.retention,
+                chrono::Utc::now(),
+            )
+            .await?;
+        serde_json::json!({
+            "days_compacted": stats.days_compacted,
+            "quotes_removed": stats.quotes_removed,
+            "daily_prices_written": stats.daily_prices_written,
+        })
+    } else {
+        serde_json::Value::Null
+    };
+
     let scope_json = match (scope, target) {
         (PriceSyncScope::All, _) => serde_json::json!({ "type": "all" }),
         (PriceSyncScope::Connection, Some(t)) => {
@@ -458,6 +513,7 @@ pub async fn sync_prices(
         "force": force,
         "quote_staleness_override_seconds": quote_staleness_override.map(|d| d.as_secs()),
         "result": price_refresh_result_to_json(result),
+        "intraday_compaction": intraday_json,
     }))
 }
 
@@ -492,6 +548,19 @@ pub async fn sync_recompact(
     }))
 }
 
+pub async fn sync_revert<S>(
+    storage: &S,
+    config: &ResolvedConfig,
+    batch_id: &str,
+) -> Result<SyncRevertResult>
+where
+    S: Storage + CompactionStorage + ?Sized,
+{
+    let result = revert_sync_batch(storage, &Id::from_string(batch_id), &SystemClock).await?;
+    maybe_auto_commit(config, &format!("sync revert {batch_id}"));
+    Ok(result)
+}
+
 pub async fn sync_backfill_metadata(
     storage: &dyn MetadataBackfillStorage,
     config: &ResolvedConfig,
@@ -530,3 +599,59 @@ pub async fn chase_login(
         "message": "Session captured successfully"
     }))
 }
+
+fn unix_to_rfc3339(secs: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp(secs, 0).map(|at| at.to_rfc3339())
+}
+
+/// Summarize cached auth sessions, labelled with their connections where
+/// they still exist.
+pub async fn auth_sessions_list(
+    storage: &dyn Storage,
+    sessions: &SessionCache,
+) -> Result<Vec<SessionOutput>> {
+    let now = chrono::Utc::now().timestamp();
+    let mut output = Vec::new();
+    for summary in sessions.list()? {
+        let connection = storage
+            .get_connection(&Id::from_string(summary.key.clone()))
+            .await?;
+        output.push(SessionOutput {
+            connection_id: summary.key,
+            connection_name: connection.map(|c| c.config.name),
+            encrypted: summary.encrypted,
+            captured_at: summary.captured_at.and_then(unix_to_rfc3339),
+            age_secs: summary.captured_at.map(|at| now - at),
+            expires_at: summary.expires_at.and_then(unix_to_rfc3339),
+            expired: summary.expires_at.is_some_and(|at| now >= at),
+            has_token: summary.has_token,
+            cookie_count: summary.cookie_count,
+            error: summary.error,
+        });
+    }
+    Ok(output)
+}
+
+/// Delete the cached session for one connection, or every session.
+pub async fn auth_sessions_clear(
+    storage: &dyn Storage,
+    sessions: &SessionCache,
+    id_or_name: Option<&str>,
+) -> Result<serde_json::Value> {
+    let keys = match id_or_name {
+        Some(id_or_name) => {
+            let connection = find_connection(storage, id_or_name)
+                .await?
+                .with_context(|| format!("Connection not found: {id_or_name}"))?;
+            vec![connection.id().to_string()]
+        }
+        None => sessions.keys()?,
+    };
+    for key in &keys {
+        sessions.delete(key)?;
+    }
+    Ok(serde_json::json!({
+        "success": true,
+        "cleared": keys,
+    }))
+}
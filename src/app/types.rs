@@ -1,6 +1,7 @@
 use serde::Serialize;
 
-use crate::models::{Asset, TransactionStandardizedMetadata};
+use crate::models::{Asset, SyncRunOutcome, TransactionStandardizedMetadata};
+use crate::reconcile::Discrepancy;
 
 /// JSON output for connections
 #[derive(Serialize)]
@@ -11,6 +12,60 @@ pub struct ConnectionOutput {
     pub status: String,
     pub account_count: usize,
     pub last_sync: Option<String>,
+    /// Consecutive failed syncs, from the run journal.
+    pub failure_streak: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failing_since: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// JSON output for one entry of a connection's sync run journal
+#[derive(Serialize)]
+pub struct SyncRunOutput {
+    pub connection_id: String,
+    pub connection_name: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub duration_ms: i64,
+    pub outcome: SyncRunOutcome,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
+    pub accounts_added: usize,
+    pub accounts_changed: usize,
+    pub balances_recorded: usize,
+    pub transactions_added: usize,
+    pub transactions_changed: usize,
+    pub prices_refreshed: usize,
+}
+
+/// JSON output for `auth sessions list`; never includes tokens or cookies
+#[derive(Serialize)]
+pub struct SessionOutput {
+    pub connection_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connection_name: Option<String>,
+    pub encrypted: bool,
+    pub captured_at: Option<String>,
+    pub age_secs: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    pub expired: bool,
+    pub has_token: bool,
+    pub cookie_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// JSON output for `reconcile`, one per account
+#[derive(Serialize)]
+pub struct ReconcileOutput {
+    pub account_id: String,
+    pub account_name: String,
+    pub windows_checked: usize,
+    pub discrepancies: Vec<Discrepancy>,
 }
 
 /// JSON output for accounts
@@ -33,6 +88,19 @@ pub struct PriceSourceOutput {
     pub enabled: bool,
     pub priority: u32,
     pub has_credentials: bool,
+    /// `ok`, `cooling_down` or `quota_exhausted`.
+    pub health: String,
+    pub consecutive_failures: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_success: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cooldown_until: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daily_quota: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_quota: Option<u32>,
 }
 
 /// JSON output for balances
@@ -123,6 +191,41 @@ pub struct TransactionAnnotationPatchOutput {
     pub effective_date: Option<Option<String>>,
 }
 
+/// Every stored version of a transaction and every annotation patch to it,
+/// oldest first.
+#[derive(Serialize)]
+pub struct TransactionHistoryOutput {
+    pub transaction_id: String,
+    pub account_id: String,
+    pub account_name: String,
+    pub entries: Vec<TransactionHistoryEntryOutput>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransactionHistoryEntryOutput {
+    /// A row of the transaction log.
+    Version {
+        /// When the sync that wrote it ran, if it came from one.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        recorded_at: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sync_batch: Option<String>,
+        timestamp: String,
+        description: String,
+        amount: String,
+        asset: serde_json::Value,
+        status: String,
+    },
+    /// A row of the annotation log, with the annotation as it stood after it.
+    AnnotationPatch {
+        recorded_at: String,
+        patch: TransactionAnnotationPatchOutput,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        annotation: Option<TransactionAnnotationOutput>,
+    },
+}
+
 /// Scope output for spending report.
 #[derive(Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -318,3 +421,94 @@ pub struct PriceHistoryOutput {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub failures: Vec<PriceHistoryFailure>,
 }
+
+/// A contiguous run of missing closes
+#[derive(Serialize)]
+pub struct MarketDataGapRangeOutput {
+    pub start_date: String,
+    pub end_date: String,
+    pub missing_days: usize,
+}
+
+/// Price coverage for one held asset
+#[derive(Serialize)]
+pub struct AssetGapOutput {
+    pub asset: Asset,
+    pub asset_id: String,
+    pub expected_days: usize,
+    pub covered_days: usize,
+    pub missing_days: usize,
+    pub ranges: Vec<MarketDataGapRangeOutput>,
+}
+
+/// FX coverage for one currency pair
+#[derive(Serialize)]
+pub struct FxGapOutput {
+    pub base: String,
+    pub quote: String,
+    pub expected_days: usize,
+    pub covered_days: usize,
+    pub missing_days: usize,
+    pub ranges: Vec<MarketDataGapRangeOutput>,
+}
+
+/// Result of executing a backfill plan
+#[derive(Serialize)]
+pub struct BackfillOutput {
+    pub resumed: bool,
+    pub planned_calls: usize,
+    pub completed_calls: usize,
+    pub failed_calls: usize,
+    pub fetched_points: usize,
+    pub calls: Vec<crate::market_data::BackfillCall>,
+}
+
+/// Output for market-data gaps command
+#[derive(Serialize)]
+pub struct MarketDataGapsOutput {
+    pub scope: PriceHistoryScopeOutput,
+    pub currency: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub assets: Vec<AssetGapOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fx: Option<Vec<FxGapOutput>>,
+    pub missing_days: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill: Option<BackfillOutput>,
+}
+
+/// How bad a `doctor` finding is.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DoctorSeverity {
+    /// Data keepbook can't read.
+    Error,
+    /// Inconsistent data that reads fine but is probably wrong.
+    Warning,
+    Info,
+}
+
+/// One `doctor` finding
+#[derive(Serialize)]
+pub struct DoctorIssue {
+    pub severity: DoctorSeverity,
+    pub code: String,
+    /// Relative to the data dir.
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub message: String,
+    /// Whether `--fix` repaired it.
+    pub fixed: bool,
+}
+
+/// Output for `doctor`; the counts exclude fixed issues
+#[derive(Serialize)]
+pub struct DoctorOutput {
+    pub data_dir: String,
+    pub errors: usize,
+    pub warnings: usize,
+    pub fixed: usize,
+    pub issues: Vec<DoctorIssue>,
+}
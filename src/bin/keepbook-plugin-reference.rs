@@ -0,0 +1,177 @@
+//! Reference `exec:` synchronizer plugin.
+//!
+//! Serves fixed demo data over the keepbook plugin protocol (see
+//! `keepbook::sync::plugin`). It asks for an `api-key` credential, keeps a
+//! sync counter in its connection state, and returns older history when a
+//! full transaction sync is requested. Use it as a starting point for new
+//! plugins and as the subject of the conformance harness.
+
+use std::io::{self, BufRead, Write};
+
+use anyhow::{bail, Context, Result};
+use chrono::{TimeZone, Utc};
+use keepbook::models::{Asset, TransactionStatus};
+use keepbook::sync::plugin::{
+    HostMessage, PluginAccount, PluginAccountBalances, PluginAccountTransactions, PluginBalance,
+    PluginMessage, PluginPrice, PluginTransaction, PluginTransactionMode, SyncResponse,
+    PROTOCOL_VERSION,
+};
+
+fn send(out: &mut impl Write, message: &PluginMessage) -> Result<()> {
+    writeln!(out, "{}", serde_json::to_string(message)?)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn read(lines: &mut impl Iterator<Item = io::Result<String>>) -> Result<HostMessage> {
+    let line = lines.next().context("keepbook closed stdin")??;
+    serde_json::from_str(&line).context("invalid message from keepbook")
+}
+
+fn transaction(
+    id: &str,
+    day: (i32, u32, u32),
+    amount: &str,
+    description: &str,
+) -> PluginTransaction {
+    PluginTransaction {
+        id: id.to_string(),
+        timestamp: Utc
+            .with_ymd_and_hms(day.0, day.1, day.2, 12, 0, 0)
+            .single()
+            .expect("valid date"),
+        amount: amount.to_string(),
+        asset: Asset::currency("USD"),
+        description: description.to_string(),
+        status: TransactionStatus::Posted,
+        synchronizer_data: serde_json::Value::Null,
+        standardized_metadata: None,
+    }
+}
+
+fn run() -> Result<()> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut out = io::stdout().lock();
+
+    let HostMessage::Sync(request) = read(&mut lines)? else {
+        bail!("expected a sync request");
+    };
+    if request.protocol_version != PROTOCOL_VERSION {
+        send(
+            &mut out,
+            &PluginMessage::Error {
+                message: format!("unsupported protocol version {}", request.protocol_version),
+            },
+        )?;
+        return Ok(());
+    }
+
+    send(
+        &mut out,
+        &PluginMessage::CredentialRequest {
+            key: "api-key".to_string(),
+        },
+    )?;
+    let HostMessage::Credential { value, .. } = read(&mut lines)? else {
+        bail!("expected a credential reply");
+    };
+    if value.is_none() {
+        send(
+            &mut out,
+            &PluginMessage::Error {
+                message: "missing api-key credential".to_string(),
+            },
+        )?;
+        return Ok(());
+    }
+
+    let sync_count = request
+        .connection
+        .synchronizer_data
+        .get("sync_count")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0)
+        + 1;
+    send(
+        &mut out,
+        &PluginMessage::Log {
+            level: Some("info".to_string()),
+            message: format!("reference plugin sync #{sync_count}"),
+        },
+    )?;
+
+    let mut checking_txns = vec![
+        transaction("txn-2", (2026, 1, 15), "-42.50", "Coffee Roasters"),
+        transaction("txn-3", (2026, 1, 31), "2500.00", "Payroll"),
+    ];
+    if request.options.transactions == PluginTransactionMode::Full {
+        checking_txns.insert(0, transaction("txn-1", (2025, 6, 1), "-19.99", "Bookshop"));
+    }
+
+    let response = SyncResponse {
+        protocol_version: PROTOCOL_VERSION,
+        accounts: vec![
+            PluginAccount {
+                id: "checking".to_string(),
+                name: "Reference Checking".to_string(),
+                tags: vec!["reference".to_string()],
+                active: true,
+                synchronizer_data: serde_json::json!({ "kind": "depository" }),
+            },
+            PluginAccount {
+                id: "brokerage".to_string(),
+                name: "Reference Brokerage".to_string(),
+                tags: vec!["reference".to_string(), "brokerage".to_string()],
+                active: true,
+                synchronizer_data: serde_json::json!({ "kind": "investment" }),
+            },
+        ],
+        balances: vec![
+            PluginAccountBalances {
+                account_id: "checking".to_string(),
+                balances: vec![PluginBalance {
+                    asset: Asset::currency("USD"),
+                    amount: "1234.56".to_string(),
+                    cost_basis: None,
+                    price: None,
+                }],
+            },
+            PluginAccountBalances {
+                account_id: "brokerage".to_string(),
+                balances: vec![
+                    PluginBalance {
+                        asset: Asset::equity("VTI"),
+                        amount: "10".to_string(),
+                        cost_basis: Some("2000.00".to_string()),
+                        price: Some(PluginPrice {
+                            price: "250.00".to_string(),
+                            quote_currency: "USD".to_string(),
+                            as_of_date: None,
+                            timestamp: None,
+                        }),
+                    },
+                    PluginBalance {
+                        asset: Asset::currency("USD"),
+                        amount: "100".to_string(),
+                        cost_basis: None,
+                        price: None,
+                    },
+                ],
+            },
+        ],
+        transactions: vec![PluginAccountTransactions {
+            account_id: "checking".to_string(),
+            transactions: checking_txns,
+        }],
+        synchronizer_data: serde_json::json!({ "sync_count": sync_count }),
+    };
+    send(&mut out, &PluginMessage::Result(response))
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("keepbook-plugin-reference: {e:#}");
+        std::process::exit(1);
+    }
+}
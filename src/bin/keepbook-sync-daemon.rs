@@ -7,18 +7,23 @@ use std::time::Duration;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Local, NaiveDate};
 use clap::Parser;
+use futures::{StreamExt, TryStreamExt};
 use keepbook::app;
 use keepbook::config::{default_config_path, ResolvedConfig};
 use keepbook::format::{currency_symbol, format_base_currency_display};
-use keepbook::storage::{JsonFileStorage, Storage};
+use keepbook::storage::{
+    prepare_for_write, storage_event_channel, watch_data_dir, DataDirLock, JsonFileStorage,
+    LockMode, ObservableStorage, Storage, StorageEventSender, TransactionOrder,
+    DEFAULT_LOCK_TIMEOUT,
+};
 use keepbook::sync::TransactionSyncMode;
 use ksni::menu::*;
 use ksni::MenuItem;
 use ksni::TrayMethods;
-use notify::{Config as NotifyConfig, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use rand::Rng;
 use rust_decimal::Decimal;
 use std::str::FromStr;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::mpsc::{self, UnboundedSender};
 use tracing::{info, warn};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
@@ -111,33 +116,6 @@ fn format_spending_window_label(days: u32) -> String {
     }
 }
 
-fn should_refresh_for_fs_event_kind(kind: &EventKind) -> bool {
-    matches!(
-        kind,
-        EventKind::Any | EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
-    )
-}
-
-fn start_data_dir_watcher(
-    data_dir: &Path,
-    refresh_signal_tx: mpsc::UnboundedSender<()>,
-) -> notify::Result<RecommendedWatcher> {
-    let mut watcher =
-        notify::recommended_watcher(move |result: notify::Result<notify::Event>| match result {
-            Ok(event) => {
-                if should_refresh_for_fs_event_kind(&event.kind) {
-                    let _ = refresh_signal_tx.send(());
-                }
-            }
-            Err(err) => {
-                warn!(error = %err, "data directory watch event failed");
-            }
-        })?;
-    watcher.configure(NotifyConfig::default())?;
-    watcher.watch(data_dir, RecursiveMode::Recursive)?;
-    Ok(watcher)
-}
-
 #[derive(Parser, Debug)]
 #[command(name = "keepbook-sync-daemon")]
 #[command(version = CLI_VERSION)]
@@ -1248,6 +1226,8 @@ async fn apply_tray_state(
 struct Daemon {
     storage: Arc<dyn Storage>,
     symlink_storage: JsonFileStorage,
+    /// Fed by `storage` and by the data dir watcher.
+    storage_events: StorageEventSender,
     config: ResolvedConfig,
     interval: Duration,
     jitter: Duration,
@@ -1366,16 +1346,25 @@ impl Daemon {
             let mut rows: Vec<TxRow> = Vec::new();
 
             for account in &accounts {
-                let txns = self.storage.get_transactions(&account.id).await?;
                 let source = account_conn_name
                     .get(&account.id.to_string())
                     .cloned()
                     .unwrap_or_else(|| "Unknown".to_string());
 
+                // Only this account's newest few can make the overall cut.
+                let txns: Vec<_> = self
+                    .storage
+                    .stream_transactions(
+                        &account.id,
+                        Some(cutoff),
+                        None,
+                        TransactionOrder::NewestFirst,
+                    )
+                    .await?
+                    .take(self.transaction_count)
+                    .try_collect()
+                    .await?;
                 for tx in txns {
-                    if tx.timestamp < cutoff {
-                        continue;
-                    }
                     rows.push(TxRow {
                         timestamp: tx.timestamp,
                         source: source.clone(),
@@ -1525,7 +1514,15 @@ impl Daemon {
     }
 
     async fn open_portfolio_graph(&self) -> Result<PathBuf> {
-        let history = self.portfolio_graph_history().await?;
+        let history = {
+            let _lock = DataDirLock::acquire(
+                &self.config.data_dir,
+                LockMode::Shared,
+                DEFAULT_LOCK_TIMEOUT,
+            )
+            .await?;
+            self.portfolio_graph_history().await?
+        };
         let html = render_portfolio_graph_html(&history, &self.config)?;
         let output_path = portfolio_graph_output_path()?;
         std::fs::write(&output_path, html)
@@ -1545,6 +1542,13 @@ impl Daemon {
         apply_tray_state(tray_handle, state).await;
 
         let cycle_result = async {
+            let _lock = DataDirLock::acquire(
+                &self.config.data_dir,
+                LockMode::Exclusive,
+                DEFAULT_LOCK_TIMEOUT,
+            )
+            .await?;
+
             app::run_preflight(
                 &self.config,
                 app::PreflightOptions {
@@ -1552,6 +1556,7 @@ impl Daemon {
                     pull_remote: self.config.git.pull_before_edit,
                 },
             )?;
+            prepare_for_write(&self.config.data_dir)?;
 
             let sync_json =
                 app::sync_all_if_stale(self.storage.clone(), &self.config, TransactionSyncMode::Auto)
@@ -1622,24 +1627,36 @@ impl Daemon {
         state: &mut KeepbookTrayState,
         tray_handle: &mut Option<ksni::Handle<KeepbookTray>>,
     ) {
+        self.refresh_tray_lines(state).await;
+        apply_tray_state(tray_handle, state).await;
+    }
+
+    async fn refresh_tray_lines(&self, state: &mut KeepbookTrayState) {
+        let _lock = match DataDirLock::acquire(
+            &self.config.data_dir,
+            LockMode::Shared,
+            DEFAULT_LOCK_TIMEOUT,
+        )
+        .await
+        {
+            Ok(lock) => lock,
+            Err(err) => {
+                warn!(error = %err, "skipping tray refresh");
+                return;
+            }
+        };
         self.refresh_history_lines(state).await;
         self.refresh_portfolio_breakdown_lines(state).await;
         self.refresh_graph_lines(state).await;
         self.refresh_spending_lines(state).await;
         self.refresh_transaction_lines(state).await;
-        apply_tray_state(tray_handle, state).await;
     }
 
     async fn run(self) -> Result<()> {
         let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel();
 
         let mut tray_state = KeepbookTrayState::default();
-        self.refresh_history_lines(&mut tray_state).await;
-        self.refresh_portfolio_breakdown_lines(&mut tray_state)
-            .await;
-        self.refresh_graph_lines(&mut tray_state).await;
-        self.refresh_spending_lines(&mut tray_state).await;
-        self.refresh_transaction_lines(&mut tray_state).await;
+        self.refresh_tray_lines(&mut tray_state).await;
 
         let mut tray_handle = match KeepbookTray::new(tray_state.clone(), cmd_tx)
             .assume_sni_available(true)
@@ -1668,8 +1685,8 @@ impl Daemon {
         refresh_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
         refresh_tick.tick().await;
 
-        let (watch_refresh_tx, mut watch_refresh_rx) = mpsc::unbounded_channel::<()>();
-        let watcher = match start_data_dir_watcher(&self.config.data_dir, watch_refresh_tx) {
+        let mut storage_events = self.storage_events.subscribe();
+        let _watcher = match watch_data_dir(&self.config.data_dir, self.storage_events.clone()) {
             Ok(watcher) => {
                 info!(
                     path = %self.config.data_dir.display(),
@@ -1706,8 +1723,10 @@ impl Daemon {
                 _ = refresh_tick.tick() => {
                     self.refresh_tray_state(&mut tray_state, &mut tray_handle).await;
                 }
-                Some(()) = watch_refresh_rx.recv(), if watcher.is_some() => {
-                    while watch_refresh_rx.try_recv().is_ok() {}
+                event = storage_events.recv() => {
+                    if let Err(RecvError::Closed) = event {
+                        continue;
+                    }
                     data_watch_debounce
                         .as_mut()
                         .reset(tokio::time::Instant::now() + DATA_WATCH_DEBOUNCE);
@@ -1794,7 +1813,11 @@ async fn main() -> Result<()> {
     }
 
     let storage_impl = JsonFileStorage::new(&config.data_dir);
-    let storage: Arc<dyn Storage> = Arc::new(storage_impl.clone());
+    let storage_events = storage_event_channel();
+    let storage: Arc<dyn Storage> = Arc::new(ObservableStorage::with_sender(
+        storage_impl.clone(),
+        storage_events.clone(),
+    ));
     let history_points = cli.history_points.unwrap_or(config.tray.history_points);
     let spending_windows_days = config.tray.spending_windows_days.clone();
     let transaction_count = config.tray.transaction_count;
@@ -1802,6 +1825,7 @@ async fn main() -> Result<()> {
     let daemon = Daemon {
         storage,
         symlink_storage: storage_impl,
+        storage_events,
         config,
         interval: cli.interval,
         jitter: cli.jitter,
@@ -1821,7 +1845,6 @@ async fn main() -> Result<()> {
 mod tests {
     use super::*;
     use ksni::Tray;
-    use notify::event::{AccessKind, CreateKind, ModifyKind, RemoveKind};
 
     #[test]
     fn compute_next_delay_without_jitter_is_constant() {
@@ -1852,27 +1875,6 @@ mod tests {
         );
     }
 
-    #[test]
-    fn fs_event_filter_includes_state_mutations() {
-        assert!(should_refresh_for_fs_event_kind(&EventKind::Any));
-        assert!(should_refresh_for_fs_event_kind(&EventKind::Create(
-            CreateKind::Any
-        )));
-        assert!(should_refresh_for_fs_event_kind(&EventKind::Modify(
-            ModifyKind::Any
-        )));
-        assert!(should_refresh_for_fs_event_kind(&EventKind::Remove(
-            RemoveKind::Any
-        )));
-    }
-
-    #[test]
-    fn fs_event_filter_excludes_access_events() {
-        assert!(!should_refresh_for_fs_event_kind(&EventKind::Access(
-            AccessKind::Any
-        )));
-    }
-
     #[test]
     fn parse_sync_counts_handles_mixed_results() {
         let value = serde_json::json!({
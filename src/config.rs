@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Component, Path, PathBuf};
 
 use anyhow::{Context, Result};
@@ -213,6 +214,27 @@ pub struct RefreshConfig {
         deserialize_with = "deserialize_duration"
     )]
     pub price_staleness: std::time::Duration,
+
+    /// Currencies tried, in order, as intermediates when no direct or inverse
+    /// FX rate is available (e.g. GBP -> USD -> THB).
+    #[serde(default = "default_fx_pivots")]
+    pub fx_pivots: Vec<String>,
+
+    /// Intraday quote recording.
+    pub intraday: IntradayConfig,
+
+    /// Maximum connections synced at once by `sync all`. Browser-based
+    /// synchronizers always run one at a time.
+    #[serde(default = "default_sync_concurrency")]
+    pub sync_concurrency: usize,
+
+    /// Lower concurrency limits for particular synchronizers, keyed by
+    /// synchronizer name (e.g. `coinbase = 1`).
+    pub synchronizer_concurrency: HashMap<String, usize>,
+
+    /// Check each synced account's newest balance change against its
+    /// transactions and report mismatches in the sync output.
+    pub reconcile_after_sync: bool,
 }
 
 impl Default for RefreshConfig {
@@ -220,6 +242,66 @@ impl Default for RefreshConfig {
         Self {
             balance_staleness: default_balance_staleness(),
             price_staleness: default_price_staleness(),
+            fx_pivots: default_fx_pivots(),
+            intraday: IntradayConfig::default(),
+            sync_concurrency: default_sync_concurrency(),
+            synchronizer_concurrency: HashMap::new(),
+            reconcile_after_sync: false,
+        }
+    }
+}
+
+/// Default number of connections synced concurrently.
+fn default_sync_concurrency() -> usize {
+    4
+}
+
+/// Default FX triangulation pivots.
+fn default_fx_pivots() -> Vec<String> {
+    crate::market_data::DEFAULT_FX_PIVOTS
+        .iter()
+        .map(|pivot| pivot.to_string())
+        .collect()
+}
+
+/// Default intraday quote interval (15 minutes).
+fn default_intraday_interval() -> std::time::Duration {
+    std::time::Duration::from_secs(15 * 60)
+}
+
+/// Default intraday quote retention (7 days).
+fn default_intraday_retention() -> std::time::Duration {
+    std::time::Duration::from_secs(7 * 24 * 60 * 60)
+}
+
+/// Intraday quote history configuration (`[refresh.intraday]`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IntradayConfig {
+    /// Record quotes into the rolling intraday store on each price sync.
+    pub enabled: bool,
+
+    /// Spacing between recorded quotes; also caps quote staleness while enabled.
+    #[serde(
+        default = "default_intraday_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: std::time::Duration,
+
+    /// How long intraday quotes are kept before compacting to one daily price.
+    #[serde(
+        default = "default_intraday_retention",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub retention: std::time::Duration,
+}
+
+impl Default for IntradayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: default_intraday_interval(),
+            retention: default_intraday_retention(),
         }
     }
 }
@@ -343,6 +425,19 @@ impl<'de> Deserialize<'de> for GitConfig {
     }
 }
 
+/// Another data dir that can be combined with others in read-only views
+/// (`--profile`), e.g. one per household member.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ProfileConfig {
+    /// Data directory. If relative, resolved from the config file location.
+    pub data_dir: PathBuf,
+
+    /// Percentage of an account (by id or name) owned by this profile, for
+    /// joint accounts. Unlisted accounts are owned 100%.
+    pub ownership: HashMap<String, f64>,
+}
+
 /// Application configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -390,6 +485,10 @@ pub struct Config {
     /// Git-related settings.
     #[serde(default)]
     pub git: GitConfig,
+
+    /// Named data dirs for combined views, keyed by profile name.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
 }
 
 impl Default for Config {
@@ -406,6 +505,7 @@ impl Default for Config {
             ignore: IgnoreConfig::default(),
             ai: AiConfig::default(),
             git: GitConfig::default(),
+            profiles: BTreeMap::new(),
         }
     }
 }
@@ -442,6 +542,26 @@ impl Config {
             None => config_dir.to_path_buf(),
         })
     }
+
+    /// Profiles with their data directories resolved like `data_dir`.
+    pub fn resolve_profiles(&self, config_dir: &Path) -> BTreeMap<String, ProfileConfig> {
+        self.profiles
+            .iter()
+            .map(|(name, profile)| {
+                let data_dir = expand_tilde_path(&profile.data_dir);
+                let data_dir = normalize_path_components(if data_dir.is_absolute() {
+                    data_dir
+                } else {
+                    config_dir.join(data_dir)
+                });
+                let profile = ProfileConfig {
+                    data_dir,
+                    ownership: profile.ownership.clone(),
+                };
+                (name.clone(), profile)
+            })
+            .collect()
+    }
 }
 
 /// Loaded configuration with resolved paths.
@@ -479,6 +599,9 @@ pub struct ResolvedConfig {
 
     /// Git-related settings.
     pub git: GitConfig,
+
+    /// Named data dirs for combined views, with resolved paths.
+    pub profiles: BTreeMap<String, ProfileConfig>,
 }
 
 /// Returns the default config file path.
@@ -563,6 +686,7 @@ impl ResolvedConfig {
 
         let config = Config::load(&config_path)?;
         let data_dir = config.resolve_data_dir(config_dir);
+        let profiles = config.resolve_profiles(config_dir);
 
         Ok(Self {
             data_dir,
@@ -576,6 +700,7 @@ impl ResolvedConfig {
             ignore: config.ignore,
             ai: config.ai,
             git: config.git,
+            profiles,
         })
     }
 
@@ -615,9 +740,53 @@ impl ResolvedConfig {
                 ignore: IgnoreConfig::default(),
                 ai: AiConfig::default(),
                 git: GitConfig::default(),
+                profiles: BTreeMap::new(),
             })
         }
     }
+
+    /// Profiles named by a `--profile` selection: comma-separated names, or
+    /// `all`.
+    pub fn select_profiles(&self, selection: &str) -> Result<Vec<(&str, &ProfileConfig)>> {
+        if self.profiles.is_empty() {
+            anyhow::bail!("No profiles configured; add [profiles.<name>] sections to the config");
+        }
+        let selected: Vec<(&str, &ProfileConfig)> = if selection.trim() == "all" {
+            self.profiles
+                .iter()
+                .map(|(name, profile)| (name.as_str(), profile))
+                .collect()
+        } else {
+            let mut selected = Vec::new();
+            for name in selection
+                .split(',')
+                .map(str::trim)
+                .filter(|n| !n.is_empty())
+            {
+                let (name, profile) = self.profiles.get_key_value(name).with_context(|| {
+                    let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                    format!("Unknown profile: {name} (configured: {})", known.join(", "))
+                })?;
+                if !selected
+                    .iter()
+                    .any(|(selected, _)| *selected == name.as_str())
+                {
+                    selected.push((name.as_str(), profile));
+                }
+            }
+            selected
+        };
+        if selected.is_empty() {
+            anyhow::bail!("No profiles selected");
+        }
+        if let Some((name, _)) = selected
+            .iter()
+            .find(|(name, _)| name.contains(':') || !crate::models::Id::is_path_safe(name))
+        {
+            anyhow::bail!("Invalid profile name {name:?}: names can't contain ':' or '/'");
+        }
+        Ok(selected)
+    }
 }
 
 #[cfg(test)]
@@ -1079,4 +1248,42 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_profiles_resolve_and_select() -> Result<()> {
+        let dir = TempDir::new()?;
+        let config_path = dir.path().join("keepbook.toml");
+        std::fs::write(
+            &config_path,
+            r#"
+[profiles.alice]
+data_dir = "alice"
+ownership = { "Joint Checking" = 50 }
+
+[profiles.bob]
+data_dir = "/srv/bob"
+"#,
+        )?;
+
+        let resolved = ResolvedConfig::load(&config_path)?;
+        let alice = &resolved.profiles["alice"];
+        assert_eq!(
+            alice.data_dir,
+            config_path.canonicalize()?.parent().unwrap().join("alice")
+        );
+        assert_eq!(alice.ownership["Joint Checking"], 50.0);
+
+        let names = |selection| -> Result<Vec<String>> {
+            Ok(resolved
+                .select_profiles(selection)?
+                .into_iter()
+                .map(|(name, _)| name.to_string())
+                .collect())
+        };
+        assert_eq!(names("all")?, vec!["alice", "bob"]);
+        assert_eq!(names("bob, alice,bob")?, vec!["bob", "alice"]);
+        assert!(names("carol").is_err());
+
+        Ok(())
+    }
 }
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::io::BufReader;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
@@ -10,7 +10,7 @@ use serde::{Deserialize, Serialize};
 use super::field_entry::FieldEntry;
 use super::CredentialStore;
 
-const AGE_IDENTITY_PATH_ENV: &str = "KEEPBOOK_CREDENTIALS_AGE_IDENTITY_PATH";
+pub(crate) const AGE_IDENTITY_PATH_ENV: &str = "KEEPBOOK_CREDENTIALS_AGE_IDENTITY_PATH";
 const DEFAULT_SSH_IDENTITY_FILES: &[&str] = &[
     "id_ed25519",
     "id_rsa",
@@ -19,6 +19,17 @@ const DEFAULT_SSH_IDENTITY_FILES: &[&str] = &[
     "id_ed25519_sk",
 ];
 
+/// Read and parse an SSH private key for use as an age identity.
+pub(crate) fn read_ssh_identity(path: &Path) -> Result<::age::ssh::Identity> {
+    let pem = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read SSH identity {}", path.display()))?;
+    ::age::ssh::Identity::from_buffer(
+        BufReader::new(pem.as_bytes()),
+        Some(path.display().to_string()),
+    )
+    .with_context(|| format!("Failed to parse SSH identity {}", path.display()))
+}
+
 /// Configuration for an age-encrypted credential entry.
 ///
 /// `path` points to an age file whose decrypted payload uses the same multiline
@@ -106,7 +117,7 @@ impl AgeCredentialStore {
         }
     }
 
-    fn default_identity_paths() -> Vec<PathBuf> {
+    pub(crate) fn default_identity_paths() -> Vec<PathBuf> {
         let Some(home_dir) = dirs::home_dir() else {
             return Vec::new();
         };
@@ -132,20 +143,8 @@ impl AgeCredentialStore {
         Ok(paths)
     }
 
-    fn decrypt_with_identity(&self, ciphertext: &[u8], identity_path: &PathBuf) -> Result<Vec<u8>> {
-        let identity_pem = std::fs::read_to_string(identity_path).with_context(|| {
-            format!(
-                "Failed to read age SSH identity {}",
-                identity_path.display()
-            )
-        })?;
-        let reader = BufReader::new(identity_pem.as_bytes());
-        let identity =
-            ::age::ssh::Identity::from_buffer(reader, Some(identity_path.display().to_string()))
-                .with_context(|| {
-                    format!("Failed to parse SSH identity {}", identity_path.display())
-                })?;
-
+    fn decrypt_with_identity(&self, ciphertext: &[u8], identity_path: &Path) -> Result<Vec<u8>> {
+        let identity = read_ssh_identity(identity_path)?;
         ::age::decrypt(&identity, ciphertext)
             .with_context(|| format!("Failed to decrypt with {}", identity_path.display()))
     }
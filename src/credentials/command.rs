@@ -0,0 +1,329 @@
+//! External command credential backend.
+//!
+//! Shells out to a password manager CLI (`op`, `bw`, `sops`, ...) instead of
+//! needing a backend per tool. Commands run through `sh -c` with `{key}`
+//! replaced by the shell-quoted field name.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use super::field_entry::FieldEntry;
+use super::CredentialStore;
+
+/// Configuration for a command credential store.
+///
+/// ```toml
+/// backend = "command"
+/// read = "op read 'op://Finance/Coinbase/'{key}"
+/// write = "op item edit Coinbase --vault Finance {key}=\"$(cat)\""
+///
+/// [fields]
+/// private_key = "private key"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandConfig {
+    /// Command printing the credential (or an entry containing it).
+    pub read: String,
+
+    /// Optional command storing a credential; the value is passed on stdin.
+    /// Without it the store is read-only.
+    #[serde(default)]
+    pub write: Option<String>,
+
+    /// How to interpret the read command's stdout.
+    #[serde(default)]
+    pub output: CommandOutput,
+
+    /// For `output = "json"`, a dotted path to the value (e.g.
+    /// `"coinbase.{key}"` or `"fields.0.value"`). Defaults to `{key}`.
+    #[serde(default)]
+    pub json_path: Option<String>,
+
+    /// Seconds to wait before killing the command.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// What to do with the command's stderr.
+    #[serde(default)]
+    pub stderr: StderrPolicy,
+
+    /// Mapping from logical key names to the names substituted for `{key}`.
+    /// If not specified, the logical key name is used as-is.
+    #[serde(default)]
+    pub fields: HashMap<String, String>,
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+/// Parsing mode for the read command's output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandOutput {
+    /// The whole output is the value (one trailing newline is dropped).
+    #[default]
+    Raw,
+    /// A pass-style entry with `field-name: value` lines.
+    Fields,
+    /// A JSON document, navigated with `json_path`.
+    Json,
+}
+
+/// Where the command's stderr goes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StderrPolicy {
+    /// Captured and included in the error if the command fails.
+    #[default]
+    Capture,
+    /// Passed through to keepbook's stderr (e.g. for unlock prompts).
+    Inherit,
+    /// Dropped.
+    Discard,
+}
+
+/// Credential store backed by external commands.
+pub struct CommandCredentialStore {
+    config: CommandConfig,
+    base_dir: Option<PathBuf>,
+}
+
+impl CommandCredentialStore {
+    pub fn new(config: CommandConfig) -> Self {
+        Self {
+            config,
+            base_dir: None,
+        }
+    }
+
+    /// Run commands from `base_dir` so they can use relative paths.
+    pub fn with_base_dir(config: CommandConfig, base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            config,
+            base_dir: Some(base_dir.into()),
+        }
+    }
+
+    fn field_name<'a>(&'a self, key: &'a str) -> &'a str {
+        self.config
+            .fields
+            .get(key)
+            .map(|s| s.as_str())
+            .unwrap_or(key)
+    }
+
+    async fn run(&self, template: &str, field: &str, stdin: Option<&str>) -> Result<String> {
+        let script = template.replace("{key}", &shell_quote(field));
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&script)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(match self.config.stderr {
+                StderrPolicy::Capture => Stdio::piped(),
+                StderrPolicy::Inherit => Stdio::inherit(),
+                StderrPolicy::Discard => Stdio::null(),
+            })
+            .kill_on_drop(true);
+        if let Some(dir) = &self.base_dir {
+            command.current_dir(dir);
+        }
+
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("Failed to run credential command: {template}"))?;
+        if let Some(input) = stdin {
+            let mut pipe = child
+                .stdin
+                .take()
+                .context("Credential command has no stdin")?;
+            pipe.write_all(input.as_bytes())
+                .await
+                .context("Failed to write to credential command stdin")?;
+        }
+
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let output = tokio::time::timeout(timeout, child.wait_with_output())
+            .await
+            .with_context(|| {
+                format!(
+                    "Credential command timed out after {}s: {template}",
+                    self.config.timeout_secs
+                )
+            })?
+            .context("Failed to wait for credential command")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.trim().is_empty() {
+                bail!("Credential command failed ({}): {template}", output.status);
+            }
+            bail!(
+                "Credential command failed ({}): {template}: {}",
+                output.status,
+                stderr.trim()
+            );
+        }
+        String::from_utf8(output.stdout).context("Invalid UTF-8 in credential command output")
+    }
+
+    fn parse(&self, field: &str, stdout: &str) -> Result<Option<String>> {
+        match self.config.output {
+            CommandOutput::Raw => {
+                let value = stdout.strip_suffix('\n').unwrap_or(stdout);
+                let value = value.strip_suffix('\r').unwrap_or(value);
+                Ok((!value.is_empty()).then(|| value.to_string()))
+            }
+            CommandOutput::Fields => Ok(FieldEntry::parse(stdout).fields.remove(field)),
+            CommandOutput::Json => {
+                let document: Value = serde_json::from_str(stdout)
+                    .context("Credential command output is not valid JSON")?;
+                let path = self.config.json_path.as_deref().unwrap_or("{key}");
+                Ok(
+                    json_lookup(&document, path, field).and_then(|value| match value {
+                        Value::Null => None,
+                        Value::String(s) => Some(s.clone()),
+                        other => Some(other.to_string()),
+                    }),
+                )
+            }
+        }
+    }
+}
+
+/// Follow a dotted path through objects (by key) and arrays (by index).
+/// `{key}` is substituted as a whole segment, so field names may contain dots.
+fn json_lookup<'a>(document: &'a Value, path: &str, field: &str) -> Option<&'a Value> {
+    let mut current = document;
+    for segment in path.split('.').filter(|segment| !segment.is_empty()) {
+        let segment = segment.replace("{key}", field);
+        current = match current {
+            Value::Object(map) => map.get(&segment)?,
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[async_trait]
+impl CredentialStore for CommandCredentialStore {
+    async fn get(&self, key: &str) -> Result<Option<SecretString>> {
+        let field = self.field_name(key);
+        let stdout = self.run(&self.config.read, field, None).await?;
+        Ok(self.parse(field, &stdout)?.map(SecretString::from))
+    }
+
+    async fn set(&self, key: &str, value: SecretString) -> Result<()> {
+        let Some(template) = self.config.write.as_deref() else {
+            bail!("command credential store has no write command configured")
+        };
+        let field = self.field_name(key);
+        self.run(template, field, Some(value.expose_secret()))
+            .await?;
+        Ok(())
+    }
+
+    fn supports_write(&self) -> bool {
+        self.config.write.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(read: &str) -> CommandConfig {
+        CommandConfig {
+            read: read.to_string(),
+            write: None,
+            output: CommandOutput::Raw,
+            json_path: None,
+            timeout_secs: 5,
+            stderr: StderrPolicy::Capture,
+            fields: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn raw_output_substitutes_quoted_key() -> Result<()> {
+        let mut config = config("printf 'value-for-%s\\n' {key}");
+        config
+            .fields
+            .insert("api_key".to_string(), "it's key".to_string());
+        let store = CommandCredentialStore::new(config);
+
+        let value = store.get("api_key").await?.expect("value");
+        assert_eq!(value.expose_secret(), "value-for-it's key");
+        assert!(!store.supports_write());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fields_and_json_outputs_are_parsed() -> Result<()> {
+        let mut fields = config("printf 'pw\\nkey-name: abc\\n'");
+        fields.output = CommandOutput::Fields;
+        let store = CommandCredentialStore::new(fields);
+        assert_eq!(store.get("key-name").await?.unwrap().expose_secret(), "abc");
+        assert_eq!(store.get("password").await?.unwrap().expose_secret(), "pw");
+        assert!(store.get("missing").await?.is_none());
+
+        let mut json = config(r#"echo '{"coinbase":{"key.name":"abc","ids":[1,2]}}'"#);
+        json.output = CommandOutput::Json;
+        json.json_path = Some("coinbase.{key}".to_string());
+        let store = CommandCredentialStore::new(json);
+        assert_eq!(store.get("key.name").await?.unwrap().expose_secret(), "abc");
+        assert!(store.get("missing").await?.is_none());
+
+        let document: Value = serde_json::json!({ "ids": [1, 2] });
+        assert_eq!(json_lookup(&document, "ids.1", "x"), Some(&Value::from(2)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn write_passes_value_on_stdin() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut config = config("cat {key}");
+        config.write = Some("cat > {key}".to_string());
+        let store = CommandCredentialStore::with_base_dir(config, dir.path());
+
+        store.set("token", SecretString::from("abc")).await?;
+
+        assert_eq!(std::fs::read_to_string(dir.path().join("token"))?, "abc");
+        assert_eq!(store.get("token").await?.unwrap().expose_secret(), "abc");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failures_and_timeouts_are_errors() {
+        let store = CommandCredentialStore::new(config("echo locked >&2; exit 3"));
+        let err = store.get("token").await.unwrap_err();
+        assert!(format!("{err:#}").contains("locked"));
+
+        let mut slow = config("sleep 5");
+        slow.timeout_secs = 0;
+        let store = CommandCredentialStore::new(slow);
+        let err = store.get("token").await.unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+    }
+}
@@ -9,8 +9,10 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
 use super::age::{AgeConfig, AgeCredentialStore};
+use super::command::{CommandConfig, CommandCredentialStore};
 use super::env::{EnvConfig, EnvCredentialStore};
 use super::pass::{PassConfig, PassCredentialStore};
+use super::vault::{VaultConfig, VaultCredentialStore};
 use super::CredentialStore;
 
 /// Configuration for a credential store.
@@ -49,7 +51,18 @@ pub enum CredentialConfig {
         #[serde(flatten)]
         config: AgeConfig,
     },
-    // Vault { ... },
+
+    /// HashiCorp Vault KV v2 backend.
+    Vault {
+        #[serde(flatten)]
+        config: VaultConfig,
+    },
+
+    /// External command backend (1Password, Bitwarden, sops, ...).
+    Command {
+        #[serde(flatten)]
+        config: CommandConfig,
+    },
 }
 
 impl CredentialConfig {
@@ -89,6 +102,20 @@ impl CredentialConfig {
                 )),
                 None => Box::new(AgeCredentialStore::new(config.clone())),
             },
+            CredentialConfig::Vault { config } => match base_dir {
+                Some(base_dir) => Box::new(VaultCredentialStore::with_base_dir(
+                    config.clone(),
+                    base_dir.to_path_buf(),
+                )),
+                None => Box::new(VaultCredentialStore::new(config.clone())),
+            },
+            CredentialConfig::Command { config } => match base_dir {
+                Some(base_dir) => Box::new(CommandCredentialStore::with_base_dir(
+                    config.clone(),
+                    base_dir.to_path_buf(),
+                )),
+                None => Box::new(CommandCredentialStore::new(config.clone())),
+            },
         }
     }
 }
@@ -216,4 +243,84 @@ key_name = "key-name"
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_vault_config() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(
+            file,
+            r#"
+backend = "vault"
+address = "https://vault.example.com:8200"
+path = "finance/coinbase-api"
+
+[auth]
+method = "approle"
+role_id_env = "KEEPBOOK_VAULT_ROLE_ID"
+
+[fields]
+key_name = "key-name"
+"#
+        )?;
+
+        let config = CredentialConfig::load(file.path())?;
+
+        match config {
+            CredentialConfig::Vault { config } => {
+                assert_eq!(
+                    config.address.as_deref(),
+                    Some("https://vault.example.com:8200")
+                );
+                assert_eq!(config.mount, "secret");
+                assert_eq!(config.path, "finance/coinbase-api");
+                match config.auth {
+                    crate::credentials::VaultAuth::AppRole {
+                        mount,
+                        role_id_env,
+                        secret_id_env,
+                    } => {
+                        assert_eq!(mount, "approle");
+                        assert_eq!(role_id_env, "KEEPBOOK_VAULT_ROLE_ID");
+                        assert_eq!(secret_id_env, "VAULT_SECRET_ID");
+                    }
+                    other => panic!("expected approle auth, got {other:?}"),
+                }
+                assert_eq!(config.fields.get("key_name"), Some(&"key-name".to_string()));
+            }
+            _ => panic!("expected vault credential config"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_command_config() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(
+            file,
+            r#"
+backend = "command"
+read = "sops -d --output-type json secrets.json"
+output = "json"
+json_path = "coinbase.{{key}}"
+stderr = "inherit"
+"#
+        )?;
+
+        let config = CredentialConfig::load(file.path())?;
+
+        match config {
+            CredentialConfig::Command { config } => {
+                assert_eq!(config.read, "sops -d --output-type json secrets.json");
+                assert!(config.write.is_none());
+                assert_eq!(config.output, crate::credentials::CommandOutput::Json);
+                assert_eq!(config.json_path.as_deref(), Some("coinbase.{key}"));
+                assert_eq!(config.timeout_secs, 30);
+                assert_eq!(config.stderr, crate::credentials::StderrPolicy::Inherit);
+            }
+            _ => panic!("expected command credential config"),
+        }
+
+        Ok(())
+    }
 }
@@ -1,7 +1,8 @@
 //! Credential storage abstraction.
 //!
 //! Provides a unified interface for retrieving and storing credentials
-//! from various backends (pass, age-encrypted files, environment variables, etc.)
+//! from various backends (pass, age-encrypted files, environment variables,
+//! Vault, external commands, etc.)
 //!
 //! # Configuration
 //!
@@ -18,17 +19,22 @@
 //! ```
 
 mod age;
+mod command;
 mod config;
 mod env;
 mod field_entry;
 mod pass;
 mod session;
+mod vault;
 
+pub(crate) use age::{read_ssh_identity, AGE_IDENTITY_PATH_ENV};
 pub use age::{AgeConfig, AgeCredentialStore};
+pub use command::{CommandConfig, CommandCredentialStore, CommandOutput, StderrPolicy};
 pub use config::CredentialConfig;
 pub use env::{EnvConfig, EnvCredentialStore};
 pub use pass::{PassConfig, PassCredentialStore};
-pub use session::{SessionCache, SessionData, StoredCookie};
+pub use session::{SessionCache, SessionData, SessionEncryption, SessionSummary, StoredCookie};
+pub use vault::{VaultAuth, VaultConfig, VaultCredentialStore};
 
 use anyhow::Result;
 use async_trait::async_trait;
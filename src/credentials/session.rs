@@ -2,13 +2,34 @@
 //!
 //! This module provides local-only storage for session tokens, cookies,
 //! and other ephemeral authentication data that shouldn't be synced.
+//!
+//! Session files are plaintext JSON unless encryption is enabled with
+//! `KEEPBOOK_SESSION_ENCRYPTION`:
+//!
+//! - `age`: encrypt to the SSH identity used for age credentials
+//!   (`KEEPBOOK_CREDENTIALS_AGE_IDENTITY_PATH`, else the first key in `~/.ssh`).
+//! - `passphrase`: encrypt with the passphrase in `KEEPBOOK_SESSION_PASSPHRASE`.
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
 
+use super::age::{read_ssh_identity, AgeCredentialStore, AGE_IDENTITY_PATH_ENV};
+
+const SESSION_ENCRYPTION_ENV: &str = "KEEPBOOK_SESSION_ENCRYPTION";
+const SESSION_PASSPHRASE_ENV: &str = "KEEPBOOK_SESSION_PASSPHRASE";
+
+/// scrypt work factor for passphrase-encrypted sessions. Lower than age's
+/// default (~1s per operation) since sessions are read on every sync and
+/// only live for hours or days.
+#[cfg(not(test))]
+const SESSION_SCRYPT_WORK_FACTOR: u8 = 15;
+#[cfg(test)]
+const SESSION_SCRYPT_WORK_FACTOR: u8 = 10;
+
 /// Session data for a connection.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SessionData {
@@ -31,6 +52,10 @@ pub struct SessionData {
     #[serde(default)]
     pub captured_at: Option<i64>,
 
+    /// When the session is expected to stop working (Unix timestamp).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+
     /// Arbitrary key-value data for synchronizer-specific needs.
     #[serde(default)]
     pub data: HashMap<String, String>,
@@ -68,6 +93,19 @@ impl SessionData {
         self
     }
 
+    /// Set the expected expiry as a lifetime from `captured_at`.
+    pub fn with_lifetime(mut self, lifetime: chrono::Duration) -> Self {
+        self.expires_at = self
+            .captured_at
+            .map(|captured_at| captured_at + lifetime.num_seconds());
+        self
+    }
+
+    /// Whether `expires_at` has passed.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
     /// Format cookies as a Cookie header value.
     pub fn cookie_header(&self) -> String {
         self.cookies
@@ -78,15 +116,137 @@ impl SessionData {
     }
 }
 
+/// How session files are encrypted at rest.
+#[derive(Clone, Default)]
+pub enum SessionEncryption {
+    /// Plaintext JSON.
+    #[default]
+    None,
+    /// age-encrypted to an SSH key, decrypted with its private key.
+    Age { identity_path: PathBuf },
+    /// age-encrypted with a passphrase (scrypt).
+    Passphrase(SecretString),
+}
+
+impl std::fmt::Debug for SessionEncryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionEncryption::None => write!(f, "None"),
+            SessionEncryption::Age { identity_path } => f
+                .debug_struct("Age")
+                .field("identity_path", identity_path)
+                .finish(),
+            SessionEncryption::Passphrase(_) => write!(f, "Passphrase(..)"),
+        }
+    }
+}
+
+impl SessionEncryption {
+    /// Read the encryption mode from `KEEPBOOK_SESSION_ENCRYPTION`.
+    pub fn from_env() -> Result<Self> {
+        let mode = std::env::var(SESSION_ENCRYPTION_ENV).unwrap_or_default();
+        match mode.trim().to_ascii_lowercase().as_str() {
+            "" | "none" => Ok(SessionEncryption::None),
+            "age" => {
+                let identity_path = match std::env::var(AGE_IDENTITY_PATH_ENV) {
+                    Ok(path) if !path.trim().is_empty() => PathBuf::from(path.trim()),
+                    _ => AgeCredentialStore::default_identity_paths()
+                        .into_iter()
+                        .next()
+                        .with_context(|| {
+                            format!(
+                                "{SESSION_ENCRYPTION_ENV}=age needs an SSH identity; set {AGE_IDENTITY_PATH_ENV} or create one under ~/.ssh"
+                            )
+                        })?,
+                };
+                Ok(SessionEncryption::Age { identity_path })
+            }
+            "passphrase" => {
+                let passphrase = std::env::var(SESSION_PASSPHRASE_ENV)
+                    .ok()
+                    .filter(|p| !p.is_empty())
+                    .with_context(|| {
+                        format!(
+                            "{SESSION_ENCRYPTION_ENV}=passphrase needs {SESSION_PASSPHRASE_ENV}"
+                        )
+                    })?;
+                Ok(SessionEncryption::Passphrase(SecretString::from(
+                    passphrase,
+                )))
+            }
+            other => bail!(
+                "Unknown {SESSION_ENCRYPTION_ENV} value {other:?}; expected age, passphrase or none"
+            ),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        !matches!(self, SessionEncryption::None)
+    }
+
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            SessionEncryption::None => Ok(plaintext.to_vec()),
+            SessionEncryption::Age { identity_path } => {
+                let identity = read_ssh_identity(identity_path)?;
+                let recipient = ::age::ssh::Recipient::try_from(identity).map_err(|err| {
+                    anyhow::anyhow!(
+                        "Cannot encrypt to SSH identity {}: {err:?}",
+                        identity_path.display()
+                    )
+                })?;
+                ::age::encrypt(&recipient, plaintext).context("Failed to encrypt session")
+            }
+            SessionEncryption::Passphrase(passphrase) => {
+                let mut recipient = ::age::scrypt::Recipient::new(passphrase.clone());
+                recipient.set_work_factor(SESSION_SCRYPT_WORK_FACTOR);
+                ::age::encrypt(&recipient, plaintext).context("Failed to encrypt session")
+            }
+        }
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            SessionEncryption::None => {
+                bail!("Session file is encrypted; set {SESSION_ENCRYPTION_ENV} to read it")
+            }
+            SessionEncryption::Age { identity_path } => {
+                let identity = read_ssh_identity(identity_path)?;
+                ::age::decrypt(&identity, ciphertext).context("Failed to decrypt session")
+            }
+            SessionEncryption::Passphrase(passphrase) => {
+                let identity = ::age::scrypt::Identity::new(passphrase.clone());
+                ::age::decrypt(&identity, ciphertext).context("Failed to decrypt session")
+            }
+        }
+    }
+}
+
+/// Metadata about a cached session, without any secrets.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub key: String,
+    pub encrypted: bool,
+    pub captured_at: Option<i64>,
+    pub expires_at: Option<i64>,
+    pub has_token: bool,
+    pub cookie_count: usize,
+    /// Set when the session file couldn't be read (e.g. wrong key).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 /// Cache for session data, stored locally (not synced).
 pub struct SessionCache {
     cache_dir: PathBuf,
+    encryption: SessionEncryption,
 }
 
 impl SessionCache {
     /// Create a new session cache.
     ///
-    /// Uses `~/.cache/keepbook/sessions/` by default.
+    /// Uses `~/.cache/keepbook/sessions/` by default, encrypted according to
+    /// `KEEPBOOK_SESSION_ENCRYPTION`.
     pub fn new() -> Result<Self> {
         let cache_dir = dirs::cache_dir()
             .context("Could not find cache directory")?
@@ -96,7 +256,10 @@ impl SessionCache {
         std::fs::create_dir_all(&cache_dir)
             .with_context(|| format!("Failed to create session cache dir: {cache_dir:?}"))?;
 
-        Ok(Self { cache_dir })
+        Ok(Self {
+            cache_dir,
+            encryption: SessionEncryption::from_env()?,
+        })
     }
 
     /// Create a session cache at a custom location.
@@ -104,22 +267,48 @@ impl SessionCache {
         let cache_dir = cache_dir.as_ref().to_path_buf();
         std::fs::create_dir_all(&cache_dir)
             .with_context(|| format!("Failed to create session cache dir: {cache_dir:?}"))?;
-        Ok(Self { cache_dir })
+        Ok(Self {
+            cache_dir,
+            encryption: SessionEncryption::None,
+        })
+    }
+
+    /// Encrypt session files written from now on.
+    pub fn with_encryption(mut self, encryption: SessionEncryption) -> Self {
+        self.encryption = encryption;
+        self
     }
 
     fn session_file(&self, connection_id: &str) -> PathBuf {
         self.cache_dir.join(format!("{connection_id}.json"))
     }
 
+    fn encrypted_session_file(&self, connection_id: &str) -> PathBuf {
+        self.cache_dir.join(format!("{connection_id}.json.age"))
+    }
+
     /// Load session data for a connection.
     pub fn get(&self, connection_id: &str) -> Result<Option<SessionData>> {
-        let path = self.session_file(connection_id);
-        if !path.exists() {
-            return Ok(None);
-        }
-
-        let content = std::fs::read_to_string(&path)
-            .with_context(|| format!("Failed to read session file: {path:?}"))?;
+        let encrypted_path = self.encrypted_session_file(connection_id);
+        let (path, content) = if encrypted_path.exists() {
+            let ciphertext = std::fs::read(&encrypted_path)
+                .with_context(|| format!("Failed to read session file: {encrypted_path:?}"))?;
+            let plaintext = self
+                .encryption
+                .decrypt(&ciphertext)
+                .with_context(|| format!("Failed to read session file: {encrypted_path:?}"))?;
+            let content = String::from_utf8(plaintext)
+                .with_context(|| format!("Invalid UTF-8 in session file: {encrypted_path:?}"))?;
+            (encrypted_path, content)
+        } else {
+            let path = self.session_file(connection_id);
+            if !path.exists() {
+                return Ok(None);
+            }
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read session file: {path:?}"))?;
+            (path, content)
+        };
 
         let session: SessionData = serde_json::from_str(&content)
             .with_context(|| format!("Failed to parse session file: {path:?}"))?;
@@ -128,24 +317,162 @@ impl SessionCache {
     }
 
     /// Save session data for a connection.
+    ///
+    /// Replaces any copy in the other format, so enabling encryption
+    /// migrates plaintext sessions as they are next saved.
     pub fn set(&self, connection_id: &str, session: &SessionData) -> Result<()> {
-        let path = self.session_file(connection_id);
         let content =
             serde_json::to_string_pretty(session).context("Failed to serialize session")?;
 
-        std::fs::write(&path, content)
+        let (path, stale) = if self.encryption.is_enabled() {
+            (
+                self.encrypted_session_file(connection_id),
+                self.session_file(connection_id),
+            )
+        } else {
+            (
+                self.session_file(connection_id),
+                self.encrypted_session_file(connection_id),
+            )
+        };
+        let bytes = if self.encryption.is_enabled() {
+            self.encryption.encrypt(content.as_bytes())?
+        } else {
+            content.into_bytes()
+        };
+
+        crate::storage::write_atomic_sync(&path, &bytes)
             .with_context(|| format!("Failed to write session file: {path:?}"))?;
+        remove_if_exists(&stale)?;
 
         Ok(())
     }
 
     /// Delete session data for a connection.
     pub fn delete(&self, connection_id: &str) -> Result<()> {
-        let path = self.session_file(connection_id);
-        if path.exists() {
-            std::fs::remove_file(&path)
-                .with_context(|| format!("Failed to delete session file: {path:?}"))?;
+        remove_if_exists(&self.session_file(connection_id))?;
+        remove_if_exists(&self.encrypted_session_file(connection_id))
+    }
+
+    /// Keys of all cached sessions, sorted.
+    pub fn keys(&self) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        let entries = std::fs::read_dir(&self.cache_dir)
+            .with_context(|| format!("Failed to read session cache dir: {:?}", self.cache_dir))?;
+        for entry in entries {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            let key = name
+                .strip_suffix(".json.age")
+                .or_else(|| name.strip_suffix(".json"));
+            if let Some(key) = key {
+                if !keys.iter().any(|k| k == key) {
+                    keys.push(key.to_string());
+                }
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Summarize every cached session without exposing tokens or cookies.
+    pub fn list(&self) -> Result<Vec<SessionSummary>> {
+        let mut summaries = Vec::new();
+        for key in self.keys()? {
+            let encrypted = self.encrypted_session_file(&key).exists();
+            let mut summary = SessionSummary {
+                key: key.clone(),
+                encrypted,
+                captured_at: None,
+                expires_at: None,
+                has_token: false,
+                cookie_count: 0,
+                error: None,
+            };
+            match self.get(&key) {
+                Ok(Some(session)) => {
+                    summary.captured_at = session.captured_at;
+                    summary.expires_at = session.expires_at;
+                    summary.has_token = session.token.is_some();
+                    summary.cookie_count = session.cookie_jar.len().max(session.cookies.len());
+                }
+                Ok(None) => continue,
+                Err(err) => summary.error = Some(format!("{err:#}")),
+            }
+            summaries.push(summary);
         }
+        Ok(summaries)
+    }
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    if path.exists() {
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to delete session file: {path:?}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> SessionData {
+        let mut session = SessionData::new()
+            .with_token("secret-token")
+            .with_cookie("sid", "abc");
+        session.captured_at = Some(1_000);
+        session.with_lifetime(chrono::Duration::hours(1))
+    }
+
+    #[test]
+    fn passphrase_encrypted_sessions_round_trip() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let plain = SessionCache::with_path(dir.path())?;
+        plain.set("conn", &session())?;
+
+        let cache = SessionCache::with_path(dir.path())?
+            .with_encryption(SessionEncryption::Passphrase(SecretString::from("hunter2")));
+        cache.set("conn", &cache.get("conn")?.expect("plaintext session"))?;
+
+        assert!(!dir.path().join("conn.json").exists());
+        let raw = std::fs::read(dir.path().join("conn.json.age"))?;
+        assert!(!String::from_utf8_lossy(&raw).contains("secret-token"));
+        let loaded = cache.get("conn")?.expect("session");
+        assert_eq!(loaded.token.as_deref(), Some("secret-token"));
+        assert_eq!(loaded.expires_at, Some(4_600));
+        assert!(loaded.is_expired(4_600));
+        assert!(!loaded.is_expired(4_599));
+
+        assert!(plain.get("conn").is_err());
+        let wrong = SessionCache::with_path(dir.path())?
+            .with_encryption(SessionEncryption::Passphrase(SecretString::from("nope")));
+        assert!(wrong.get("conn").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn list_reports_metadata_only() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = SessionCache::with_path(dir.path())?;
+        cache.set("b", &session())?;
+        cache.set("a", &SessionData::new())?;
+        std::fs::write(dir.path().join("c.json.age"), b"not age")?;
+
+        let summaries = cache.list()?;
+
+        let keys: Vec<&str> = summaries.iter().map(|s| s.key.as_str()).collect();
+        assert_eq!(keys, vec!["a", "b", "c"]);
+        assert!(summaries[1].has_token);
+        assert_eq!(summaries[1].cookie_count, 1);
+        assert_eq!(summaries[1].captured_at, Some(1_000));
+        assert!(summaries[2].encrypted);
+        assert!(summaries[2].error.is_some());
+        let json = serde_json::to_string(&summaries)?;
+        assert!(!json.contains("secret-token"));
+
+        cache.delete("b")?;
+        cache.delete("c")?;
+        assert_eq!(cache.keys()?, vec!["a".to_string()]);
         Ok(())
     }
 }
@@ -0,0 +1,361 @@
+//! HashiCorp Vault KV v2 credential backend.
+//!
+//! Reads and writes the fields of a single KV v2 secret over Vault's HTTP
+//! API. Anything that speaks the same API (e.g. OpenBao) works too.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use super::CredentialStore;
+
+const VAULT_ADDR_ENV: &str = "VAULT_ADDR";
+const VAULT_TOKEN_ENV: &str = "VAULT_TOKEN";
+
+/// Configuration for a Vault KV v2 credential store.
+///
+/// ```toml
+/// backend = "vault"
+/// address = "https://vault.example.com:8200"
+/// mount = "secret"
+/// path = "finance/coinbase-api"
+///
+/// [auth]
+/// method = "approle"
+///
+/// [fields]
+/// key_name = "key-name"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultConfig {
+    /// Vault server address. Defaults to `VAULT_ADDR`.
+    #[serde(default)]
+    pub address: Option<String>,
+
+    /// KV v2 secrets engine mount.
+    #[serde(default = "default_mount")]
+    pub mount: String,
+
+    /// Secret path within the mount (e.g., "finance/coinbase-api").
+    pub path: String,
+
+    #[serde(default)]
+    pub auth: VaultAuth,
+
+    /// Mapping from logical key names to keys in the secret's data.
+    /// If not specified, the logical key name is used as-is.
+    #[serde(default)]
+    pub fields: HashMap<String, String>,
+}
+
+fn default_mount() -> String {
+    "secret".to_string()
+}
+
+/// How to obtain a Vault token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "lowercase")]
+pub enum VaultAuth {
+    /// A token read from `token_file`, else `VAULT_TOKEN`, else
+    /// `~/.vault-token` (where `vault login` leaves it).
+    Token {
+        #[serde(default)]
+        token_file: Option<String>,
+    },
+
+    /// AppRole login with the role and secret ids taken from the environment.
+    AppRole {
+        #[serde(default = "default_approle_mount")]
+        mount: String,
+        #[serde(default = "default_role_id_env")]
+        role_id_env: String,
+        #[serde(default = "default_secret_id_env")]
+        secret_id_env: String,
+    },
+}
+
+impl Default for VaultAuth {
+    fn default() -> Self {
+        VaultAuth::Token { token_file: None }
+    }
+}
+
+fn default_approle_mount() -> String {
+    "approle".to_string()
+}
+
+fn default_role_id_env() -> String {
+    "VAULT_ROLE_ID".to_string()
+}
+
+fn default_secret_id_env() -> String {
+    "VAULT_SECRET_ID".to_string()
+}
+
+/// A secret's data and the version it was read at (0 if it doesn't exist).
+struct KvSecret {
+    data: serde_json::Map<String, Value>,
+    version: u64,
+}
+
+/// Credential store backed by a Vault KV v2 secret.
+///
+/// The token is obtained on first use and reused for the life of the store.
+/// Writes use check-and-set against the version just read, so a concurrent
+/// change to the secret fails the write instead of being overwritten.
+pub struct VaultCredentialStore {
+    config: VaultConfig,
+    base_dir: Option<PathBuf>,
+    client: reqwest::Client,
+    token: Mutex<Option<SecretString>>,
+}
+
+impl VaultCredentialStore {
+    pub fn new(config: VaultConfig) -> Self {
+        Self {
+            config,
+            base_dir: None,
+            client: reqwest::Client::new(),
+            token: Mutex::new(None),
+        }
+    }
+
+    pub fn with_base_dir(config: VaultConfig, base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            base_dir: Some(base_dir.into()),
+            ..Self::new(config)
+        }
+    }
+
+    fn field_name<'a>(&'a self, key: &'a str) -> &'a str {
+        self.config
+            .fields
+            .get(key)
+            .map(|s| s.as_str())
+            .unwrap_or(key)
+    }
+
+    fn address(&self) -> Result<String> {
+        let address = match self.config.address.as_deref() {
+            Some(address) => address.to_string(),
+            None => std::env::var(VAULT_ADDR_ENV).with_context(|| {
+                format!("Vault address is not configured; set address or {VAULT_ADDR_ENV}")
+            })?,
+        };
+        Ok(address.trim_end_matches('/').to_string())
+    }
+
+    fn data_url(&self) -> Result<String> {
+        Ok(format!(
+            "{}/v1/{}/data/{}",
+            self.address()?,
+            self.config.mount.trim_matches('/'),
+            self.config.path.trim_matches('/')
+        ))
+    }
+
+    fn resolve_path(&self, configured: &str) -> PathBuf {
+        let path = PathBuf::from(configured);
+        if path.is_absolute() {
+            path
+        } else {
+            self.base_dir
+                .as_ref()
+                .map(|base| base.join(&path))
+                .unwrap_or(path)
+        }
+    }
+
+    fn read_token_file(path: &PathBuf) -> Result<SecretString> {
+        let token = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read Vault token file {}", path.display()))?;
+        Ok(SecretString::from(token.trim().to_string()))
+    }
+
+    async fn login(&self) -> Result<SecretString> {
+        match &self.config.auth {
+            VaultAuth::Token {
+                token_file: Some(path),
+            } => Self::read_token_file(&self.resolve_path(path)),
+            VaultAuth::Token { token_file: None } => {
+                if let Ok(token) = std::env::var(VAULT_TOKEN_ENV) {
+                    if !token.trim().is_empty() {
+                        return Ok(SecretString::from(token.trim().to_string()));
+                    }
+                }
+                let default_file = dirs::home_dir()
+                    .map(|home| home.join(".vault-token"))
+                    .filter(|path| path.exists())
+                    .with_context(|| {
+                        format!(
+                            "Vault token is not configured; set auth.token_file, {VAULT_TOKEN_ENV}, or run `vault login`"
+                        )
+                    })?;
+                Self::read_token_file(&default_file)
+            }
+            VaultAuth::AppRole {
+                mount,
+                role_id_env,
+                secret_id_env,
+            } => {
+                let role_id = std::env::var(role_id_env)
+                    .with_context(|| format!("Vault AppRole role id not set in {role_id_env}"))?;
+                let secret_id = std::env::var(secret_id_env).with_context(|| {
+                    format!("Vault AppRole secret id not set in {secret_id_env}")
+                })?;
+                let url = format!(
+                    "{}/v1/auth/{}/login",
+                    self.address()?,
+                    mount.trim_matches('/')
+                );
+                let response = self
+                    .client
+                    .post(&url)
+                    .json(&serde_json::json!({
+                        "role_id": role_id,
+                        "secret_id": secret_id,
+                    }))
+                    .send()
+                    .await
+                    .context("Failed to reach Vault for AppRole login")?;
+                let status = response.status();
+                if !status.is_success() {
+                    bail!("Vault AppRole login failed: HTTP {status}");
+                }
+                let body: Value = response
+                    .json()
+                    .await
+                    .context("Invalid Vault login response")?;
+                let token = body
+                    .pointer("/auth/client_token")
+                    .and_then(Value::as_str)
+                    .context("Vault login response has no auth.client_token")?;
+                Ok(SecretString::from(token.to_string()))
+            }
+        }
+    }
+
+    async fn token(&self) -> Result<SecretString> {
+        let mut cached = self.token.lock().await;
+        if let Some(token) = cached.as_ref() {
+            return Ok(token.clone());
+        }
+        let token = self.login().await?;
+        *cached = Some(token.clone());
+        Ok(token)
+    }
+
+    async fn read_secret(&self) -> Result<KvSecret> {
+        let url = self.data_url()?;
+        let token = self.token().await?;
+        let response = self
+            .client
+            .get(&url)
+            .header("X-Vault-Token", token.expose_secret())
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach Vault at {url}"))?;
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(KvSecret {
+                data: serde_json::Map::new(),
+                version: 0,
+            });
+        }
+        if !status.is_success() {
+            bail!(
+                "Vault read of {}/{} failed: HTTP {status}",
+                self.config.mount,
+                self.config.path
+            );
+        }
+        let body: Value = response.json().await.context("Invalid Vault response")?;
+        let data = body
+            .pointer("/data/data")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default();
+        let version = body
+            .pointer("/data/metadata/version")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        Ok(KvSecret { data, version })
+    }
+
+    async fn write_secret(&self, secret: &KvSecret) -> Result<()> {
+        let url = self.data_url()?;
+        let token = self.token().await?;
+        let response = self
+            .client
+            .post(&url)
+            .header("X-Vault-Token", token.expose_secret())
+            .json(&serde_json::json!({
+                "options": { "cas": secret.version },
+                "data": secret.data,
+            }))
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach Vault at {url}"))?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!(
+                "Vault write of {}/{} failed: HTTP {status}",
+                self.config.mount,
+                self.config.path
+            );
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CredentialStore for VaultCredentialStore {
+    async fn get(&self, key: &str) -> Result<Option<SecretString>> {
+        let field = self.field_name(key);
+        let secret = self.read_secret().await?;
+        Ok(secret.data.get(field).map(|value| {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            SecretString::from(value)
+        }))
+    }
+
+    async fn set(&self, key: &str, value: SecretString) -> Result<()> {
+        let field = self.field_name(key).to_string();
+        let mut secret = self.read_secret().await?;
+        secret
+            .data
+            .insert(field, Value::String(value.expose_secret().to_string()));
+        self.write_secret(&secret).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_url_joins_mount_and_path() -> Result<()> {
+        let store = VaultCredentialStore::new(VaultConfig {
+            address: Some("https://vault.example.com:8200/".to_string()),
+            mount: "/kv/".to_string(),
+            path: "finance/coinbase".to_string(),
+            auth: VaultAuth::default(),
+            fields: HashMap::new(),
+        });
+
+        assert_eq!(
+            store.data_url()?,
+            "https://vault.example.com:8200/v1/kv/data/finance/coinbase"
+        );
+        Ok(())
+    }
+}
@@ -22,6 +22,9 @@ pub mod market_data;
 #[cfg(feature = "portfolio")]
 pub mod portfolio;
 
+#[cfg(feature = "reconcile")]
+pub mod reconcile;
+
 #[cfg(feature = "staleness")]
 pub mod staleness;
 
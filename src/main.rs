@@ -1,11 +1,17 @@
+use std::io::Write;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use keepbook::app;
 use keepbook::config::{default_config_path, ResolvedConfig};
-use keepbook::storage::{JsonFileStorage, Storage};
+use keepbook::storage::{
+    prepare_for_write, AsOfStorage, DataDirLock, JsonFileStorage, LockMode, Storage,
+    DEFAULT_LOCK_TIMEOUT,
+};
 use keepbook::sync::TransactionSyncMode;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
@@ -20,6 +26,19 @@ fn parse_duration_arg(s: &str) -> Result<std::time::Duration, String> {
     keepbook::duration::parse_duration(s).map_err(|e| e.to_string())
 }
 
+/// An RFC 3339 timestamp, or a YYYY-MM-DD date meaning the end of that day (UTC).
+fn parse_as_of_arg(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(s) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(|date| {
+            date.and_time(NaiveTime::MIN).and_utc() + chrono::Duration::days(1)
+                - chrono::Duration::nanoseconds(1)
+        })
+        .map_err(|_| format!("expected an RFC 3339 timestamp or YYYY-MM-DD date, got {s:?}"))
+}
+
 #[derive(Args, Debug, Clone)]
 struct PriceSyncOptions {
     /// Force fetching even if cached data looks fresh (best-effort for quotes).
@@ -112,6 +131,20 @@ struct Cli {
     #[arg(long, global = true)]
     schwab_password: Option<String>,
 
+    /// Seconds to wait for another keepbook process to release the data dir.
+    #[arg(long, global = true, value_name = "SECONDS", default_value_t = DEFAULT_LOCK_TIMEOUT.as_secs())]
+    lock_timeout: u64,
+
+    /// Read from these profiles combined instead of the data dir: comma-separated
+    /// names from [profiles] in the config, or `all` (portfolio, spending, list and search only)
+    #[arg(long, global = true, value_name = "NAMES")]
+    profile: Option<String>,
+
+    /// Show the data as it was at this moment: an RFC 3339 timestamp, or a
+    /// YYYY-MM-DD date meaning the end of that day in UTC (spending, list and search only)
+    #[arg(long, global = true, value_name = "TIMESTAMP", value_parser = parse_as_of_arg)]
+    as_of: Option<DateTime<Utc>>,
+
     #[command(subcommand)]
     command: Option<Command>,
 }
@@ -142,6 +175,10 @@ enum Command {
     #[command(subcommand)]
     Remove(RemoveCommand),
 
+    /// Merge, move or close accounts
+    #[command(subcommand)]
+    Account(AccountCommand),
+
     /// Set/update values
     #[command(subcommand)]
     Set(SetCommand),
@@ -185,6 +222,49 @@ enum Command {
         net_worth_interval: NetWorthIntervalArg,
     },
 
+    /// Search every transaction with a query such as
+    /// `merchant:starbucks amount:<-20 category:dining after:2026-01-01`
+    ///
+    /// Terms all have to match. Bare words and "quoted phrases" search the
+    /// description, note, merchant, category, subcategory and tags; fields are
+    /// description (desc), note, merchant, category, subcategory, tag, status,
+    /// amount and date (with <, <=, =, >=, >), after, before, account and
+    /// connection. Prefix a term with - to exclude its matches.
+    Search {
+        /// The query (several arguments are joined with spaces)
+        #[arg(required = true, num_args = 1.., allow_hyphen_values = true)]
+        query: Vec<String>,
+
+        /// Sort transactions by amount (ascending)
+        #[arg(long, default_value_t = false)]
+        sort_by_amount: bool,
+
+        /// Include transactions that would otherwise be ignored by spending/list ignore rules
+        #[arg(long, default_value_t = false)]
+        include_ignored: bool,
+    },
+
+    /// Check that balance changes match the transactions between snapshots
+    Reconcile {
+        /// Only reconcile this account (ID or name)
+        #[arg(long)]
+        account: Option<String>,
+    },
+
+    /// Upgrade the data directory to the current on-disk format
+    Migrate,
+
+    /// Encrypt the data directory at rest with age
+    #[command(subcommand)]
+    Encryption(EncryptionCommand),
+
+    /// Check the data directory for malformed or inconsistent files
+    Doctor {
+        /// Quarantine malformed lines and repair the safe cases
+        #[arg(long)]
+        fix: bool,
+    },
+
     /// Spending reports based on transaction logs
     Spending {
         /// Period granularity: daily, weekly, monthly, quarterly, yearly, range, custom
@@ -335,13 +415,21 @@ enum Command {
 impl Command {
     fn edits_data(&self) -> bool {
         match self {
+            Command::Sync(SyncCommand::History { .. })
+            | Command::Sync(SyncCommand::Connection { dry_run: true, .. }) => false,
             Command::Add(_)
             | Command::Remove(_)
+            | Command::Account(_)
             | Command::Set(_)
             | Command::Propose(_)
             | Command::Import(_)
             | Command::Sync(_)
+            | Command::Migrate
+            | Command::Encryption(EncryptionCommand::Enable { .. })
+            | Command::Encryption(EncryptionCommand::Disable)
             | Command::MarketData(MarketDataCommand::Fetch { .. }) => true,
+            Command::MarketData(MarketDataCommand::Gaps { fill, .. }) => *fill,
+            Command::Doctor { fix } => *fix,
             Command::ProposedEdits(ProposedEditsCommand::List { .. }) => false,
             Command::ProposedEdits(_) => true,
             Command::Portfolio(PortfolioCommand::Snapshot {
@@ -350,6 +438,28 @@ impl Command {
             _ => false,
         }
     }
+
+    /// Whether the command can read from a combined profile view.
+    fn accepts_profile(&self) -> bool {
+        matches!(
+            self,
+            Command::Portfolio(_)
+                | Command::Spending { .. }
+                | Command::List(_)
+                | Command::Search { .. }
+        )
+    }
+
+    /// Whether the command can read from a point-in-time view.
+    fn accepts_as_of(&self) -> bool {
+        matches!(
+            self,
+            Command::Spending { .. }
+                | Command::SpendingCategories { .. }
+                | Command::List(_)
+                | Command::Search { .. }
+        )
+    }
 }
 
 #[derive(Subcommand)]
@@ -379,6 +489,53 @@ enum AddCommand {
     },
 }
 
+#[derive(Subcommand)]
+enum EncryptionCommand {
+    /// Encrypt all data files to the recipients in encryption.toml
+    Enable {
+        /// Recipient public key (SSH or age1); repeatable. Writes encryption.toml
+        #[arg(long = "recipient", value_name = "KEY")]
+        recipients: Vec<String>,
+
+        /// Recipients file relative to the data dir, one key per line
+        /// (e.g. from `keepbook-age-recipients`). Writes encryption.toml
+        #[arg(long, value_name = "PATH")]
+        recipients_file: Option<PathBuf>,
+    },
+    /// Decrypt all data files back to plaintext and remove encryption.toml
+    Disable,
+    /// Print a data file decrypted (git diff textconv helper)
+    Textconv { file: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum AccountCommand {
+    /// Move an account's history into another account and delete it
+    Merge {
+        /// Account to merge away (ID or name)
+        from: String,
+
+        /// Account that keeps the combined history (ID or name)
+        into: String,
+    },
+
+    /// Move an account to another connection
+    Move {
+        /// Account ID or name
+        account: String,
+
+        /// Connection to move it to (ID or name)
+        #[arg(long)]
+        connection: String,
+    },
+
+    /// Record a zero balance and mark an account inactive
+    Close {
+        /// Account ID or name
+        account: String,
+    },
+}
+
 #[derive(Subcommand)]
 enum SetCommand {
     /// Set or update a balance for an account
@@ -596,6 +753,9 @@ enum SyncCommand {
         /// Transaction sync mode (auto: stop when overlap detected; full: backfill as far as possible)
         #[arg(long, value_enum, default_value = "auto")]
         transactions: TransactionsModeArg,
+        /// Print what the sync would write without saving anything
+        #[arg(long, conflicts_with = "if_stale")]
+        dry_run: bool,
     },
     /// Sync all connections
     All {
@@ -622,6 +782,21 @@ enum SyncCommand {
     Recompact,
     /// Persist backfilled standardized transaction metadata to JSONL without compaction
     BackfillMetadata,
+    /// Show past sync runs (newest first) from each connection's run journal
+    History {
+        /// Only show runs for this connection (ID or name)
+        #[arg(long)]
+        connection: Option<String>,
+        /// Maximum number of runs to show
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Back out a sync batch: drop its balance snapshots and transaction
+    /// versions, and restore the account states it changed
+    Revert {
+        /// Sync batch ID (shown as batch_id in `sync history`)
+        batch_id: String,
+    },
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -685,6 +860,24 @@ enum AuthCommand {
     /// Chase authentication commands
     #[command(subcommand)]
     Chase(ChaseAuthCommand),
+    /// Cached login sessions
+    #[command(subcommand)]
+    Sessions(SessionsAuthCommand),
+}
+
+#[derive(Subcommand)]
+enum SessionsAuthCommand {
+    /// Show each cached session's capture time, age and expiry (no secrets)
+    List,
+    /// Delete cached sessions
+    Clear {
+        /// Connection ID or name (omit with --all to clear every session)
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        id_or_name: Option<String>,
+        /// Clear every cached session
+        #[arg(long)]
+        all: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -712,6 +905,12 @@ enum RemoveCommand {
         /// Connection ID to remove
         id: String,
     },
+
+    /// Remove an account and all its data
+    Account {
+        /// Account ID or name
+        account: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -747,6 +946,18 @@ enum ListCommand {
         include_ignored: bool,
     },
 
+    /// List every stored version of a transaction and every annotation
+    /// patch to it, oldest first
+    TransactionHistory {
+        /// Transaction ID
+        #[arg(long)]
+        transaction: String,
+
+        /// Only look in this account (ID or name)
+        #[arg(long)]
+        account: Option<String>,
+    },
+
     /// List everything
     All,
 }
@@ -791,6 +1002,41 @@ enum MarketDataCommand {
         #[arg(long)]
         no_fx: bool,
     },
+
+    /// Report missing trading-day price and FX coverage for held assets
+    Gaps {
+        /// Account ID or name (mutually exclusive with --connection)
+        #[arg(long)]
+        account: Option<String>,
+
+        /// Connection ID or name (mutually exclusive with --account)
+        #[arg(long)]
+        connection: Option<String>,
+
+        /// Start date (YYYY-MM-DD, default: earliest balance date in scope)
+        #[arg(long)]
+        start: Option<String>,
+
+        /// End date (YYYY-MM-DD, default: today)
+        #[arg(long)]
+        end: Option<String>,
+
+        /// Base currency for FX rates (default: from config)
+        #[arg(long)]
+        currency: Option<String>,
+
+        /// Skip FX pair coverage
+        #[arg(long)]
+        no_fx: bool,
+
+        /// Fetch the missing ranges (resumes an interrupted fill with the same arguments)
+        #[arg(long)]
+        fill: bool,
+
+        /// Fetch gaps separated by at most this many days with a single range call
+        #[arg(long, default_value_t = app::DEFAULT_BACKFILL_MERGE_DAYS)]
+        merge_days: u32,
+    },
 }
 
 #[derive(Subcommand)]
@@ -975,16 +1221,35 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
     apply_runtime_credential_overrides(&cli);
 
+    // git runs the textconv helper on temp copies of blobs, so it must not
+    // lock, merge or otherwise touch the data dir.
+    if let Some(Command::Encryption(EncryptionCommand::Textconv { file })) = &cli.command {
+        std::io::stdout().write_all(&app::encryption_textconv(file)?)?;
+        return Ok(());
+    }
+
     let config = ResolvedConfig::load_or_default(&cli.config)?;
     let storage = JsonFileStorage::new(&config.data_dir);
-    let storage_arc: Arc<dyn Storage> = Arc::new(storage.clone());
+    let mut storage_arc: Arc<dyn Storage> = Arc::new(storage.clone());
+
+    // Profile views are read-only, so commands using one never edit data.
+    let profile_view = cli.profile.is_some();
+    if profile_view && !cli.command.as_ref().is_some_and(Command::accepts_profile) {
+        anyhow::bail!("--profile only applies to portfolio, spending, list and search commands");
+    }
+    if cli.as_of.is_some() && !cli.command.as_ref().is_some_and(Command::accepts_as_of) {
+        anyhow::bail!("--as-of only applies to spending, list and search commands");
+    }
+    // Reports default to ending on the --as-of date rather than today.
+    let default_end = cli.as_of.map(|as_of| as_of.date_naive().to_string());
 
     // Pre-command hook (decoupled from CLI parsing; CLI only computes enablement).
-    let edits_data = cli
-        .command
-        .as_ref()
-        .map(|command| command.edits_data())
-        .unwrap_or(false);
+    let edits_data = !profile_view
+        && cli
+            .command
+            .as_ref()
+            .map(|command| command.edits_data())
+            .unwrap_or(false);
     let push_after_sync = if cli.git_push_after_sync {
         true
     } else if cli.skip_git_push_after_sync {
@@ -1006,6 +1271,37 @@ async fn main() -> Result<()> {
     } else {
         config.git.pull_before_edit
     };
+    // The TUI is long-lived and interactive, so it locks the data dir around
+    // each load and write instead of for its whole session.
+    let _data_dir_lock = if matches!(cli.command, Some(Command::Tui { .. })) {
+        None
+    } else {
+        let mode = if edits_data || merge_enabled {
+            LockMode::Exclusive
+        } else {
+            LockMode::Shared
+        };
+        Some(
+            DataDirLock::acquire(
+                &config.data_dir,
+                mode,
+                Duration::from_secs(cli.lock_timeout),
+            )
+            .await?,
+        )
+    };
+
+    let mut _profile_locks = Vec::new();
+    if let Some(selection) = &cli.profile {
+        _profile_locks =
+            app::lock_profile_data_dirs(&config, selection, Duration::from_secs(cli.lock_timeout))
+                .await?;
+        storage_arc = Arc::new(app::profile_storage(&config, selection)?);
+    }
+    if let Some(as_of) = cli.as_of {
+        storage_arc = Arc::new(AsOfStorage::new(storage_arc, as_of));
+    }
+
     app::run_preflight(
         &config,
         app::PreflightOptions {
@@ -1013,6 +1309,10 @@ async fn main() -> Result<()> {
             pull_remote: edits_data && pull_enabled,
         },
     )?;
+    // `migrate` does its own version checks.
+    if edits_data && !matches!(cli.command, Some(Command::Migrate)) {
+        prepare_for_write(&config.data_dir)?;
+    }
 
     match cli.command {
         Some(Command::Config) => {
@@ -1044,6 +1344,29 @@ async fn main() -> Result<()> {
                 let result = app::remove_connection(storage_arc.as_ref(), &config, &id).await?;
                 println!("{}", serde_json::to_string_pretty(&result)?);
             }
+            RemoveCommand::Account { account } => {
+                let result = app::remove_account(storage_arc.as_ref(), &config, &account).await?;
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
+        },
+
+        Some(Command::Account(account_cmd)) => match account_cmd {
+            AccountCommand::Merge { from, into } => {
+                let result = app::merge_accounts(&storage, &config, &from, &into).await?;
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
+            AccountCommand::Move {
+                account,
+                connection,
+            } => {
+                let result =
+                    app::move_account(storage_arc.as_ref(), &config, &account, &connection).await?;
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
+            AccountCommand::Close { account } => {
+                let result = app::close_account(storage_arc.as_ref(), &config, &account).await?;
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
         },
 
         Some(Command::Set(set_cmd)) => match set_cmd {
@@ -1205,10 +1528,26 @@ async fn main() -> Result<()> {
         },
 
         Some(Command::Sync(sync_cmd)) => match sync_cmd {
+            SyncCommand::Connection {
+                id_or_name,
+                dry_run: true,
+                transactions,
+                ..
+            } => {
+                let preview = app::sync_connection_dry_run(
+                    storage_arc.clone(),
+                    &config,
+                    &id_or_name,
+                    transactions.into(),
+                )
+                .await?;
+                println!("{}", serde_json::to_string_pretty(&preview)?);
+            }
             SyncCommand::Connection {
                 id_or_name,
                 if_stale,
                 transactions,
+                ..
             } => {
                 let transactions: TransactionSyncMode = transactions.into();
                 let result = if if_stale {
@@ -1275,6 +1614,15 @@ async fn main() -> Result<()> {
                 app::maybe_push_after_sync(&config, push_after_sync);
                 println!("{}", serde_json::to_string_pretty(&result)?);
             }
+            SyncCommand::History { connection, limit } => {
+                let result =
+                    app::sync_history(storage_arc.as_ref(), connection.as_deref(), limit).await?;
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
+            SyncCommand::Revert { batch_id } => {
+                let result = app::sync_revert(&storage, &config, &batch_id).await?;
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
         },
 
         Some(Command::Auth(auth_cmd)) => match auth_cmd {
@@ -1294,6 +1642,21 @@ async fn main() -> Result<()> {
                     println!("{}", serde_json::to_string_pretty(&result)?);
                 }
             },
+            AuthCommand::Sessions(sessions_cmd) => {
+                let sessions = keepbook::credentials::SessionCache::new()?;
+                match sessions_cmd {
+                    SessionsAuthCommand::List => {
+                        let result = app::auth_sessions_list(&storage, &sessions).await?;
+                        println!("{}", serde_json::to_string_pretty(&result)?);
+                    }
+                    SessionsAuthCommand::Clear { id_or_name, .. } => {
+                        let result =
+                            app::auth_sessions_clear(&storage, &sessions, id_or_name.as_deref())
+                                .await?;
+                        println!("{}", serde_json::to_string_pretty(&result)?);
+                    }
+                }
+            }
         },
 
         Some(Command::MarketData(market_cmd)) => match market_cmd {
@@ -1324,6 +1687,31 @@ async fn main() -> Result<()> {
                 .await?;
                 println!("{}", serde_json::to_string_pretty(&output)?);
             }
+            MarketDataCommand::Gaps {
+                account,
+                connection,
+                start,
+                end,
+                currency,
+                no_fx,
+                fill,
+                merge_days,
+            } => {
+                let output = app::market_data_gaps(app::MarketDataGapsRequest {
+                    storage: storage_arc.as_ref(),
+                    config: &config,
+                    account: account.as_deref(),
+                    connection: connection.as_deref(),
+                    start: start.as_deref(),
+                    end: end.as_deref(),
+                    currency,
+                    include_fx: !no_fx,
+                    fill,
+                    merge_days,
+                })
+                .await?;
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            }
         },
 
         Some(Command::List(list_cmd)) => match list_cmd {
@@ -1356,7 +1744,7 @@ async fn main() -> Result<()> {
                 let transactions = app::list_transactions(
                     storage_arc.as_ref(),
                     start,
-                    end,
+                    end.or(default_end),
                     sort_by_amount,
                     !include_ignored,
                     &config,
@@ -1365,6 +1753,19 @@ async fn main() -> Result<()> {
                 println!("{}", serde_json::to_string_pretty(&transactions)?);
             }
 
+            ListCommand::TransactionHistory {
+                transaction,
+                account,
+            } => {
+                let history = app::list_transaction_history(
+                    storage_arc.as_ref(),
+                    &transaction,
+                    account.as_deref(),
+                )
+                .await?;
+                println!("{}", serde_json::to_string_pretty(&history)?);
+            }
+
             ListCommand::All => {
                 let output = app::list_all(storage_arc.as_ref(), &config).await?;
                 println!("{}", serde_json::to_string_pretty(&output)?);
@@ -1381,6 +1782,7 @@ async fn main() -> Result<()> {
                 keepbook::tui::TuiOptions {
                     start_view: view.into(),
                     net_worth_interval: net_worth_interval.into(),
+                    lock_timeout: Duration::from_secs(cli.lock_timeout),
                 },
             )
             .await?;
@@ -1411,7 +1813,8 @@ async fn main() -> Result<()> {
                     equity_change_percent,
                     target_pre_tax_total_value,
                     auto,
-                    offline,
+                    // Each profile syncs from its own data dir.
+                    offline || profile_view,
                     dry_run,
                     force_refresh,
                 )
@@ -1550,6 +1953,52 @@ async fn main() -> Result<()> {
             }
         },
 
+        Some(Command::Search {
+            query,
+            sort_by_amount,
+            include_ignored,
+        }) => {
+            let transactions = app::search_transactions(
+                storage_arc.as_ref(),
+                &query.join(" "),
+                sort_by_amount,
+                !include_ignored,
+                &config,
+            )
+            .await?;
+            println!("{}", serde_json::to_string_pretty(&transactions)?);
+        }
+
+        Some(Command::Reconcile { account }) => {
+            let output = app::reconcile(&storage, account.as_deref()).await?;
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+
+        Some(Command::Migrate) => {
+            let output = app::migrate(&config).await?;
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+
+        Some(Command::Encryption(encryption_cmd)) => match encryption_cmd {
+            EncryptionCommand::Enable {
+                recipients,
+                recipients_file,
+            } => {
+                let result = app::enable_encryption(&config, recipients, recipients_file).await?;
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
+            EncryptionCommand::Disable => {
+                let result = app::disable_encryption(&config).await?;
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
+            EncryptionCommand::Textconv { .. } => unreachable!("handled before setup"),
+        },
+
+        Some(Command::Doctor { fix }) => {
+            let output = app::doctor(&config, fix).await?;
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+
         Some(Command::Spending {
             period,
             period_alignment,
@@ -1575,7 +2024,7 @@ async fn main() -> Result<()> {
                 app::SpendingReportOptions {
                     currency,
                     start,
-                    end,
+                    end: end.or(default_end),
                     period,
                     period_alignment: Some(period_alignment),
                     tz,
@@ -1620,7 +2069,7 @@ async fn main() -> Result<()> {
                 app::SpendingReportOptions {
                     currency,
                     start,
-                    end,
+                    end: end.or(default_end),
                     period,
                     period_alignment: Some(period_alignment),
                     tz,
@@ -3,8 +3,8 @@ use std::sync::Arc;
 
 use crate::clock::{Clock, SystemClock};
 use crate::market_data::{
-    CryptoPriceRouter, EquityPriceRouter, FxRateRouter, JsonlMarketDataStore, MarketDataService,
-    MarketDataStore, PriceSourceRegistry,
+    CryptoPriceRouter, EquityPriceRouter, FxRateRouter, IntradayQuoteStore, JsonlMarketDataStore,
+    MarketDataService, MarketDataStore, PriceSourceRegistry, SourceHealthTracker,
 };
 
 /// Builds a [`MarketDataService`] from a data directory and optional configured price sources.
@@ -18,6 +18,8 @@ pub struct MarketDataServiceBuilder {
     include_fx: bool,
     quote_staleness: Option<std::time::Duration>,
     lookback_days: Option<u32>,
+    intraday_interval: Option<std::time::Duration>,
+    fx_pivots: Option<Vec<String>>,
     offline_only: bool,
     clock: Arc<dyn Clock>,
 }
@@ -39,6 +41,8 @@ impl MarketDataServiceBuilder {
             include_fx: true,
             quote_staleness: None,
             lookback_days: None,
+            intraday_interval: None,
+            fx_pivots: None,
             offline_only: false,
             clock: Arc::new(SystemClock),
         }
@@ -73,8 +77,21 @@ impl MarketDataServiceBuilder {
         self
     }
 
+    /// Attach the intraday quote store under `data_dir`, recording at most one
+    /// live quote per `interval`.
+    pub fn with_intraday(mut self, interval: std::time::Duration) -> Self {
+        self.intraday_interval = Some(interval);
+        self
+    }
+
+    /// Override the pivot currencies used to triangulate FX rates.
+    pub fn with_fx_pivots(mut self, pivots: Vec<String>) -> Self {
+        self.fx_pivots = Some(pivots);
+        self
+    }
+
     pub async fn build(self) -> MarketDataService {
-        let mut service = MarketDataService::new(self.store, None).with_clock(self.clock);
+        let mut service = MarketDataService::new(self.store, None).with_clock(self.clock.clone());
 
         if let Some(staleness) = self.quote_staleness {
             service = service.with_quote_staleness(staleness);
@@ -84,6 +101,15 @@ impl MarketDataServiceBuilder {
             service = service.with_lookback_days(days);
         }
 
+        if let Some(pivots) = self.fx_pivots {
+            service = service.with_fx_pivots(pivots);
+        }
+
+        if let Some(interval) = self.intraday_interval {
+            service = service
+                .with_intraday_store(Arc::new(IntradayQuoteStore::new(&self.data_dir)), interval);
+        }
+
         if self.offline_only {
             return service;
         }
@@ -93,13 +119,19 @@ impl MarketDataServiceBuilder {
             tracing::warn!(error = %e, "failed to load price sources; continuing without network fetch");
             return service;
         }
+        let health = Arc::new(
+            SourceHealthTracker::load(&self.data_dir)
+                .with_quotas(registry.daily_quotas())
+                .with_clock(self.clock.clone()),
+        );
 
         if self.include_equity {
             match registry.build_equity_sources().await {
                 Ok(sources) => {
                     if !sources.is_empty() {
-                        service =
-                            service.with_equity_router(Arc::new(EquityPriceRouter::new(sources)));
+                        service = service.with_equity_router(Arc::new(
+                            EquityPriceRouter::new(sources).with_health(health.clone()),
+                        ));
                     }
                 }
                 Err(e) => {
@@ -115,8 +147,9 @@ impl MarketDataServiceBuilder {
             match registry.build_crypto_sources().await {
                 Ok(sources) => {
                     if !sources.is_empty() {
-                        service =
-                            service.with_crypto_router(Arc::new(CryptoPriceRouter::new(sources)));
+                        service = service.with_crypto_router(Arc::new(
+                            CryptoPriceRouter::new(sources).with_health(health.clone()),
+                        ));
                     }
                 }
                 Err(e) => {
@@ -132,7 +165,9 @@ impl MarketDataServiceBuilder {
             match registry.build_fx_sources().await {
                 Ok(sources) => {
                     if !sources.is_empty() {
-                        service = service.with_fx_router(Arc::new(FxRateRouter::new(sources)));
+                        service = service.with_fx_router(Arc::new(
+                            FxRateRouter::new(sources).with_health(health.clone()),
+                        ));
                     }
                 }
                 Err(e) => {
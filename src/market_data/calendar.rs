@@ -0,0 +1,438 @@
+//! Exchange trading calendars.
+//!
+//! Holiday rules are computed locally so that lookback and staleness decisions
+//! know when a new close is actually expected. A price fetched after Friday's
+//! close is still current on Saturday, Sunday and a Monday holiday.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::models::Asset;
+
+/// Trading calendar used to decide which days produce a close.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradingCalendar {
+    /// Trades every day (crypto).
+    Continuous,
+    /// Monday through Friday with no holidays. Used for FX and for equities
+    /// whose exchange is unknown.
+    Weekdays,
+    /// New York Stock Exchange and Nasdaq.
+    Nyse,
+    /// London Stock Exchange.
+    Lse,
+}
+
+/// Days the NYSE closed outside its regular holiday rules.
+const NYSE_SPECIAL_CLOSURES: &[(i32, u32, u32)] = &[
+    (2001, 9, 11),
+    (2001, 9, 12),
+    (2001, 9, 13),
+    (2001, 9, 14),
+    (2004, 6, 11),
+    (2007, 1, 2),
+    (2012, 10, 29),
+    (2012, 10, 30),
+    (2018, 12, 5),
+    (2025, 1, 9),
+];
+
+/// Days the LSE closed outside its regular holiday rules.
+const LSE_SPECIAL_CLOSURES: &[(i32, u32, u32)] = &[
+    (1999, 12, 31),
+    (2002, 6, 3),
+    (2011, 4, 29),
+    (2012, 6, 5),
+    (2022, 6, 3),
+    (2022, 9, 19),
+    (2023, 5, 8),
+];
+
+impl TradingCalendar {
+    /// Calendar for an exchange code such as `NYSE`, `NASDAQ`, `XNYS` or `LSE`.
+    pub fn for_exchange(exchange: &str) -> Option<Self> {
+        match exchange.trim().to_uppercase().as_str() {
+            "NYSE" | "XNYS" | "NASDAQ" | "XNAS" | "NYSEARCA" | "ARCA" | "AMEX" | "NYSEAMERICAN"
+            | "BATS" | "CBOE" | "US" => Some(Self::Nyse),
+            "LSE" | "XLON" | "LON" | "LN" => Some(Self::Lse),
+            _ => None,
+        }
+    }
+
+    /// Calendar for an IANA timezone from an asset registry entry.
+    pub fn for_timezone(tz: &str) -> Option<Self> {
+        match tz.trim() {
+            "America/New_York" | "US/Eastern" => Some(Self::Nyse),
+            "Europe/London" => Some(Self::Lse),
+            _ => None,
+        }
+    }
+
+    /// Calendar for an asset, using `Asset::Equity { exchange }` first and the
+    /// registry timezone second.
+    pub fn for_asset(asset: &Asset, tz: Option<&str>) -> Self {
+        match asset {
+            Asset::Crypto { .. } => Self::Continuous,
+            Asset::Currency { .. } => Self::Weekdays,
+            Asset::Equity { exchange, .. } => exchange
+                .as_deref()
+                .and_then(Self::for_exchange)
+                .or_else(|| tz.and_then(Self::for_timezone))
+                .unwrap_or(Self::Weekdays),
+        }
+    }
+
+    /// Whether the market holds a session on `date`.
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        match self {
+            Self::Continuous => true,
+            Self::Weekdays => is_weekday(date),
+            Self::Nyse => is_weekday(date) && !is_nyse_holiday(date),
+            Self::Lse => is_weekday(date) && !is_lse_holiday(date),
+        }
+    }
+
+    /// The latest trading day on or before `date`.
+    pub fn latest_trading_day(&self, date: NaiveDate) -> NaiveDate {
+        let mut day = date;
+        // No calendar has more than a handful of consecutive closed days.
+        for _ in 0..14 {
+            if self.is_trading_day(day) {
+                return day;
+            }
+            match day.pred_opt() {
+                Some(prev) => day = prev,
+                None => break,
+            }
+        }
+        date
+    }
+
+    fn session(&self) -> Option<(Tz, NaiveTime, Option<NaiveTime>)> {
+        match self {
+            Self::Continuous => None,
+            // Without an exchange the whole UTC day counts as the session.
+            Self::Weekdays => Some((chrono_tz::UTC, NaiveTime::MIN, None)),
+            Self::Nyse => Some((
+                chrono_tz::America::New_York,
+                NaiveTime::from_hms_opt(9, 30, 0)?,
+                NaiveTime::from_hms_opt(16, 0, 0),
+            )),
+            Self::Lse => Some((
+                chrono_tz::Europe::London,
+                NaiveTime::from_hms_opt(8, 0, 0)?,
+                NaiveTime::from_hms_opt(16, 30, 0),
+            )),
+        }
+    }
+
+    fn session_bounds(&self, date: NaiveDate) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let (tz, open, close) = self.session()?;
+        let local = |date: NaiveDate, time: NaiveTime| {
+            tz.from_local_datetime(&date.and_time(time))
+                .earliest()
+                .map(|dt| dt.with_timezone(&Utc))
+        };
+        let open_at = local(date, open)?;
+        let close_at = match close {
+            Some(close) => local(date, close)?,
+            None => local(date.succ_opt()?, NaiveTime::MIN)?,
+        };
+        Some((open_at, close_at))
+    }
+
+    /// When the session on `date` closes, or `None` for continuous markets and
+    /// non-trading days.
+    pub fn close_time(&self, date: NaiveDate) -> Option<DateTime<Utc>> {
+        if !self.is_trading_day(date) {
+            return None;
+        }
+        self.session_bounds(date).map(|(_, close)| close)
+    }
+
+    /// The trading day of the most recent session that closed at or before
+    /// `now`. This is the latest close a provider can be expected to have.
+    pub fn expected_latest_close(&self, now: DateTime<Utc>) -> Option<NaiveDate> {
+        let (tz, _, _) = self.session()?;
+        let mut day = now.with_timezone(&tz).date_naive();
+        for _ in 0..14 {
+            if let Some(close) = self.close_time(day) {
+                if close <= now {
+                    return Some(day);
+                }
+            }
+            day = day.pred_opt()?;
+        }
+        None
+    }
+
+    /// Whether an observation taken at `observed_at` for `as_of_date` already
+    /// reflects the latest close expected at `now`, so no newer price exists.
+    ///
+    /// Only applies on days without a session (weekends and holidays); on
+    /// trading days and for continuous markets this is always false, leaving
+    /// the decision to the flat staleness threshold.
+    pub fn is_current(
+        &self,
+        as_of_date: NaiveDate,
+        observed_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        let Some((tz, _, _)) = self.session() else {
+            return false;
+        };
+        if self.is_trading_day(now.with_timezone(&tz).date_naive()) {
+            return false;
+        }
+        let Some(expected) = self.expected_latest_close(now) else {
+            return false;
+        };
+        if as_of_date < expected {
+            return false;
+        }
+        self.close_time(expected)
+            .is_some_and(|close| observed_at >= close)
+    }
+}
+
+fn is_weekday(date: NaiveDate) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+fn ymd(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u8) -> Option<NaiveDate> {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, n)
+}
+
+fn last_weekday(year: i32, month: u32, weekday: Weekday) -> Option<NaiveDate> {
+    let first_of_next = if month == 12 {
+        ymd(year + 1, 1, 1)?
+    } else {
+        ymd(year, month + 1, 1)?
+    };
+    let mut day = first_of_next.pred_opt()?;
+    while day.weekday() != weekday {
+        day = day.pred_opt()?;
+    }
+    Some(day)
+}
+
+/// Western (Gregorian) Easter Sunday.
+fn easter_sunday(year: i32) -> Option<NaiveDate> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    ymd(year, month as u32, day as u32)
+}
+
+/// NYSE observance: Saturday holidays move to Friday, Sunday holidays to Monday.
+fn nyse_observed(date: NaiveDate) -> NaiveDate {
+    match date.weekday() {
+        Weekday::Sat => date - Duration::days(1),
+        Weekday::Sun => date + Duration::days(1),
+        _ => date,
+    }
+}
+
+fn is_special_closure(closures: &[(i32, u32, u32)], date: NaiveDate) -> bool {
+    closures.iter().any(|&(y, m, d)| ymd(y, m, d) == Some(date))
+}
+
+fn is_nyse_holiday(date: NaiveDate) -> bool {
+    let year = date.year();
+    let mut holidays = Vec::with_capacity(10);
+
+    // New Year's Day falling on a Saturday is not observed on the prior Friday.
+    if let Some(new_year) = ymd(year, 1, 1) {
+        if new_year.weekday() != Weekday::Sat {
+            holidays.push(Some(nyse_observed(new_year)));
+        }
+    }
+    holidays.push(nth_weekday(year, 1, Weekday::Mon, 3));
+    holidays.push(nth_weekday(year, 2, Weekday::Mon, 3));
+    holidays.push(easter_sunday(year).map(|easter| easter - Duration::days(2)));
+    holidays.push(last_weekday(year, 5, Weekday::Mon));
+    if year >= 2022 {
+        holidays.push(ymd(year, 6, 19).map(nyse_observed));
+    }
+    holidays.push(ymd(year, 7, 4).map(nyse_observed));
+    holidays.push(nth_weekday(year, 9, Weekday::Mon, 1));
+    holidays.push(nth_weekday(year, 11, Weekday::Thu, 4));
+    holidays.push(ymd(year, 12, 25).map(nyse_observed));
+
+    holidays
+        .into_iter()
+        .flatten()
+        .any(|holiday| holiday == date)
+        || is_special_closure(NYSE_SPECIAL_CLOSURES, date)
+}
+
+fn is_lse_holiday(date: NaiveDate) -> bool {
+    let year = date.year();
+    let mut holidays = Vec::with_capacity(10);
+
+    // Weekend New Year's Day is substituted by the following Monday.
+    holidays.push(ymd(year, 1, 1).map(|new_year| match new_year.weekday() {
+        Weekday::Sat => new_year + Duration::days(2),
+        Weekday::Sun => new_year + Duration::days(1),
+        _ => new_year,
+    }));
+    if let Some(easter) = easter_sunday(year) {
+        holidays.push(Some(easter - Duration::days(2)));
+        holidays.push(Some(easter + Duration::days(1)));
+    }
+    holidays.push(match year {
+        1995 | 2020 => ymd(year, 5, 8),
+        _ => nth_weekday(year, 5, Weekday::Mon, 1),
+    });
+    holidays.push(match year {
+        2002 | 2012 => ymd(year, 6, 4),
+        2022 => ymd(year, 6, 2),
+        _ => last_weekday(year, 5, Weekday::Mon),
+    });
+    holidays.push(last_weekday(year, 8, Weekday::Mon));
+    // Christmas and Boxing Day, with weekend days substituted by the next
+    // free weekdays.
+    if let Some(christmas) = ymd(year, 12, 25) {
+        let (first, second) = match christmas.weekday() {
+            Weekday::Fri => (0, 3),
+            Weekday::Sat => (2, 3),
+            Weekday::Sun => (1, 2),
+            _ => (0, 1),
+        };
+        holidays.push(Some(christmas + Duration::days(first)));
+        holidays.push(Some(christmas + Duration::days(second)));
+    }
+
+    holidays
+        .into_iter()
+        .flatten()
+        .any(|holiday| holiday == date)
+        || is_special_closure(LSE_SPECIAL_CLOSURES, date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn nyse_2024_holidays() {
+        let cal = TradingCalendar::Nyse;
+        for holiday in [
+            "2024-01-01",
+            "2024-01-15",
+            "2024-02-19",
+            "2024-03-29",
+            "2024-05-27",
+            "2024-06-19",
+            "2024-07-04",
+            "2024-09-02",
+            "2024-11-28",
+            "2024-12-25",
+        ] {
+            assert!(
+                !cal.is_trading_day(d(holiday)),
+                "{holiday} should be closed"
+            );
+        }
+        assert!(cal.is_trading_day(d("2024-07-05")));
+        assert!(cal.is_trading_day(d("2024-11-29")));
+    }
+
+    #[test]
+    fn nyse_observance_rules() {
+        let cal = TradingCalendar::Nyse;
+        // New Year's Day 2022 fell on a Saturday; Dec 31 2021 stayed open.
+        assert!(cal.is_trading_day(d("2021-12-31")));
+        // Juneteenth 2022 fell on a Sunday and was observed Monday.
+        assert!(!cal.is_trading_day(d("2022-06-20")));
+        // Independence Day 2026 falls on a Saturday and is observed Friday.
+        assert!(!cal.is_trading_day(d("2026-07-03")));
+        assert!(cal.is_trading_day(d("2021-06-18")));
+    }
+
+    #[test]
+    fn lse_holidays() {
+        let cal = TradingCalendar::Lse;
+        assert!(!cal.is_trading_day(d("2024-04-01"))); // Easter Monday
+        assert!(!cal.is_trading_day(d("2020-05-08"))); // VE Day move
+        assert!(!cal.is_trading_day(d("2022-12-26")));
+        assert!(!cal.is_trading_day(d("2022-12-27"))); // Christmas substitute
+        assert!(!cal.is_trading_day(d("2021-12-28"))); // Boxing Day substitute
+        assert!(cal.is_trading_day(d("2024-07-04")));
+    }
+
+    #[test]
+    fn calendar_for_asset_prefers_exchange_then_timezone() {
+        let listed = Asset::Equity {
+            ticker: "VOD".to_string(),
+            exchange: Some("LSE".to_string()),
+        };
+        assert_eq!(
+            TradingCalendar::for_asset(&listed, Some("America/New_York")),
+            TradingCalendar::Lse
+        );
+        assert_eq!(
+            TradingCalendar::for_asset(&Asset::equity("AAPL"), Some("America/New_York")),
+            TradingCalendar::Nyse
+        );
+        assert_eq!(
+            TradingCalendar::for_asset(&Asset::equity("AAPL"), None),
+            TradingCalendar::Weekdays
+        );
+        assert_eq!(
+            TradingCalendar::for_asset(&Asset::crypto("BTC"), None),
+            TradingCalendar::Continuous
+        );
+    }
+
+    #[test]
+    fn expected_latest_close_skips_long_weekend() {
+        let cal = TradingCalendar::Nyse;
+        // Memorial Day 2024 (Monday) afternoon: last close was Friday.
+        let now = utc("2024-05-27T18:00:00Z");
+        assert_eq!(cal.expected_latest_close(now), Some(d("2024-05-24")));
+        // Tuesday before the open.
+        let now = utc("2024-05-28T12:00:00Z");
+        assert_eq!(cal.expected_latest_close(now), Some(d("2024-05-24")));
+        // Tuesday after the close.
+        let now = utc("2024-05-28T21:00:00Z");
+        assert_eq!(cal.expected_latest_close(now), Some(d("2024-05-28")));
+        assert_eq!(TradingCalendar::Continuous.expected_latest_close(now), None);
+    }
+
+    #[test]
+    fn is_current_requires_observation_after_close() {
+        let cal = TradingCalendar::Nyse;
+        let now = utc("2024-05-27T18:00:00Z");
+        // Fetched after Friday's 16:00 ET close.
+        assert!(cal.is_current(d("2024-05-24"), utc("2024-05-24T21:00:00Z"), now));
+        // Fetched mid-session on Friday.
+        assert!(!cal.is_current(d("2024-05-24"), utc("2024-05-24T15:00:00Z"), now));
+        // On trading days the flat staleness threshold decides.
+        let tuesday = utc("2024-05-28T12:00:00Z");
+        assert!(!cal.is_current(d("2024-05-24"), utc("2024-05-24T21:00:00Z"), tuesday));
+    }
+}
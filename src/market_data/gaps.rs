@@ -0,0 +1,290 @@
+//! Coverage gap detection and backfill planning for stored market data.
+//!
+//! Gap detection compares the days an asset was held against the days the
+//! store already has closes for. Planning turns the resulting gaps into as few
+//! `fetch_closes` range calls as possible, and the progress journal lets a
+//! long backfill resume after an interruption.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use super::{AssetId, TradingCalendar};
+use crate::models::Asset;
+use crate::storage::write_atomic_sync;
+
+/// An inclusive range of calendar dates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateRange {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl DateRange {
+    pub fn new(start: NaiveDate, end: NaiveDate) -> Self {
+        Self { start, end }
+    }
+
+    /// Number of calendar days covered (inclusive).
+    pub fn days(&self) -> i64 {
+        (self.end - self.start).num_days() + 1
+    }
+
+    /// Iterate every calendar date in the range.
+    pub fn dates(&self) -> impl Iterator<Item = NaiveDate> {
+        let end = self.end;
+        self.start.iter_days().take_while(move |d| *d <= end)
+    }
+}
+
+/// Whether a close is expected to exist for `asset` on `date`.
+///
+/// Crypto trades every day; equities follow their exchange calendar and
+/// fall back to weekdays when the exchange is unknown.
+pub fn expects_close(asset: &Asset, date: NaiveDate) -> bool {
+    TradingCalendar::for_asset(asset, None).is_trading_day(date)
+}
+
+/// Whether an FX close is expected for `date`.
+pub fn expects_fx_close(date: NaiveDate) -> bool {
+    TradingCalendar::Weekdays.is_trading_day(date)
+}
+
+/// Collapse the dates in `expected` that are missing from `covered` into
+/// contiguous ranges.
+///
+/// `expected` must be sorted. Two missing dates belong to the same range when
+/// no expected date between them is covered, so a weekend does not split an
+/// equity gap.
+pub fn missing_ranges(expected: &[NaiveDate], covered: &BTreeSet<NaiveDate>) -> Vec<DateRange> {
+    let mut ranges = Vec::new();
+    let mut current: Option<DateRange> = None;
+
+    for date in expected {
+        if covered.contains(date) {
+            if let Some(range) = current.take() {
+                ranges.push(range);
+            }
+            continue;
+        }
+        current = Some(match current {
+            Some(range) => DateRange::new(range.start, *date),
+            None => DateRange::new(*date, *date),
+        });
+    }
+
+    if let Some(range) = current {
+        ranges.push(range);
+    }
+
+    ranges
+}
+
+/// Merge gaps into range fetch calls.
+///
+/// Gaps separated by at most `merge_within_days` calendar days are fetched
+/// with a single call: re-downloading a few covered days is cheaper than
+/// spending another request from a rate-limited budget.
+pub fn plan_range_fetches(gaps: &[DateRange], merge_within_days: u32) -> Vec<DateRange> {
+    let mut sorted = gaps.to_vec();
+    sorted.sort_by_key(|range| range.start);
+
+    let mut calls: Vec<DateRange> = Vec::new();
+    for gap in sorted {
+        match calls.last_mut() {
+            Some(last) if gap.start <= last.end + Duration::days(merge_within_days as i64 + 1) => {
+                last.end = last.end.max(gap.end);
+            }
+            _ => calls.push(gap),
+        }
+    }
+    calls
+}
+
+/// What a planned backfill call fetches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackfillTarget {
+    Price { asset: Asset, asset_id: AssetId },
+    Fx { base: String, quote: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BackfillStatus {
+    Pending,
+    Done { fetched: usize },
+    Failed { error: String },
+}
+
+/// A single range fetch in a backfill plan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackfillCall {
+    pub target: BackfillTarget,
+    pub range: DateRange,
+    #[serde(flatten)]
+    pub status: BackfillStatus,
+}
+
+/// Persisted progress for a backfill run.
+///
+/// The journal is rewritten after every call so an interrupted run can pick
+/// up its pending calls instead of re-planning from scratch. `key` identifies
+/// the request that produced the plan; a journal for a different request is
+/// discarded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackfillJournal {
+    pub key: String,
+    pub calls: Vec<BackfillCall>,
+}
+
+impl BackfillJournal {
+    pub const FILE_NAME: &'static str = "market_data_backfill.json";
+
+    pub fn path(data_dir: &Path) -> PathBuf {
+        data_dir.join(Self::FILE_NAME)
+    }
+
+    /// Load the journal for `key`, ignoring journals left by other requests.
+    pub fn load_matching(data_dir: &Path, key: &str) -> Result<Option<Self>> {
+        let path = Self::path(data_dir);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let journal: Self = match serde_json::from_str(&content) {
+            Ok(journal) => journal,
+            Err(e) => {
+                tracing::warn!(
+                    path = %path.display(),
+                    error = %e,
+                    "ignoring unreadable backfill journal"
+                );
+                return Ok(None);
+            }
+        };
+        Ok((journal.key == key).then_some(journal))
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        let path = Self::path(data_dir);
+        let content = serde_json::to_string_pretty(self)?;
+        write_atomic_sync(&path, content.as_bytes())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn remove(data_dir: &Path) -> Result<()> {
+        let path = Self::path(data_dir);
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+        }
+    }
+
+    pub fn pending(&self) -> usize {
+        self.calls
+            .iter()
+            .filter(|call| call.status == BackfillStatus::Pending)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn d(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn weekdays(start: &str, end: &str) -> Vec<NaiveDate> {
+        DateRange::new(d(start), d(end))
+            .dates()
+            .filter(|date| expects_close(&Asset::equity("AAPL"), *date))
+            .collect()
+    }
+
+    #[test]
+    fn missing_ranges_spans_weekends() {
+        // Mon 2024-01-08 .. Fri 2024-01-19, with Wed 2024-01-10 covered.
+        let expected = weekdays("2024-01-08", "2024-01-19");
+        let covered: BTreeSet<NaiveDate> = [d("2024-01-10")].into_iter().collect();
+
+        let ranges = missing_ranges(&expected, &covered);
+
+        assert_eq!(
+            ranges,
+            vec![
+                DateRange::new(d("2024-01-08"), d("2024-01-09")),
+                DateRange::new(d("2024-01-11"), d("2024-01-19")),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_ranges_empty_when_fully_covered() {
+        let expected = weekdays("2024-01-08", "2024-01-12");
+        let covered: BTreeSet<NaiveDate> = expected.iter().copied().collect();
+        assert!(missing_ranges(&expected, &covered).is_empty());
+    }
+
+    #[test]
+    fn crypto_expects_weekend_closes() {
+        assert!(expects_close(&Asset::crypto("BTC"), d("2024-01-13")));
+        assert!(!expects_close(&Asset::equity("AAPL"), d("2024-01-13")));
+        assert!(!expects_fx_close(d("2024-01-14")));
+    }
+
+    #[test]
+    fn plan_range_fetches_merges_nearby_gaps() {
+        let gaps = vec![
+            DateRange::new(d("2024-03-01"), d("2024-03-05")),
+            DateRange::new(d("2024-01-01"), d("2024-01-10")),
+            DateRange::new(d("2024-01-14"), d("2024-01-20")),
+        ];
+
+        let calls = plan_range_fetches(&gaps, 7);
+
+        assert_eq!(
+            calls,
+            vec![
+                DateRange::new(d("2024-01-01"), d("2024-01-20")),
+                DateRange::new(d("2024-03-01"), d("2024-03-05")),
+            ]
+        );
+        assert_eq!(plan_range_fetches(&gaps, 0).len(), 3);
+    }
+
+    #[test]
+    fn journal_only_resumes_matching_request() -> Result<()> {
+        let dir = TempDir::new()?;
+        let journal = BackfillJournal {
+            key: "portfolio|2024-01-01|2024-01-31|USD".to_string(),
+            calls: vec![BackfillCall {
+                target: BackfillTarget::Fx {
+                    base: "EUR".to_string(),
+                    quote: "USD".to_string(),
+                },
+                range: DateRange::new(d("2024-01-01"), d("2024-01-31")),
+                status: BackfillStatus::Pending,
+            }],
+        };
+        journal.save(dir.path())?;
+
+        let loaded =
+            BackfillJournal::load_matching(dir.path(), &journal.key)?.expect("journal should load");
+        assert_eq!(loaded.calls, journal.calls);
+        assert_eq!(loaded.pending(), 1);
+        assert!(BackfillJournal::load_matching(dir.path(), "other")?.is_none());
+
+        BackfillJournal::remove(dir.path())?;
+        assert!(BackfillJournal::load_matching(dir.path(), &journal.key)?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,526 @@
+//! Price source health tracking.
+//!
+//! Routers consult a [`SourceHealthTracker`] before calling a source so that a
+//! provider returning rate-limit or auth errors is skipped for a cooldown
+//! instead of being retried on every lookup. State is persisted in the data
+//! directory so cooldowns and daily quotas survive across CLI invocations.
+//! Several processes may share the file, so writes merge with what is on disk
+//! rather than replacing it.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::clock::{Clock, SystemClock};
+use crate::storage::write_atomic_sync;
+
+/// Consecutive failures before a source is put into cooldown.
+const FAILURE_THRESHOLD: u32 = 3;
+/// Cooldown after reaching the failure threshold; doubles per further failure.
+const BASE_COOLDOWN_MINUTES: i64 = 5;
+const MAX_COOLDOWN_MINUTES: i64 = 6 * 60;
+const RATE_LIMITED_COOLDOWN_MINUTES: i64 = 30;
+const AUTH_FAILURE_COOLDOWN_MINUTES: i64 = 6 * 60;
+/// How often a healthy source's `last_success_at` is written out.
+const SUCCESS_PERSIST_INTERVAL_MINUTES: i64 = 10;
+
+/// Persisted health state for one price source.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceHealth {
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_success_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown_until: Option<DateTime<Utc>>,
+    /// UTC day `requests_today` counts against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub requests_today: u32,
+}
+
+impl SourceHealth {
+    fn requests_on(&self, today: NaiveDate) -> u32 {
+        if self.quota_date == Some(today) {
+            self.requests_today
+        } else {
+            0
+        }
+    }
+}
+
+/// Why a router should not call a source right now.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceUnavailable {
+    CoolingDown { until: DateTime<Utc> },
+    QuotaExhausted { quota: u32 },
+}
+
+impl std::fmt::Display for SourceUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CoolingDown { until } => write!(f, "cooling down until {}", until.to_rfc3339()),
+            Self::QuotaExhausted { quota } => write!(f, "daily quota of {quota} requests used"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FailureKind {
+    RateLimited,
+    Auth,
+    Other,
+}
+
+fn classify_error(error: &anyhow::Error) -> FailureKind {
+    let message = format!("{error:#}").to_lowercase();
+    if message.contains("429") || message.contains("too many requests") {
+        FailureKind::RateLimited
+    } else if message.contains("401 unauthorized") || message.contains("403 forbidden") {
+        FailureKind::Auth
+    } else {
+        FailureKind::Other
+    }
+}
+
+/// Read-only view of a source's health for reporting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceHealthReport {
+    pub health: SourceHealth,
+    pub daily_quota: Option<u32>,
+    pub remaining_quota: Option<u32>,
+    pub unavailable: Option<SourceUnavailable>,
+}
+
+/// Tracks per-source failures, cooldowns and daily request counts.
+///
+/// Sources are keyed by [`EquityPriceSource::name`](super::EquityPriceSource::name)
+/// (and the crypto/FX equivalents).
+pub struct SourceHealthTracker {
+    path: Option<PathBuf>,
+    quotas: HashMap<String, u32>,
+    state: Arc<Mutex<TrackerState>>,
+    clock: Arc<dyn Clock>,
+}
+
+#[derive(Debug, Default)]
+struct TrackerState {
+    sources: BTreeMap<String, SourceHealth>,
+    /// Requests counted since the last write, with the UTC day they count
+    /// against. These are added to the file's counts when writing.
+    unsaved_requests: HashMap<String, (NaiveDate, u32)>,
+    /// When each source's success was last written out by this tracker.
+    success_saved_at: HashMap<String, DateTime<Utc>>,
+}
+
+impl TrackerState {
+    fn new(sources: BTreeMap<String, SourceHealth>) -> Self {
+        Self {
+            sources,
+            ..Self::default()
+        }
+    }
+}
+
+impl SourceHealthTracker {
+    pub const FILE_NAME: &'static str = "price_source_health.json";
+
+    /// In-memory tracker that is never persisted.
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            quotas: HashMap::new(),
+            state: Arc::new(Mutex::new(TrackerState::default())),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn path(data_dir: &Path) -> PathBuf {
+        data_dir.join(Self::FILE_NAME)
+    }
+
+    /// Load persisted health from `data_dir`. An unreadable file is treated as
+    /// empty so a corrupt health file never blocks price fetching.
+    pub fn load(data_dir: &Path) -> Self {
+        let path = Self::path(data_dir);
+        let state = match Self::read_state(&path) {
+            Ok(state) => state,
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "ignoring unreadable price source health");
+                BTreeMap::new()
+            }
+        };
+        Self {
+            path: Some(path),
+            quotas: HashMap::new(),
+            state: Arc::new(Mutex::new(TrackerState::new(state))),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    fn read_state(path: &Path) -> Result<BTreeMap<String, SourceHealth>> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Daily request quotas keyed by source name.
+    pub fn with_quotas(mut self, quotas: HashMap<String, u32>) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Whether `source` may be called now.
+    pub fn check(&self, source: &str) -> Result<(), SourceUnavailable> {
+        let now = self.clock.now();
+        let state = self.state.lock().expect("health lock poisoned");
+        let Some(health) = state.sources.get(source) else {
+            return Ok(());
+        };
+        Self::availability(health, self.quotas.get(source).copied(), now)
+    }
+
+    fn availability(
+        health: &SourceHealth,
+        quota: Option<u32>,
+        now: DateTime<Utc>,
+    ) -> Result<(), SourceUnavailable> {
+        if let Some(until) = health.cooldown_until {
+            if until > now {
+                return Err(SourceUnavailable::CoolingDown { until });
+            }
+        }
+        if let Some(quota) = quota {
+            if health.requests_on(now.date_naive()) >= quota {
+                return Err(SourceUnavailable::QuotaExhausted { quota });
+            }
+        }
+        Ok(())
+    }
+
+    /// Count a request against `source`'s daily quota. The count is only
+    /// written out right away for sources that have a quota.
+    pub fn record_request(&self, source: &str) {
+        let today = self.clock.today();
+        {
+            let mut state = self.state.lock().expect("health lock poisoned");
+            let health = state.sources.entry(source.to_string()).or_default();
+            health.requests_today = health.requests_on(today) + 1;
+            health.quota_date = Some(today);
+            let unsaved = state
+                .unsaved_requests
+                .entry(source.to_string())
+                .or_insert((today, 0));
+            if unsaved.0 != today {
+                *unsaved = (today, 0);
+            }
+            unsaved.1 += 1;
+        }
+        if self.quotas.contains_key(source) {
+            self.persist();
+        }
+    }
+
+    /// Record a call that reached the provider, whether or not it had data.
+    /// Written out when it ends a run of failures, and otherwise at most
+    /// every few minutes.
+    pub fn record_success(&self, source: &str) {
+        let now = self.clock.now();
+        let persist = {
+            let mut state = self.state.lock().expect("health lock poisoned");
+            let health = state.sources.entry(source.to_string()).or_default();
+            let recovered = health.consecutive_failures > 0 || health.cooldown_until.is_some();
+            health.consecutive_failures = 0;
+            health.cooldown_until = None;
+            health.last_success_at = Some(now);
+            let due = state.success_saved_at.get(source).is_none_or(|saved| {
+                now - *saved >= Duration::minutes(SUCCESS_PERSIST_INTERVAL_MINUTES)
+            });
+            if recovered || due {
+                state.success_saved_at.insert(source.to_string(), now);
+            }
+            recovered || due
+        };
+        if persist {
+            self.persist();
+        }
+    }
+
+    /// Record a failed call and start a cooldown when warranted.
+    pub fn record_failure(&self, source: &str, error: &anyhow::Error) {
+        let now = self.clock.now();
+        let kind = classify_error(error);
+        {
+            let mut state = self.state.lock().expect("health lock poisoned");
+            let health = state.sources.entry(source.to_string()).or_default();
+            health.consecutive_failures += 1;
+            health.last_error = Some(format!("{error:#}"));
+            health.last_error_at = Some(now);
+
+            let cooldown = match kind {
+                FailureKind::RateLimited => Some(RATE_LIMITED_COOLDOWN_MINUTES),
+                FailureKind::Auth => Some(AUTH_FAILURE_COOLDOWN_MINUTES),
+                FailureKind::Other if health.consecutive_failures >= FAILURE_THRESHOLD => {
+                    let doublings = (health.consecutive_failures - FAILURE_THRESHOLD).min(10);
+                    Some((BASE_COOLDOWN_MINUTES << doublings).min(MAX_COOLDOWN_MINUTES))
+                }
+                FailureKind::Other => None,
+            };
+            if let Some(minutes) = cooldown {
+                let until = now + Duration::minutes(minutes);
+                info!(
+                    source = source,
+                    failures = health.consecutive_failures,
+                    until = %until,
+                    "price source cooling down"
+                );
+                health.cooldown_until = Some(until);
+            }
+        }
+        self.persist();
+    }
+
+    /// Health, quota and availability for `source`.
+    pub fn report(&self, source: &str) -> SourceHealthReport {
+        let now = self.clock.now();
+        let health = self
+            .state
+            .lock()
+            .expect("health lock poisoned")
+            .sources
+            .get(source)
+            .cloned()
+            .unwrap_or_default();
+        let daily_quota = self.quotas.get(source).copied();
+        let remaining_quota =
+            daily_quota.map(|quota| quota.saturating_sub(health.requests_on(now.date_naive())));
+        let unavailable = Self::availability(&health, daily_quota, now).err();
+        SourceHealthReport {
+            health,
+            daily_quota,
+            remaining_quota,
+            unavailable,
+        }
+    }
+
+    /// Write the state out, off the async runtime when there is one.
+    fn persist(&self) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        let state = self.state.clone();
+        let write = move || {
+            if let Err(e) = Self::merge_and_write(&path, &state) {
+                warn!(path = %path.display(), error = %e, "failed to persist price source health");
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(write);
+            }
+            Err(_) => write(),
+        }
+    }
+
+    /// Merge the in-memory state into the file under a lock, so concurrent
+    /// writers (in this process or another) never drop each other's counts.
+    fn merge_and_write(path: &Path, state: &Mutex<TrackerState>) -> Result<()> {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir)?;
+        // Kept out of the repository like the data-dir lock.
+        let git_dir = dir.join(".git");
+        let lock_path = if git_dir.is_dir() {
+            git_dir.join("price_source_health.lock")
+        } else {
+            dir.join(".price_source_health.lock")
+        };
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+        lock.lock()
+            .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+
+        let mut merged = Self::read_state(path).unwrap_or_else(|e| {
+            warn!(path = %path.display(), error = %e, "replacing unreadable price source health");
+            BTreeMap::new()
+        });
+        {
+            let mut state = state.lock().expect("health lock poisoned");
+            let unsaved = std::mem::take(&mut state.unsaved_requests);
+            for (source, ours) in &state.sources {
+                let health = match merged.remove(source) {
+                    Some(theirs) => merge_health(ours, theirs, unsaved.get(source).copied()),
+                    None => ours.clone(),
+                };
+                merged.insert(source.clone(), health);
+            }
+            state.sources = merged.clone();
+        }
+
+        let content = serde_json::to_string_pretty(&merged)?;
+        write_atomic_sync(path, content.as_bytes())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Combine a source's in-memory health with what another writer saved: the
+/// failure state from whichever saw the latest outcome, and the saved request
+/// count plus the requests not yet written.
+fn merge_health(
+    ours: &SourceHealth,
+    theirs: SourceHealth,
+    unsaved_requests: Option<(NaiveDate, u32)>,
+) -> SourceHealth {
+    let last_outcome = |health: &SourceHealth| health.last_error_at.max(health.last_success_at);
+    let mut merged = if last_outcome(ours) >= last_outcome(&theirs) {
+        SourceHealth {
+            quota_date: theirs.quota_date,
+            requests_today: theirs.requests_today,
+            ..ours.clone()
+        }
+    } else {
+        theirs
+    };
+    if let Some((day, count)) = unsaved_requests {
+        merged.requests_today = merged.requests_on(day) + count;
+        merged.quota_date = Some(day);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn clock_at(hour: u32) -> Arc<dyn Clock> {
+        Arc::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2026, 3, 2, hour, 0, 0).unwrap(),
+        ))
+    }
+
+    #[test]
+    fn repeated_failures_trigger_cooldown() {
+        let tracker = SourceHealthTracker::in_memory().with_clock(clock_at(12));
+        let error = anyhow::anyhow!("connection reset");
+
+        tracker.record_failure("eodhd", &error);
+        tracker.record_failure("eodhd", &error);
+        assert!(tracker.check("eodhd").is_ok());
+
+        tracker.record_failure("eodhd", &error);
+        assert!(matches!(
+            tracker.check("eodhd"),
+            Err(SourceUnavailable::CoolingDown { .. })
+        ));
+
+        tracker.record_success("eodhd");
+        assert!(tracker.check("eodhd").is_ok());
+    }
+
+    #[test]
+    fn rate_limit_error_cools_down_immediately() {
+        let tracker = SourceHealthTracker::in_memory().with_clock(clock_at(12));
+        tracker.record_failure(
+            "twelve_data",
+            &anyhow::anyhow!("Twelve Data API error: 429 Too Many Requests"),
+        );
+        let report = tracker.report("twelve_data");
+        assert_eq!(report.health.consecutive_failures, 1);
+        assert!(matches!(
+            report.unavailable,
+            Some(SourceUnavailable::CoolingDown { .. })
+        ));
+    }
+
+    #[test]
+    fn quota_resets_each_day_and_persists() {
+        let dir = TempDir::new().unwrap();
+        let quotas = HashMap::from([("alpha_vantage".to_string(), 2)]);
+
+        let tracker = SourceHealthTracker::load(dir.path())
+            .with_quotas(quotas.clone())
+            .with_clock(clock_at(12));
+        tracker.record_request("alpha_vantage");
+        tracker.record_request("alpha_vantage");
+        assert_eq!(
+            tracker.check("alpha_vantage"),
+            Err(SourceUnavailable::QuotaExhausted { quota: 2 })
+        );
+
+        let reloaded = SourceHealthTracker::load(dir.path())
+            .with_quotas(quotas.clone())
+            .with_clock(clock_at(13));
+        assert_eq!(reloaded.report("alpha_vantage").remaining_quota, Some(0));
+
+        let next_day = SourceHealthTracker::load(dir.path())
+            .with_quotas(quotas)
+            .with_clock(Arc::new(FixedClock::new(
+                Utc.with_ymd_and_hms(2026, 3, 3, 0, 30, 0).unwrap(),
+            )));
+        assert!(next_day.check("alpha_vantage").is_ok());
+        assert_eq!(next_day.report("alpha_vantage").remaining_quota, Some(2));
+    }
+
+    #[test]
+    fn concurrent_trackers_add_up_requests() {
+        let dir = TempDir::new().unwrap();
+        let quotas = HashMap::from([("alpha_vantage".to_string(), 5)]);
+        let load = || {
+            SourceHealthTracker::load(dir.path())
+                .with_quotas(quotas.clone())
+                .with_clock(clock_at(12))
+        };
+
+        let first = load();
+        let second = load();
+        first.record_request("alpha_vantage");
+        second.record_request("alpha_vantage");
+        second.record_failure("alpha_vantage", &anyhow::anyhow!("connection reset"));
+        first.record_request("alpha_vantage");
+
+        assert_eq!(first.report("alpha_vantage").remaining_quota, Some(2));
+        first.record_success("alpha_vantage");
+        let report = load().report("alpha_vantage");
+        assert_eq!(report.health.requests_today, 3);
+        assert_eq!(report.health.consecutive_failures, 0);
+        assert!(report.health.last_success_at.is_some());
+    }
+
+    #[test]
+    fn healthy_success_is_persisted() {
+        let dir = TempDir::new().unwrap();
+        let tracker = SourceHealthTracker::load(dir.path()).with_clock(clock_at(12));
+        tracker.record_success("frankfurter");
+
+        let reloaded = SourceHealthTracker::load(dir.path());
+        assert_eq!(
+            reloaded.report("frankfurter").health.last_success_at,
+            Some(clock_at(12).now())
+        );
+    }
+}
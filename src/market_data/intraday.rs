@@ -0,0 +1,327 @@
+//! Rolling store of intraday quotes.
+//!
+//! Quotes are kept separately from the daily price store, one file per asset
+//! per UTC day: `intraday/{asset-id}/{YYYY-MM-DD}.jsonl`. Days older than the
+//! retention window are compacted: the day's last quote is kept in the daily
+//! store (unless it already has a close or a later quote) and the day file is
+//! removed.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use super::{AssetId, MarketDataStore, PriceKind, PricePoint};
+
+/// Counts from an [`IntradayQuoteStore::compact`] run.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IntradayCompactionStats {
+    pub days_compacted: usize,
+    pub quotes_removed: usize,
+    pub daily_prices_written: usize,
+}
+
+pub struct IntradayQuoteStore {
+    base_path: PathBuf,
+}
+
+impl IntradayQuoteStore {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            base_path: data_dir.join("intraday"),
+        }
+    }
+
+    fn asset_dir(&self, asset_id: &AssetId) -> PathBuf {
+        self.base_path.join(asset_id.to_string())
+    }
+
+    fn day_file(&self, asset_id: &AssetId, date: NaiveDate) -> PathBuf {
+        self.asset_dir(asset_id)
+            .join(format!("{}.jsonl", date.format("%Y-%m-%d")))
+    }
+
+    async fn read_day(&self, path: &Path) -> Result<Vec<PricePoint>> {
+        let content = match fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let mut quotes = Vec::new();
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let quote: PricePoint = serde_json::from_str(line)
+                .with_context(|| format!("Failed to parse JSONL line: {line}"))?;
+            quotes.push(quote);
+        }
+        quotes.sort_by_key(|quote| quote.timestamp);
+        Ok(quotes)
+    }
+
+    /// Record a quote unless one was already recorded for the same
+    /// `interval`-sized slot. Returns whether the quote was written.
+    pub async fn record(&self, quote: &PricePoint, interval: std::time::Duration) -> Result<bool> {
+        let path = self.day_file(&quote.asset_id, quote.timestamp.date_naive());
+        let existing = self.read_day(&path).await?;
+        let slot_secs = interval.as_secs().max(1) as i64;
+        let slot = |at: DateTime<Utc>| at.timestamp().div_euclid(slot_secs);
+        if existing
+            .iter()
+            .any(|prev| slot(prev.timestamp) == slot(quote.timestamp))
+        {
+            return Ok(false);
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut line = serde_json::to_string(quote).context("Failed to serialize quote")?;
+        line.push('\n');
+        file.write_all(line.as_bytes()).await?;
+        Ok(true)
+    }
+
+    /// All quotes recorded for `asset_id` on `date`, oldest first.
+    pub async fn quotes_on(&self, asset_id: &AssetId, date: NaiveDate) -> Result<Vec<PricePoint>> {
+        self.read_day(&self.day_file(asset_id, date)).await
+    }
+
+    /// The latest quote at or before `at` on the same UTC day.
+    pub async fn quote_at(
+        &self,
+        asset_id: &AssetId,
+        at: DateTime<Utc>,
+    ) -> Result<Option<PricePoint>> {
+        let quotes = self.quotes_on(asset_id, at.date_naive()).await?;
+        Ok(quotes.into_iter().rfind(|quote| quote.timestamp <= at))
+    }
+
+    /// Day files currently in the store, as `(asset_id, date, path)`.
+    async fn day_files(&self) -> Result<Vec<(AssetId, NaiveDate, PathBuf)>> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.base_path.clone()];
+
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to read {}", dir.display()))
+                }
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let Some(date) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .filter(|_| path.extension().and_then(|e| e.to_str()) == Some("jsonl"))
+                    .and_then(|stem| NaiveDate::parse_from_str(stem, "%Y-%m-%d").ok())
+                else {
+                    continue;
+                };
+                let Some(asset_id) = path
+                    .parent()
+                    .and_then(|parent| parent.strip_prefix(&self.base_path).ok())
+                    .and_then(|relative| relative.to_str())
+                    .map(|relative| AssetId::from(relative.replace('\\', "/")))
+                else {
+                    continue;
+                };
+                files.push((asset_id, date, path));
+            }
+        }
+
+        files.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+        Ok(files)
+    }
+
+    /// All retained quotes for `asset_id`, oldest first.
+    pub async fn quotes(&self, asset_id: &AssetId) -> Result<Vec<PricePoint>> {
+        let mut quotes = Vec::new();
+        for (id, _, path) in self.day_files().await? {
+            if &id == asset_id {
+                quotes.extend(self.read_day(&path).await?);
+            }
+        }
+        Ok(quotes)
+    }
+
+    /// Assets with at least one recorded quote.
+    pub async fn asset_ids(&self) -> Result<Vec<AssetId>> {
+        let mut ids: Vec<AssetId> = self
+            .day_files()
+            .await?
+            .into_iter()
+            .map(|(asset_id, _, _)| asset_id)
+            .collect();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    /// Compact days that ended more than `retention` before `now` down to one
+    /// daily price in `store`.
+    pub async fn compact(
+        &self,
+        store: &dyn MarketDataStore,
+        retention: std::time::Duration,
+        now: DateTime<Utc>,
+    ) -> Result<IntradayCompactionStats> {
+        let retention = Duration::from_std(retention).unwrap_or(Duration::MAX);
+        let cutoff = now
+            .checked_sub_signed(retention)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let mut stats = IntradayCompactionStats::default();
+
+        for (asset_id, date, path) in self.day_files().await? {
+            let day_end = date
+                .succ_opt()
+                .and_then(|next| next.and_hms_opt(0, 0, 0))
+                .map(|dt| dt.and_utc());
+            if day_end.is_none_or(|end| end > cutoff) {
+                continue;
+            }
+
+            let quotes = self.read_day(&path).await?;
+            if let Some(last) = quotes.last() {
+                let has_close = store
+                    .get_price(&asset_id, date, PriceKind::Close)
+                    .await?
+                    .is_some();
+                let has_later_quote = store
+                    .get_price(&asset_id, date, PriceKind::Quote)
+                    .await?
+                    .is_some_and(|existing| existing.timestamp >= last.timestamp);
+                if !has_close && !has_later_quote {
+                    store.put_prices(std::slice::from_ref(last)).await?;
+                    stats.daily_prices_written += 1;
+                }
+            }
+
+            fs::remove_file(&path)
+                .await
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+            stats.days_compacted += 1;
+            stats.quotes_removed += quotes.len();
+        }
+
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_data::MemoryMarketDataStore;
+    use crate::models::Asset;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn quote(asset_id: &AssetId, at: DateTime<Utc>, price: &str) -> PricePoint {
+        PricePoint {
+            asset_id: asset_id.clone(),
+            as_of_date: at.date_naive(),
+            timestamp: at,
+            price: price.to_string(),
+            quote_currency: "USD".to_string(),
+            kind: PriceKind::Quote,
+            source: "test".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn record_keeps_one_quote_per_interval() -> Result<()> {
+        let dir = TempDir::new()?;
+        let store = IntradayQuoteStore::new(dir.path());
+        let asset_id = AssetId::from_asset(&Asset::equity("AAPL"));
+        let interval = std::time::Duration::from_secs(15 * 60);
+        let at = |h, m| Utc.with_ymd_and_hms(2026, 3, 2, h, m, 0).unwrap();
+
+        assert!(
+            store
+                .record(&quote(&asset_id, at(14, 0), "100"), interval)
+                .await?
+        );
+        assert!(
+            !store
+                .record(&quote(&asset_id, at(14, 10), "101"), interval)
+                .await?
+        );
+        assert!(
+            store
+                .record(&quote(&asset_id, at(14, 15), "102"), interval)
+                .await?
+        );
+
+        let quotes = store.quotes_on(&asset_id, at(0, 0).date_naive()).await?;
+        assert_eq!(quotes.len(), 2);
+        assert_eq!(store.quotes(&asset_id).await?.len(), 2);
+        let found = store.quote_at(&asset_id, at(14, 20)).await?.unwrap();
+        assert_eq!(found.price, "102");
+        assert!(store.quote_at(&asset_id, at(13, 0)).await?.is_none());
+        assert_eq!(store.asset_ids().await?, vec![asset_id]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn compact_moves_last_quote_of_expired_days_to_daily_store() -> Result<()> {
+        let dir = TempDir::new()?;
+        let store = IntradayQuoteStore::new(dir.path());
+        let daily = MemoryMarketDataStore::new();
+        let asset_id = AssetId::from_asset(&Asset::crypto("BTC"));
+        let interval = std::time::Duration::from_secs(15 * 60);
+
+        let old_day = Utc.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap();
+        store
+            .record(&quote(&asset_id, old_day, "1"), interval)
+            .await?;
+        store
+            .record(
+                &quote(&asset_id, old_day + Duration::hours(2), "2"),
+                interval,
+            )
+            .await?;
+        let recent = Utc.with_ymd_and_hms(2026, 3, 8, 10, 0, 0).unwrap();
+        store
+            .record(&quote(&asset_id, recent, "3"), interval)
+            .await?;
+
+        let now = Utc.with_ymd_and_hms(2026, 3, 9, 12, 0, 0).unwrap();
+        let stats = store
+            .compact(&daily, std::time::Duration::from_secs(7 * 24 * 3600), now)
+            .await?;
+
+        assert_eq!(
+            stats,
+            IntradayCompactionStats {
+                days_compacted: 1,
+                quotes_removed: 2,
+                daily_prices_written: 1,
+            }
+        );
+        let kept = daily.get_all_prices(&asset_id).await?;
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].price, "2");
+        assert!(store
+            .quotes_on(&asset_id, old_day.date_naive())
+            .await?
+            .is_empty());
+        assert_eq!(
+            store.quotes_on(&asset_id, recent.date_naive()).await?.len(),
+            1
+        );
+        Ok(())
+    }
+}
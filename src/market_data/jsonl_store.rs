@@ -9,6 +9,8 @@ use serde::Serialize;
 use tokio::fs;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 
+use crate::storage::write_atomic;
+
 use super::{
     AssetId, AssetRegistryEntry, FxRateKind, FxRatePoint, MarketDataStore, PriceKind, PricePoint,
 };
@@ -108,13 +110,19 @@ impl JsonlMarketDataStore {
         let reader = BufReader::new(file);
         let mut lines = reader.lines();
         let mut items = Vec::new();
+        let mut line_number = 0usize;
 
         while let Some(line) = lines.next_line().await.context("Failed to read line")? {
+            line_number += 1;
             if line.trim().is_empty() {
                 continue;
             }
-            let item: T = serde_json::from_str(&line)
-                .with_context(|| format!("Failed to parse JSONL line: {line}"))?;
+            let item: T = serde_json::from_str(&line).with_context(|| {
+                format!(
+                    "Failed to parse JSONL line {line_number} of {} (run `keepbook doctor`): {line}",
+                    path.display()
+                )
+            })?;
             items.push(item);
         }
 
@@ -128,18 +136,20 @@ impl JsonlMarketDataStore {
 
         self.ensure_dir(path).await?;
 
+        let mut content = String::new();
+        for item in items {
+            let line = serde_json::to_string(item).context("Failed to serialize item")?;
+            content.push_str(&line);
+            content.push('\n');
+        }
+
         let mut file = fs::OpenOptions::new()
             .create(true)
             .append(true)
             .open(path)
             .await
             .context("Failed to open file for append")?;
-
-        for item in items {
-            let line = serde_json::to_string(item).context("Failed to serialize item")?;
-            file.write_all(line.as_bytes()).await?;
-            file.write_all(b"\n").await?;
-        }
+        file.write_all(content.as_bytes()).await?;
 
         Ok(())
     }
@@ -154,7 +164,7 @@ impl JsonlMarketDataStore {
             content.push('\n');
         }
 
-        fs::write(path, content)
+        write_atomic(path, content)
             .await
             .context("Failed to write JSONL file")?;
         Ok(())
@@ -1,5 +1,9 @@
 mod asset_id;
 mod builder;
+mod calendar;
+mod gaps;
+mod health;
+mod intraday;
 mod jsonl_store;
 mod models;
 mod provider;
@@ -11,11 +15,18 @@ mod sources;
 mod store;
 pub use asset_id::AssetId;
 pub use builder::MarketDataServiceBuilder;
+pub use calendar::TradingCalendar;
+pub use gaps::{
+    expects_close, expects_fx_close, missing_ranges, plan_range_fetches, BackfillCall,
+    BackfillJournal, BackfillStatus, BackfillTarget, DateRange,
+};
+pub use health::{SourceHealth, SourceHealthReport, SourceHealthTracker, SourceUnavailable};
+pub use intraday::{IntradayCompactionStats, IntradayQuoteStore};
 pub use jsonl_store::{JsonlMarketDataStore, MarketDataJsonlNormalizationStats};
 pub use models::{AssetRegistryEntry, FxRateKind, FxRatePoint, PriceKind, PricePoint};
 pub use provider::{MarketDataSource, NoopSource};
 pub use registry::PriceSourceRegistry;
-pub use service::MarketDataService;
+pub use service::{MarketDataService, DEFAULT_FX_PIVOTS};
 pub use source_config::{AssetCategory, LoadedPriceSource, PriceSourceConfig, PriceSourceType};
 pub use sources::{
     CryptoPriceRouter, CryptoPriceSource, EquityPriceRouter, EquityPriceSource, FxRateRouter,
@@ -4,7 +4,7 @@
 //! ECB publishes rates with EUR as the base currency, so cross-rate
 //! computation is needed when requesting non-EUR base currencies.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use anyhow::{anyhow, Result};
 use chrono::{NaiveDate, Utc};
@@ -31,6 +31,13 @@ struct FrankfurterResponse {
     rates: HashMap<String, f64>,
 }
 
+/// Response from the Frankfurter time-series endpoint (`/{start}..{end}`).
+#[derive(Debug, Deserialize)]
+struct FrankfurterSeriesResponse {
+    /// Rates by date; days without a fixing (weekends, holidays) are absent.
+    rates: BTreeMap<NaiveDate, HashMap<String, f64>>,
+}
+
 /// Frankfurter FX rate provider.
 ///
 /// Uses the Frankfurter API which provides ECB (European Central Bank)
@@ -86,6 +93,54 @@ impl FrankfurterRateSource {
         Ok(response.rates)
     }
 
+    /// Fetches daily rates over `start..=end` with EUR as base.
+    async fn fetch_eur_rate_series(
+        &self,
+        currencies: &[&str],
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<BTreeMap<NaiveDate, HashMap<String, f64>>> {
+        let symbols = currencies.join(",");
+        let base = self.base_url.trim_end_matches('/');
+        let url = format!("{base}/{start}..{end}?from=EUR&to={symbols}");
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<FrankfurterSeriesResponse>()
+            .await?;
+
+        Ok(response.rates)
+    }
+
+    /// The currencies to request for base/quote; EUR itself is implied.
+    fn eur_symbols<'a>(base: &'a str, quote: &'a str) -> Vec<&'a str> {
+        [base, quote]
+            .into_iter()
+            .filter(|currency| *currency != "EUR")
+            .collect()
+    }
+
+    /// base/quote from one day's EUR-based rates.
+    fn rate_from_eur_rates(base: &str, quote: &str, rates: &HashMap<String, f64>) -> Result<f64> {
+        let eur_to = |currency: &str, role: &str| -> Result<f64> {
+            if currency == "EUR" {
+                return Ok(1.0);
+            }
+            rates
+                .get(currency)
+                .copied()
+                .ok_or_else(|| anyhow!("{role} currency {currency} not found in response"))
+        };
+        Ok(Self::compute_cross_rate(
+            eur_to(base, "Base")?,
+            eur_to(quote, "Quote")?,
+        ))
+    }
+
     /// Computes the cross-rate for base/quote when base != EUR.
     ///
     /// Given EUR/base and EUR/quote rates, computes base/quote = (EUR/quote) / (EUR/base).
@@ -124,36 +179,10 @@ impl FxRateSource for FrankfurterRateSource {
             }));
         }
 
-        let rate = if base_upper == "EUR" {
-            // Direct EUR-based rate
-            let rates = self.fetch_eur_rates(&[&quote_upper], date).await?;
-            rates
-                .get(&quote_upper)
-                .copied()
-                .ok_or_else(|| anyhow!("Quote currency {quote_upper} not found in response"))?
-        } else if quote_upper == "EUR" {
-            // Inverse rate: base/EUR = 1 / (EUR/base)
-            let rates = self.fetch_eur_rates(&[&base_upper], date).await?;
-            let eur_to_base = rates
-                .get(&base_upper)
-                .copied()
-                .ok_or_else(|| anyhow!("Base currency {base_upper} not found in response"))?;
-            1.0 / eur_to_base
-        } else {
-            // Cross-rate: base/quote via EUR
-            let rates = self
-                .fetch_eur_rates(&[&base_upper, &quote_upper], date)
-                .await?;
-            let eur_to_base = rates
-                .get(&base_upper)
-                .copied()
-                .ok_or_else(|| anyhow!("Base currency {base_upper} not found in response"))?;
-            let eur_to_quote = rates
-                .get(&quote_upper)
-                .copied()
-                .ok_or_else(|| anyhow!("Quote currency {quote_upper} not found in response"))?;
-            Self::compute_cross_rate(eur_to_base, eur_to_quote)
-        };
+        let rates = self
+            .fetch_eur_rates(&Self::eur_symbols(&base_upper, &quote_upper), date)
+            .await?;
+        let rate = Self::rate_from_eur_rates(&base_upper, &quote_upper, &rates)?;
 
         Ok(Some(FxRatePoint {
             base: base_upper,
@@ -166,6 +195,40 @@ impl FxRateSource for FrankfurterRateSource {
         }))
     }
 
+    /// One request for the whole range via the time-series endpoint.
+    async fn fetch_closes(
+        &self,
+        base: &str,
+        quote: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<FxRatePoint>> {
+        let base_upper = base.to_uppercase();
+        let quote_upper = quote.to_uppercase();
+        if start > end || base_upper == quote_upper {
+            return Ok(Vec::new());
+        }
+
+        let series = self
+            .fetch_eur_rate_series(&Self::eur_symbols(&base_upper, &quote_upper), start, end)
+            .await?;
+        let timestamp = Utc::now();
+        series
+            .range(start..=end)
+            .map(|(date, rates)| {
+                Ok(FxRatePoint {
+                    base: base_upper.clone(),
+                    quote: quote_upper.clone(),
+                    as_of_date: *date,
+                    timestamp,
+                    rate: Self::rate_from_eur_rates(&base_upper, &quote_upper, rates)?.to_string(),
+                    kind: FxRateKind::Close,
+                    source: "frankfurter".to_string(),
+                })
+            })
+            .collect()
+    }
+
     fn name(&self) -> &str {
         "frankfurter"
     }
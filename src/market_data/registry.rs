@@ -2,6 +2,7 @@
 //!
 //! Loads and manages price sources from the data directory.
 
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
@@ -14,8 +15,8 @@ use super::providers::{
     AlphaVantagePriceSource, CoinCapPriceSource, CoinGeckoPriceSource, CryptoComparePriceSource,
     EodhdPriceSource, FrankfurterRateSource, MarketstackPriceSource, TwelveDataPriceSource,
 };
-use super::source_config::{LoadedPriceSource, PriceSourceConfig, PriceSourceType};
-use super::sources::{CryptoPriceSource, EquityPriceSource, FxRateSource};
+use super::source_config::{AssetCategory, LoadedPriceSource, PriceSourceConfig, PriceSourceType};
+use super::sources::{CryptoPriceSource, EquityPriceSource, FxRateSource, RateLimitConfig};
 
 /// Registry of configured price sources.
 ///
@@ -233,6 +234,33 @@ impl PriceSourceRegistry {
         Ok(sources)
     }
 
+    /// The tightest configured rate limit among loaded sources for `category`.
+    ///
+    /// Routers may fall through to any source in the category, so bulk callers
+    /// pace themselves against the slowest one.
+    pub fn rate_limit_for(&self, category: AssetCategory) -> Option<RateLimitConfig> {
+        self.loaded
+            .iter()
+            .filter(|s| s.config.source_type.supported_assets().contains(&category))
+            .filter_map(|s| s.config.rate_limit.clone())
+            .max_by_key(|limit| limit.min_interval())
+    }
+
+    /// Configured daily quotas keyed by runtime source name. When several
+    /// directories configure the same provider, the smallest quota wins.
+    pub fn daily_quotas(&self) -> HashMap<String, u32> {
+        let mut quotas: HashMap<String, u32> = HashMap::new();
+        for loaded in &self.loaded {
+            if let Some(quota) = loaded.config.daily_quota {
+                quotas
+                    .entry(loaded.config.source_type.provider_name().to_string())
+                    .and_modify(|existing| *existing = (*existing).min(quota))
+                    .or_insert(quota);
+            }
+        }
+        quotas
+    }
+
     /// Get the path to the price_sources directory.
     pub fn sources_dir(&self) -> &Path {
         &self.sources_dir
@@ -264,6 +292,8 @@ mod tests {
                 priority: 1,
                 credentials: None,
                 config: None,
+                rate_limit: None,
+                daily_quota: None,
             },
         }];
 
@@ -316,6 +346,33 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn rate_limit_for_picks_slowest_source_in_category() -> Result<()> {
+        let dir = TempDir::new()?;
+        let sources_dir = dir.path().join("price_sources");
+
+        for (name, max_requests) in [("coingecko", 30), ("coincap", 5)] {
+            let source_dir = sources_dir.join(name);
+            fs::create_dir_all(&source_dir)?;
+            let mut file = fs::File::create(source_dir.join("source.toml"))?;
+            writeln!(file, r#"type = "{name}""#)?;
+            writeln!(file, "[rate_limit]")?;
+            writeln!(file, "max_requests = {max_requests}")?;
+            writeln!(file, "window_seconds = 60")?;
+        }
+
+        let mut registry = PriceSourceRegistry::new(dir.path());
+        registry.load()?;
+
+        assert_eq!(
+            registry.rate_limit_for(AssetCategory::Crypto),
+            Some(RateLimitConfig::new(5, 60))
+        );
+        assert_eq!(registry.rate_limit_for(AssetCategory::Equity), None);
+
+        Ok(())
+    }
+
     #[test]
     fn test_disabled_source_not_loaded() -> Result<()> {
         let dir = TempDir::new()?;
@@ -1,16 +1,21 @@
+use std::str::FromStr;
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
-use chrono::{Duration, NaiveDate};
-use tracing::{debug, info};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use tracing::{debug, info, warn};
 
 use crate::clock::{Clock, SystemClock};
 
 use super::{
     AssetId, CryptoPriceRouter, EquityPriceRouter, FxRateKind, FxRatePoint, FxRateRouter,
-    MarketDataSource, MarketDataStore, PricePoint,
+    IntradayQuoteStore, MarketDataSource, MarketDataStore, PriceKind, PricePoint, TradingCalendar,
 };
-use crate::models::Asset;
+use crate::models::{is_iso_currency_code, Asset};
+
+/// Pivot currencies used for FX triangulation unless configured otherwise.
+pub const DEFAULT_FX_PIVOTS: &[&str] = &["USD", "EUR"];
 
 pub struct MarketDataService {
     store: Arc<dyn MarketDataStore>,
@@ -28,6 +33,12 @@ pub struct MarketDataService {
     allow_future_projection: bool,
     /// How old a quote can be before we fetch a new one. None means always fetch.
     quote_staleness: Option<std::time::Duration>,
+    /// Rolling intraday store that live quotes are recorded into, with the
+    /// spacing between recorded quotes.
+    intraday: Option<(Arc<IntradayQuoteStore>, std::time::Duration)>,
+    /// Currencies tried, in order, as intermediates when no direct or inverse
+    /// FX rate exists.
+    fx_pivots: Vec<String>,
     clock: Arc<dyn Clock>,
 }
 
@@ -46,6 +57,8 @@ impl MarketDataService {
             fetch_lookback_days: 7,
             allow_future_projection: false,
             quote_staleness: None,
+            intraday: None,
+            fx_pivots: DEFAULT_FX_PIVOTS.iter().map(|c| c.to_string()).collect(),
             clock: Arc::new(SystemClock),
         }
     }
@@ -65,6 +78,8 @@ impl MarketDataService {
         self
     }
 
+    /// Bound store lookups and fetch attempts to `days` calendar days back.
+    /// Fetch attempts skip days without a session on the asset's trading calendar.
     pub fn with_lookback_days(mut self, days: u32) -> Self {
         self.store_lookback_days = Some(days);
         self.fetch_lookback_days = days;
@@ -76,6 +91,24 @@ impl MarketDataService {
         self
     }
 
+    /// Record live quotes into `store`, at most one per `interval`.
+    pub fn with_intraday_store(
+        mut self,
+        store: Arc<IntradayQuoteStore>,
+        interval: std::time::Duration,
+    ) -> Self {
+        self.intraday = Some((store, interval));
+        self
+    }
+
+    pub fn with_fx_pivots(mut self, pivots: Vec<String>) -> Self {
+        self.fx_pivots = pivots
+            .into_iter()
+            .map(|pivot| pivot.trim().to_uppercase())
+            .collect();
+        self
+    }
+
     pub fn with_future_projection(mut self, enabled: bool) -> Self {
         self.allow_future_projection = enabled;
         self
@@ -158,8 +191,12 @@ impl MarketDataService {
             return Ok(price);
         }
 
+        let calendar = self.calendar_for(&asset, &asset_id).await?;
         for offset in 0..=self.fetch_lookback_days {
             let target_date = date - Duration::days(offset as i64);
+            if !calendar.is_trading_day(target_date) {
+                continue;
+            }
             if let Some(price) = self
                 .fetch_price_from_sources(&asset, &asset_id, target_date)
                 .await?
@@ -195,8 +232,12 @@ impl MarketDataService {
 
         let had_cached = self.price_from_store(&asset, date).await?.is_some();
 
+        let calendar = self.calendar_for(&asset, &asset_id).await?;
         for offset in 0..=self.fetch_lookback_days {
             let target_date = date - Duration::days(offset as i64);
+            if !calendar.is_trading_day(target_date) {
+                continue;
+            }
             if let Some(price) = self
                 .fetch_price_from_sources(&asset, &asset_id, target_date)
                 .await?
@@ -279,14 +320,18 @@ impl MarketDataService {
         // Check for a cached same-day price first if staleness is configured (unless forced).
         if !force {
             if let Some(staleness) = self.quote_staleness {
+                let now = self.clock.now();
+                let calendar = self.calendar_for(&asset, &asset_id).await?;
                 if let Some(cached) = self
                     .latest_price_on_date_from_store(&asset_id, date)
                     .await?
                 {
-                    let age = (self.clock.now() - cached.timestamp)
+                    let age = (now - cached.timestamp)
                         .to_std()
                         .unwrap_or(std::time::Duration::ZERO);
-                    if age < staleness {
+                    if age < staleness
+                        || calendar.is_current(cached.as_of_date, cached.timestamp, now)
+                    {
                         debug!(
                             asset_id = %asset_id,
                             price = %cached.price,
@@ -302,6 +347,18 @@ impl MarketDataService {
                         staleness_secs = staleness.as_secs(),
                         "cached price is stale, fetching new quote"
                     );
+                } else if let Some(cached) = self.price_from_store(&asset, date).await? {
+                    // No session has closed since the cached price was taken
+                    // (weekend or holiday), so a new quote would not differ.
+                    if calendar.is_current(cached.as_of_date, cached.timestamp, now) {
+                        debug!(
+                            asset_id = %asset_id,
+                            date = %cached.as_of_date,
+                            price = %cached.price,
+                            "returning cached price (market closed since)"
+                        );
+                        return Ok((cached, false));
+                    }
                 }
             }
         }
@@ -316,6 +373,14 @@ impl MarketDataService {
                 "live quote fetched and stored"
             );
             self.store.put_prices(std::slice::from_ref(&price)).await?;
+            if let Some((intraday, interval)) = &self.intraday {
+                if price.kind == PriceKind::Quote {
+                    // The intraday store is only a cache for hourly history.
+                    if let Err(e) = intraday.record(&price, *interval).await {
+                        warn!(asset_id = %asset_id, error = %e, "failed to record intraday quote");
+                    }
+                }
+            }
             return Ok((price, true));
         }
 
@@ -343,6 +408,34 @@ impl MarketDataService {
         Ok((price, true))
     }
 
+    /// Latest recorded intraday quote at or before `at` on the same UTC day.
+    /// Returns `None` when no intraday store is attached.
+    pub async fn intraday_price_at(
+        &self,
+        asset: &Asset,
+        at: DateTime<Utc>,
+    ) -> Result<Option<PricePoint>> {
+        let Some((intraday, _)) = &self.intraday else {
+            return Ok(None);
+        };
+        let asset_id = AssetId::from_asset(&asset.normalized());
+        intraday.quote_at(&asset_id, at).await
+    }
+
+    /// Trading calendar for an asset. Equities without an exchange fall back to
+    /// the timezone recorded in the asset registry.
+    async fn calendar_for(&self, asset: &Asset, asset_id: &AssetId) -> Result<TradingCalendar> {
+        let tz = match asset {
+            Asset::Equity { exchange: None, .. } => self
+                .store
+                .get_asset_entry(asset_id)
+                .await?
+                .and_then(|entry| entry.tz),
+            _ => None,
+        };
+        Ok(TradingCalendar::for_asset(asset, tz.as_deref()))
+    }
+
     async fn latest_price_on_date_from_store(
         &self,
         asset_id: &AssetId,
@@ -369,7 +462,80 @@ impl MarketDataService {
             });
         }
 
-        if let Some(rate) = self.fx_from_store(&base, &quote, date).await? {
+        // Everything cached, including pivot legs, is tried before fetching.
+        for fetch in [false, true] {
+            if let Some(rate) = self.fx_leg(&base, &quote, date, fetch).await? {
+                return Ok(rate);
+            }
+            if let Some(rate) = self.fx_via_pivot(&base, &quote, date, fetch).await? {
+                return Ok(rate);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "No FX rate found for {base}->{quote} on or before {date}"
+        ))
+    }
+
+    /// Triangulate through a pivot currency, e.g. GBP -> USD -> THB.
+    async fn fx_via_pivot(
+        &self,
+        base: &str,
+        quote: &str,
+        date: NaiveDate,
+        fetch: bool,
+    ) -> Result<Option<FxRatePoint>> {
+        for pivot in &self.fx_pivots {
+            if pivot == base || pivot == quote {
+                continue;
+            }
+            let Some(first) = self.fx_pivot_leg(base, pivot, date, fetch).await? else {
+                continue;
+            };
+            let Some(second) = self.fx_pivot_leg(pivot, quote, date, fetch).await? else {
+                continue;
+            };
+            let rate = compose_fx_rates(&first, &second)?;
+            debug!(
+                base = %base,
+                quote = %quote,
+                pivot = %pivot,
+                rate = %rate.rate,
+                source = %rate.source,
+                "FX rate triangulated"
+            );
+            return Ok(Some(rate));
+        }
+        Ok(None)
+    }
+
+    /// A leg for triangulation. Legs older than the fetch lookback window are
+    /// dropped so a long-stale rate is never combined into a current one.
+    async fn fx_pivot_leg(
+        &self,
+        base: &str,
+        quote: &str,
+        date: NaiveDate,
+        fetch: bool,
+    ) -> Result<Option<FxRatePoint>> {
+        let max_age = Duration::days(i64::from(self.fetch_lookback_days));
+        Ok(self
+            .fx_leg(base, quote, date, fetch)
+            .await?
+            .filter(|leg| date - leg.as_of_date <= max_age))
+    }
+
+    /// A single conversion step: a direct rate, the inverse of the reverse
+    /// pair, or a crypto price when one side is a crypto asset (e.g. USDC).
+    /// Only cached data is used unless `fetch` is set.
+    async fn fx_leg(
+        &self,
+        base: &str,
+        quote: &str,
+        date: NaiveDate,
+        fetch: bool,
+    ) -> Result<Option<FxRatePoint>> {
+        if let Some(rate) = self.fx_from_store(base, quote, date).await? {
             debug!(
                 base = %base,
                 quote = %quote,
@@ -377,15 +543,44 @@ impl MarketDataService {
                 rate = %rate.rate,
                 "FX rate found in cache"
             );
-            return Ok(rate);
+            return Ok(Some(rate));
+        }
+        if let Some(rate) = self.fx_from_store(quote, base, date).await? {
+            return invert_fx_rate(&rate);
+        }
+
+        if fetch {
+            if let Some(rate) = self.fetch_fx_close(base, quote, date).await? {
+                return Ok(Some(rate));
+            }
+            if let Some(rate) = self.fetch_fx_close(quote, base, date).await? {
+                return invert_fx_rate(&rate);
+            }
+        }
+
+        if let Some(rate) = self.crypto_fx_rate(base, quote, date, fetch).await? {
+            return Ok(Some(rate));
+        }
+        if let Some(rate) = self.crypto_fx_rate(quote, base, date, fetch).await? {
+            return invert_fx_rate(&rate);
         }
 
+        Ok(None)
+    }
+
+    /// Fetch and store a direct close, stepping back over weekends.
+    async fn fetch_fx_close(
+        &self,
+        base: &str,
+        quote: &str,
+        date: NaiveDate,
+    ) -> Result<Option<FxRatePoint>> {
         for offset in 0..=self.fetch_lookback_days {
             let target_date = date - Duration::days(offset as i64);
-            if let Some(rate) = self
-                .fetch_fx_from_sources(&base, &quote, target_date)
-                .await?
-            {
+            if !TradingCalendar::Weekdays.is_trading_day(target_date) {
+                continue;
+            }
+            if let Some(rate) = self.fetch_fx_from_sources(base, quote, target_date).await? {
                 info!(
                     base = %base,
                     quote = %quote,
@@ -395,13 +590,49 @@ impl MarketDataService {
                     "FX rate fetched and stored"
                 );
                 self.store.put_fx_rates(std::slice::from_ref(&rate)).await?;
-                return Ok(rate);
+                return Ok(Some(rate));
             }
         }
+        Ok(None)
+    }
 
-        Err(anyhow::anyhow!(
-            "No FX rate found for {base}->{quote} on or before {date}"
-        ))
+    /// Treat `symbol` as a crypto asset and express its price as an FX rate
+    /// into `quote`. ISO 4217 codes are never looked up as crypto, and
+    /// fetching needs `fetch` and a crypto router.
+    async fn crypto_fx_rate(
+        &self,
+        symbol: &str,
+        quote: &str,
+        date: NaiveDate,
+        fetch: bool,
+    ) -> Result<Option<FxRatePoint>> {
+        if is_iso_currency_code(symbol) {
+            return Ok(None);
+        }
+        let asset = Asset::crypto(symbol);
+        let price = match self.price_from_store(&asset, date).await? {
+            Some(price) => price,
+            None if fetch && self.crypto_router.is_some() => {
+                match self.price_close(&asset, date).await {
+                    Ok(price) => price,
+                    Err(_) => return Ok(None),
+                }
+            }
+            None => return Ok(None),
+        };
+        if !price.quote_currency.eq_ignore_ascii_case(quote) {
+            return Ok(None);
+        }
+
+        Ok(Some(FxRatePoint {
+            base: symbol.to_string(),
+            quote: quote.to_string(),
+            as_of_date: price.as_of_date,
+            timestamp: price.timestamp,
+            rate: price.price,
+            kind: FxRateKind::Close,
+            source: format!("crypto({})", price.source),
+        }))
     }
 
     /// Like [`Self::fx_close`] but tries to fetch from sources first, even if the store already
@@ -434,21 +665,40 @@ impl MarketDataService {
 
         let had_cached = self.fx_from_store(&base, &quote, date).await?.is_some();
 
-        for offset in 0..=self.fetch_lookback_days {
-            let target_date = date - Duration::days(offset as i64);
-            if let Some(rate) = self
-                .fetch_fx_from_sources(&base, &quote, target_date)
-                .await?
-            {
-                self.store.put_fx_rates(std::slice::from_ref(&rate)).await?;
-                return Ok((rate, true));
-            }
+        if let Some(rate) = self.fetch_fx_close(&base, &quote, date).await? {
+            return Ok((rate, true));
         }
 
         let rate = self.fx_close(&base, &quote, date).await?;
         Ok((rate, !had_cached))
     }
 
+    /// Fetch and store daily FX closes for a pair over a date range.
+    pub async fn fx_closes_range(
+        &self,
+        base: &str,
+        quote: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<FxRatePoint>> {
+        let base = base.trim().to_uppercase();
+        let quote = quote.trim().to_uppercase();
+        if start > end || base == quote {
+            return Ok(Vec::new());
+        }
+
+        let rates = match &self.fx_router {
+            Some(router) => router.fetch_closes(&base, &quote, start, end).await?,
+            None => Vec::new(),
+        };
+
+        if !rates.is_empty() {
+            self.store.put_fx_rates(&rates).await?;
+        }
+
+        Ok(rates)
+    }
+
     /// Get FX rate from store only, no external fetching.
     /// Returns the latest close on or before `date`.
     ///
@@ -647,6 +897,50 @@ impl MarketDataService {
     }
 }
 
+fn parse_fx_rate(rate: &FxRatePoint) -> Result<Decimal> {
+    Decimal::from_str(rate.rate.trim()).with_context(|| {
+        format!(
+            "Invalid FX rate {} for {}/{}",
+            rate.rate, rate.base, rate.quote
+        )
+    })
+}
+
+/// The reverse of `rate`, or `None` when it is zero or too small to invert.
+fn invert_fx_rate(rate: &FxRatePoint) -> Result<Option<FxRatePoint>> {
+    let value = parse_fx_rate(rate)?;
+    let Some(inverse) = Decimal::ONE.checked_div(value) else {
+        return Ok(None);
+    };
+    Ok(Some(FxRatePoint {
+        base: rate.quote.clone(),
+        quote: rate.base.clone(),
+        as_of_date: rate.as_of_date,
+        timestamp: rate.timestamp,
+        rate: inverse.normalize().to_string(),
+        kind: rate.kind,
+        source: format!("inverse({})", rate.source),
+    }))
+}
+
+/// Chain `first` (A/B) and `second` (B/C) into A/C. The source records each
+/// leg, e.g. `GBP/USD[frankfurter] -> USD/THB[inverse(frankfurter)]`.
+fn compose_fx_rates(first: &FxRatePoint, second: &FxRatePoint) -> Result<FxRatePoint> {
+    let rate = parse_fx_rate(first)? * parse_fx_rate(second)?;
+    Ok(FxRatePoint {
+        base: first.base.clone(),
+        quote: second.quote.clone(),
+        as_of_date: first.as_of_date.min(second.as_of_date),
+        timestamp: first.timestamp.min(second.timestamp),
+        rate: rate.normalize().to_string(),
+        kind: FxRateKind::Close,
+        source: format!(
+            "{}/{}[{}] -> {}/{}[{}]",
+            first.base, first.quote, first.source, second.base, second.quote, second.source
+        ),
+    })
+}
+
 fn select_latest_price_on_or_before(
     prices: Vec<PricePoint>,
     date: NaiveDate,
@@ -729,9 +1023,9 @@ fn select_earliest_fx_rate_on_or_after(
 mod tests {
     use super::*;
     use crate::clock::FixedClock;
-    use crate::market_data::{AssetId, EquityPriceRouter, EquityPriceSource};
-    use crate::market_data::{MemoryMarketDataStore, PriceKind, PricePoint};
-    use chrono::{TimeZone, Utc};
+    use crate::market_data::{AssetId, EquityPriceRouter, EquityPriceSource, FxRateSource};
+    use crate::market_data::{MemoryMarketDataStore, PricePoint};
+    use chrono::TimeZone;
     use std::sync::Arc;
 
     struct FixedEquityQuoteSource {
@@ -881,6 +1175,44 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn price_latest_records_live_quote_in_intraday_store() -> Result<()> {
+        let now = Utc.with_ymd_and_hms(2026, 2, 6, 15, 5, 0).unwrap();
+        let dir = tempfile::TempDir::new()?;
+        let intraday = Arc::new(IntradayQuoteStore::new(dir.path()));
+
+        let asset = Asset::Equity {
+            ticker: "AAPL".to_string(),
+            exchange: Some("NASDAQ".to_string()),
+        };
+        let asset_id = AssetId::from_asset(&asset.normalized());
+        let today = now.date_naive();
+
+        let src_quote = make_quote(&asset_id, today, now, "200");
+        let router = Arc::new(EquityPriceRouter::new(vec![Arc::new(
+            FixedEquityQuoteSource { point: src_quote },
+        )]));
+        let svc = MarketDataService::new(Arc::new(MemoryMarketDataStore::default()), None)
+            .with_clock(Arc::new(FixedClock::new(now)))
+            .with_equity_router(router)
+            .with_intraday_store(intraday.clone(), std::time::Duration::from_secs(15 * 60));
+
+        assert!(svc
+            .intraday_price_at(&asset, now - chrono::Duration::minutes(1))
+            .await?
+            .is_none());
+        svc.price_latest_force(&asset, today).await?;
+        svc.price_latest_force(&asset, today).await?;
+
+        assert_eq!(intraday.quotes_on(&asset_id, today).await?.len(), 1);
+        let recorded = svc
+            .intraday_price_at(&asset, now + chrono::Duration::minutes(30))
+            .await?
+            .expect("recorded quote");
+        assert_eq!(recorded.price, "200");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn price_latest_with_status_uses_stale_quote_before_older_close_when_fetch_unavailable(
     ) -> Result<()> {
@@ -913,6 +1245,107 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn price_latest_with_status_keeps_close_over_market_holiday() -> Result<()> {
+        // Memorial Day 2024: no session has closed since Friday.
+        let now = Utc.with_ymd_and_hms(2024, 5, 27, 18, 0, 0).unwrap();
+        let clock = Arc::new(FixedClock::new(now));
+
+        let store = Arc::new(MemoryMarketDataStore::default());
+        let asset = Asset::Equity {
+            ticker: "AAPL".to_string(),
+            exchange: Some("NASDAQ".to_string()),
+        };
+        let asset_id = AssetId::from_asset(&asset.normalized());
+        let friday = NaiveDate::from_ymd_opt(2024, 5, 24).unwrap();
+        let friday_close = make_close(
+            &asset_id,
+            friday,
+            Utc.with_ymd_and_hms(2024, 5, 24, 21, 0, 0).unwrap(),
+            "100",
+        );
+        store
+            .put_prices(std::slice::from_ref(&friday_close))
+            .await?;
+
+        let router = Arc::new(EquityPriceRouter::new(vec![Arc::new(
+            FixedEquityQuoteSource {
+                point: make_quote(&asset_id, now.date_naive(), now, "200"),
+            },
+        )]));
+        let svc = MarketDataService::new(store.clone(), None)
+            .with_quote_staleness(std::time::Duration::from_secs(3600))
+            .with_equity_router(router)
+            .with_clock(clock);
+
+        let (p, fetched) = svc
+            .price_latest_with_status(&asset, now.date_naive())
+            .await?;
+        assert!(!fetched);
+        assert_eq!(p.as_of_date, friday);
+        assert_eq!(p.price, "100");
+        Ok(())
+    }
+
+    struct RecordingCloseSource {
+        requested: std::sync::Mutex<Vec<NaiveDate>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EquityPriceSource for RecordingCloseSource {
+        async fn fetch_close(
+            &self,
+            _asset: &Asset,
+            _asset_id: &AssetId,
+            date: NaiveDate,
+        ) -> Result<Option<PricePoint>> {
+            self.requested.lock().unwrap().push(date);
+            Ok(None)
+        }
+
+        async fn fetch_quote(
+            &self,
+            _asset: &Asset,
+            _asset_id: &AssetId,
+        ) -> Result<Option<PricePoint>> {
+            Ok(None)
+        }
+
+        fn name(&self) -> &str {
+            "recording"
+        }
+    }
+
+    #[tokio::test]
+    async fn price_close_lookback_skips_non_trading_days() -> Result<()> {
+        let store = Arc::new(MemoryMarketDataStore::default());
+        let source = Arc::new(RecordingCloseSource {
+            requested: std::sync::Mutex::new(Vec::new()),
+        });
+        let router = Arc::new(EquityPriceRouter::new(vec![
+            source.clone() as Arc<dyn EquityPriceSource>
+        ]));
+        let svc = MarketDataService::new(store, None)
+            .with_lookback_days(4)
+            .with_equity_router(router);
+
+        let asset = Asset::Equity {
+            ticker: "AAPL".to_string(),
+            exchange: Some("NYSE".to_string()),
+        };
+        let memorial_day = NaiveDate::from_ymd_opt(2024, 5, 27).unwrap();
+        assert!(svc.price_close(&asset, memorial_day).await.is_err());
+
+        assert_eq!(
+            *source.requested.lock().unwrap(),
+            vec![
+                NaiveDate::from_ymd_opt(2024, 5, 24).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 5, 23).unwrap(),
+            ]
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn price_latest_force_ignores_fresh_cached_quote() -> Result<()> {
         let now = Utc.with_ymd_and_hms(2026, 2, 6, 12, 0, 0).unwrap();
@@ -1127,4 +1560,137 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn fx_close_triangulates_through_pivot_with_inverse_leg() -> Result<()> {
+        let date = NaiveDate::from_ymd_opt(2026, 2, 4).unwrap();
+        let ts = Utc.with_ymd_and_hms(2026, 2, 4, 16, 0, 0).unwrap();
+        let store = Arc::new(MemoryMarketDataStore::default());
+        store
+            .put_fx_rates(&[
+                make_fx_close("EUR", "GBP", date, ts, "0.8"),
+                make_fx_close("EUR", "JPY", date, ts, "160"),
+            ])
+            .await?;
+        let svc = MarketDataService::new(store, None);
+
+        let inverse = svc.fx_close("GBP", "EUR", date).await?;
+        assert_eq!(inverse.rate, "1.25");
+        assert_eq!(inverse.source, "inverse(fixed)");
+
+        let rate = svc.fx_close("GBP", "JPY", date).await?;
+        assert_eq!(rate.rate, "200");
+        assert_eq!(rate.source, "GBP/EUR[inverse(fixed)] -> EUR/JPY[fixed]");
+
+        let no_pivots = MarketDataService::new(Arc::new(MemoryMarketDataStore::default()), None)
+            .with_fx_pivots(Vec::new());
+        assert!(no_pivots.fx_close("GBP", "JPY", date).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fx_close_ignores_pivot_legs_older_than_lookback() -> Result<()> {
+        let date = NaiveDate::from_ymd_opt(2026, 2, 4).unwrap();
+        let old = NaiveDate::from_ymd_opt(2023, 2, 3).unwrap();
+        let ts = Utc.with_ymd_and_hms(2026, 2, 4, 16, 0, 0).unwrap();
+        let old_ts = Utc.with_ymd_and_hms(2023, 2, 3, 16, 0, 0).unwrap();
+        let store = Arc::new(MemoryMarketDataStore::default());
+        store
+            .put_fx_rates(&[
+                make_fx_close("GBP", "USD", date, ts, "1.25"),
+                make_fx_close("USD", "THB", old, old_ts, "30"),
+            ])
+            .await?;
+        let svc = MarketDataService::new(store, None);
+
+        assert!(svc.fx_close("GBP", "THB", date).await.is_err());
+        // A direct lookup still falls back to the old rate.
+        assert_eq!(svc.fx_close("USD", "THB", date).await?.rate, "30");
+        Ok(())
+    }
+
+    #[test]
+    fn invert_fx_rate_handles_tiny_and_zero_rates() {
+        let date = NaiveDate::from_ymd_opt(2026, 2, 4).unwrap();
+        let ts = Utc.with_ymd_and_hms(2026, 2, 4, 16, 0, 0).unwrap();
+        let tiny = make_fx_close("ABC", "XYZ", date, ts, "0.0000000000000000000000000001");
+        let inverse = invert_fx_rate(&tiny).unwrap().expect("inverse");
+        assert_eq!(inverse.rate, "10000000000000000000000000000");
+        let zero = make_fx_close("ABC", "XYZ", date, ts, "0");
+        assert!(invert_fx_rate(&zero).unwrap().is_none());
+    }
+
+    struct CountingFxSource {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl FxRateSource for CountingFxSource {
+        async fn fetch_close(
+            &self,
+            _base: &str,
+            _quote: &str,
+            _date: NaiveDate,
+        ) -> Result<Option<FxRatePoint>> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(None)
+        }
+
+        fn name(&self) -> &str {
+            "counting"
+        }
+    }
+
+    #[tokio::test]
+    async fn fx_close_uses_cached_pivot_legs_before_fetching() -> Result<()> {
+        let date = NaiveDate::from_ymd_opt(2026, 2, 4).unwrap();
+        let ts = Utc.with_ymd_and_hms(2026, 2, 4, 16, 0, 0).unwrap();
+        let store = Arc::new(MemoryMarketDataStore::default());
+        store
+            .put_fx_rates(&[
+                make_fx_close("GBP", "USD", date, ts, "1.25"),
+                make_fx_close("USD", "THB", date, ts, "36"),
+            ])
+            .await?;
+        let source = Arc::new(CountingFxSource {
+            calls: std::sync::atomic::AtomicUsize::new(0),
+        });
+        let svc =
+            MarketDataService::new(store, None).with_fx_router(Arc::new(FxRateRouter::new(vec![
+                source.clone() as Arc<dyn FxRateSource>,
+            ])));
+
+        let rate = svc.fx_close("GBP", "THB", date).await?;
+        assert_eq!(rate.rate, "45");
+        assert_eq!(source.calls.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fx_close_values_crypto_currency_via_crypto_price() -> Result<()> {
+        let date = NaiveDate::from_ymd_opt(2026, 2, 4).unwrap();
+        let ts = Utc.with_ymd_and_hms(2026, 2, 4, 16, 0, 0).unwrap();
+        let store = Arc::new(MemoryMarketDataStore::default());
+        let usdc = AssetId::from_asset(&Asset::crypto("USDC"));
+        store
+            .put_prices(&[make_close(&usdc, date, ts, "0.999")])
+            .await?;
+        store
+            .put_fx_rates(&[make_fx_close("EUR", "USD", date, ts, "1.25")])
+            .await?;
+        let svc = MarketDataService::new(store, None);
+
+        let direct = svc.fx_close("USDC", "USD", date).await?;
+        assert_eq!(direct.rate, "0.999");
+        assert_eq!(direct.source, "crypto(fixed)");
+
+        let rate = svc.fx_close("USDC", "EUR", date).await?;
+        assert_eq!(
+            rate.source,
+            "USDC/USD[crypto(fixed)] -> USD/EUR[inverse(fixed)]"
+        );
+        assert_eq!(rate.rate, "0.7992");
+        Ok(())
+    }
 }
@@ -7,6 +7,7 @@ use std::path::{Path, PathBuf};
 use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
 
+use super::RateLimitConfig;
 use crate::credentials::CredentialConfig;
 
 /// Known price source types.
@@ -40,6 +41,20 @@ impl PriceSourceType {
         }
     }
 
+    /// Name the built source reports at runtime; used to key source health.
+    pub fn provider_name(&self) -> &'static str {
+        match self {
+            Self::Eodhd => "eodhd",
+            Self::TwelveData => "twelve_data",
+            Self::AlphaVantage => "alpha_vantage",
+            Self::Marketstack => "marketstack",
+            Self::Coingecko => "coingecko",
+            Self::Cryptocompare => "cryptocompare",
+            Self::Coincap => "coincap",
+            Self::Frankfurter => "frankfurter",
+        }
+    }
+
     /// The asset types this source can provide prices for.
     pub fn supported_assets(&self) -> &'static [AssetCategory] {
         match self {
@@ -53,7 +68,7 @@ impl PriceSourceType {
 }
 
 /// Categories of assets a source can provide.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AssetCategory {
     Equity,
     Crypto,
@@ -83,6 +98,14 @@ pub struct PriceSourceConfig {
     /// Source-specific configuration options.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub config: Option<toml::Value>,
+
+    /// Request budget for this source (e.g. `max_requests = 5`, `window_seconds = 60`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+
+    /// Maximum requests per UTC day. The source is skipped once it is used up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_quota: Option<u32>,
 }
 
 fn default_enabled() -> bool {
@@ -186,6 +209,28 @@ priority = 20
         Ok(())
     }
 
+    #[test]
+    fn test_parse_rate_limit() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(
+            file,
+            r#"
+type = "coingecko"
+
+[rate_limit]
+max_requests = 30
+window_seconds = 60
+"#
+        )?;
+
+        let config = PriceSourceConfig::load(file.path())?;
+        let limit = config.rate_limit.expect("rate limit should be parsed");
+        assert_eq!(limit, RateLimitConfig::new(30, 60));
+        assert_eq!(limit.min_interval(), std::time::Duration::from_secs(2));
+
+        Ok(())
+    }
+
     #[test]
     fn test_missing_credentials_for_eodhd() {
         let mut file = NamedTempFile::new().unwrap();
@@ -3,9 +3,10 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, info, warn};
 
-use super::{AssetId, FxRatePoint, PricePoint};
+use super::{AssetId, FxRatePoint, PricePoint, SourceHealthTracker, TradingCalendar};
 use crate::models::Asset;
 
 #[async_trait::async_trait]
@@ -98,10 +99,38 @@ pub trait FxRateSource: Send + Sync {
         date: NaiveDate,
     ) -> Result<Option<FxRatePoint>>;
 
+    /// Fetch daily closing rates for a date range.
+    ///
+    /// The default issues one `fetch_close` per weekday (FX markets don't
+    /// fix on weekends); sources with a native time-series endpoint should
+    /// override it.
+    async fn fetch_closes(
+        &self,
+        base: &str,
+        quote: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<FxRatePoint>> {
+        let mut rates = Vec::new();
+        let mut current = start;
+        while current <= end {
+            if TradingCalendar::Weekdays.is_trading_day(current) {
+                if let Some(rate) = self.fetch_close(base, quote, current).await? {
+                    rates.push(rate);
+                }
+            }
+            let Some(next) = current.succ_opt() else {
+                break;
+            };
+            current = next;
+        }
+        Ok(rates)
+    }
+
     fn name(&self) -> &str;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RateLimitConfig {
     pub max_requests: u32,
     pub window_seconds: u64,
@@ -114,11 +143,45 @@ impl RateLimitConfig {
             window_seconds,
         }
     }
+
+    /// Minimum spacing between requests that keeps a caller within the limit.
+    pub fn min_interval(&self) -> std::time::Duration {
+        if self.max_requests == 0 {
+            return std::time::Duration::from_secs(self.window_seconds);
+        }
+        std::time::Duration::from_secs_f64(self.window_seconds as f64 / self.max_requests as f64)
+    }
+}
+
+fn source_available(health: Option<&SourceHealthTracker>, source: &str) -> bool {
+    let Some(health) = health else {
+        return true;
+    };
+    match health.check(source) {
+        Ok(()) => {
+            health.record_request(source);
+            true
+        }
+        Err(reason) => {
+            debug!(source = source, reason = %reason, "skipping unavailable price source");
+            false
+        }
+    }
+}
+
+fn record_outcome<T>(health: Option<&SourceHealthTracker>, source: &str, result: &Result<T>) {
+    if let Some(health) = health {
+        match result {
+            Ok(_) => health.record_success(source),
+            Err(e) => health.record_failure(source, e),
+        }
+    }
 }
 
 pub struct EquityPriceRouter {
     sources: Vec<Arc<dyn EquityPriceSource>>,
     rate_limits: HashMap<String, RateLimitConfig>,
+    health: Option<Arc<SourceHealthTracker>>,
 }
 
 impl EquityPriceRouter {
@@ -126,6 +189,7 @@ impl EquityPriceRouter {
         Self {
             sources,
             rate_limits: HashMap::new(),
+            health: None,
         }
     }
 
@@ -134,6 +198,12 @@ impl EquityPriceRouter {
         self
     }
 
+    /// Skip sources that are cooling down or out of quota, and record outcomes.
+    pub fn with_health(mut self, health: Arc<SourceHealthTracker>) -> Self {
+        self.health = Some(health);
+        self
+    }
+
     pub async fn fetch_close(
         &self,
         asset: &Asset,
@@ -143,7 +213,12 @@ impl EquityPriceRouter {
         debug!(asset_id = %asset_id, date = %date, "fetching equity close price");
         for source in &self.sources {
             let _limit = self.rate_limits.get(source.name());
-            match source.fetch_close(asset, asset_id, date).await {
+            if !source_available(self.health.as_deref(), source.name()) {
+                continue;
+            }
+            let result = source.fetch_close(asset, asset_id, date).await;
+            record_outcome(self.health.as_deref(), source.name(), &result);
+            match result {
                 Ok(Some(price)) => {
                     info!(
                         source = source.name(),
@@ -184,7 +259,12 @@ impl EquityPriceRouter {
         debug!(asset_id = %asset_id, start = %start, end = %end, "fetching equity close price range");
         for source in &self.sources {
             let _limit = self.rate_limits.get(source.name());
-            match source.fetch_closes(asset, asset_id, start, end).await {
+            if !source_available(self.health.as_deref(), source.name()) {
+                continue;
+            }
+            let result = source.fetch_closes(asset, asset_id, start, end).await;
+            record_outcome(self.health.as_deref(), source.name(), &result);
+            match result {
                 Ok(prices) if !prices.is_empty() => {
                     info!(
                         source = source.name(),
@@ -223,7 +303,12 @@ impl EquityPriceRouter {
         debug!(asset_id = %asset_id, "fetching equity quote");
         for source in &self.sources {
             let _limit = self.rate_limits.get(source.name());
-            match source.fetch_quote(asset, asset_id).await {
+            if !source_available(self.health.as_deref(), source.name()) {
+                continue;
+            }
+            let result = source.fetch_quote(asset, asset_id).await;
+            record_outcome(self.health.as_deref(), source.name(), &result);
+            match result {
                 Ok(Some(price)) => {
                     info!(
                         source = source.name(),
@@ -257,6 +342,7 @@ impl EquityPriceRouter {
 pub struct CryptoPriceRouter {
     sources: Vec<Arc<dyn CryptoPriceSource>>,
     rate_limits: HashMap<String, RateLimitConfig>,
+    health: Option<Arc<SourceHealthTracker>>,
 }
 
 impl CryptoPriceRouter {
@@ -264,6 +350,7 @@ impl CryptoPriceRouter {
         Self {
             sources,
             rate_limits: HashMap::new(),
+            health: None,
         }
     }
 
@@ -272,6 +359,12 @@ impl CryptoPriceRouter {
         self
     }
 
+    /// Skip sources that are cooling down or out of quota, and record outcomes.
+    pub fn with_health(mut self, health: Arc<SourceHealthTracker>) -> Self {
+        self.health = Some(health);
+        self
+    }
+
     pub async fn fetch_close(
         &self,
         asset: &Asset,
@@ -281,7 +374,12 @@ impl CryptoPriceRouter {
         debug!(asset_id = %asset_id, date = %date, "fetching crypto close price");
         for source in &self.sources {
             let _limit = self.rate_limits.get(source.name());
-            match source.fetch_close(asset, asset_id, date).await {
+            if !source_available(self.health.as_deref(), source.name()) {
+                continue;
+            }
+            let result = source.fetch_close(asset, asset_id, date).await;
+            record_outcome(self.health.as_deref(), source.name(), &result);
+            match result {
                 Ok(Some(price)) => {
                     info!(
                         source = source.name(),
@@ -322,7 +420,12 @@ impl CryptoPriceRouter {
         debug!(asset_id = %asset_id, start = %start, end = %end, "fetching crypto close price range");
         for source in &self.sources {
             let _limit = self.rate_limits.get(source.name());
-            match source.fetch_closes(asset, asset_id, start, end).await {
+            if !source_available(self.health.as_deref(), source.name()) {
+                continue;
+            }
+            let result = source.fetch_closes(asset, asset_id, start, end).await;
+            record_outcome(self.health.as_deref(), source.name(), &result);
+            match result {
                 Ok(prices) if !prices.is_empty() => {
                     info!(
                         source = source.name(),
@@ -361,7 +464,12 @@ impl CryptoPriceRouter {
         debug!(asset_id = %asset_id, "fetching crypto quote");
         for source in &self.sources {
             let _limit = self.rate_limits.get(source.name());
-            match source.fetch_quote(asset, asset_id).await {
+            if !source_available(self.health.as_deref(), source.name()) {
+                continue;
+            }
+            let result = source.fetch_quote(asset, asset_id).await;
+            record_outcome(self.health.as_deref(), source.name(), &result);
+            match result {
                 Ok(Some(price)) => {
                     info!(
                         source = source.name(),
@@ -395,6 +503,7 @@ impl CryptoPriceRouter {
 pub struct FxRateRouter {
     sources: Vec<Arc<dyn FxRateSource>>,
     rate_limits: HashMap<String, RateLimitConfig>,
+    health: Option<Arc<SourceHealthTracker>>,
 }
 
 impl FxRateRouter {
@@ -402,6 +511,7 @@ impl FxRateRouter {
         Self {
             sources,
             rate_limits: HashMap::new(),
+            health: None,
         }
     }
 
@@ -410,6 +520,12 @@ impl FxRateRouter {
         self
     }
 
+    /// Skip sources that are cooling down or out of quota, and record outcomes.
+    pub fn with_health(mut self, health: Arc<SourceHealthTracker>) -> Self {
+        self.health = Some(health);
+        self
+    }
+
     pub async fn fetch_close(
         &self,
         base: &str,
@@ -419,7 +535,12 @@ impl FxRateRouter {
         debug!(base = base, quote = quote, date = %date, "fetching FX rate");
         for source in &self.sources {
             let _limit = self.rate_limits.get(source.name());
-            match source.fetch_close(base, quote, date).await {
+            if !source_available(self.health.as_deref(), source.name()) {
+                continue;
+            }
+            let result = source.fetch_close(base, quote, date).await;
+            record_outcome(self.health.as_deref(), source.name(), &result);
+            match result {
                 Ok(Some(rate)) => {
                     info!(
                         source = source.name(),
@@ -455,4 +576,102 @@ impl FxRateRouter {
         warn!(base = base, quote = quote, date = %date, "no FX rate found from any source");
         Ok(None)
     }
+
+    pub async fn fetch_closes(
+        &self,
+        base: &str,
+        quote: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<FxRatePoint>> {
+        debug!(base = base, quote = quote, start = %start, end = %end, "fetching FX rate range");
+        for source in &self.sources {
+            let _limit = self.rate_limits.get(source.name());
+            if !source_available(self.health.as_deref(), source.name()) {
+                continue;
+            }
+            let result = source.fetch_closes(base, quote, start, end).await;
+            record_outcome(self.health.as_deref(), source.name(), &result);
+            match result {
+                Ok(rates) if !rates.is_empty() => {
+                    info!(
+                        source = source.name(),
+                        base = base,
+                        quote = quote,
+                        start = %start,
+                        end = %end,
+                        count = rates.len(),
+                        "FX rate range fetched"
+                    );
+                    return Ok(rates);
+                }
+                Ok(_) => {
+                    debug!(
+                        source = source.name(),
+                        base = base,
+                        quote = quote,
+                        "no rates from source"
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    warn!(
+                        source = source.name(),
+                        base = base,
+                        quote = quote,
+                        error = %e,
+                        "FX rate range fetch failed"
+                    );
+                    continue;
+                }
+            }
+        }
+        warn!(base = base, quote = quote, start = %start, end = %end, "no FX rates found from any source");
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct FailingFxSource {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl FxRateSource for FailingFxSource {
+        async fn fetch_close(
+            &self,
+            _base: &str,
+            _quote: &str,
+            _date: NaiveDate,
+        ) -> Result<Option<FxRatePoint>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Err(anyhow::anyhow!("FX API error: 429 Too Many Requests"))
+        }
+
+        fn name(&self) -> &str {
+            "failing"
+        }
+    }
+
+    #[tokio::test]
+    async fn router_skips_source_cooling_down() -> Result<()> {
+        let source = Arc::new(FailingFxSource {
+            calls: AtomicUsize::new(0),
+        });
+        let health = Arc::new(SourceHealthTracker::in_memory());
+        let router = FxRateRouter::new(vec![source.clone() as Arc<dyn FxRateSource>])
+            .with_health(health.clone());
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+
+        assert!(router.fetch_close("EUR", "USD", date).await?.is_none());
+        assert!(router.fetch_close("EUR", "USD", date).await?.is_none());
+
+        assert_eq!(source.calls.load(Ordering::SeqCst), 1);
+        assert!(health.check("failing").is_err());
+        Ok(())
+    }
 }
@@ -20,7 +20,7 @@ pub enum BalanceBackfillPolicy {
 }
 
 /// An individual financial account (checking, savings, credit card, brokerage, etc.)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Account {
     pub id: Id,
     pub name: String,
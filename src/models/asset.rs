@@ -72,6 +72,32 @@ fn normalize_currency_code(value: &str) -> String {
     }
 }
 
+/// Active ISO 4217 alphabetic codes, sorted.
+const ISO_4217_CODES: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BOV", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD",
+    "CAD", "CDF", "CHE", "CHF", "CHW", "CLF", "CLP", "CNY", "COP", "COU", "CRC", "CUC", "CUP",
+    "CVE", "CZK", "DJF", "DKK", "DOP", "DZD", "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP",
+    "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ", "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS",
+    "INR", "IQD", "IRR", "ISK", "JMD", "JOD", "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW",
+    "KWD", "KYD", "KZT", "LAK", "LBP", "LKR", "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD",
+    "MMK", "MNT", "MOP", "MRU", "MUR", "MVR", "MWK", "MXN", "MXV", "MYR", "MZN", "NAD", "NGN",
+    "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN", "PGK", "PHP", "PKR", "PLN", "PYG", "QAR",
+    "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR", "SDG", "SEK", "SGD", "SHP", "SLE", "SLL",
+    "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB", "TJS", "TMT", "TND", "TOP", "TRY",
+    "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "USN", "UYI", "UYU", "UYW", "UZS", "VED", "VES",
+    "VND", "VUV", "WST", "XAF", "XAG", "XAU", "XBA", "XBB", "XBC", "XBD", "XCD", "XCG", "XDR",
+    "XOF", "XPD", "XPF", "XPT", "XSU", "XTS", "XUA", "XXX", "YER", "ZAR", "ZMW", "ZWG", "ZWL",
+];
+
+/// Whether `code` is an ISO 4217 currency code (case-insensitive), as opposed
+/// to e.g. a crypto symbol or a provider's custom unit.
+pub fn is_iso_currency_code(code: &str) -> bool {
+    ISO_4217_CODES
+        .binary_search(&code.trim().to_uppercase().as_str())
+        .is_ok()
+}
+
 fn normalize_upper(value: &str) -> String {
     value.trim().to_uppercase()
 }
@@ -167,6 +193,15 @@ mod tests {
         assert_eq!(json, r#"{"type":"crypto","symbol":"BTC"}"#);
     }
 
+    #[test]
+    fn test_iso_currency_codes() {
+        assert!(is_iso_currency_code("usd"));
+        assert!(is_iso_currency_code(" EUR "));
+        assert!(!is_iso_currency_code("USDC"));
+        assert!(!is_iso_currency_code("BTC"));
+        assert!(ISO_4217_CODES.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
     #[test]
     fn test_asset_equality() {
         let usd1 = Asset::currency("USD");
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::clock::Clock;
 
-use super::Asset;
+use super::{Asset, Id};
 
 /// A single asset's balance without timestamp (belongs to a snapshot).
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +38,9 @@ impl AssetBalance {
 pub struct BalanceSnapshot {
     pub timestamp: DateTime<Utc>,
     pub balances: Vec<AssetBalance>,
+    /// Sync batch that recorded this snapshot, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_batch: Option<Id>,
 }
 
 impl BalanceSnapshot {
@@ -45,6 +48,7 @@ impl BalanceSnapshot {
         Self {
             timestamp,
             balances,
+            sync_batch: None,
         }
     }
 
@@ -55,4 +59,9 @@ impl BalanceSnapshot {
     pub fn now_with(clock: &dyn Clock, balances: Vec<AssetBalance>) -> Self {
         Self::new(clock.now(), balances)
     }
+
+    pub fn with_sync_batch(mut self, batch_id: Id) -> Self {
+        self.sync_batch = Some(batch_id);
+        self
+    }
 }
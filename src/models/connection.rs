@@ -15,7 +15,8 @@ use super::{Id, IdGenerator, UuidIdGenerator};
 pub struct ConnectionConfig {
     /// Display name for this connection.
     pub name: String,
-    /// Which synchronizer plugin to use (e.g., "schwab", "plaid", "coinbase").
+    /// Which synchronizer plugin to use (e.g., "schwab", "plaid", "coinbase",
+    /// "simplefin", or "exec:/path/to/plugin" for an external plugin).
     pub synchronizer: String,
     /// Credential configuration for this connection.
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -5,11 +5,13 @@ mod connection;
 mod id;
 mod id_generator;
 mod proposed_transaction_edit;
+mod sync_batch;
+mod sync_run;
 mod transaction;
 mod transaction_annotation;
 
 pub use account::{Account, AccountConfig, BalanceBackfillPolicy};
-pub use asset::Asset;
+pub use asset::{is_iso_currency_code, Asset};
 pub use balance::{AssetBalance, BalanceSnapshot};
 pub use connection::{
     Connection, ConnectionConfig, ConnectionState, ConnectionStatus, LastSync, SyncStatus,
@@ -17,5 +19,7 @@ pub use connection::{
 pub use id::Id;
 pub use id_generator::{FixedIdGenerator, IdGenerator, UuidIdGenerator};
 pub use proposed_transaction_edit::{ProposedTransactionEdit, ProposedTransactionEditStatus};
+pub use sync_batch::{latest_sync_batches, SyncBatch, SyncBatchAccountChange};
+pub use sync_run::{failure_streak, FailureStreak, SyncRun, SyncRunOutcome};
 pub use transaction::{Transaction, TransactionStandardizedMetadata, TransactionStatus};
 pub use transaction_annotation::{TransactionAnnotation, TransactionAnnotationPatch};
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{Account, Id};
+
+/// An account as it was before and after a sync batch saved it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncBatchAccountChange {
+    /// `None` if the batch created the account.
+    pub before: Option<Account>,
+    pub after: Account,
+}
+
+/// One entry in a connection's `sync_batches.jsonl` journal: everything a
+/// single `SyncResult::save` wrote, so it can be reverted later.
+///
+/// Balance snapshots and transactions appended by the batch carry its id in
+/// their `sync_batch` field; account changes are recorded here since account
+/// files are overwritten in place.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncBatch {
+    pub id: Id,
+    pub connection_id: Id,
+    pub created_at: DateTime<Utc>,
+    /// Accounts that received balance snapshots or transactions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub account_ids: Vec<Id>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub account_changes: Vec<SyncBatchAccountChange>,
+    /// Set on the journal entry appended when the batch is reverted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverted_at: Option<DateTime<Utc>>,
+}
+
+impl SyncBatch {
+    pub fn new(id: Id, connection_id: Id, created_at: DateTime<Utc>) -> Self {
+        Self {
+            id,
+            connection_id,
+            created_at,
+            account_ids: Vec::new(),
+            account_changes: Vec::new(),
+            reverted_at: None,
+        }
+    }
+
+    /// Whether the batch wrote anything worth journaling.
+    pub fn is_empty(&self) -> bool {
+        self.account_ids.is_empty() && self.account_changes.is_empty()
+    }
+}
+
+/// Collapse a batch journal (oldest first) to the latest entry per batch id,
+/// keeping first-seen order.
+pub fn latest_sync_batches(entries: Vec<SyncBatch>) -> Vec<SyncBatch> {
+    let mut batches: Vec<SyncBatch> = Vec::new();
+    for entry in entries {
+        match batches.iter_mut().find(|batch| batch.id == entry.id) {
+            Some(existing) => *existing = entry,
+            None => batches.push(entry),
+        }
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest_sync_batches_keeps_last_entry_per_id() {
+        let at = Utc::now();
+        let first = SyncBatch::new(Id::from_string("b1"), Id::from_string("c"), at);
+        let second = SyncBatch::new(Id::from_string("b2"), Id::from_string("c"), at);
+        let mut reverted = first.clone();
+        reverted.reverted_at = Some(at);
+
+        let batches = latest_sync_batches(vec![first, second.clone(), reverted.clone()]);
+
+        assert_eq!(batches, vec![reverted, second]);
+    }
+}
@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::Id;
+
+/// How a sync run ended. Mirrors the attempted `SyncOutcome` variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncRunOutcome {
+    Synced,
+    AuthRequired,
+    Failed,
+}
+
+impl SyncRunOutcome {
+    pub fn is_failure(self) -> bool {
+        !matches!(self, SyncRunOutcome::Synced)
+    }
+}
+
+/// One entry in a connection's `sync_runs.jsonl` journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncRun {
+    pub connection_id: Id,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub outcome: SyncRunOutcome,
+    /// Error chain, outermost context first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    /// Sync batch the run's writes were stamped with (see `sync revert`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<Id>,
+    #[serde(default)]
+    pub accounts_added: usize,
+    #[serde(default)]
+    pub accounts_changed: usize,
+    #[serde(default)]
+    pub balances_recorded: usize,
+    #[serde(default)]
+    pub transactions_added: usize,
+    #[serde(default)]
+    pub transactions_changed: usize,
+    #[serde(default)]
+    pub prices_refreshed: usize,
+}
+
+impl SyncRun {
+    /// A run with no changes recorded yet.
+    pub fn new(
+        connection_id: Id,
+        started_at: DateTime<Utc>,
+        finished_at: DateTime<Utc>,
+        outcome: SyncRunOutcome,
+    ) -> Self {
+        Self {
+            connection_id,
+            started_at,
+            finished_at,
+            outcome,
+            errors: Vec::new(),
+            batch_id: None,
+            accounts_added: 0,
+            accounts_changed: 0,
+            balances_recorded: 0,
+            transactions_added: 0,
+            transactions_changed: 0,
+            prices_refreshed: 0,
+        }
+    }
+
+    pub fn duration_ms(&self) -> i64 {
+        (self.finished_at - self.started_at).num_milliseconds()
+    }
+}
+
+/// Consecutive failed runs at the end of a journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FailureStreak {
+    pub count: usize,
+    /// Start of the first failed run in the streak.
+    pub since: DateTime<Utc>,
+    /// Error of the most recent failed run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// The current failure streak of `runs` (in chronological order), if the most
+/// recent run failed.
+pub fn failure_streak(runs: &[SyncRun]) -> Option<FailureStreak> {
+    let failed: Vec<&SyncRun> = runs
+        .iter()
+        .rev()
+        .take_while(|run| run.outcome.is_failure())
+        .collect();
+    let latest = failed.first()?;
+    let earliest = failed.last()?;
+    Some(FailureStreak {
+        count: failed.len(),
+        since: earliest.started_at,
+        last_error: latest.errors.first().cloned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn run(day: u32, outcome: SyncRunOutcome) -> SyncRun {
+        let at = Utc.with_ymd_and_hms(2026, 4, day, 8, 0, 0).unwrap();
+        let mut run = SyncRun::new(Id::from_string("conn"), at, at, outcome);
+        if outcome.is_failure() {
+            run.errors = vec![format!("failed on day {day}")];
+        }
+        run
+    }
+
+    #[test]
+    fn failure_streak_counts_trailing_failures() {
+        let runs = vec![
+            run(1, SyncRunOutcome::Failed),
+            run(2, SyncRunOutcome::Synced),
+            run(3, SyncRunOutcome::AuthRequired),
+            run(4, SyncRunOutcome::Failed),
+        ];
+
+        let streak = failure_streak(&runs).unwrap();
+        assert_eq!(streak.count, 2);
+        assert_eq!(streak.since, runs[2].started_at);
+        assert_eq!(streak.last_error.as_deref(), Some("failed on day 4"));
+
+        assert!(failure_streak(&runs[..2]).is_none());
+        assert!(failure_streak(&[]).is_none());
+    }
+}
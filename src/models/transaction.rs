@@ -75,6 +75,9 @@ pub struct Transaction {
     /// Provider-agnostic metadata used for categorization/rule matching.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub standardized_metadata: Option<TransactionStandardizedMetadata>,
+    /// Sync batch that appended this version, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_batch: Option<Id>,
 }
 
 impl Transaction {
@@ -98,6 +101,7 @@ impl Transaction {
             status: TransactionStatus::Posted,
             synchronizer_data: serde_json::Value::Null,
             standardized_metadata: None,
+            sync_batch: None,
         }
     }
 
@@ -311,6 +315,7 @@ mod tests {
                 "merchant_category_code": "5814",
             }),
             standardized_metadata: None,
+            sync_batch: None,
         }
         .backfill_standardized_metadata();
 
@@ -355,6 +360,7 @@ mod tests {
                 transaction_kind: None,
                 is_internal_transfer_hint: None,
             }),
+            sync_batch: None,
         }
         .backfill_standardized_metadata();
 
@@ -90,6 +90,24 @@ impl PortfolioService {
     }
 
     pub async fn calculate(&self, query: &PortfolioQuery) -> Result<PortfolioSnapshot> {
+        self.calculate_inner(query, None).await
+    }
+
+    /// Like [`Self::calculate`] but values equities and crypto with the latest
+    /// recorded intraday quote at or before `at`, when one exists.
+    pub async fn calculate_at(
+        &self,
+        query: &PortfolioQuery,
+        at: DateTime<Utc>,
+    ) -> Result<PortfolioSnapshot> {
+        self.calculate_inner(query, Some(at)).await
+    }
+
+    async fn calculate_inner(
+        &self,
+        query: &PortfolioQuery,
+        intraday_at: Option<DateTime<Utc>>,
+    ) -> Result<PortfolioSnapshot> {
         // Load accounts, connections, and balances
         let ctx = self
             .load_calculation_context(query.as_of_date, &query.account_ids)
@@ -100,7 +118,12 @@ impl PortfolioService {
 
         // Fetch valuations for all unique assets (cached)
         let price_cache = self
-            .fetch_asset_valuations(&by_asset_agg, &query.currency, query.as_of_date)
+            .fetch_asset_valuations(
+                &by_asset_agg,
+                &query.currency,
+                query.as_of_date,
+                intraday_at,
+            )
             .await?;
 
         let valuation_scenario = Self::resolve_equity_valuation_scenario(
@@ -301,12 +324,19 @@ impl PortfolioService {
         by_asset: &HashMap<Asset, AssetAggregate>,
         target_currency: &str,
         as_of_date: NaiveDate,
+        intraday_at: Option<DateTime<Utc>>,
     ) -> Result<HashMap<Asset, AssetValuation>> {
         let mut cache = HashMap::new();
 
         for asset in by_asset.keys() {
             let valuation = self
-                .value_asset(asset, Decimal::ONE, target_currency, as_of_date)
+                .value_asset(
+                    asset,
+                    Decimal::ONE,
+                    target_currency,
+                    as_of_date,
+                    intraday_at,
+                )
                 .await?;
             cache.insert(asset.clone(), valuation);
         }
@@ -642,12 +672,14 @@ impl PortfolioService {
 
     /// Value an asset in the target currency.
     /// Uses live quotes when available, falls back to cached or fetched historical prices.
+    /// With `intraday_at`, a recorded intraday quote at or before that instant wins.
     async fn value_asset(
         &self,
         asset: &Asset,
         amount: Decimal,
         target_currency: &str,
         as_of_date: NaiveDate,
+        intraday_at: Option<DateTime<Utc>>,
     ) -> Result<AssetValuation> {
         match asset {
             Asset::Currency { iso_code } => {
@@ -696,7 +728,13 @@ impl PortfolioService {
             Asset::Equity { .. } | Asset::Crypto { .. } => {
                 // Use live pricing for today. Historical valuation uses cached/fetched prices
                 // at or before the requested date without special-casing price kind.
-                let price_result = if as_of_date == self.clock.today() {
+                let intraday_price = match intraday_at {
+                    Some(at) => self.market_data.intraday_price_at(asset, at).await?,
+                    None => None,
+                };
+                let price_result = if let Some(price) = intraday_price {
+                    Ok(price)
+                } else if as_of_date == self.clock.today() {
                     self.market_data.price_latest(asset, as_of_date).await
                 } else {
                     match self
@@ -1550,7 +1588,7 @@ mod tests {
 
         let service = PortfolioService::new(Arc::new(MemoryStorage::new()), market_data);
         let valuation = service
-            .value_asset(&asset, Decimal::ONE, "USD", as_of_date)
+            .value_asset(&asset, Decimal::ONE, "USD", as_of_date, None)
             .await?;
 
         assert_eq!(valuation.price.as_deref(), Some("100"));
@@ -1594,7 +1632,7 @@ mod tests {
         let market_data = Arc::new(MarketDataService::new(store, None));
         let service = PortfolioService::new(storage, market_data);
         let valuation = service
-            .value_asset(&asset, Decimal::ONE, "USD", as_of_date)
+            .value_asset(&asset, Decimal::ONE, "USD", as_of_date, None)
             .await?;
 
         assert_eq!(valuation.price.as_deref(), Some("110"));
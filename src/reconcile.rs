@@ -0,0 +1,296 @@
+//! Balance-vs-transaction reconciliation.
+//!
+//! Between two consecutive balance snapshots, the change in a currency
+//! balance should equal the sum of the posted transactions in that currency
+//! dated after the first snapshot and up to the second. Windows where it
+//! doesn't are reported along with transactions that could explain the gap.
+
+use std::str::FromStr;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::models::{Asset, BalanceSnapshot, Id, Transaction, TransactionStatus};
+use crate::storage::Storage;
+
+/// Differences smaller than this are treated as rounding.
+const TOLERANCE: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+
+/// How far outside a window a transaction may be dated and still be
+/// suggested as belonging to it (posting dates often lag balances).
+const NEAR_WINDOW_DAYS: i64 = 3;
+
+/// A window between two snapshots whose balance change doesn't match its
+/// transactions.
+#[derive(Debug, Clone, Serialize)]
+pub struct Discrepancy {
+    pub asset: Asset,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub balance_before: String,
+    pub balance_after: String,
+    pub balance_delta: String,
+    pub transactions_total: String,
+    /// `balance_delta - transactions_total`: positive when transactions are
+    /// missing credits (or have extra debits), negative the other way round.
+    pub difference: String,
+    /// Transactions not counted in the window that would close the gap.
+    pub missing_candidates: Vec<CandidateTransaction>,
+    /// Counted transactions whose removal would close the gap.
+    pub duplicate_candidates: Vec<CandidateTransaction>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CandidateTransaction {
+    pub id: Id,
+    pub timestamp: DateTime<Utc>,
+    pub amount: String,
+    pub description: String,
+    pub status: TransactionStatus,
+    pub reason: String,
+}
+
+/// Outcome of reconciling one account.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Reconciliation {
+    pub windows_checked: usize,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+/// Reconcile every window between `snapshots` against `transactions`.
+///
+/// Windows that start before the earliest transaction are skipped, since
+/// transaction history usually begins later than balance history. Empty
+/// snapshots (recorded when an account goes inactive) break the sequence.
+pub fn reconcile(snapshots: &[BalanceSnapshot], transactions: &[Transaction]) -> Reconciliation {
+    let mut snapshots: Vec<&BalanceSnapshot> = snapshots.iter().collect();
+    snapshots.sort_by_key(|s| s.timestamp);
+
+    let mut result = Reconciliation::default();
+    let Some(coverage_start) = transactions.iter().map(|t| t.timestamp).min() else {
+        return result;
+    };
+
+    for pair in snapshots.windows(2) {
+        let (before, after) = (pair[0], pair[1]);
+        if before.timestamp < coverage_start
+            || before.balances.is_empty()
+            || after.balances.is_empty()
+        {
+            continue;
+        }
+        for asset in currency_assets(before, after) {
+            result.windows_checked += 1;
+            if let Some(discrepancy) = check_window(&asset, before, after, transactions) {
+                result.discrepancies.push(discrepancy);
+            }
+        }
+    }
+    result
+}
+
+/// Reconcile an account's full history.
+pub async fn reconcile_account(storage: &dyn Storage, account_id: &Id) -> Result<Reconciliation> {
+    let snapshots = storage.get_balance_snapshots(account_id).await?;
+    let transactions = storage.get_transactions(account_id).await?;
+    Ok(reconcile(&snapshots, &transactions))
+}
+
+/// Reconcile only the most recent window of an account (e.g. right after a
+/// sync recorded a new snapshot).
+pub async fn reconcile_latest(storage: &dyn Storage, account_id: &Id) -> Result<Reconciliation> {
+    let mut snapshots = storage.get_balance_snapshots(account_id).await?;
+    snapshots.sort_by_key(|s| s.timestamp);
+    let latest = snapshots.split_off(snapshots.len().saturating_sub(2));
+    let transactions = storage.get_transactions(account_id).await?;
+    Ok(reconcile(&latest, &transactions))
+}
+
+fn currency_assets(before: &BalanceSnapshot, after: &BalanceSnapshot) -> Vec<Asset> {
+    let mut assets: Vec<Asset> = Vec::new();
+    for balance in before.balances.iter().chain(&after.balances) {
+        let asset = balance.asset.normalized();
+        if matches!(asset, Asset::Currency { .. }) && !assets.contains(&asset) {
+            assets.push(asset);
+        }
+    }
+    assets
+}
+
+fn balance_of(snapshot: &BalanceSnapshot, asset: &Asset) -> Option<Decimal> {
+    let mut total = Decimal::ZERO;
+    for balance in &snapshot.balances {
+        if balance.asset.normalized() == *asset {
+            total += Decimal::from_str(&balance.amount).ok()?;
+        }
+    }
+    Some(total)
+}
+
+fn amount_of(txn: &Transaction) -> Option<Decimal> {
+    Decimal::from_str(&txn.amount).ok()
+}
+
+fn check_window(
+    asset: &Asset,
+    before: &BalanceSnapshot,
+    after: &BalanceSnapshot,
+    transactions: &[Transaction],
+) -> Option<Discrepancy> {
+    let balance_before = balance_of(before, asset)?;
+    let balance_after = balance_of(after, asset)?;
+    let in_asset: Vec<&Transaction> = transactions
+        .iter()
+        .filter(|t| t.asset.normalized() == *asset)
+        .collect();
+    let in_window =
+        |t: &&Transaction| t.timestamp > before.timestamp && t.timestamp <= after.timestamp;
+
+    let counted: Vec<&Transaction> = in_asset
+        .iter()
+        .copied()
+        .filter(in_window)
+        .filter(|t| t.status == TransactionStatus::Posted)
+        .collect();
+    let total: Decimal = counted.iter().filter_map(|t| amount_of(t)).sum();
+    let delta = balance_after - balance_before;
+    let difference = delta - total;
+    if difference.abs() < TOLERANCE {
+        return None;
+    }
+
+    let near = Duration::days(NEAR_WINDOW_DAYS);
+    let mut missing_candidates = Vec::new();
+    for txn in &in_asset {
+        if amount_of(txn) != Some(difference) {
+            continue;
+        }
+        if in_window(txn) && txn.status == TransactionStatus::Pending {
+            missing_candidates.push(candidate(txn, "pending in window".to_string()));
+        } else if !in_window(txn)
+            && txn.status == TransactionStatus::Posted
+            && txn.timestamp > before.timestamp - near
+            && txn.timestamp <= after.timestamp + near
+        {
+            missing_candidates.push(candidate(txn, "posted just outside window".to_string()));
+        }
+    }
+
+    let mut duplicate_candidates = Vec::new();
+    for txn in &counted {
+        if amount_of(txn) != Some(-difference) {
+            continue;
+        }
+        let twin = in_asset.iter().find(|other| {
+            other.id != txn.id
+                && other.amount == txn.amount
+                && other.description.eq_ignore_ascii_case(&txn.description)
+                && (other.timestamp - txn.timestamp).abs() <= near
+        });
+        let reason = match twin {
+            Some(twin) => format!("same amount and description as {}", twin.id),
+            None => "amount matches difference".to_string(),
+        };
+        duplicate_candidates.push(candidate(txn, reason));
+    }
+
+    Some(Discrepancy {
+        asset: asset.clone(),
+        from: before.timestamp,
+        to: after.timestamp,
+        balance_before: balance_before.normalize().to_string(),
+        balance_after: balance_after.normalize().to_string(),
+        balance_delta: delta.normalize().to_string(),
+        transactions_total: total.normalize().to_string(),
+        difference: difference.normalize().to_string(),
+        missing_candidates,
+        duplicate_candidates,
+    })
+}
+
+fn candidate(txn: &Transaction, reason: String) -> CandidateTransaction {
+    CandidateTransaction {
+        id: txn.id.clone(),
+        timestamp: txn.timestamp,
+        amount: txn.amount.clone(),
+        description: txn.description.clone(),
+        status: txn.status,
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AssetBalance;
+    use chrono::TimeZone;
+
+    fn day(d: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, d, 12, 0, 0).unwrap()
+    }
+
+    fn snapshot(d: u32, amount: &str) -> BalanceSnapshot {
+        BalanceSnapshot::new(
+            day(d),
+            vec![AssetBalance::new(Asset::currency("USD"), amount)],
+        )
+    }
+
+    fn txn(id: &str, d: u32, amount: &str, description: &str) -> Transaction {
+        Transaction::new(amount, Asset::currency("USD"), description)
+            .with_id(Id::from_string(id))
+            .with_timestamp(day(d))
+    }
+
+    #[test]
+    fn matching_windows_have_no_discrepancies() {
+        let snapshots = vec![
+            snapshot(1, "100"),
+            snapshot(5, "80.50"),
+            snapshot(9, "1080.50"),
+        ];
+        let txns = vec![
+            txn("a", 1, "-1", "Before coverage"),
+            txn("b", 3, "-19.50", "Groceries"),
+            txn("c", 7, "1000", "Payroll"),
+        ];
+
+        let result = reconcile(&snapshots, &txns);
+
+        assert_eq!(result.windows_checked, 2);
+        assert!(result.discrepancies.is_empty());
+    }
+
+    #[test]
+    fn reports_duplicated_and_missing_transactions() {
+        let snapshots = vec![snapshot(1, "100"), snapshot(5, "80"), snapshot(9, "50")];
+        let txns = vec![
+            txn("a", 1, "0", "Opening"),
+            txn("b", 3, "-20", "Coffee beans"),
+            txn("b-dup", 3, "-20", "COFFEE BEANS"),
+            // Belongs to the second window but is dated just after it.
+            txn("c", 10, "-30", "Gas"),
+        ];
+
+        let result = reconcile(&snapshots, &txns);
+
+        assert_eq!(result.discrepancies.len(), 2);
+        let first = &result.discrepancies[0];
+        assert_eq!(first.difference, "20");
+        assert_eq!(first.duplicate_candidates.len(), 2);
+        assert_eq!(
+            first.duplicate_candidates[0].reason,
+            "same amount and description as b-dup"
+        );
+
+        let second = &result.discrepancies[1];
+        assert_eq!(second.difference, "-30");
+        assert_eq!(second.missing_candidates[0].id, Id::from_string("c"));
+        assert_eq!(
+            second.missing_candidates[0].reason,
+            "posted just outside window"
+        );
+    }
+}
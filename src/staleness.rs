@@ -6,7 +6,7 @@ use chrono::{DateTime, Utc};
 use tracing::info;
 
 use crate::config::RefreshConfig;
-use crate::market_data::PricePoint;
+use crate::market_data::{PricePoint, TradingCalendar};
 use crate::models::{AccountConfig, Connection};
 
 /// Result of a staleness check.
@@ -81,15 +81,19 @@ pub fn check_balance_staleness_at(
 }
 
 /// Check if a price is stale.
+///
+/// A price older than `threshold` is still fresh when `calendar` has had no
+/// session close since it was taken (e.g. a Friday close on a Monday holiday).
 pub fn check_price_staleness_at(
     price: Option<&PricePoint>,
     threshold: Duration,
+    calendar: &TradingCalendar,
     now: DateTime<Utc>,
 ) -> StalenessCheck {
     match price {
         Some(p) => {
             let age = (now - p.timestamp).to_std().unwrap_or(Duration::ZERO);
-            if age >= threshold {
+            if age >= threshold && !calendar.is_current(p.as_of_date, p.timestamp, now) {
                 StalenessCheck::stale(age, threshold)
             } else {
                 StalenessCheck::fresh(age, threshold)
@@ -105,8 +109,12 @@ pub fn check_balance_staleness(connection: &Connection, threshold: Duration) ->
 }
 
 /// Convenience wrapper that checks staleness relative to `Utc::now()`.
-pub fn check_price_staleness(price: Option<&PricePoint>, threshold: Duration) -> StalenessCheck {
-    check_price_staleness_at(price, threshold, Utc::now())
+pub fn check_price_staleness(
+    price: Option<&PricePoint>,
+    threshold: Duration,
+    calendar: &TradingCalendar,
+) -> StalenessCheck {
+    check_price_staleness_at(price, threshold, calendar, Utc::now())
 }
 
 /// Log staleness check results for a connection's balances.
@@ -148,7 +156,7 @@ pub fn log_price_staleness(asset_id: &str, check: &StalenessCheck) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::market_data::{AssetId, PriceKind, PricePoint};
+    use crate::market_data::{AssetId, PriceKind, PricePoint, TradingCalendar};
     use crate::models::Asset;
     use crate::models::{ConnectionConfig, ConnectionState, LastSync, SyncStatus};
     use chrono::TimeZone;
@@ -253,10 +261,45 @@ mod tests {
             source: "test".to_string(),
         };
 
-        let check = check_price_staleness_at(Some(&price), threshold, now);
+        let check =
+            check_price_staleness_at(Some(&price), threshold, &TradingCalendar::Continuous, now);
         assert!(check.is_stale, "age == threshold should be stale");
     }
 
+    #[test]
+    fn test_price_fresh_over_holiday_weekend() {
+        let threshold = Duration::from_secs(24 * 60 * 60);
+        // Fetched after the close on Friday 2024-05-24; Monday is Memorial Day.
+        let timestamp = Utc.with_ymd_and_hms(2024, 5, 24, 21, 0, 0).unwrap();
+        let price = PricePoint {
+            asset_id: AssetId::from_asset(&Asset::equity("AAPL")),
+            as_of_date: chrono::NaiveDate::from_ymd_opt(2024, 5, 24).unwrap(),
+            timestamp,
+            price: "1".to_string(),
+            quote_currency: "USD".to_string(),
+            kind: PriceKind::Close,
+            source: "test".to_string(),
+        };
+
+        let holiday = Utc.with_ymd_and_hms(2024, 5, 27, 18, 0, 0).unwrap();
+        let check =
+            check_price_staleness_at(Some(&price), threshold, &TradingCalendar::Nyse, holiday);
+        assert!(!check.is_stale, "no session has closed since Friday");
+
+        let check =
+            check_price_staleness_at(Some(&price), threshold, &TradingCalendar::Weekdays, holiday);
+        assert!(check.is_stale, "weekday calendar expects a Monday close");
+
+        let tuesday_close = Utc.with_ymd_and_hms(2024, 5, 28, 21, 0, 0).unwrap();
+        let check = check_price_staleness_at(
+            Some(&price),
+            threshold,
+            &TradingCalendar::Nyse,
+            tuesday_close,
+        );
+        assert!(check.is_stale);
+    }
+
     #[test]
     fn test_resolve_account_override() {
         let account_config = AccountConfig {
@@ -304,7 +347,7 @@ mod tests {
     fn test_price_stale_when_old() {
         let price = make_price_point(48);
         let threshold = Duration::from_secs(24 * 60 * 60);
-        let check = check_price_staleness(Some(&price), threshold);
+        let check = check_price_staleness(Some(&price), threshold, &TradingCalendar::Continuous);
         assert!(check.is_stale);
     }
 
@@ -312,14 +355,14 @@ mod tests {
     fn test_price_fresh_when_recent() {
         let price = make_price_point(1);
         let threshold = Duration::from_secs(24 * 60 * 60);
-        let check = check_price_staleness(Some(&price), threshold);
+        let check = check_price_staleness(Some(&price), threshold, &TradingCalendar::Continuous);
         assert!(!check.is_stale);
     }
 
     #[test]
     fn test_price_stale_when_missing() {
         let threshold = Duration::from_secs(24 * 60 * 60);
-        let check = check_price_staleness(None, threshold);
+        let check = check_price_staleness(None, threshold, &TradingCalendar::Continuous);
         assert!(check.is_stale);
         assert!(check.age.is_none());
     }
@@ -329,7 +372,7 @@ mod tests {
         let mut price = make_price_point(0);
         price.timestamp = Utc::now() + chrono::Duration::hours(1);
         let threshold = Duration::from_secs(24 * 60 * 60);
-        let check = check_price_staleness(Some(&price), threshold);
+        let check = check_price_staleness(Some(&price), threshold, &TradingCalendar::Continuous);
         assert!(
             !check.is_stale,
             "future price timestamps should be treated as fresh"
@@ -0,0 +1,450 @@
+//! Read-only view of a storage as it was at a past moment.
+//!
+//! The logs are append-only, so most of the past can be recovered by
+//! dropping what was written later: annotation patches carry the time they
+//! were written, and balance snapshots and transaction versions written by a
+//! sync carry its batch, whose journal entry has the time. Rows without a
+//! batch (manual entries, imports, older data) count as written no earlier
+//! than the batch rows before them in the log, and never before their own
+//! timestamp.
+//!
+//! Anything that rewrote a log since (`sync revert`, account merges) or
+//! overwrote a JSON file (account names, `active`) shows its current state.
+//! `sync recompact` sorts logs by timestamp, which can put rows without a
+//! batch after a later batch's rows and hide them; batch rows are unaffected.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use super::{dedupe_transactions_last_write_wins, Storage};
+use crate::credentials::CredentialStore;
+use crate::models::{
+    Account, AccountConfig, BalanceSnapshot, Connection, ConnectionConfig, Id,
+    ProposedTransactionEdit, SyncBatch, SyncRun, Transaction, TransactionAnnotationPatch,
+};
+
+/// When each sync batch of an account's connection ran, by batch id.
+pub async fn sync_batch_times(
+    storage: &dyn Storage,
+    account_id: &Id,
+) -> Result<HashMap<Id, DateTime<Utc>>> {
+    let Some(account) = storage.get_account(account_id).await? else {
+        return Ok(HashMap::new());
+    };
+    let mut times = HashMap::new();
+    for batch in storage.get_sync_batches(&account.connection_id).await? {
+        times.entry(batch.id).or_insert(batch.created_at);
+    }
+    Ok(times)
+}
+
+/// The earliest moment each row of an append-only log can have been
+/// written, in log order. `key` gives a row's own timestamp and sync batch.
+pub fn written_at<T>(
+    rows: Vec<T>,
+    batch_times: &HashMap<Id, DateTime<Utc>>,
+    key: impl Fn(&T) -> (DateTime<Utc>, Option<&Id>),
+) -> Vec<(DateTime<Utc>, T)> {
+    // A batch row was written when its batch ran. For other rows, the latest
+    // batch time before them in the log is a lower bound.
+    let mut written_after = None;
+    let mut dated = Vec::with_capacity(rows.len());
+    for row in rows {
+        let (timestamp, batch) = key(&row);
+        let written = match batch.and_then(|batch| batch_times.get(batch)) {
+            Some(batch_time) => {
+                written_after = written_after.max(Some(*batch_time));
+                Some(*batch_time)
+            }
+            None => written_after,
+        };
+        dated.push((written.map_or(timestamp, |time| time.max(timestamp)), row));
+    }
+    dated
+}
+
+/// Keep the rows of an append-only log that were written by `as_of`.
+fn written_by<T>(
+    rows: Vec<T>,
+    as_of: DateTime<Utc>,
+    batch_times: &HashMap<Id, DateTime<Utc>>,
+    key: impl Fn(&T) -> (DateTime<Utc>, Option<&Id>),
+) -> Vec<T> {
+    written_at(rows, batch_times, key)
+        .into_iter()
+        .filter(|(written, _)| *written <= as_of)
+        .map(|(_, row)| row)
+        .collect()
+}
+
+/// Storage showing `inner` as it was at `as_of`. Every write fails.
+pub struct AsOfStorage {
+    inner: Arc<dyn Storage>,
+    as_of: DateTime<Utc>,
+}
+
+impl AsOfStorage {
+    pub fn new(inner: Arc<dyn Storage>, as_of: DateTime<Utc>) -> Self {
+        Self { inner, as_of }
+    }
+
+    pub fn as_of(&self) -> DateTime<Utc> {
+        self.as_of
+    }
+
+    fn account_visible(&self, account: &Account) -> bool {
+        account.created_at <= self.as_of
+    }
+
+    fn connection_visible(&self, connection: &Connection) -> bool {
+        connection.state.created_at <= self.as_of
+    }
+}
+
+fn read_only<T>() -> Result<T> {
+    anyhow::bail!("Point-in-time views are read-only; drop --as-of to edit")
+}
+
+#[async_trait::async_trait]
+impl Storage for AsOfStorage {
+    fn get_credential_store(
+        &self,
+        _connection_id: &Id,
+    ) -> Result<Option<Box<dyn CredentialStore>>> {
+        Ok(None)
+    }
+
+    fn get_account_config(&self, account_id: &Id) -> Result<Option<AccountConfig>> {
+        self.inner.get_account_config(account_id)
+    }
+
+    async fn list_connections(&self) -> Result<Vec<Connection>> {
+        let mut connections = self.inner.list_connections().await?;
+        connections.retain(|connection| self.connection_visible(connection));
+        Ok(connections)
+    }
+
+    async fn get_connection(&self, id: &Id) -> Result<Option<Connection>> {
+        Ok(self
+            .inner
+            .get_connection(id)
+            .await?
+            .filter(|connection| self.connection_visible(connection)))
+    }
+
+    async fn save_connection(&self, _conn: &Connection) -> Result<()> {
+        read_only()
+    }
+
+    async fn delete_connection(&self, _id: &Id) -> Result<bool> {
+        read_only()
+    }
+
+    async fn save_connection_config(&self, _id: &Id, _config: &ConnectionConfig) -> Result<()> {
+        read_only()
+    }
+
+    async fn list_accounts(&self) -> Result<Vec<Account>> {
+        let mut accounts = self.inner.list_accounts().await?;
+        accounts.retain(|account| self.account_visible(account));
+        Ok(accounts)
+    }
+
+    async fn get_account(&self, id: &Id) -> Result<Option<Account>> {
+        Ok(self
+            .inner
+            .get_account(id)
+            .await?
+            .filter(|account| self.account_visible(account)))
+    }
+
+    async fn save_account(&self, _account: &Account) -> Result<()> {
+        read_only()
+    }
+
+    async fn delete_account(&self, _id: &Id) -> Result<bool> {
+        read_only()
+    }
+
+    async fn save_account_config(&self, _id: &Id, _config: &AccountConfig) -> Result<()> {
+        read_only()
+    }
+
+    async fn get_balance_snapshots(&self, account_id: &Id) -> Result<Vec<BalanceSnapshot>> {
+        let batch_times = sync_batch_times(self.inner.as_ref(), account_id).await?;
+        Ok(written_by(
+            self.inner.get_balance_snapshots(account_id).await?,
+            self.as_of,
+            &batch_times,
+            |snapshot| (snapshot.timestamp, snapshot.sync_batch.as_ref()),
+        ))
+    }
+
+    async fn append_balance_snapshot(
+        &self,
+        _account_id: &Id,
+        _snapshot: &BalanceSnapshot,
+    ) -> Result<()> {
+        read_only()
+    }
+
+    async fn get_latest_balance_snapshot(
+        &self,
+        account_id: &Id,
+    ) -> Result<Option<BalanceSnapshot>> {
+        Ok(self
+            .get_balance_snapshots(account_id)
+            .await?
+            .into_iter()
+            .max_by_key(|snapshot| snapshot.timestamp))
+    }
+
+    async fn get_latest_balances(&self) -> Result<Vec<(Id, BalanceSnapshot)>> {
+        let mut balances = Vec::new();
+        for account in self.list_accounts().await? {
+            if let Some(snapshot) = self.get_latest_balance_snapshot(&account.id).await? {
+                balances.push((account.id, snapshot));
+            }
+        }
+        Ok(balances)
+    }
+
+    async fn get_latest_balances_for_connection(
+        &self,
+        connection_id: &Id,
+    ) -> Result<Vec<(Id, BalanceSnapshot)>> {
+        let mut balances = Vec::new();
+        for account in self.list_accounts().await? {
+            if account.connection_id != *connection_id {
+                continue;
+            }
+            if let Some(snapshot) = self.get_latest_balance_snapshot(&account.id).await? {
+                balances.push((account.id, snapshot));
+            }
+        }
+        Ok(balances)
+    }
+
+    async fn get_transactions(&self, account_id: &Id) -> Result<Vec<Transaction>> {
+        Ok(dedupe_transactions_last_write_wins(
+            self.get_transactions_raw(account_id).await?,
+        ))
+    }
+
+    async fn get_transactions_raw(&self, account_id: &Id) -> Result<Vec<Transaction>> {
+        let batch_times = sync_batch_times(self.inner.as_ref(), account_id).await?;
+        Ok(written_by(
+            self.inner.get_transactions_raw(account_id).await?,
+            self.as_of,
+            &batch_times,
+            |txn| (txn.timestamp, txn.sync_batch.as_ref()),
+        ))
+    }
+
+    async fn append_transactions(&self, _account_id: &Id, _txns: &[Transaction]) -> Result<()> {
+        read_only()
+    }
+
+    async fn get_transaction_annotation_patches(
+        &self,
+        account_id: &Id,
+    ) -> Result<Vec<TransactionAnnotationPatch>> {
+        let mut patches = self
+            .inner
+            .get_transaction_annotation_patches(account_id)
+            .await?;
+        patches.retain(|patch| patch.timestamp <= self.as_of);
+        Ok(patches)
+    }
+
+    async fn append_transaction_annotation_patches(
+        &self,
+        _account_id: &Id,
+        _patches: &[TransactionAnnotationPatch],
+    ) -> Result<()> {
+        read_only()
+    }
+
+    async fn get_proposed_transaction_edits(&self) -> Result<Vec<ProposedTransactionEdit>> {
+        let mut edits = self.inner.get_proposed_transaction_edits().await?;
+        edits.retain(|edit| edit.updated_at <= self.as_of);
+        Ok(edits)
+    }
+
+    async fn append_proposed_transaction_edits(
+        &self,
+        _edits: &[ProposedTransactionEdit],
+    ) -> Result<()> {
+        read_only()
+    }
+
+    async fn get_sync_runs(&self, connection_id: &Id) -> Result<Vec<SyncRun>> {
+        let mut runs = self.inner.get_sync_runs(connection_id).await?;
+        runs.retain(|run| run.finished_at <= self.as_of);
+        Ok(runs)
+    }
+
+    async fn append_sync_run(&self, _run: &SyncRun) -> Result<()> {
+        read_only()
+    }
+
+    async fn get_sync_batches(&self, connection_id: &Id) -> Result<Vec<SyncBatch>> {
+        let mut batches = self.inner.get_sync_batches(connection_id).await?;
+        batches.retain(|batch| batch.created_at <= self.as_of);
+        // Batches reverted since were still live then.
+        for batch in &mut batches {
+            if batch
+                .reverted_at
+                .is_some_and(|reverted| reverted > self.as_of)
+            {
+                batch.reverted_at = None;
+            }
+        }
+        Ok(batches)
+    }
+
+    async fn append_sync_batch(&self, _batch: &SyncBatch) -> Result<()> {
+        read_only()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Asset, ConnectionConfig};
+    use crate::storage::MemoryStorage;
+    use chrono::TimeZone;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, day, 12, 0, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn hides_versions_and_patches_written_later() -> Result<()> {
+        let storage = Arc::new(MemoryStorage::new());
+        let connection = Connection::new(ConnectionConfig {
+            name: "Bank".to_string(),
+            synchronizer: "manual".to_string(),
+            credentials: None,
+            balance_staleness: None,
+        });
+        storage.save_connection(&connection).await?;
+        let account = Account::new_with(
+            Id::from_string("acct"),
+            at(1),
+            "Checking",
+            connection.id().clone(),
+        );
+        storage.save_account(&account).await?;
+
+        let mut batch = SyncBatch::new(Id::from_string("b1"), connection.id().clone(), at(5));
+        batch.account_ids.push(account.id.clone());
+        storage.append_sync_batch(&batch).await?;
+
+        let pending = Transaction::new("-5", Asset::currency("USD"), "COFFEE PENDING")
+            .with_id(Id::from_string("t1"))
+            .with_timestamp(at(2));
+        let mut posted = pending.clone();
+        posted.description = "Coffee".to_string();
+        posted.sync_batch = Some(batch.id.clone());
+        storage
+            .append_transactions(&account.id, &[pending, posted])
+            .await?;
+        storage
+            .append_transaction_annotation_patches(
+                &account.id,
+                &[TransactionAnnotationPatch {
+                    transaction_id: Id::from_string("t1"),
+                    timestamp: at(3),
+                    description: None,
+                    note: None,
+                    category: Some(Some("Dining".to_string())),
+                    subcategory: None,
+                    tags: None,
+                    effective_date: None,
+                }],
+            )
+            .await?;
+
+        let before_sync = AsOfStorage::new(storage.clone(), at(4));
+        let txns = before_sync.get_transactions(&account.id).await?;
+        assert_eq!(txns.len(), 1);
+        assert_eq!(txns[0].description, "COFFEE PENDING");
+        assert_eq!(
+            before_sync
+                .get_transaction_annotation_patches(&account.id)
+                .await?
+                .len(),
+            1
+        );
+
+        let before_patch = AsOfStorage::new(storage.clone(), at(2));
+        assert!(before_patch
+            .get_transaction_annotation_patches(&account.id)
+            .await?
+            .is_empty());
+
+        let before_account = AsOfStorage::new(storage.clone(), at(1) - chrono::Duration::hours(1));
+        assert!(before_account.list_accounts().await?.is_empty());
+
+        let now = AsOfStorage::new(storage, at(6));
+        assert_eq!(
+            now.get_transactions(&account.id).await?[0].description,
+            "Coffee"
+        );
+        assert!(now.save_account(&account).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn batch_rows_are_judged_by_their_own_batch() {
+        let batch_times = HashMap::from([
+            (Id::from_string("early"), at(3)),
+            (Id::from_string("late"), at(9)),
+        ]);
+        let early = Id::from_string("early");
+        let late = Id::from_string("late");
+        // Sorted by timestamp, as `sync recompact` leaves a log: a row
+        // backfilled by the late batch comes before rows written earlier.
+        let rows = vec![
+            ("backfilled", at(1), Some(&late)),
+            ("synced", at(2), Some(&early)),
+            ("manual", at(4), None),
+            ("latest", at(8), Some(&late)),
+        ];
+
+        let kept: Vec<&str> = written_by(rows, at(5), &batch_times, |row| (row.1, row.2))
+            .into_iter()
+            .map(|row| row.0)
+            .collect();
+        assert_eq!(kept, vec!["synced"]);
+    }
+
+    #[tokio::test]
+    async fn batches_reverted_later_were_live() -> Result<()> {
+        let storage = Arc::new(MemoryStorage::new());
+        let connection_id = Id::from_string("conn");
+        let mut batch = SyncBatch::new(Id::from_string("b1"), connection_id.clone(), at(2));
+        batch.reverted_at = Some(at(6));
+        storage.append_sync_batch(&batch).await?;
+
+        let before_revert = AsOfStorage::new(storage.clone(), at(4));
+        let batches = before_revert.get_sync_batches(&connection_id).await?;
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].reverted_at, None);
+
+        let after_revert = AsOfStorage::new(storage.clone(), at(7));
+        assert_eq!(
+            after_revert.get_sync_batches(&connection_id).await?[0].reverted_at,
+            Some(at(6))
+        );
+        assert!(AsOfStorage::new(storage, at(1))
+            .get_sync_batches(&connection_id)
+            .await?
+            .is_empty());
+        Ok(())
+    }
+}
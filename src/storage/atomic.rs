@@ -0,0 +1,103 @@
+//! Crash-safe file replacement.
+//!
+//! Rewrites go to a temporary file in the same directory, which is fsynced
+//! and then renamed over the target, so readers (and a crash at any point)
+//! see either the old contents or the new ones, never a truncated file.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(
+        ".{name}.{}.{}.tmp",
+        std::process::id(),
+        uuid::Uuid::new_v4().simple()
+    ))
+}
+
+/// Make a rename in `dir` durable.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<()> {
+    std::fs::File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .with_context(|| format!("Failed to sync directory {}", dir.display()))
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Atomically replace `path` with `contents`.
+pub fn write_atomic_sync(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp = temp_path(path);
+    let result = (|| -> Result<()> {
+        let mut file = std::fs::File::create(&tmp)
+            .with_context(|| format!("Failed to create {}", tmp.display()))?;
+        file.write_all(contents)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        file.sync_all()
+            .with_context(|| format!("Failed to sync {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to rename {} to {}", tmp.display(), path.display()))?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result?;
+
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => sync_dir(parent),
+        _ => Ok(()),
+    }
+}
+
+/// Async wrapper around [`write_atomic_sync`].
+pub async fn write_atomic(path: &Path, contents: impl Into<Vec<u8>>) -> Result<()> {
+    let path = path.to_path_buf();
+    let contents = contents.into();
+    tokio::task::spawn_blocking(move || write_atomic_sync(&path, &contents))
+        .await
+        .context("Atomic write task panicked")?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn replaces_contents_without_leaving_temp_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("connection.json");
+        std::fs::write(&path, "old")?;
+
+        write_atomic(&path, "new").await?;
+
+        assert_eq!(std::fs::read_to_string(&path)?, "new");
+        let entries: Vec<_> = std::fs::read_dir(dir.path())?.collect();
+        assert_eq!(entries.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn failed_write_keeps_original() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let target = dir.path().join("accounts");
+        std::fs::create_dir(&target)?;
+        std::fs::write(target.join("keep"), "x")?;
+
+        // Renaming a file over a non-empty directory fails.
+        assert!(write_atomic_sync(&target, b"data").is_err());
+
+        assert!(target.join("keep").exists());
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,512 @@
+//! Read-only view over several data dirs ("profiles") as one storage.
+//!
+//! Ids are namespaced as `{profile}:{id}` so accounts and connections from
+//! different data dirs can't collide. An account can be owned partly by a
+//! profile (a joint account that also shows up in another profile's data);
+//! its balances and transaction amounts are then scaled to that share.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures::{StreamExt, TryStreamExt};
+use rust_decimal::Decimal;
+
+use super::{Storage, TransactionOrder, TransactionStream};
+use crate::credentials::CredentialStore;
+use crate::models::{
+    Account, AccountConfig, BalanceSnapshot, Connection, ConnectionConfig, Id,
+    ProposedTransactionEdit, SyncBatch, SyncRun, Transaction, TransactionAnnotationPatch,
+};
+use chrono::{DateTime, Utc};
+
+/// Separates the profile name from the id inside it.
+pub const PROFILE_ID_SEPARATOR: char = ':';
+
+struct Profile {
+    name: String,
+    storage: Arc<dyn Storage>,
+    /// Owned fraction by account id or name; accounts not listed are owned
+    /// outright.
+    ownership: HashMap<String, Decimal>,
+}
+
+impl Profile {
+    fn namespaced(&self, id: &Id) -> Id {
+        Id::from_string(format!("{}{PROFILE_ID_SEPARATOR}{id}", self.name))
+    }
+
+    fn share(&self, account: &Account) -> Option<Decimal> {
+        self.ownership
+            .get(account.id.as_str())
+            .or_else(|| self.ownership.get(&account.name))
+            .copied()
+            .filter(|share| *share != Decimal::ONE)
+    }
+
+    async fn share_of(&self, account_id: &Id) -> Result<Option<Decimal>> {
+        if self.ownership.is_empty() {
+            return Ok(None);
+        }
+        Ok(self
+            .storage
+            .get_account(account_id)
+            .await?
+            .and_then(|account| self.share(&account)))
+    }
+}
+
+/// Storage presenting several profiles' data as one. Every write fails.
+#[derive(Default)]
+pub struct CompositeStorage {
+    profiles: Vec<Profile>,
+}
+
+impl CompositeStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a profile. `ownership` maps account ids or names to the fraction
+    /// of the account the profile owns (e.g. `0.5` for a two-way joint
+    /// account).
+    pub fn with_profile(
+        mut self,
+        name: impl Into<String>,
+        storage: Arc<dyn Storage>,
+        ownership: HashMap<String, Decimal>,
+    ) -> Self {
+        self.profiles.push(Profile {
+            name: name.into(),
+            storage,
+            ownership,
+        });
+        self
+    }
+
+    /// The profile a namespaced id belongs to, and the id within it.
+    fn resolve(&self, id: &Id) -> Option<(&Profile, Id)> {
+        let (name, inner) = id.as_str().split_once(PROFILE_ID_SEPARATOR)?;
+        let profile = self.profiles.iter().find(|profile| profile.name == name)?;
+        Some((profile, Id::from_string(inner)))
+    }
+}
+
+fn read_only<T>() -> Result<T> {
+    anyhow::bail!("Combined profile views are read-only; run the command against one data dir")
+}
+
+fn scale(amount: &str, share: Decimal) -> String {
+    match Decimal::from_str(amount) {
+        Ok(value) => (value * share).normalize().to_string(),
+        Err(_) => amount.to_string(),
+    }
+}
+
+fn scale_snapshot(mut snapshot: BalanceSnapshot, share: Option<Decimal>) -> BalanceSnapshot {
+    if let Some(share) = share {
+        for balance in &mut snapshot.balances {
+            balance.amount = scale(&balance.amount, share);
+            if let Some(cost_basis) = &balance.cost_basis {
+                balance.cost_basis = Some(scale(cost_basis, share));
+            }
+        }
+    }
+    snapshot
+}
+
+fn scale_transaction(mut txn: Transaction, share: Option<Decimal>) -> Transaction {
+    if let Some(share) = share {
+        txn.amount = scale(&txn.amount, share);
+    }
+    txn
+}
+
+fn namespace_connection(profile: &Profile, mut connection: Connection) -> Connection {
+    connection.state.id = profile.namespaced(&connection.state.id);
+    for account_id in &mut connection.state.account_ids {
+        *account_id = profile.namespaced(account_id);
+    }
+    connection
+}
+
+fn namespace_account(profile: &Profile, mut account: Account) -> Account {
+    account.id = profile.namespaced(&account.id);
+    account.connection_id = profile.namespaced(&account.connection_id);
+    account
+}
+
+#[async_trait::async_trait]
+impl Storage for CompositeStorage {
+    fn get_credential_store(
+        &self,
+        _connection_id: &Id,
+    ) -> Result<Option<Box<dyn CredentialStore>>> {
+        Ok(None)
+    }
+
+    fn get_account_config(&self, account_id: &Id) -> Result<Option<AccountConfig>> {
+        match self.resolve(account_id) {
+            Some((profile, id)) => profile.storage.get_account_config(&id),
+            None => Ok(None),
+        }
+    }
+
+    async fn list_connections(&self) -> Result<Vec<Connection>> {
+        let mut connections = Vec::new();
+        for profile in &self.profiles {
+            for connection in profile.storage.list_connections().await? {
+                connections.push(namespace_connection(profile, connection));
+            }
+        }
+        Ok(connections)
+    }
+
+    async fn get_connection(&self, id: &Id) -> Result<Option<Connection>> {
+        let Some((profile, id)) = self.resolve(id) else {
+            return Ok(None);
+        };
+        Ok(profile
+            .storage
+            .get_connection(&id)
+            .await?
+            .map(|connection| namespace_connection(profile, connection)))
+    }
+
+    async fn save_connection(&self, _conn: &Connection) -> Result<()> {
+        read_only()
+    }
+
+    async fn delete_connection(&self, _id: &Id) -> Result<bool> {
+        read_only()
+    }
+
+    async fn save_connection_config(&self, _id: &Id, _config: &ConnectionConfig) -> Result<()> {
+        read_only()
+    }
+
+    async fn list_accounts(&self) -> Result<Vec<Account>> {
+        let mut accounts = Vec::new();
+        for profile in &self.profiles {
+            for account in profile.storage.list_accounts().await? {
+                accounts.push(namespace_account(profile, account));
+            }
+        }
+        Ok(accounts)
+    }
+
+    async fn get_account(&self, id: &Id) -> Result<Option<Account>> {
+        let Some((profile, id)) = self.resolve(id) else {
+            return Ok(None);
+        };
+        Ok(profile
+            .storage
+            .get_account(&id)
+            .await?
+            .map(|account| namespace_account(profile, account)))
+    }
+
+    async fn save_account(&self, _account: &Account) -> Result<()> {
+        read_only()
+    }
+
+    async fn delete_account(&self, _id: &Id) -> Result<bool> {
+        read_only()
+    }
+
+    async fn save_account_config(&self, _id: &Id, _config: &AccountConfig) -> Result<()> {
+        read_only()
+    }
+
+    async fn get_balance_snapshots(&self, account_id: &Id) -> Result<Vec<BalanceSnapshot>> {
+        let Some((profile, id)) = self.resolve(account_id) else {
+            return Ok(Vec::new());
+        };
+        let share = profile.share_of(&id).await?;
+        Ok(profile
+            .storage
+            .get_balance_snapshots(&id)
+            .await?
+            .into_iter()
+            .map(|snapshot| scale_snapshot(snapshot, share))
+            .collect())
+    }
+
+    async fn append_balance_snapshot(
+        &self,
+        _account_id: &Id,
+        _snapshot: &BalanceSnapshot,
+    ) -> Result<()> {
+        read_only()
+    }
+
+    async fn get_latest_balance_snapshot(
+        &self,
+        account_id: &Id,
+    ) -> Result<Option<BalanceSnapshot>> {
+        let Some((profile, id)) = self.resolve(account_id) else {
+            return Ok(None);
+        };
+        let share = profile.share_of(&id).await?;
+        Ok(profile
+            .storage
+            .get_latest_balance_snapshot(&id)
+            .await?
+            .map(|snapshot| scale_snapshot(snapshot, share)))
+    }
+
+    async fn get_latest_balances(&self) -> Result<Vec<(Id, BalanceSnapshot)>> {
+        let mut balances = Vec::new();
+        for profile in &self.profiles {
+            for (id, snapshot) in profile.storage.get_latest_balances().await? {
+                let share = profile.share_of(&id).await?;
+                balances.push((profile.namespaced(&id), scale_snapshot(snapshot, share)));
+            }
+        }
+        Ok(balances)
+    }
+
+    async fn get_latest_balances_for_connection(
+        &self,
+        connection_id: &Id,
+    ) -> Result<Vec<(Id, BalanceSnapshot)>> {
+        let Some((profile, id)) = self.resolve(connection_id) else {
+            return Ok(Vec::new());
+        };
+        let mut balances = Vec::new();
+        for (id, snapshot) in profile
+            .storage
+            .get_latest_balances_for_connection(&id)
+            .await?
+        {
+            let share = profile.share_of(&id).await?;
+            balances.push((profile.namespaced(&id), scale_snapshot(snapshot, share)));
+        }
+        Ok(balances)
+    }
+
+    async fn get_transactions(&self, account_id: &Id) -> Result<Vec<Transaction>> {
+        let Some((profile, id)) = self.resolve(account_id) else {
+            return Ok(Vec::new());
+        };
+        let share = profile.share_of(&id).await?;
+        Ok(profile
+            .storage
+            .get_transactions(&id)
+            .await?
+            .into_iter()
+            .map(|txn| scale_transaction(txn, share))
+            .collect())
+    }
+
+    async fn get_transactions_raw(&self, account_id: &Id) -> Result<Vec<Transaction>> {
+        let Some((profile, id)) = self.resolve(account_id) else {
+            return Ok(Vec::new());
+        };
+        let share = profile.share_of(&id).await?;
+        Ok(profile
+            .storage
+            .get_transactions_raw(&id)
+            .await?
+            .into_iter()
+            .map(|txn| scale_transaction(txn, share))
+            .collect())
+    }
+
+    async fn get_transactions_in_range(
+        &self,
+        account_id: &Id,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Transaction>> {
+        let Some((profile, id)) = self.resolve(account_id) else {
+            return Ok(Vec::new());
+        };
+        let share = profile.share_of(&id).await?;
+        Ok(profile
+            .storage
+            .get_transactions_in_range(&id, start, end)
+            .await?
+            .into_iter()
+            .map(|txn| scale_transaction(txn, share))
+            .collect())
+    }
+
+    async fn stream_transactions(
+        &self,
+        account_id: &Id,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+        order: TransactionOrder,
+    ) -> Result<TransactionStream<'_>> {
+        let Some((profile, id)) = self.resolve(account_id) else {
+            return Ok(Box::pin(futures::stream::empty()));
+        };
+        let share = profile.share_of(&id).await?;
+        let stream = profile
+            .storage
+            .stream_transactions(&id, start, end, order)
+            .await?;
+        Ok(stream
+            .map_ok(move |txn| scale_transaction(txn, share))
+            .boxed())
+    }
+
+    async fn find_transactions(
+        &self,
+        account_id: &Id,
+        prefixes: &[String],
+    ) -> Result<Vec<Transaction>> {
+        let Some((profile, id)) = self.resolve(account_id) else {
+            return Ok(Vec::new());
+        };
+        let share = profile.share_of(&id).await?;
+        Ok(profile
+            .storage
+            .find_transactions(&id, prefixes)
+            .await?
+            .into_iter()
+            .map(|txn| scale_transaction(txn, share))
+            .collect())
+    }
+
+    async fn append_transactions(&self, _account_id: &Id, _txns: &[Transaction]) -> Result<()> {
+        read_only()
+    }
+
+    async fn get_transaction_annotation_patches(
+        &self,
+        account_id: &Id,
+    ) -> Result<Vec<TransactionAnnotationPatch>> {
+        match self.resolve(account_id) {
+            Some((profile, id)) => {
+                profile
+                    .storage
+                    .get_transaction_annotation_patches(&id)
+                    .await
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn append_transaction_annotation_patches(
+        &self,
+        _account_id: &Id,
+        _patches: &[TransactionAnnotationPatch],
+    ) -> Result<()> {
+        read_only()
+    }
+
+    async fn get_proposed_transaction_edits(&self) -> Result<Vec<ProposedTransactionEdit>> {
+        let mut edits = Vec::new();
+        for profile in &self.profiles {
+            for mut edit in profile.storage.get_proposed_transaction_edits().await? {
+                edit.id = profile.namespaced(&edit.id);
+                edit.account_id = profile.namespaced(&edit.account_id);
+                edits.push(edit);
+            }
+        }
+        Ok(edits)
+    }
+
+    async fn append_proposed_transaction_edits(
+        &self,
+        _edits: &[ProposedTransactionEdit],
+    ) -> Result<()> {
+        read_only()
+    }
+
+    async fn get_sync_runs(&self, connection_id: &Id) -> Result<Vec<SyncRun>> {
+        let Some((profile, id)) = self.resolve(connection_id) else {
+            return Ok(Vec::new());
+        };
+        let mut runs = profile.storage.get_sync_runs(&id).await?;
+        for run in &mut runs {
+            run.connection_id = profile.namespaced(&run.connection_id);
+        }
+        Ok(runs)
+    }
+
+    async fn append_sync_run(&self, _run: &SyncRun) -> Result<()> {
+        read_only()
+    }
+
+    async fn get_sync_batches(&self, connection_id: &Id) -> Result<Vec<SyncBatch>> {
+        let Some((profile, id)) = self.resolve(connection_id) else {
+            return Ok(Vec::new());
+        };
+        let mut batches = profile.storage.get_sync_batches(&id).await?;
+        for batch in &mut batches {
+            batch.connection_id = profile.namespaced(&batch.connection_id);
+            for account_id in &mut batch.account_ids {
+                *account_id = profile.namespaced(account_id);
+            }
+        }
+        Ok(batches)
+    }
+
+    async fn append_sync_batch(&self, _batch: &SyncBatch) -> Result<()> {
+        read_only()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Asset, AssetBalance};
+    use crate::storage::MemoryStorage;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    async fn namespaces_ids_and_scales_joint_accounts() -> Result<()> {
+        let alice = Arc::new(MemoryStorage::new());
+        let connection = Connection::new(ConnectionConfig {
+            name: "Bank".to_string(),
+            synchronizer: "manual".to_string(),
+            credentials: None,
+            balance_staleness: None,
+        });
+        alice.save_connection(&connection).await?;
+        let joint = Account::new("Joint Checking", connection.id().clone());
+        alice.save_account(&joint).await?;
+        alice
+            .append_balance_snapshot(
+                &joint.id,
+                &BalanceSnapshot::new(
+                    Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap(),
+                    vec![AssetBalance::new(Asset::currency("USD"), "1000.50")],
+                ),
+            )
+            .await?;
+        alice
+            .append_transactions(
+                &joint.id,
+                &[Transaction::new("-80", Asset::currency("USD"), "Groceries")],
+            )
+            .await?;
+
+        let storage = CompositeStorage::new().with_profile(
+            "alice",
+            alice,
+            HashMap::from([("Joint Checking".to_string(), Decimal::new(5, 1))]),
+        );
+
+        let accounts = storage.list_accounts().await?;
+        assert_eq!(accounts.len(), 1);
+        let id = &accounts[0].id;
+        assert_eq!(id.as_str(), format!("alice:{}", joint.id));
+        assert_eq!(
+            accounts[0].connection_id.as_str(),
+            format!("alice:{}", connection.id())
+        );
+
+        let latest = storage.get_latest_balances().await?;
+        assert_eq!(latest[0].0, *id);
+        assert_eq!(latest[0].1.balances[0].amount, "500.25");
+        assert_eq!(storage.get_transactions(id).await?[0].amount, "-40");
+        assert!(storage.get_account(&joint.id).await?.is_none());
+        assert!(storage.save_account(&accounts[0]).await.is_err());
+        Ok(())
+    }
+}
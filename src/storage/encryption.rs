@@ -0,0 +1,431 @@
+//! Optional at-rest encryption of the data dir with age.
+//!
+//! A data dir is encrypted when it has an `encryption.toml` at its top:
+//!
+//! ```toml
+//! # Public keys every data file is encrypted to (SSH or age1 keys)
+//! recipients = ["ssh-ed25519 AAAA... laptop"]
+//! # And/or a file with one key per line, relative to the data dir
+//! recipients_file = "age-recipients.txt"
+//! ```
+//!
+//! `JsonFileStorage` then keeps each of its JSON, JSONL and TOML files as
+//! `<name>.age` and decrypts them in memory with the SSH identity used for
+//! age credentials (`KEEPBOOK_CREDENTIALS_AGE_IDENTITY_PATH`, else the
+//! default keys in `~/.ssh`). Market data (prices, FX rates) stays plaintext:
+//! it is public and read far too often to decrypt.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::atomic::write_atomic_sync;
+use crate::credentials::{read_ssh_identity, AgeCredentialStore, AGE_IDENTITY_PATH_ENV};
+
+/// Name of the encryption settings file at the top of the data dir.
+pub const ENCRYPTION_CONFIG_FILE: &str = "encryption.toml";
+
+/// Extension appended to the name of each encrypted file.
+pub const ENCRYPTED_EXTENSION: &str = "age";
+
+/// Files under `connections/{id}/` that are encrypted.
+const CONNECTION_FILES: &[&str] = &[
+    "connection.toml",
+    "connection.json",
+    "sync_runs.jsonl",
+    "sync_batches.jsonl",
+];
+
+/// Files under `accounts/{id}/` that are encrypted.
+const ACCOUNT_FILES: &[&str] = &[
+    "account.json",
+    "account_config.toml",
+    "balances.jsonl",
+    "transactions.jsonl",
+    "transaction_annotations.jsonl",
+];
+
+/// Files at the top of the data dir that are encrypted.
+const TOP_LEVEL_FILES: &[&str] = &["proposed_transaction_edits.jsonl"];
+
+/// Contents of `encryption.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EncryptionConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recipients: Vec<String>,
+
+    /// Recipients file (one key per line, `#` comments), relative to the
+    /// data dir. `keepbook-age-recipients` prints one from `keys.nix`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipients_file: Option<PathBuf>,
+}
+
+impl EncryptionConfig {
+    /// Read `encryption.toml`, or `None` if the data dir isn't encrypted.
+    pub fn load(data_dir: &Path) -> Result<Option<Self>> {
+        let path = data_dir.join(ENCRYPTION_CONFIG_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map(Some)
+                .with_context(|| format!("Failed to parse {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        let content =
+            toml::to_string_pretty(self).context("Failed to serialize encryption config")?;
+        write_atomic_sync(&data_dir.join(ENCRYPTION_CONFIG_FILE), content.as_bytes())
+    }
+
+    /// All configured recipient keys, inline ones first.
+    pub fn recipient_keys(&self, data_dir: &Path) -> Result<Vec<String>> {
+        let mut keys: Vec<String> = self
+            .recipients
+            .iter()
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+            .collect();
+        if let Some(file) = &self.recipients_file {
+            let path = data_dir.join(file);
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read recipients file {}", path.display()))?;
+            keys.extend(
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+        Ok(keys)
+    }
+}
+
+type BoxedRecipient = Box<dyn ::age::Recipient + Send + Sync>;
+
+fn parse_recipient(key: &str) -> Result<BoxedRecipient> {
+    if key.starts_with("age1") {
+        let recipient: ::age::x25519::Recipient = key
+            .parse()
+            .map_err(|err| anyhow!("Invalid age recipient {key:?}: {err}"))?;
+        Ok(Box::new(recipient))
+    } else {
+        let recipient: ::age::ssh::Recipient = key
+            .parse()
+            .map_err(|err| anyhow!("Invalid SSH recipient {key:?}: {err:?}"))?;
+        Ok(Box::new(recipient))
+    }
+}
+
+type BoxedIdentity = Box<dyn ::age::Identity + Send + Sync>;
+
+/// Read an identity file: an SSH private key, or an age key
+/// (`AGE-SECRET-KEY-1...`, as written by `age-keygen`).
+fn read_identity(path: &Path) -> Result<BoxedIdentity> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read identity {}", path.display()))?;
+    let age_key = content
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with("AGE-SECRET-KEY-"));
+    match age_key {
+        Some(key) => {
+            let identity: ::age::x25519::Identity = key
+                .parse()
+                .map_err(|err| anyhow!("Invalid age identity {}: {err}", path.display()))?;
+            Ok(Box::new(identity))
+        }
+        None => Ok(Box::new(read_ssh_identity(path)?)),
+    }
+}
+
+/// The identities data files are decrypted with, loaded on first use.
+///
+/// This is the identity lookup of the age credential store: the key in
+/// `KEEPBOOK_CREDENTIALS_AGE_IDENTITY_PATH`, else every default key in
+/// `~/.ssh`.
+#[derive(Default)]
+pub struct AgeIdentities {
+    loaded: Mutex<Option<Arc<Vec<BoxedIdentity>>>>,
+}
+
+impl AgeIdentities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get(&self) -> Result<Arc<Vec<BoxedIdentity>>> {
+        let mut loaded = self.loaded.lock().expect("age identities poisoned");
+        if let Some(identities) = loaded.as_ref() {
+            return Ok(identities.clone());
+        }
+
+        let paths = match std::env::var(AGE_IDENTITY_PATH_ENV) {
+            Ok(path) if !path.trim().is_empty() => vec![PathBuf::from(path.trim())],
+            _ => AgeCredentialStore::default_identity_paths(),
+        };
+        if paths.is_empty() {
+            bail!(
+                "Encrypted data needs an identity; set {AGE_IDENTITY_PATH_ENV} or create one under ~/.ssh"
+            );
+        }
+        let mut identities = Vec::new();
+        let mut failures = Vec::new();
+        for path in &paths {
+            match read_identity(path) {
+                Ok(identity) => identities.push(identity),
+                Err(err) => failures.push(format!("{err:#}")),
+            }
+        }
+        if identities.is_empty() {
+            bail!("No usable identity: {}", failures.join("; "));
+        }
+        let identities = Arc::new(identities);
+        *loaded = Some(identities.clone());
+        Ok(identities)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let identities = self.get()?;
+        let decryptor =
+            ::age::Decryptor::new_buffered(ciphertext).context("Not an age-encrypted file")?;
+        let mut reader = decryptor
+            .decrypt(
+                identities
+                    .iter()
+                    .map(|identity| identity.as_ref() as &dyn ::age::Identity),
+            )
+            .context("None of the identities can decrypt it")?;
+        let mut plaintext = Vec::new();
+        reader
+            .read_to_end(&mut plaintext)
+            .context("Failed to decrypt")?;
+        Ok(plaintext)
+    }
+}
+
+/// Encrypts data files to the recipients in `encryption.toml` and decrypts
+/// them with the local identity.
+pub struct DataCipher {
+    recipients: Vec<BoxedRecipient>,
+    identities: AgeIdentities,
+}
+
+impl DataCipher {
+    /// The cipher for an encrypted data dir, `None` for a plaintext one.
+    pub fn load(data_dir: &Path) -> Result<Option<Self>> {
+        EncryptionConfig::load(data_dir)?
+            .map(|config| Self::from_config(&config, data_dir))
+            .transpose()
+    }
+
+    pub fn from_config(config: &EncryptionConfig, data_dir: &Path) -> Result<Self> {
+        let recipients = config
+            .recipient_keys(data_dir)?
+            .iter()
+            .map(|key| parse_recipient(key))
+            .collect::<Result<Vec<_>>>()?;
+        if recipients.is_empty() {
+            bail!("{ENCRYPTION_CONFIG_FILE} lists no recipients");
+        }
+        Ok(Self {
+            recipients,
+            identities: AgeIdentities::new(),
+        })
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let encryptor = ::age::Encryptor::with_recipients(
+            self.recipients
+                .iter()
+                .map(|recipient| recipient.as_ref() as &dyn ::age::Recipient),
+        )
+        .map_err(|err| anyhow!("Failed to set up encryption: {err}"))?;
+        let mut ciphertext = Vec::new();
+        let mut writer = encryptor
+            .wrap_output(&mut ciphertext)
+            .context("Failed to encrypt")?;
+        writer.write_all(plaintext).context("Failed to encrypt")?;
+        writer.finish().context("Failed to encrypt")?;
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        self.identities.decrypt(ciphertext)
+    }
+}
+
+/// Where `path` is stored when the data dir is encrypted.
+pub fn encrypted_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(ENCRYPTED_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Plaintext paths of every file encryption covers, whichever form they are
+/// in now.
+fn covered_files(data_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = TOP_LEVEL_FILES
+        .iter()
+        .map(|name| data_dir.join(name))
+        .collect();
+    for (dir, names) in [
+        ("connections", CONNECTION_FILES),
+        ("accounts", ACCOUNT_FILES),
+    ] {
+        let dir = data_dir.join(dir);
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+        };
+        let mut entity_dirs = Vec::new();
+        for entry in entries {
+            let entry = entry.with_context(|| format!("Failed to read {}", dir.display()))?;
+            // `file_type` doesn't follow symlinks, which skips `by-name`'s links.
+            if entry.file_type()?.is_dir() && entry.file_name() != "by-name" {
+                entity_dirs.push(entry.path());
+            }
+        }
+        entity_dirs.sort();
+        for entity_dir in entity_dirs {
+            files.extend(names.iter().map(|name| entity_dir.join(name)));
+        }
+    }
+    Ok(files)
+}
+
+/// Rewrite `from` as `to` with `convert` applied, then remove `from`.
+fn convert_file(from: &Path, to: &Path, convert: impl Fn(&[u8]) -> Result<Vec<u8>>) -> Result<()> {
+    if to.exists() {
+        bail!(
+            "Both {} and {} exist; remove the stale one first",
+            from.display(),
+            to.display()
+        );
+    }
+    let content =
+        std::fs::read(from).with_context(|| format!("Failed to read {}", from.display()))?;
+    let converted =
+        convert(&content).with_context(|| format!("Failed to convert {}", from.display()))?;
+    write_atomic_sync(to, &converted)?;
+    std::fs::remove_file(from).with_context(|| format!("Failed to remove {}", from.display()))
+}
+
+/// Encrypt every plaintext data file in place. Returns the files encrypted,
+/// relative to the data dir.
+pub fn encrypt_data_dir(data_dir: &Path, cipher: &DataCipher) -> Result<Vec<PathBuf>> {
+    let mut converted = Vec::new();
+    for path in covered_files(data_dir)? {
+        if path.is_file() {
+            convert_file(&path, &encrypted_path(&path), |content| {
+                cipher.encrypt(content)
+            })?;
+            converted.push(path.strip_prefix(data_dir).unwrap_or(&path).to_path_buf());
+        }
+    }
+    Ok(converted)
+}
+
+/// Decrypt every encrypted data file back to plaintext in place. Returns the
+/// files decrypted, relative to the data dir.
+pub fn decrypt_data_dir(data_dir: &Path, identities: &AgeIdentities) -> Result<Vec<PathBuf>> {
+    let mut converted = Vec::new();
+    for path in covered_files(data_dir)? {
+        let encrypted = encrypted_path(&path);
+        if encrypted.is_file() {
+            convert_file(&encrypted, &path, |content| identities.decrypt(content))?;
+            converted.push(path.strip_prefix(data_dir).unwrap_or(&path).to_path_buf());
+        }
+    }
+    Ok(converted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_cipher() -> (::age::x25519::Identity, DataCipher) {
+        let identity = ::age::x25519::Identity::generate();
+        let config = EncryptionConfig {
+            recipients: vec![identity.to_public().to_string()],
+            recipients_file: None,
+        };
+        let cipher = DataCipher::from_config(&config, Path::new("/nonexistent")).unwrap();
+        (identity, cipher)
+    }
+
+    #[test]
+    fn encrypts_to_configured_recipients() -> Result<()> {
+        let (identity, cipher) = test_cipher();
+        let ciphertext = cipher.encrypt(b"{\"id\":\"acct-1\"}\n")?;
+        assert_ne!(ciphertext, b"{\"id\":\"acct-1\"}\n");
+        assert_eq!(
+            ::age::decrypt(&identity, &ciphertext)?,
+            b"{\"id\":\"acct-1\"}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reads_recipients_file_and_rejects_empty_config() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let identity = ::age::x25519::Identity::generate();
+        std::fs::write(
+            dir.path().join("recipients.txt"),
+            format!("# laptop\n{}\n\n", identity.to_public()),
+        )?;
+        let config = EncryptionConfig {
+            recipients: Vec::new(),
+            recipients_file: Some(PathBuf::from("recipients.txt")),
+        };
+        assert_eq!(
+            config.recipient_keys(dir.path())?,
+            vec![identity.to_public().to_string()]
+        );
+        assert!(DataCipher::from_config(&config, dir.path()).is_ok());
+
+        let err = DataCipher::from_config(&EncryptionConfig::default(), dir.path())
+            .err()
+            .expect("no recipients");
+        assert!(err.to_string().contains("no recipients"));
+        Ok(())
+    }
+
+    #[test]
+    fn encrypt_data_dir_covers_storage_files_only() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let account_dir = dir.path().join("accounts/acct-1");
+        let prices_dir = dir.path().join("prices/equity/AAPL");
+        std::fs::create_dir_all(&account_dir)?;
+        std::fs::create_dir_all(&prices_dir)?;
+        std::fs::write(account_dir.join("transactions.jsonl"), "{}\n")?;
+        std::fs::write(prices_dir.join("2026.jsonl"), "{}\n")?;
+        std::fs::write(dir.path().join("proposed_transaction_edits.jsonl"), "")?;
+
+        let (identity, cipher) = test_cipher();
+        let encrypted = encrypt_data_dir(dir.path(), &cipher)?;
+        assert_eq!(
+            encrypted,
+            vec![
+                PathBuf::from("proposed_transaction_edits.jsonl"),
+                PathBuf::from("accounts/acct-1/transactions.jsonl"),
+            ]
+        );
+        assert!(!account_dir.join("transactions.jsonl").exists());
+        let ciphertext = std::fs::read(account_dir.join("transactions.jsonl.age"))?;
+        assert_eq!(::age::decrypt(&identity, &ciphertext)?, b"{}\n");
+        assert!(prices_dir.join("2026.jsonl").exists());
+
+        // Re-running only touches what's still plaintext.
+        assert!(encrypt_data_dir(dir.path(), &cipher)?.is_empty());
+        Ok(())
+    }
+}
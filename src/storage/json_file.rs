@@ -3,18 +3,33 @@ use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use futures::{StreamExt, TryStreamExt};
 use tokio::fs;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::AsyncWriteExt;
 use tracing::warn;
 
-use super::{dedupe_transactions_last_write_wins, Storage};
+use super::encryption::{encrypted_path, DataCipher, ENCRYPTED_EXTENSION, ENCRYPTION_CONFIG_FILE};
+use super::search_index::SearchIndex;
+use super::transaction_index::{read_lines, LineRef, TransactionIndex};
+use super::{
+    dedupe_transactions_last_write_wins, transaction_dedupe_keys, transactions_in_range,
+    write_atomic, Storage, TransactionOrder, TransactionStream,
+};
 use crate::credentials::CredentialStore;
 use crate::models::{
     Account, AccountConfig, BalanceSnapshot, Connection, ConnectionConfig, ConnectionState, Id,
-    ProposedTransactionEdit, Transaction, TransactionAnnotation, TransactionAnnotationPatch,
+    ProposedTransactionEdit, SyncBatch, SyncRun, Transaction, TransactionAnnotation,
+    TransactionAnnotationPatch,
+};
+use crate::storage::{
+    merge_account_logs, AccountHistoryMerged, AccountLogs, JsonlCompactionStats,
+    SyncBatchRowsRemoved, TransactionMetadataBackfillStats,
 };
-use crate::storage::{JsonlCompactionStats, TransactionMetadataBackfillStats};
+
+/// Transactions read per blocking task by `stream_transactions`.
+const STREAM_CHUNK_LEN: usize = 64;
 
 /// JSON file-based storage implementation.
 ///
@@ -33,10 +48,45 @@ use crate::storage::{JsonlCompactionStats, TransactionMetadataBackfillStats};
 ///       transactions.jsonl
 ///       transaction_annotations.jsonl
 /// ```
+///
+/// Date-range queries go through a per-account index of `transactions.jsonl`
+/// kept in an uncommitted cache dir (see `local_cache_dir`).
+///
+/// If the data dir has an `encryption.toml`, each of these files is stored
+/// age-encrypted as `<name>.age` instead (see [`super::encryption`]). The
+/// file is checked on every access, so long-lived processes follow
+/// `keepbook encryption enable`/`disable` run elsewhere.
 #[derive(Clone)]
 pub struct JsonFileStorage {
     base_path: PathBuf,
     cache: Arc<Mutex<JsonFileStorageCache>>,
+    encryption: Arc<Mutex<EncryptionState>>,
+}
+
+/// `encryption.toml` as last loaded. `Err` holds why it couldn't be loaded;
+/// every file access then fails instead of falling back to plaintext.
+type Encryption = std::result::Result<Option<Arc<DataCipher>>, String>;
+
+struct EncryptionState {
+    /// Size and mtime of `encryption.toml` when loaded; `None` if missing.
+    key: Option<FsCacheKey>,
+    cipher: Encryption,
+}
+
+impl EncryptionState {
+    fn load(base_path: &Path) -> Self {
+        let key = JsonFileStorage::fs_cache_key_sync(&base_path.join(ENCRYPTION_CONFIG_FILE));
+        let cipher = match &key {
+            Ok(_) => DataCipher::load(base_path)
+                .map(|cipher| cipher.map(Arc::new))
+                .map_err(|err| format!("{err:#}")),
+            Err(err) => Err(format!("{err:#}")),
+        };
+        Self {
+            key: key.ok().flatten(),
+            cipher,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -59,15 +109,90 @@ struct JsonFileStorageCache {
     account_configs: HashMap<Id, CachedRead<Option<AccountConfig>>>,
     balance_snapshots: HashMap<Id, CachedRead<Vec<BalanceSnapshot>>>,
     transactions: HashMap<Id, CachedRead<Vec<Transaction>>>,
+    transaction_indexes: HashMap<Id, CachedRead<Arc<TransactionIndex>>>,
     transaction_annotations: HashMap<Id, CachedRead<Vec<TransactionAnnotationPatch>>>,
     proposed_transaction_edits: Option<CachedRead<Vec<ProposedTransactionEdit>>>,
 }
 
 impl JsonFileStorage {
     pub fn new(base_path: impl AsRef<Path>) -> Self {
+        let base_path = base_path.as_ref().to_path_buf();
+        let encryption = EncryptionState::load(&base_path);
         Self {
-            base_path: base_path.as_ref().to_path_buf(),
+            base_path,
             cache: Arc::new(Mutex::new(JsonFileStorageCache::default())),
+            encryption: Arc::new(Mutex::new(encryption)),
+        }
+    }
+
+    /// The current encryption setting, reloaded when `encryption.toml` has
+    /// changed since it was last read.
+    fn encryption(&self) -> Encryption {
+        let config_path = self.base_path.join(ENCRYPTION_CONFIG_FILE);
+        let mut state = self.encryption.lock().expect("encryption state poisoned");
+        let unchanged = matches!(
+            Self::fs_cache_key_sync(&config_path),
+            Ok(key) if key == state.key
+        );
+        if !unchanged {
+            *state = EncryptionState::load(&self.base_path);
+            // Cached reads came from the other form of each file.
+            self.clear_cache();
+        }
+        state.cipher.clone()
+    }
+
+    /// Whether data files are stored encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        !matches!(self.encryption(), Ok(None))
+    }
+
+    fn cipher(&self) -> Result<Option<Arc<DataCipher>>> {
+        self.encryption()
+            .map_err(|err| anyhow!("Invalid {ENCRYPTION_CONFIG_FILE}: {err}"))
+    }
+
+    /// The cipher to write `path` with, refusing when `path` is not in the
+    /// form the data dir currently uses, or when the file also exists in the
+    /// other form (both mean encryption was switched under this process).
+    fn cipher_for_write(&self, path: &Path) -> Result<Option<Arc<DataCipher>>> {
+        let cipher = self.cipher()?;
+        let encrypted = path
+            .extension()
+            .is_some_and(|extension| extension == ENCRYPTED_EXTENSION);
+        if encrypted != cipher.is_some() {
+            anyhow::bail!(
+                "Refusing to write {}: the data dir is now {}",
+                path.display(),
+                if cipher.is_some() {
+                    "encrypted"
+                } else {
+                    "plaintext"
+                }
+            );
+        }
+        let other = if encrypted {
+            path.with_extension("")
+        } else {
+            encrypted_path(path)
+        };
+        if other.exists() {
+            anyhow::bail!(
+                "Refusing to write {}: {} also exists; remove the stale one first",
+                path.display(),
+                other.display()
+            );
+        }
+        Ok(cipher)
+    }
+
+    /// Where a data file is stored: `path` itself, or `<path>.age` when the
+    /// data dir is encrypted.
+    fn data_file(&self, path: PathBuf) -> PathBuf {
+        if self.is_encrypted() {
+            encrypted_path(&path)
+        } else {
+            path
         }
     }
 
@@ -84,7 +209,7 @@ impl JsonFileStorage {
     }
 
     fn proposed_transaction_edits_file(&self) -> PathBuf {
-        self.base_path.join("proposed_transaction_edits.jsonl")
+        self.data_file(self.base_path.join("proposed_transaction_edits.jsonl"))
     }
 
     fn ensure_id_path_safe(&self, id: &Id) -> Result<()> {
@@ -106,11 +231,19 @@ impl JsonFileStorage {
     }
 
     fn connection_config_file(&self, id: &Id) -> Result<PathBuf> {
-        Ok(self.connection_dir(id)?.join("connection.toml"))
+        Ok(self.data_file(self.connection_dir(id)?.join("connection.toml")))
     }
 
     fn connection_state_file(&self, id: &Id) -> Result<PathBuf> {
-        Ok(self.connection_dir(id)?.join("connection.json"))
+        Ok(self.data_file(self.connection_dir(id)?.join("connection.json")))
+    }
+
+    fn sync_runs_file(&self, id: &Id) -> Result<PathBuf> {
+        Ok(self.data_file(self.connection_dir(id)?.join("sync_runs.jsonl")))
+    }
+
+    fn sync_batches_file(&self, id: &Id) -> Result<PathBuf> {
+        Ok(self.data_file(self.connection_dir(id)?.join("sync_batches.jsonl")))
     }
 
     /// Get the path to a connection's config file.
@@ -128,11 +261,7 @@ impl JsonFileStorage {
     ) -> Result<Option<Box<dyn CredentialStore>>> {
         // First try to load from connection config
         let config_path = self.connection_config_file(connection_id)?;
-        if config_path.exists() {
-            let content = std::fs::read_to_string(&config_path)
-                .with_context(|| format!("Failed to read {}", config_path.display()))?;
-            let config: ConnectionConfig = toml::from_str(&content)
-                .with_context(|| format!("Failed to parse {}", config_path.display()))?;
+        if let Some(config) = self.read_toml_sync::<ConnectionConfig>(&config_path)? {
             if let Some(cred_config) = config.credentials {
                 let base_dir = config_path.parent();
                 return Ok(Some(cred_config.build_with_base_dir(base_dir)));
@@ -156,11 +285,11 @@ impl JsonFileStorage {
     }
 
     fn account_file(&self, id: &Id) -> Result<PathBuf> {
-        Ok(self.account_dir(id)?.join("account.json"))
+        Ok(self.data_file(self.account_dir(id)?.join("account.json")))
     }
 
     fn account_config_file(&self, id: &Id) -> Result<PathBuf> {
-        Ok(self.account_dir(id)?.join("account_config.toml"))
+        Ok(self.data_file(self.account_dir(id)?.join("account_config.toml")))
     }
 
     /// Load optional account config.
@@ -192,22 +321,72 @@ impl JsonFileStorage {
     }
 
     fn balances_file(&self, account_id: &Id) -> Result<PathBuf> {
-        Ok(self.account_dir(account_id)?.join("balances.jsonl"))
+        Ok(self.data_file(self.account_dir(account_id)?.join("balances.jsonl")))
     }
 
     fn transactions_file(&self, account_id: &Id) -> Result<PathBuf> {
-        Ok(self.account_dir(account_id)?.join("transactions.jsonl"))
+        Ok(self.data_file(self.account_dir(account_id)?.join("transactions.jsonl")))
     }
 
-    fn transaction_annotations_file(&self, account_id: &Id) -> Result<PathBuf> {
+    /// Derived files that must never be committed: inside `.git` when the
+    /// data dir is a repository, otherwise in a dot dir at its top.
+    pub fn local_cache_dir_for(data_dir: &Path) -> PathBuf {
+        let git_dir = data_dir.join(".git");
+        if git_dir.is_dir() {
+            git_dir.join("keepbook-cache")
+        } else {
+            data_dir.join(".keepbook-cache")
+        }
+    }
+
+    fn local_cache_dir(&self) -> PathBuf {
+        Self::local_cache_dir_for(&self.base_path)
+    }
+
+    fn transaction_index_file(&self, account_id: &Id) -> Result<PathBuf> {
+        self.ensure_id_path_safe(account_id)?;
         Ok(self
-            .account_dir(account_id)?
-            .join("transaction_annotations.jsonl"))
+            .local_cache_dir()
+            .join("transactions")
+            .join(format!("{account_id}.json")))
+    }
+
+    fn search_index_file(&self, account_id: &Id) -> Result<PathBuf> {
+        self.ensure_id_path_safe(account_id)?;
+        Ok(self
+            .local_cache_dir()
+            .join("search")
+            .join(format!("{account_id}.json")))
+    }
+
+    /// Drop the saved indexes after rewriting an account's transaction or
+    /// annotation log in place.
+    fn remove_account_indexes(&self, account_id: &Id) -> Result<()> {
+        for path in [
+            self.transaction_index_file(account_id)?,
+            self.search_index_file(account_id)?,
+        ] {
+            match std::fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn transaction_annotations_file(&self, account_id: &Id) -> Result<PathBuf> {
+        Ok(self.data_file(
+            self.account_dir(account_id)?
+                .join("transaction_annotations.jsonl"),
+        ))
     }
 
     /// Sanitize a name for use as a symlink filename.
     /// Returns None if the result would be empty.
-    fn sanitize_name(name: &str) -> Option<String> {
+    pub(crate) fn sanitize_name(name: &str) -> Option<String> {
         let sanitized: String = name
             .trim()
             .chars()
@@ -282,105 +461,262 @@ impl JsonFileStorage {
         *self.cache.lock().expect("storage cache poisoned") = JsonFileStorageCache::default();
     }
 
-    async fn read_json<T: for<'de> serde::Deserialize<'de>>(
+    fn has_cached_transactions(&self, account_id: &Id, key: &Option<FsCacheKey>) -> bool {
+        self.cache
+            .lock()
+            .expect("storage cache poisoned")
+            .transactions
+            .get(account_id)
+            .is_some_and(|cached| &cached.key == key)
+    }
+
+    async fn transaction_index(
         &self,
-        path: &Path,
-    ) -> Result<Option<T>> {
-        match fs::read_to_string(path).await {
-            Ok(content) => {
-                let value = serde_json::from_str(&content)
-                    .with_context(|| format!("Failed to parse JSON from {path:?}"))?;
-                Ok(Some(value))
+        account_id: &Id,
+        key: Option<FsCacheKey>,
+    ) -> Result<Arc<TransactionIndex>> {
+        {
+            let cache = self.cache.lock().expect("storage cache poisoned");
+            if let Some(cached) = cache.transaction_indexes.get(account_id) {
+                if cached.key == key {
+                    return Ok(cached.value.clone());
+                }
             }
+        }
+
+        let index_path = self.transaction_index_file(account_id)?;
+        let log_path = self.transactions_file(account_id)?;
+        let index =
+            tokio::task::spawn_blocking(move || TransactionIndex::load(&index_path, &log_path))
+                .await
+                .context("Transaction index task failed")??;
+        let index = Arc::new(index);
+        self.cache
+            .lock()
+            .expect("storage cache poisoned")
+            .transaction_indexes
+            .insert(
+                account_id.clone(),
+                CachedRead {
+                    key,
+                    value: index.clone(),
+                },
+            );
+        Ok(index)
+    }
+
+    /// Where the deduped transactions in `[start, end)` live in the log, or
+    /// `None` if the whole history is already in memory.
+    async fn transaction_lines_in_range(
+        &self,
+        account_id: &Id,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<Option<Vec<LineRef>>> {
+        // Line offsets into ciphertext are meaningless.
+        if self.is_encrypted() {
+            return Ok(None);
+        }
+        let path = self.transactions_file(account_id)?;
+        let key = Self::fs_cache_key(&path).await?;
+        if self.has_cached_transactions(account_id, &key) {
+            return Ok(None);
+        }
+        let index = self.transaction_index(account_id, key).await?;
+        Ok(Some(index.in_range(start, end)))
+    }
+
+    /// Ids of the transactions whose words include every one of `prefixes`,
+    /// per the search index, or `None` when there can't be one.
+    async fn search_candidates(
+        &self,
+        account_id: &Id,
+        prefixes: &[String],
+    ) -> Result<Option<HashSet<String>>> {
+        // The index holds plaintext words.
+        if self.is_encrypted() {
+            return Ok(None);
+        }
+        let index_path = self.search_index_file(account_id)?;
+        let transactions_path = self.transactions_file(account_id)?;
+        let annotations_path = self.transaction_annotations_file(account_id)?;
+        let prefixes = prefixes.to_vec();
+        let candidates = tokio::task::spawn_blocking(move || {
+            SearchIndex::load(&index_path, &transactions_path, &annotations_path)
+                .map(|index| index.candidates(&prefixes))
+        })
+        .await
+        .context("Search index task failed")??;
+        Ok(Some(candidates))
+    }
+
+    /// Extend an account's search index after appending to its logs, so the
+    /// next search doesn't have to. Accounts never searched have no index.
+    async fn refresh_search_index(&self, account_id: &Id) {
+        if self.is_encrypted() {
+            return;
+        }
+        let Ok(index_path) = self.search_index_file(account_id) else {
+            return;
+        };
+        if !index_path.exists() {
+            return;
+        }
+        let (Ok(transactions_path), Ok(annotations_path)) = (
+            self.transactions_file(account_id),
+            self.transaction_annotations_file(account_id),
+        ) else {
+            return;
+        };
+        let refreshed = tokio::task::spawn_blocking(move || {
+            SearchIndex::load(&index_path, &transactions_path, &annotations_path)
+        })
+        .await;
+        if let Ok(Err(error)) = refreshed {
+            tracing::debug!("Failed to refresh search index for {account_id}: {error:#}");
+        }
+    }
+
+    /// Decrypt `content` read from `path` if the data dir is encrypted.
+    fn decode(&self, path: &Path, content: Vec<u8>) -> Result<String> {
+        let content = match self.cipher()? {
+            Some(cipher) => cipher
+                .decrypt(&content)
+                .with_context(|| format!("Failed to decrypt {}", path.display()))?,
+            None => content,
+        };
+        String::from_utf8(content).with_context(|| format!("{} is not UTF-8", path.display()))
+    }
+
+    /// Encrypt `content` for `path` if the data dir is encrypted.
+    fn encode(&self, path: &Path, content: String) -> Result<Vec<u8>> {
+        match self.cipher_for_write(path)? {
+            Some(cipher) => cipher
+                .encrypt(content.as_bytes())
+                .with_context(|| format!("Failed to encrypt {}", path.display())),
+            None => Ok(content.into_bytes()),
+        }
+    }
+
+    async fn read_text(&self, path: &Path) -> Result<Option<String>> {
+        match fs::read(path).await {
+            Ok(content) => self.decode(path, content).map(Some),
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
             Err(e) => Err(e).context("Failed to read file"),
         }
     }
 
-    async fn write_json<T: serde::Serialize>(&self, path: &Path, value: &T) -> Result<()> {
+    fn read_text_sync(&self, path: &Path) -> Result<Option<String>> {
+        match std::fs::read(path) {
+            Ok(content) => self.decode(path, content).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context("Failed to read file"),
+        }
+    }
+
+    async fn write_text(&self, path: &Path, content: String) -> Result<()> {
         self.ensure_dir(path).await?;
-        let content = serde_json::to_string_pretty(value).context("Failed to serialize JSON")?;
-        fs::write(path, content)
+        let content = self.encode(path, content)?;
+        write_atomic(path, content)
             .await
-            .context("Failed to write file")?;
-        Ok(())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    async fn read_json<T: for<'de> serde::Deserialize<'de>>(
+        &self,
+        path: &Path,
+    ) -> Result<Option<T>> {
+        let Some(content) = self.read_text(path).await? else {
+            return Ok(None);
+        };
+        let value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse JSON from {path:?}"))?;
+        Ok(Some(value))
+    }
+
+    async fn write_json<T: serde::Serialize>(&self, path: &Path, value: &T) -> Result<()> {
+        let content = serde_json::to_string_pretty(value).context("Failed to serialize JSON")?;
+        self.write_text(path, content).await
     }
 
     fn read_toml_sync<T: for<'de> serde::Deserialize<'de>>(
         &self,
         path: &Path,
     ) -> Result<Option<T>> {
-        match std::fs::read_to_string(path) {
-            Ok(content) => {
-                let value = toml::from_str(&content)
-                    .with_context(|| format!("Failed to parse TOML from {path:?}"))?;
-                Ok(Some(value))
-            }
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
-            Err(e) => Err(e).context("Failed to read file"),
-        }
+        let Some(content) = self.read_text_sync(path)? else {
+            return Ok(None);
+        };
+        let value = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse TOML from {path:?}"))?;
+        Ok(Some(value))
     }
 
     async fn read_jsonl<T: for<'de> serde::Deserialize<'de>>(&self, path: &Path) -> Result<Vec<T>> {
-        let file = match fs::File::open(path).await {
-            Ok(f) => f,
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
-            Err(e) => return Err(e).context("Failed to open file"),
+        let Some(content) = self.read_text(path).await? else {
+            return Ok(Vec::new());
         };
 
-        let reader = BufReader::new(file);
-        let mut lines = reader.lines();
         let mut items = Vec::new();
-
-        while let Some(line) = lines.next_line().await.context("Failed to read line")? {
+        for (index, line) in content.lines().enumerate() {
             if line.trim().is_empty() {
                 continue;
             }
-            let item: T = serde_json::from_str(&line)
-                .with_context(|| format!("Failed to parse JSONL line: {line}"))?;
+            let item: T = serde_json::from_str(line).with_context(|| {
+                format!(
+                    "Failed to parse JSONL line {} of {} (run `keepbook doctor`): {line}",
+                    index + 1,
+                    path.display()
+                )
+            })?;
             items.push(item);
         }
 
         Ok(items)
     }
 
+    fn jsonl_content<T: serde::Serialize>(items: &[T]) -> Result<String> {
+        let mut content = String::new();
+        for item in items {
+            let line = serde_json::to_string(item).context("Failed to serialize item")?;
+            content.push_str(&line);
+            content.push('\n');
+        }
+        Ok(content)
+    }
+
     async fn append_jsonl<T: serde::Serialize>(&self, path: &Path, items: &[T]) -> Result<()> {
         if items.is_empty() {
             return Ok(());
         }
 
-        self.ensure_dir(path).await?;
+        let content = Self::jsonl_content(items)?;
+
+        // An age file can't be appended to, so rewrite it whole.
+        if self.cipher_for_write(path)?.is_some() {
+            let existing = self.read_text(path).await?.unwrap_or_default();
+            return self.write_text(path, existing + &content).await;
+        }
 
+        self.ensure_dir(path).await?;
         let mut file = fs::OpenOptions::new()
             .create(true)
             .append(true)
             .open(path)
             .await
             .context("Failed to open file for append")?;
-
-        for item in items {
-            let line = serde_json::to_string(item).context("Failed to serialize item")?;
-            file.write_all(line.as_bytes()).await?;
-            file.write_all(b"\n").await?;
-        }
+        // One write per batch so a crash can't leave a partial batch behind
+        // a complete line.
+        file.write_all(content.as_bytes()).await?;
+        file.sync_data().await?;
 
         Ok(())
     }
 
     async fn write_jsonl<T: serde::Serialize>(&self, path: &Path, items: &[T]) -> Result<()> {
-        self.ensure_dir(path).await?;
-
-        let mut content = String::new();
-        for item in items {
-            let line = serde_json::to_string(item).context("Failed to serialize item")?;
-            content.push_str(&line);
-            content.push('\n');
-        }
-
-        fs::write(path, content)
+        let content = Self::jsonl_content(items)?;
+        self.write_text(path, content)
             .await
-            .with_context(|| format!("Failed to write JSONL file {}", path.display()))?;
-        Ok(())
+            .with_context(|| format!("Failed to write JSONL file {}", path.display()))
     }
 
     async fn list_dirs(&self, path: &Path) -> Result<Vec<Id>> {
@@ -788,7 +1124,7 @@ impl JsonFileStorage {
                     .map(Transaction::backfill_standardized_metadata)
                     .collect();
                 stats.transactions_before += raw.len();
-                let mut compacted = dedupe_transactions_last_write_wins(raw);
+                let mut compacted = compact_transactions(raw);
                 compacted.sort_by(|a, b| {
                     a.timestamp
                         .cmp(&b.timestamp)
@@ -796,6 +1132,7 @@ impl JsonFileStorage {
                 });
                 stats.transactions_after += compacted.len();
                 self.write_jsonl(&tx_path, &compacted).await?;
+                self.remove_account_indexes(&account_id)?;
                 stats.files_rewritten += 1;
             }
 
@@ -808,6 +1145,7 @@ impl JsonFileStorage {
                 let compacted = compact_transaction_annotation_patches(raw);
                 stats.annotation_patches_after += compacted.len();
                 self.write_jsonl(&ann_path, &compacted).await?;
+                self.remove_account_indexes(&account_id)?;
                 stats.files_rewritten += 1;
             }
         }
@@ -816,6 +1154,115 @@ impl JsonFileStorage {
         Ok(stats)
     }
 
+    pub async fn remove_sync_batch_rows(
+        &self,
+        account_id: &Id,
+        batch_id: &Id,
+    ) -> Result<SyncBatchRowsRemoved> {
+        let mut removed = SyncBatchRowsRemoved::default();
+
+        let balances_path = self.balances_file(account_id)?;
+        if balances_path.exists() {
+            let snapshots = self.read_jsonl::<BalanceSnapshot>(&balances_path).await?;
+            let before = snapshots.len();
+            let kept: Vec<BalanceSnapshot> = snapshots
+                .into_iter()
+                .filter(|s| s.sync_batch.as_ref() != Some(batch_id))
+                .collect();
+            removed.balance_snapshots = before - kept.len();
+            if removed.balance_snapshots > 0 {
+                self.write_jsonl(&balances_path, &kept).await?;
+            }
+        }
+
+        let tx_path = self.transactions_file(account_id)?;
+        if tx_path.exists() {
+            let txns = self.read_jsonl::<Transaction>(&tx_path).await?;
+            let before = txns.len();
+            let kept: Vec<Transaction> = txns
+                .into_iter()
+                .filter(|t| t.sync_batch.as_ref() != Some(batch_id))
+                .collect();
+            removed.transactions = before - kept.len();
+            if removed.transactions > 0 {
+                self.write_jsonl(&tx_path, &kept).await?;
+                self.remove_account_indexes(account_id)?;
+            }
+        }
+
+        self.clear_cache();
+        Ok(removed)
+    }
+
+    /// Move `from`'s balance, transaction and annotation logs into `into`'s
+    /// (see `merge_account_logs`), then delete `from`'s logs.
+    pub async fn merge_account_history(
+        &self,
+        from: &Id,
+        into: &Id,
+    ) -> Result<AccountHistoryMerged> {
+        let from_logs = self.read_account_logs(from).await?;
+        let merged = AccountHistoryMerged {
+            balance_snapshots: from_logs.balances.len(),
+            transactions: from_logs.transactions.len(),
+            annotation_patches: from_logs.annotations.len(),
+        };
+        if merged == AccountHistoryMerged::default() {
+            return Ok(merged);
+        }
+        let into_logs = self.read_account_logs(into).await?;
+        let logs = merge_account_logs(from_logs, into_logs);
+
+        // Write the merged logs before removing the originals, so a crash in
+        // between leaves `from`'s rows in both accounts rather than losing
+        // them. Merging again absorbs the copies: transaction dedupe keeps one
+        // version, repeated annotation patches reapply the same values, and
+        // identical balance snapshots are dropped.
+        if !logs.balances.is_empty() {
+            self.write_jsonl(&self.balances_file(into)?, &logs.balances)
+                .await?;
+        }
+        if !logs.transactions.is_empty() {
+            self.write_jsonl(&self.transactions_file(into)?, &logs.transactions)
+                .await?;
+            self.remove_account_indexes(into)?;
+        }
+        if !logs.annotations.is_empty() {
+            self.write_jsonl(&self.transaction_annotations_file(into)?, &logs.annotations)
+                .await?;
+            self.remove_account_indexes(into)?;
+        }
+        for path in [
+            self.balances_file(from)?,
+            self.transactions_file(from)?,
+            self.transaction_annotations_file(from)?,
+        ] {
+            match fs::remove_file(&path).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+                }
+            }
+        }
+        self.remove_account_indexes(from)?;
+
+        self.clear_cache();
+        Ok(merged)
+    }
+
+    async fn read_account_logs(&self, account_id: &Id) -> Result<AccountLogs> {
+        Ok(AccountLogs {
+            balances: self.read_jsonl(&self.balances_file(account_id)?).await?,
+            transactions: self
+                .read_jsonl(&self.transactions_file(account_id)?)
+                .await?,
+            annotations: self
+                .read_jsonl(&self.transaction_annotations_file(account_id)?)
+                .await?,
+        })
+    }
+
     pub async fn backfill_transaction_metadata_all(
         &self,
     ) -> Result<TransactionMetadataBackfillStats> {
@@ -849,6 +1296,7 @@ impl JsonFileStorage {
 
             if updated > 0 {
                 self.write_jsonl(&tx_path, &backfilled).await?;
+                self.remove_account_indexes(&account_id)?;
                 stats.files_rewritten += 1;
                 stats.transactions_updated += updated;
             }
@@ -859,6 +1307,27 @@ impl JsonFileStorage {
     }
 }
 
+/// Keep the last version of each transaction. A survivor that replaced an
+/// earlier version loses its `sync_batch`: reverting that batch would
+/// otherwise delete the transaction instead of restoring the discarded
+/// version.
+fn compact_transactions(raw: Vec<Transaction>) -> Vec<Transaction> {
+    let mut versions: HashMap<String, usize> = HashMap::new();
+    for key in raw.iter().flat_map(transaction_dedupe_keys) {
+        *versions.entry(key).or_default() += 1;
+    }
+    let mut compacted = dedupe_transactions_last_write_wins(raw);
+    for txn in &mut compacted {
+        if transaction_dedupe_keys(txn)
+            .iter()
+            .any(|key| versions.get(key).is_some_and(|count| *count > 1))
+        {
+            txn.sync_batch = None;
+        }
+    }
+    compacted
+}
+
 fn compact_transaction_annotation_patches(
     patches: Vec<TransactionAnnotationPatch>,
 ) -> Vec<TransactionAnnotationPatch> {
@@ -969,12 +1438,9 @@ impl Storage for JsonFileStorage {
 
     async fn save_connection_config(&self, id: &Id, config: &ConnectionConfig) -> Result<()> {
         let path = self.connection_config_file(id)?;
-        self.ensure_dir(&path).await?;
         let config_toml =
             toml::to_string_pretty(config).context("Failed to serialize connection config")?;
-        fs::write(&path, config_toml)
-            .await
-            .with_context(|| format!("Failed to write {}", path.display()))?;
+        self.write_text(&path, config_toml).await?;
         self.clear_cache();
         Ok(())
     }
@@ -1058,12 +1524,9 @@ impl Storage for JsonFileStorage {
 
     async fn save_account_config(&self, id: &Id, config: &AccountConfig) -> Result<()> {
         let path = self.account_config_file(id)?;
-        self.ensure_dir(&path).await?;
         let config_toml =
             toml::to_string_pretty(config).context("Failed to serialize account config")?;
-        fs::write(&path, config_toml)
-            .await
-            .with_context(|| format!("Failed to write {}", path.display()))?;
+        self.write_text(&path, config_toml).await?;
         self.clear_cache();
         Ok(())
     }
@@ -1156,10 +1619,98 @@ impl Storage for JsonFileStorage {
         Ok(txns)
     }
 
+    async fn get_transactions_in_range(
+        &self,
+        account_id: &Id,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Transaction>> {
+        let Some(refs) = self
+            .transaction_lines_in_range(account_id, start, end)
+            .await?
+        else {
+            let txns = self.get_transactions(account_id).await?;
+            return Ok(transactions_in_range(txns, start, end));
+        };
+        let path = self.transactions_file(account_id)?;
+        tokio::task::spawn_blocking(move || read_lines(&path, &refs))
+            .await
+            .context("Transaction read task failed")?
+    }
+
+    async fn stream_transactions(
+        &self,
+        account_id: &Id,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+        order: TransactionOrder,
+    ) -> Result<TransactionStream<'_>> {
+        let Some(mut refs) = self
+            .transaction_lines_in_range(account_id, start, end)
+            .await?
+        else {
+            let mut txns =
+                transactions_in_range(self.get_transactions(account_id).await?, start, end);
+            if order == TransactionOrder::NewestFirst {
+                txns.reverse();
+            }
+            return Ok(Box::pin(futures::stream::iter(txns.into_iter().map(Ok))));
+        };
+        if order == TransactionOrder::NewestFirst {
+            refs.reverse();
+        }
+
+        // Read lazily, a chunk at a time, so a consumer that stops early
+        // never touches the rest of the log.
+        let path = self.transactions_file(account_id)?;
+        let chunks: Vec<Vec<LineRef>> = refs
+            .chunks(STREAM_CHUNK_LEN)
+            .map(<[LineRef]>::to_vec)
+            .collect();
+        let stream = futures::stream::iter(chunks)
+            .then(move |chunk| {
+                let path = path.clone();
+                async move {
+                    tokio::task::spawn_blocking(move || read_lines(&path, &chunk))
+                        .await
+                        .context("Transaction read task failed")?
+                }
+            })
+            .map_ok(|txns| futures::stream::iter(txns.into_iter().map(Ok)))
+            .try_flatten();
+        Ok(Box::pin(stream))
+    }
+
+    async fn find_transactions(
+        &self,
+        account_id: &Id,
+        prefixes: &[String],
+    ) -> Result<Vec<Transaction>> {
+        let path = self.transactions_file(account_id)?;
+        let key = Self::fs_cache_key(&path).await?;
+        if prefixes.is_empty() || self.has_cached_transactions(account_id, &key) {
+            return self.get_transactions(account_id).await;
+        }
+        let Some(ids) = self.search_candidates(account_id, prefixes).await? else {
+            return self.get_transactions(account_id).await;
+        };
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let refs = self
+            .transaction_index(account_id, key)
+            .await?
+            .latest_with_ids(&ids);
+        tokio::task::spawn_blocking(move || read_lines(&path, &refs))
+            .await
+            .context("Transaction read task failed")?
+    }
+
     async fn append_transactions(&self, account_id: &Id, txns: &[Transaction]) -> Result<()> {
         let path = self.transactions_file(account_id)?;
         self.append_jsonl(&path, txns).await?;
         self.clear_cache();
+        self.refresh_search_index(account_id).await;
         Ok(())
     }
 
@@ -1201,6 +1752,7 @@ impl Storage for JsonFileStorage {
         let path = self.transaction_annotations_file(account_id)?;
         self.append_jsonl(&path, patches).await?;
         self.clear_cache();
+        self.refresh_search_index(account_id).await;
         Ok(())
     }
 
@@ -1255,6 +1807,26 @@ impl Storage for JsonFileStorage {
         Ok(())
     }
 
+    async fn get_sync_runs(&self, connection_id: &Id) -> Result<Vec<SyncRun>> {
+        let path = self.sync_runs_file(connection_id)?;
+        self.read_jsonl(&path).await
+    }
+
+    async fn append_sync_run(&self, run: &SyncRun) -> Result<()> {
+        let path = self.sync_runs_file(&run.connection_id)?;
+        self.append_jsonl(&path, std::slice::from_ref(run)).await
+    }
+
+    async fn get_sync_batches(&self, connection_id: &Id) -> Result<Vec<SyncBatch>> {
+        let path = self.sync_batches_file(connection_id)?;
+        self.read_jsonl(&path).await
+    }
+
+    async fn append_sync_batch(&self, batch: &SyncBatch) -> Result<()> {
+        let path = self.sync_batches_file(&batch.connection_id)?;
+        self.append_jsonl(&path, std::slice::from_ref(batch)).await
+    }
+
     async fn get_latest_balance_snapshot(
         &self,
         account_id: &Id,
@@ -1441,6 +2013,47 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn remove_sync_batch_rows_drops_only_that_batch() -> anyhow::Result<()> {
+        let temp = tempfile::tempdir()?;
+        let storage = JsonFileStorage::new(temp.path());
+        let account_id = Id::from_string("acct-1");
+        let batch = Id::from_string("batch-1");
+
+        let kept = BalanceSnapshot::now(vec![AssetBalance::new(Asset::currency("USD"), "10")]);
+        let stamped = BalanceSnapshot::now(vec![AssetBalance::new(Asset::currency("USD"), "99")])
+            .with_sync_batch(batch.clone());
+        storage.append_balance_snapshot(&account_id, &kept).await?;
+        storage
+            .append_balance_snapshot(&account_id, &stamped)
+            .await?;
+
+        let tx = Transaction::new("-5", Asset::currency("USD"), "Coffee");
+        let mut update = tx.clone();
+        update.description = "COFFEE #123".to_string();
+        update.sync_batch = Some(batch.clone());
+        storage
+            .append_transactions(&account_id, &[tx, update])
+            .await?;
+        assert_eq!(
+            storage.get_transactions(&account_id).await?[0].description,
+            "COFFEE #123"
+        );
+
+        let removed = storage.remove_sync_batch_rows(&account_id, &batch).await?;
+
+        assert_eq!(removed.balance_snapshots, 1);
+        assert_eq!(removed.transactions, 1);
+        let snapshots = storage.get_balance_snapshots(&account_id).await?;
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].balances[0].amount, "10");
+        assert_eq!(
+            storage.get_transactions(&account_id).await?[0].description,
+            "Coffee"
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn recompact_all_jsonl_compacts_and_sorts_logs() -> anyhow::Result<()> {
         let temp = tempfile::tempdir()?;
@@ -1614,6 +2227,7 @@ mod tests {
                 transaction_kind: None,
                 is_internal_transfer_hint: None,
             }),
+            sync_batch: None,
         };
         storage.append_transactions(&account_id, &[tx]).await?;
 
@@ -1649,4 +2263,64 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn range_queries_use_an_uncommitted_index() -> anyhow::Result<()> {
+        let temp = tempfile::tempdir()?;
+        std::fs::create_dir(temp.path().join(".git"))?;
+        let storage = JsonFileStorage::new(temp.path());
+        let account_id = Id::from_string("acct-1");
+        let day = |d| Utc.with_ymd_and_hms(2024, 3, d, 12, 0, 0).unwrap();
+        let txn = |id: &str, d, description: &str| {
+            Transaction::new("-5", Asset::currency("USD"), description)
+                .with_id(Id::from_string(id))
+                .with_timestamp(day(d))
+        };
+
+        storage
+            .append_transactions(
+                &account_id,
+                &[
+                    txn("a", 1, "a"),
+                    txn("b", 10, "b"),
+                    txn("c", 20, "c"),
+                    txn("b", 25, "b moved"),
+                ],
+            )
+            .await?;
+
+        let in_range = storage
+            .get_transactions_in_range(&account_id, Some(day(1)), Some(day(21)))
+            .await?;
+        let ids: Vec<&str> = in_range.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "c"]);
+        let index_path = storage.transaction_index_file(&account_id)?;
+        assert!(index_path.starts_with(temp.path().join(".git")));
+        assert!(index_path.exists());
+
+        let newest: Vec<Transaction> = storage
+            .stream_transactions(&account_id, None, None, TransactionOrder::NewestFirst)
+            .await?
+            .take(2)
+            .try_collect()
+            .await?;
+        let descriptions: Vec<&str> = newest.iter().map(|t| t.description.as_str()).collect();
+        assert_eq!(descriptions, vec!["b moved", "c"]);
+
+        // Recompaction rewrites the log; results must not change.
+        storage.recompact_all_jsonl().await?;
+        let after = storage
+            .get_transactions_in_range(&account_id, Some(day(1)), Some(day(21)))
+            .await?;
+        assert_eq!(after.len(), 2);
+        assert_eq!(after[1].description, "c");
+
+        // A fully cached history is filtered in memory and agrees.
+        storage.get_transactions(&account_id).await?;
+        let cached = storage
+            .get_transactions_in_range(&account_id, Some(day(1)), Some(day(21)))
+            .await?;
+        assert_eq!(cached.len(), 2);
+        Ok(())
+    }
 }
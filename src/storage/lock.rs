@@ -0,0 +1,160 @@
+//! Advisory reader/writer lock on a data directory.
+//!
+//! The CLI, sync daemon and keepbook-server can all point at the same data
+//! dir. Each takes a shared lock while reading and an exclusive lock while
+//! writing, so a sync never interleaves with another writer (or with a read
+//! that would see it half done).
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+/// How long entrypoints wait for a busy data dir by default.
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// Any number of readers.
+    Shared,
+    /// A single writer, with no readers.
+    Exclusive,
+}
+
+/// Returned (inside `anyhow::Error`) when the lock isn't free in time.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "data dir busy: {} is locked by another keepbook process (waited {}s)",
+    data_dir.display(),
+    waited.as_secs()
+)]
+pub struct DataDirBusy {
+    pub data_dir: PathBuf,
+    pub waited: Duration,
+}
+
+/// A held data-dir lock, released on drop.
+#[derive(Debug)]
+pub struct DataDirLock {
+    // `None` for a shared lock on a data dir that doesn't exist yet.
+    _file: Option<File>,
+    mode: LockMode,
+}
+
+impl DataDirLock {
+    /// The lock file: inside `.git` when the data dir is a repository (so it
+    /// is never committed), otherwise at the top of the data dir.
+    pub fn lock_path(data_dir: &Path) -> PathBuf {
+        let git_dir = data_dir.join(".git");
+        if git_dir.is_dir() {
+            git_dir.join("keepbook.lock")
+        } else {
+            data_dir.join(".keepbook.lock")
+        }
+    }
+
+    pub fn mode(&self) -> LockMode {
+        self.mode
+    }
+
+    /// Take the lock if it is free right now.
+    pub fn try_acquire(data_dir: &Path, mode: LockMode) -> Result<Option<Self>> {
+        if mode == LockMode::Shared && !data_dir.exists() {
+            return Ok(Some(Self { _file: None, mode }));
+        }
+        std::fs::create_dir_all(data_dir)
+            .with_context(|| format!("Failed to create data dir {}", data_dir.display()))?;
+        let path = Self::lock_path(data_dir);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+        let attempt = match mode {
+            LockMode::Shared => file.try_lock_shared(),
+            LockMode::Exclusive => file.try_lock(),
+        };
+        match attempt {
+            Ok(()) => Ok(Some(Self {
+                _file: Some(file),
+                mode,
+            })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(err)) => {
+                Err(err).with_context(|| format!("Failed to lock {}", path.display()))
+            }
+        }
+    }
+
+    /// Take the lock, waiting up to `timeout` for other processes to release
+    /// it. Fails with [`DataDirBusy`] if they don't.
+    pub async fn acquire(data_dir: &Path, mode: LockMode, timeout: Duration) -> Result<Self> {
+        let start = Instant::now();
+        loop {
+            if let Some(lock) = Self::try_acquire(data_dir, mode)? {
+                return Ok(lock);
+            }
+            let waited = start.elapsed();
+            if waited >= timeout {
+                return Err(DataDirBusy {
+                    data_dir: data_dir.to_path_buf(),
+                    waited,
+                }
+                .into());
+            }
+            tokio::time::sleep(POLL_INTERVAL.min(timeout - waited)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn readers_share_and_writers_exclude() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let data_dir = dir.path();
+
+        let reader = DataDirLock::acquire(data_dir, LockMode::Shared, Duration::ZERO).await?;
+        let second_reader = DataDirLock::try_acquire(data_dir, LockMode::Shared)?;
+        assert!(second_reader.is_some());
+        assert!(DataDirLock::try_acquire(data_dir, LockMode::Exclusive)?.is_none());
+
+        drop(reader);
+        drop(second_reader);
+        let writer = DataDirLock::acquire(data_dir, LockMode::Exclusive, Duration::ZERO).await?;
+        assert_eq!(writer.mode(), LockMode::Exclusive);
+
+        let err = DataDirLock::acquire(data_dir, LockMode::Shared, Duration::from_millis(150))
+            .await
+            .unwrap_err();
+        let busy = err.downcast_ref::<DataDirBusy>().expect("DataDirBusy");
+        assert_eq!(busy.data_dir, data_dir);
+        assert!(err.to_string().starts_with("data dir busy"));
+        Ok(())
+    }
+
+    #[test]
+    fn lock_file_lives_in_git_dir_when_present() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert_eq!(
+            DataDirLock::lock_path(dir.path()),
+            dir.path().join(".keepbook.lock")
+        );
+        std::fs::create_dir(dir.path().join(".git"))?;
+        assert_eq!(
+            DataDirLock::lock_path(dir.path()),
+            dir.path().join(".git").join("keepbook.lock")
+        );
+
+        let missing = dir.path().join("missing");
+        assert!(DataDirLock::try_acquire(&missing, LockMode::Shared)?.is_some());
+        assert!(!missing.exists());
+        Ok(())
+    }
+}
@@ -5,15 +5,20 @@ use std::collections::HashMap;
 use std::sync::Mutex as StdMutex;
 
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use tokio::sync::Mutex;
 
 use crate::credentials::CredentialStore;
 use crate::models::{
     Account, AccountConfig, BalanceSnapshot, Connection, ConnectionConfig, ConnectionState, Id,
-    ProposedTransactionEdit, Transaction, TransactionAnnotationPatch,
+    ProposedTransactionEdit, SyncBatch, SyncRun, Transaction, TransactionAnnotationPatch,
 };
 
-use super::{dedupe_transactions_last_write_wins, Storage};
+use super::{
+    dedupe_last_write_wins_by, dedupe_transactions_last_write_wins, merge_account_logs,
+    timestamp_in_range, transaction_dedupe_keys, AccountHistoryMerged, AccountHistoryStorage,
+    AccountLogs, CompactionStorage, JsonlCompactionStats, Storage, SyncBatchRowsRemoved,
+};
 
 /// In-memory storage for testing purposes.
 pub struct MemoryStorage {
@@ -24,6 +29,8 @@ pub struct MemoryStorage {
     transactions: Mutex<HashMap<Id, Vec<Transaction>>>,
     transaction_annotation_patches: Mutex<HashMap<Id, Vec<TransactionAnnotationPatch>>>,
     proposed_transaction_edits: Mutex<Vec<ProposedTransactionEdit>>,
+    sync_runs: Mutex<HashMap<Id, Vec<SyncRun>>>,
+    sync_batches: Mutex<HashMap<Id, Vec<SyncBatch>>>,
 }
 
 impl MemoryStorage {
@@ -36,6 +43,8 @@ impl MemoryStorage {
             transactions: Mutex::new(HashMap::new()),
             transaction_annotation_patches: Mutex::new(HashMap::new()),
             proposed_transaction_edits: Mutex::new(Vec::new()),
+            sync_runs: Mutex::new(HashMap::new()),
+            sync_batches: Mutex::new(HashMap::new()),
         }
     }
 
@@ -225,6 +234,27 @@ impl Storage for MemoryStorage {
         Ok(txns.get(account_id).cloned().unwrap_or_default())
     }
 
+    async fn get_transactions_in_range(
+        &self,
+        account_id: &Id,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Transaction>> {
+        let txns = self.transactions.lock().await;
+        let Some(history) = txns.get(account_id) else {
+            return Ok(Vec::new());
+        };
+        // Dedupe by reference so only the matches get cloned.
+        let mut in_range: Vec<Transaction> =
+            dedupe_last_write_wins_by(history.iter().collect(), |txn| transaction_dedupe_keys(txn))
+                .into_iter()
+                .filter(|txn| timestamp_in_range(txn.timestamp, start, end))
+                .cloned()
+                .collect();
+        in_range.sort_by_key(|txn| txn.timestamp);
+        Ok(in_range)
+    }
+
     async fn append_transactions(&self, account_id: &Id, new_txns: &[Transaction]) -> Result<()> {
         let mut txns = self.transactions.lock().await;
         txns.entry(account_id.clone())
@@ -292,6 +322,92 @@ impl Storage for MemoryStorage {
             .extend(edits.iter().cloned());
         Ok(())
     }
+
+    async fn get_sync_runs(&self, connection_id: &Id) -> Result<Vec<SyncRun>> {
+        let runs = self.sync_runs.lock().await;
+        Ok(runs.get(connection_id).cloned().unwrap_or_default())
+    }
+
+    async fn append_sync_run(&self, run: &SyncRun) -> Result<()> {
+        self.sync_runs
+            .lock()
+            .await
+            .entry(run.connection_id.clone())
+            .or_default()
+            .push(run.clone());
+        Ok(())
+    }
+
+    async fn get_sync_batches(&self, connection_id: &Id) -> Result<Vec<SyncBatch>> {
+        let batches = self.sync_batches.lock().await;
+        Ok(batches.get(connection_id).cloned().unwrap_or_default())
+    }
+
+    async fn append_sync_batch(&self, batch: &SyncBatch) -> Result<()> {
+        self.sync_batches
+            .lock()
+            .await
+            .entry(batch.connection_id.clone())
+            .or_default()
+            .push(batch.clone());
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl CompactionStorage for MemoryStorage {
+    async fn recompact_all_jsonl(&self) -> Result<JsonlCompactionStats> {
+        // Nothing is stored as JSONL.
+        Ok(JsonlCompactionStats::default())
+    }
+
+    async fn remove_sync_batch_rows(
+        &self,
+        account_id: &Id,
+        batch_id: &Id,
+    ) -> Result<SyncBatchRowsRemoved> {
+        let mut removed = SyncBatchRowsRemoved::default();
+        if let Some(snapshots) = self.balances.lock().await.get_mut(account_id) {
+            let before = snapshots.len();
+            snapshots.retain(|s| s.sync_batch.as_ref() != Some(batch_id));
+            removed.balance_snapshots = before - snapshots.len();
+        }
+        if let Some(txns) = self.transactions.lock().await.get_mut(account_id) {
+            let before = txns.len();
+            txns.retain(|t| t.sync_batch.as_ref() != Some(batch_id));
+            removed.transactions = before - txns.len();
+        }
+        Ok(removed)
+    }
+}
+
+#[async_trait::async_trait]
+impl AccountHistoryStorage for MemoryStorage {
+    async fn merge_account_history(&self, from: &Id, into: &Id) -> Result<AccountHistoryMerged> {
+        let mut balances = self.balances.lock().await;
+        let mut txns = self.transactions.lock().await;
+        let mut patches = self.transaction_annotation_patches.lock().await;
+        let from_logs = AccountLogs {
+            balances: balances.remove(from).unwrap_or_default(),
+            transactions: txns.remove(from).unwrap_or_default(),
+            annotations: patches.remove(from).unwrap_or_default(),
+        };
+        let merged = AccountHistoryMerged {
+            balance_snapshots: from_logs.balances.len(),
+            transactions: from_logs.transactions.len(),
+            annotation_patches: from_logs.annotations.len(),
+        };
+        let into_logs = AccountLogs {
+            balances: balances.remove(into).unwrap_or_default(),
+            transactions: txns.remove(into).unwrap_or_default(),
+            annotations: patches.remove(into).unwrap_or_default(),
+        };
+        let logs = merge_account_logs(from_logs, into_logs);
+        balances.insert(into.clone(), logs.balances);
+        txns.insert(into.clone(), logs.transactions);
+        patches.insert(into.clone(), logs.annotations);
+        Ok(merged)
+    }
 }
 
 #[cfg(test)]
@@ -311,4 +427,28 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn range_query_dedupes_before_filtering() -> Result<()> {
+        use chrono::TimeZone;
+
+        let storage = MemoryStorage::new();
+        let account_id = Id::from_string("acct-1");
+        let day = |d| Utc.with_ymd_and_hms(2024, 3, d, 0, 0, 0).unwrap();
+        let txn = |id: &str, d| {
+            Transaction::new("-1", crate::models::Asset::currency("USD"), id)
+                .with_id(Id::from_string(id))
+                .with_timestamp(day(d))
+        };
+        storage
+            .append_transactions(&account_id, &[txn("b", 5), txn("a", 2), txn("b", 30)])
+            .await?;
+
+        let txns = storage
+            .get_transactions_in_range(&account_id, Some(day(1)), Some(day(30)))
+            .await?;
+        let ids: Vec<&str> = txns.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["a"]);
+        Ok(())
+    }
 }
@@ -0,0 +1,302 @@
+//! On-disk format versioning and migrations.
+//!
+//! The data dir records the layout version it was last migrated to in a
+//! `format_version` file. Migrations are ordered and idempotent: each one
+//! brings the data dir up to its `version`, and running it again on data it
+//! already handled changes nothing, so an interrupted `keepbook migrate` can
+//! simply be rerun.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use super::atomic::write_atomic_sync;
+use super::JsonFileStorage;
+
+/// Name of the version marker at the top of the data dir.
+pub const FORMAT_VERSION_FILE: &str = "format_version";
+
+/// The format this binary reads and writes.
+pub const CURRENT_FORMAT_VERSION: u32 = 2;
+
+/// A registered migration.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// The format version the data dir is at once this migration has run.
+    pub version: u32,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// All migrations, in the order they run.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "transaction_standardized_metadata",
+        description:
+            "Persist standardized metadata derived from synchronizer data on every transaction",
+    },
+    Migration {
+        version: 2,
+        name: "inline_connection_credentials",
+        description:
+            "Move legacy credentials.toml files into the [credentials] table of connection.toml",
+    },
+];
+
+/// Returned (inside `anyhow::Error`) when the data dir was written by a newer
+/// keepbook.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "data dir {} is at format version {found}, but this keepbook only supports up to {supported}; upgrade keepbook before writing to it",
+    data_dir.display()
+)]
+pub struct NewerFormatVersion {
+    pub data_dir: std::path::PathBuf,
+    pub found: u32,
+    pub supported: u32,
+}
+
+/// The version recorded in the data dir, if any.
+pub fn read_format_version(data_dir: &Path) -> Result<Option<u32>> {
+    let path = data_dir.join(FORMAT_VERSION_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => content
+            .trim()
+            .parse()
+            .map(Some)
+            .with_context(|| format!("Invalid format version in {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+pub fn write_format_version(data_dir: &Path, version: u32) -> Result<()> {
+    std::fs::create_dir_all(data_dir)
+        .with_context(|| format!("Failed to create data dir {}", data_dir.display()))?;
+    write_atomic_sync(
+        &data_dir.join(FORMAT_VERSION_FILE),
+        format!("{version}\n").as_bytes(),
+    )
+}
+
+/// The data dir's effective format version. Dirs from before versioning
+/// existed are version 0; a dir with no data yet is already current.
+pub fn format_version(data_dir: &Path) -> Result<u32> {
+    match read_format_version(data_dir)? {
+        Some(version) => Ok(version),
+        None if has_data(data_dir) => Ok(0),
+        None => Ok(CURRENT_FORMAT_VERSION),
+    }
+}
+
+fn has_data(data_dir: &Path) -> bool {
+    data_dir.join("connections").exists() || data_dir.join("accounts").exists()
+}
+
+/// Migrations that haven't run on a data dir at `version`.
+pub fn pending_migrations(version: u32) -> impl Iterator<Item = &'static Migration> {
+    MIGRATIONS
+        .iter()
+        .filter(move |migration| migration.version > version)
+}
+
+/// Check the data dir before a command writes to it.
+///
+/// Fails with [`NewerFormatVersion`] if a newer keepbook wrote it. A new data
+/// dir is stamped with the current version; an older one still works (reads
+/// handle every older format) but gets a warning to run `keepbook migrate`.
+pub fn prepare_for_write(data_dir: &Path) -> Result<()> {
+    match format_version(data_dir)? {
+        version if version > CURRENT_FORMAT_VERSION => Err(NewerFormatVersion {
+            data_dir: data_dir.to_path_buf(),
+            found: version,
+            supported: CURRENT_FORMAT_VERSION,
+        }
+        .into()),
+        version if version < CURRENT_FORMAT_VERSION => {
+            tracing::warn!(
+                "Data dir {} is at format version {version} (current: {CURRENT_FORMAT_VERSION}); run `keepbook migrate`",
+                data_dir.display()
+            );
+            Ok(())
+        }
+        _ => {
+            if read_format_version(data_dir)?.is_none() {
+                write_format_version(data_dir, CURRENT_FORMAT_VERSION)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// One migration applied by [`migrate`].
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Migration-specific counts of what changed.
+    pub changes: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub applied: Vec<AppliedMigration>,
+}
+
+/// Bring the data dir up to [`CURRENT_FORMAT_VERSION`], recording progress
+/// after each migration.
+pub async fn migrate(data_dir: &Path) -> Result<MigrationReport> {
+    let from_version = format_version(data_dir)?;
+    if from_version > CURRENT_FORMAT_VERSION {
+        return Err(NewerFormatVersion {
+            data_dir: data_dir.to_path_buf(),
+            found: from_version,
+            supported: CURRENT_FORMAT_VERSION,
+        }
+        .into());
+    }
+
+    let storage = JsonFileStorage::new(data_dir);
+    let mut applied = Vec::new();
+    for migration in pending_migrations(from_version) {
+        let changes = run_migration(migration, &storage, data_dir)
+            .await
+            .with_context(|| {
+                format!(
+                    "Migration {} ({}) failed",
+                    migration.version, migration.name
+                )
+            })?;
+        write_format_version(data_dir, migration.version)?;
+        applied.push(AppliedMigration {
+            version: migration.version,
+            name: migration.name,
+            description: migration.description,
+            changes,
+        });
+    }
+    if read_format_version(data_dir)?.is_none() {
+        write_format_version(data_dir, CURRENT_FORMAT_VERSION)?;
+    }
+
+    Ok(MigrationReport {
+        from_version,
+        to_version: CURRENT_FORMAT_VERSION,
+        applied,
+    })
+}
+
+async fn run_migration(
+    migration: &Migration,
+    storage: &JsonFileStorage,
+    data_dir: &Path,
+) -> Result<serde_json::Value> {
+    match migration.version {
+        1 => Ok(serde_json::to_value(
+            storage.backfill_transaction_metadata_all().await?,
+        )?),
+        2 => inline_connection_credentials(data_dir),
+        version => anyhow::bail!("No implementation for migration {version}"),
+    }
+}
+
+/// Append each legacy `credentials.toml` to its `connection.toml` as a
+/// `[credentials]` table, leaving the hand-written part of the file as is.
+fn inline_connection_credentials(data_dir: &Path) -> Result<serde_json::Value> {
+    let connections_dir = data_dir.join("connections");
+    let mut inlined = 0usize;
+    let mut skipped = Vec::new();
+
+    let entries = match std::fs::read_dir(&connections_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(serde_json::json!({
+                "connections_inlined": 0,
+                "connections_skipped": [],
+            }))
+        }
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", connections_dir.display()))
+        }
+    };
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let dir = entry.path();
+        let credentials_path = dir.join("credentials.toml");
+        let config_path = dir.join("connection.toml");
+        if !credentials_path.exists() || !config_path.exists() {
+            continue;
+        }
+
+        let credentials_text = std::fs::read_to_string(&credentials_path)
+            .with_context(|| format!("Failed to read {}", credentials_path.display()))?;
+        let credentials: toml::Table = toml::from_str(&credentials_text)
+            .with_context(|| format!("Failed to parse {}", credentials_path.display()))?;
+        let config_text = std::fs::read_to_string(&config_path)
+            .with_context(|| format!("Failed to read {}", config_path.display()))?;
+        let config: toml::Table = toml::from_str(&config_text)
+            .with_context(|| format!("Failed to parse {}", config_path.display()))?;
+
+        // Inline credentials already win over the legacy file, so it is dead.
+        // Leave it for the user to look at rather than guess.
+        if config.contains_key("credentials") {
+            skipped.push(entry.file_name().to_string_lossy().to_string());
+            continue;
+        }
+
+        let mut table = toml::Table::new();
+        table.insert("credentials".to_string(), toml::Value::Table(credentials));
+        let mut content = config_text;
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push('\n');
+        content.push_str(&toml::to_string(&table)?);
+        write_atomic_sync(&config_path, content.as_bytes())?;
+        std::fs::remove_file(&credentials_path)
+            .with_context(|| format!("Failed to remove {}", credentials_path.display()))?;
+        inlined += 1;
+    }
+
+    Ok(serde_json::json!({
+        "connections_inlined": inlined,
+        "connections_skipped": skipped,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrations_are_ordered_and_end_at_current() {
+        let versions: Vec<u32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        let expected: Vec<u32> = (1..=CURRENT_FORMAT_VERSION).collect();
+        assert_eq!(versions, expected);
+    }
+
+    #[test]
+    fn new_data_dir_is_current_and_gets_stamped() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert_eq!(format_version(dir.path())?, CURRENT_FORMAT_VERSION);
+
+        prepare_for_write(dir.path())?;
+        assert_eq!(
+            read_format_version(dir.path())?,
+            Some(CURRENT_FORMAT_VERSION)
+        );
+
+        std::fs::create_dir(dir.path().join("legacy"))?;
+        std::fs::create_dir(dir.path().join("legacy").join("accounts"))?;
+        assert_eq!(format_version(&dir.path().join("legacy"))?, 0);
+        Ok(())
+    }
+}
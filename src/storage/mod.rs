@@ -1,20 +1,82 @@
+mod as_of;
+mod atomic;
+mod composite;
+mod encryption;
 mod json_file;
+mod lock;
 pub mod lookup;
 mod memory;
-
+mod migrate;
+mod observable;
+mod search_index;
+mod transaction_index;
+
+pub use as_of::{sync_batch_times, written_at, AsOfStorage};
+pub use atomic::{write_atomic, write_atomic_sync};
+pub use composite::{CompositeStorage, PROFILE_ID_SEPARATOR};
+pub use encryption::{
+    decrypt_data_dir, encrypt_data_dir, encrypted_path, AgeIdentities, DataCipher,
+    EncryptionConfig, ENCRYPTED_EXTENSION, ENCRYPTION_CONFIG_FILE,
+};
 pub use json_file::JsonFileStorage;
+pub use lock::{DataDirBusy, DataDirLock, LockMode, DEFAULT_LOCK_TIMEOUT};
 pub use lookup::{find_account, find_connection};
 pub use memory::MemoryStorage;
+pub use migrate::{
+    format_version, migrate, pending_migrations, prepare_for_write, read_format_version,
+    write_format_version, AppliedMigration, Migration, MigrationReport, NewerFormatVersion,
+    CURRENT_FORMAT_VERSION, FORMAT_VERSION_FILE, MIGRATIONS,
+};
+pub use observable::{
+    classify_data_dir_path, storage_event_channel, ObservableStorage, StorageEvent,
+    StorageEventSender, STORAGE_EVENT_CAPACITY,
+};
+#[cfg(feature = "watch")]
+pub use observable::{watch_data_dir, DataDirWatcher};
+pub(crate) use search_index::search_words;
 
 use crate::credentials::CredentialStore;
 use crate::models::{
     Account, AccountConfig, BalanceSnapshot, Connection, ConnectionConfig, Id,
-    ProposedTransactionEdit, Transaction, TransactionAnnotationPatch,
+    ProposedTransactionEdit, SyncBatch, SyncRun, Transaction, TransactionAnnotationPatch,
 };
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use std::collections::{HashMap, HashSet};
 
+/// Order in which `Storage::stream_transactions` yields transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionOrder {
+    OldestFirst,
+    NewestFirst,
+}
+
+/// Transactions yielded one at a time by `Storage::stream_transactions`.
+pub type TransactionStream<'a> = futures::stream::BoxStream<'a, Result<Transaction>>;
+
+pub(crate) fn timestamp_in_range(
+    timestamp: DateTime<Utc>,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> bool {
+    start.is_none_or(|start| timestamp >= start) && end.is_none_or(|end| timestamp < end)
+}
+
+/// Keep the transactions with `start <= timestamp < end`, oldest first.
+pub(crate) fn transactions_in_range(
+    txns: Vec<Transaction>,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> Vec<Transaction> {
+    let mut txns: Vec<Transaction> = txns
+        .into_iter()
+        .filter(|txn| timestamp_in_range(txn.timestamp, start, end))
+        .collect();
+    txns.sort_by_key(|txn| txn.timestamp);
+    txns
+}
+
 /// Storage trait for persisting financial data.
 #[async_trait::async_trait]
 pub trait Storage: Send + Sync {
@@ -65,6 +127,54 @@ pub trait Storage: Send + Sync {
     async fn get_transactions_raw(&self, account_id: &Id) -> Result<Vec<Transaction>> {
         self.get_transactions(account_id).await
     }
+    /// Get deduplicated transactions with `start <= timestamp < end` (an
+    /// open bound is unbounded), oldest first.
+    ///
+    /// Deduplication runs over the whole history, so a transaction whose
+    /// latest version moved out of the range is not returned.
+    async fn get_transactions_in_range(
+        &self,
+        account_id: &Id,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Transaction>> {
+        Ok(transactions_in_range(
+            self.get_transactions(account_id).await?,
+            start,
+            end,
+        ))
+    }
+    /// Streaming variant of `get_transactions_in_range`, yielding in `order`.
+    /// Callers that only need the first few (e.g. the most recent N) can stop
+    /// early without the rest being loaded.
+    async fn stream_transactions(
+        &self,
+        account_id: &Id,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+        order: TransactionOrder,
+    ) -> Result<TransactionStream<'_>> {
+        let mut txns = self
+            .get_transactions_in_range(account_id, start, end)
+            .await?;
+        if order == TransactionOrder::NewestFirst {
+            txns.reverse();
+        }
+        Ok(Box::pin(futures::stream::iter(txns.into_iter().map(Ok))))
+    }
+    /// Deduplicated transactions that may contain every one of `prefixes`
+    /// (lowercased) at the start of a word of a version's description or
+    /// merchant metadata, or of an annotation patch's text or tags: a
+    /// superset of the matches, which callers check themselves. Without a
+    /// search index this is every transaction.
+    async fn find_transactions(
+        &self,
+        account_id: &Id,
+        prefixes: &[String],
+    ) -> Result<Vec<Transaction>> {
+        let _ = prefixes;
+        self.get_transactions(account_id).await
+    }
     async fn append_transactions(&self, account_id: &Id, txns: &[Transaction]) -> Result<()>;
 
     // Transaction annotations (append-only patches)
@@ -84,6 +194,14 @@ pub trait Storage: Send + Sync {
         &self,
         edits: &[ProposedTransactionEdit],
     ) -> Result<()>;
+
+    // Sync run journal (append-only, oldest first)
+    async fn get_sync_runs(&self, connection_id: &Id) -> Result<Vec<SyncRun>>;
+    async fn append_sync_run(&self, run: &SyncRun) -> Result<()>;
+
+    // Sync batch journal (append-only, oldest first; later entries supersede earlier ones)
+    async fn get_sync_batches(&self, connection_id: &Id) -> Result<Vec<SyncBatch>>;
+    async fn append_sync_batch(&self, batch: &SyncBatch) -> Result<()>;
 }
 
 #[derive(Debug, Clone, Copy, Default, Serialize)]
@@ -98,6 +216,13 @@ pub struct JsonlCompactionStats {
     pub annotation_patches_after: usize,
 }
 
+/// Rows dropped from an account's logs when a sync batch is reverted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SyncBatchRowsRemoved {
+    pub balance_snapshots: usize,
+    pub transactions: usize,
+}
+
 #[derive(Debug, Clone, Copy, Default, Serialize)]
 pub struct TransactionMetadataBackfillStats {
     pub accounts_processed: usize,
@@ -123,6 +248,14 @@ impl SymlinkStorage for JsonFileStorage {
 #[async_trait::async_trait]
 pub trait CompactionStorage: Send + Sync {
     async fn recompact_all_jsonl(&self) -> Result<JsonlCompactionStats>;
+
+    /// Rewrite an account's balance and transaction logs without the rows
+    /// stamped with `batch_id`.
+    async fn remove_sync_batch_rows(
+        &self,
+        account_id: &Id,
+        batch_id: &Id,
+    ) -> Result<SyncBatchRowsRemoved>;
 }
 
 #[async_trait::async_trait]
@@ -130,6 +263,127 @@ impl CompactionStorage for JsonFileStorage {
     async fn recompact_all_jsonl(&self) -> Result<JsonlCompactionStats> {
         JsonFileStorage::recompact_all_jsonl(self).await
     }
+
+    async fn remove_sync_batch_rows(
+        &self,
+        account_id: &Id,
+        batch_id: &Id,
+    ) -> Result<SyncBatchRowsRemoved> {
+        JsonFileStorage::remove_sync_batch_rows(self, account_id, batch_id).await
+    }
+}
+
+/// Rows moved by `AccountHistoryStorage::merge_account_history`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct AccountHistoryMerged {
+    pub balance_snapshots: usize,
+    pub transactions: usize,
+    pub annotation_patches: usize,
+}
+
+/// Moving one account's append-only logs into another's.
+#[async_trait::async_trait]
+pub trait AccountHistoryStorage: Send + Sync {
+    /// Move every balance snapshot, transaction version and annotation patch
+    /// of `from` into `into`, leaving `from`'s logs empty. The account
+    /// records themselves are untouched.
+    async fn merge_account_history(&self, from: &Id, into: &Id) -> Result<AccountHistoryMerged>;
+}
+
+#[async_trait::async_trait]
+impl AccountHistoryStorage for JsonFileStorage {
+    async fn merge_account_history(&self, from: &Id, into: &Id) -> Result<AccountHistoryMerged> {
+        JsonFileStorage::merge_account_history(self, from, into).await
+    }
+}
+
+/// An account's raw logs.
+#[derive(Debug, Default)]
+pub(crate) struct AccountLogs {
+    pub balances: Vec<BalanceSnapshot>,
+    pub transactions: Vec<Transaction>,
+    pub annotations: Vec<TransactionAnnotationPatch>,
+}
+
+/// Combine `from`'s logs into `into`'s.
+///
+/// `from`'s transaction versions go first, so where both accounts hold the
+/// same transaction (e.g. a provider re-reporting it under a new account id)
+/// `into`'s newer version keeps winning dedupe. `from`'s annotation patches
+/// are pointed at whichever transaction id wins, and go before `into`'s own.
+/// Balance snapshots are interleaved by timestamp, without `from`'s closing
+/// all-zero snapshot (see `close_account`) and without exact duplicates.
+pub(crate) fn merge_account_logs(from: AccountLogs, into: AccountLogs) -> AccountLogs {
+    let from_keys: HashMap<Id, Vec<String>> = from
+        .transactions
+        .iter()
+        .map(|txn| (txn.id.clone(), transaction_dedupe_keys(txn)))
+        .collect();
+
+    let mut transactions = from.transactions;
+    transactions.extend(into.transactions);
+
+    let mut winner_by_key: HashMap<String, Id> = HashMap::new();
+    for winner in dedupe_transactions_last_write_wins(transactions.clone()) {
+        for key in transaction_dedupe_keys(&winner) {
+            winner_by_key.insert(key, winner.id.clone());
+        }
+    }
+    let mut annotations: Vec<TransactionAnnotationPatch> = from
+        .annotations
+        .into_iter()
+        .map(|mut patch| {
+            let winner = from_keys
+                .get(&patch.transaction_id)
+                .and_then(|keys| keys.iter().find_map(|key| winner_by_key.get(key)));
+            if let Some(winner) = winner {
+                patch.transaction_id = winner.clone();
+            }
+            patch
+        })
+        .collect();
+    annotations.extend(into.annotations);
+
+    let mut balances = from.balances;
+    let latest = (0..balances.len()).max_by_key(|&i| balances[i].timestamp);
+    if let Some(latest) = latest.filter(|&i| is_closing_snapshot(&balances[i])) {
+        balances.remove(latest);
+    }
+    balances.extend(into.balances);
+    balances.sort_by_key(|snapshot| snapshot.timestamp);
+    let mut seen = HashSet::new();
+    balances.retain(|snapshot| {
+        let key: Vec<_> = snapshot
+            .balances
+            .iter()
+            .map(|balance| {
+                (
+                    balance.asset.clone(),
+                    balance.amount.clone(),
+                    balance.cost_basis.clone(),
+                )
+            })
+            .collect();
+        seen.insert((snapshot.timestamp, key))
+    });
+
+    AccountLogs {
+        balances,
+        transactions,
+        annotations,
+    }
+}
+
+/// A manual snapshot with every balance at zero, as closing an account writes.
+fn is_closing_snapshot(snapshot: &BalanceSnapshot) -> bool {
+    snapshot.sync_batch.is_none()
+        && snapshot.balances.iter().all(|balance| {
+            balance
+                .amount
+                .trim()
+                .parse::<rust_decimal::Decimal>()
+                .is_ok_and(|amount| amount.is_zero())
+        })
 }
 
 /// Filesystem-y maintenance operation for persisting canonical transaction metadata.
@@ -156,7 +410,7 @@ fn non_empty_sync_string(
         .map(ToOwned::to_owned)
 }
 
-fn transaction_dedupe_keys(txn: &Transaction) -> Vec<String> {
+pub(crate) fn transaction_dedupe_keys(txn: &Transaction) -> Vec<String> {
     let mut keys = vec![format!("id:{}", txn.id)];
 
     // Chase sometimes surfaces the same transaction under different stable id sources
@@ -184,12 +438,22 @@ fn transaction_dedupe_keys(txn: &Transaction) -> Vec<String> {
 }
 
 pub(crate) fn dedupe_transactions_last_write_wins(txns: Vec<Transaction>) -> Vec<Transaction> {
+    dedupe_last_write_wins_by(txns, transaction_dedupe_keys)
+}
+
+/// Last-write-wins dedupe over anything that can produce a transaction's
+/// dedupe keys (transactions themselves, or index entries standing in for
+/// them). Survivors keep the position of the first version of each.
+pub(crate) fn dedupe_last_write_wins_by<T>(
+    items: Vec<T>,
+    dedupe_keys: impl Fn(&T) -> Vec<String>,
+) -> Vec<T> {
     let mut key_to_index: HashMap<String, usize> = HashMap::new();
     let mut index_to_keys: HashMap<usize, HashSet<String>> = HashMap::new();
-    let mut deduped: Vec<Option<Transaction>> = Vec::new();
+    let mut deduped: Vec<Option<T>> = Vec::new();
 
-    for txn in txns {
-        let keys = transaction_dedupe_keys(&txn);
+    for txn in items {
+        let keys = dedupe_keys(&txn);
         let mut matched: HashSet<usize> = HashSet::new();
 
         for key in &keys {
@@ -280,6 +544,7 @@ mod tests {
             status: TransactionStatus::Posted,
             synchronizer_data: serde_json::Value::Object(obj),
             standardized_metadata: None,
+            sync_batch: None,
         }
         .backfill_standardized_metadata()
     }
@@ -299,4 +564,85 @@ mod tests {
         assert_eq!(out.len(), 1);
         assert_eq!(out[0].id.as_str(), "tx-new");
     }
+
+    #[test]
+    fn merged_annotations_follow_the_winning_alias() {
+        use super::{merge_account_logs, AccountLogs};
+        use crate::models::TransactionAnnotationPatch;
+
+        let old = chase_tx("tx-old", "202602151536556260124#20260124", None, None);
+        let new = chase_tx(
+            "tx-new",
+            "466046216565116",
+            Some("466046216565116"),
+            Some("202602151536556260124#20260124"),
+        );
+        let patch = TransactionAnnotationPatch {
+            transaction_id: Id::from_string("tx-old"),
+            timestamp: chrono::Utc.with_ymd_and_hms(2026, 2, 21, 0, 0, 0).unwrap(),
+            description: None,
+            note: Some(Some("split with Sam".to_string())),
+            category: None,
+            subcategory: None,
+            tags: None,
+            effective_date: None,
+        };
+
+        let merged = merge_account_logs(
+            AccountLogs {
+                transactions: vec![old],
+                annotations: vec![patch],
+                ..Default::default()
+            },
+            AccountLogs {
+                transactions: vec![new],
+                ..Default::default()
+            },
+        );
+        assert_eq!(merged.transactions.len(), 2);
+        assert_eq!(
+            dedupe_transactions_last_write_wins(merged.transactions)[0].id,
+            Id::from_string("tx-new")
+        );
+        assert_eq!(
+            merged.annotations[0].transaction_id,
+            Id::from_string("tx-new")
+        );
+    }
+
+    #[test]
+    fn merged_balances_drop_closing_snapshot_and_duplicates() {
+        use super::{merge_account_logs, AccountLogs};
+        use crate::models::{Asset, AssetBalance, BalanceSnapshot};
+
+        let at = |day| chrono::Utc.with_ymd_and_hms(2026, 1, day, 0, 0, 0).unwrap();
+        let usd = |amount: &str| vec![AssetBalance::new(Asset::currency("USD"), amount)];
+        let from = vec![
+            BalanceSnapshot::new(at(1), usd("40")),
+            BalanceSnapshot::new(at(5), usd("0")),
+        ];
+        // `into` already holds `from`'s first snapshot, as after a merge that
+        // crashed before removing `from`'s logs.
+        let into = vec![
+            BalanceSnapshot::new(at(1), usd("40")),
+            BalanceSnapshot::new(at(3), usd("100")),
+        ];
+
+        let merged = merge_account_logs(
+            AccountLogs {
+                balances: from,
+                ..Default::default()
+            },
+            AccountLogs {
+                balances: into,
+                ..Default::default()
+            },
+        );
+        let amounts: Vec<_> = merged
+            .balances
+            .iter()
+            .map(|snapshot| (snapshot.timestamp, snapshot.balances[0].amount.as_str()))
+            .collect();
+        assert_eq!(amounts, vec![(at(1), "40"), (at(3), "100")]);
+    }
 }
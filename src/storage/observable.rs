@@ -0,0 +1,613 @@
+//! Change feed for storage writes.
+//!
+//! [`ObservableStorage`] wraps any [`Storage`] and broadcasts a
+//! [`StorageEvent`] after every successful write made through it. Writes by
+//! other processes (the CLI while the daemon runs, a `git pull`, ...) don't go
+//! through the wrapper; with the `watch` feature, [`watch_data_dir`] turns
+//! filesystem notifications for the data dir into the same events.
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use super::{
+    AccountHistoryMerged, AccountHistoryStorage, CompactionStorage, JsonlCompactionStats, Storage,
+    SyncBatchRowsRemoved, TransactionOrder, TransactionStream,
+};
+use crate::credentials::CredentialStore;
+use crate::models::{
+    Account, AccountConfig, BalanceSnapshot, Connection, ConnectionConfig, Id,
+    ProposedTransactionEdit, SyncBatch, SyncRun, Transaction, TransactionAnnotationPatch,
+};
+
+/// Events buffered per subscriber before the slowest one starts lagging.
+pub const STORAGE_EVENT_CAPACITY: usize = 256;
+
+/// What changed in storage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StorageEvent {
+    ConnectionSaved {
+        connection_id: Id,
+    },
+    ConnectionDeleted {
+        connection_id: Id,
+    },
+    AccountSaved {
+        account_id: Id,
+    },
+    AccountDeleted {
+        account_id: Id,
+    },
+    BalanceAppended {
+        account_id: Id,
+    },
+    /// `transaction_ids` is empty when another process did the write.
+    TransactionsAppended {
+        account_id: Id,
+        transaction_ids: Vec<Id>,
+    },
+    /// `transaction_ids` is empty when another process did the write.
+    AnnotationsPatched {
+        account_id: Id,
+        transaction_ids: Vec<Id>,
+    },
+    /// An account's logs were rewritten rather than appended to (merge,
+    /// sync revert).
+    AccountHistoryRewritten {
+        account_id: Id,
+    },
+    ProposedEditsChanged,
+    SyncRecorded {
+        connection_id: Id,
+    },
+    /// A data-dir file outside the typed events (market data, category
+    /// rules, ...) changed. Only reported by the file watcher.
+    FileChanged {
+        path: PathBuf,
+    },
+}
+
+impl StorageEvent {
+    /// The account the event is about, if any.
+    pub fn account_id(&self) -> Option<&Id> {
+        match self {
+            StorageEvent::AccountSaved { account_id }
+            | StorageEvent::AccountDeleted { account_id }
+            | StorageEvent::BalanceAppended { account_id }
+            | StorageEvent::TransactionsAppended { account_id, .. }
+            | StorageEvent::AnnotationsPatched { account_id, .. }
+            | StorageEvent::AccountHistoryRewritten { account_id } => Some(account_id),
+            _ => None,
+        }
+    }
+}
+
+/// Sending half of a change feed; clone it to feed events from elsewhere
+/// (e.g. [`watch_data_dir`]).
+pub type StorageEventSender = broadcast::Sender<StorageEvent>;
+
+pub fn storage_event_channel() -> StorageEventSender {
+    broadcast::channel(STORAGE_EVENT_CAPACITY).0
+}
+
+/// A [`Storage`] that reports its writes.
+pub struct ObservableStorage<S> {
+    inner: S,
+    events: StorageEventSender,
+}
+
+impl<S> ObservableStorage<S> {
+    pub fn new(inner: S) -> Self {
+        Self::with_sender(inner, storage_event_channel())
+    }
+
+    /// Report into an existing feed, e.g. one a file watcher also feeds or
+    /// one that must outlive this storage.
+    pub fn with_sender(inner: S, events: StorageEventSender) -> Self {
+        Self { inner, events }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn sender(&self) -> StorageEventSender {
+        self.events.clone()
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn emit(&self, event: StorageEvent) {
+        // No subscribers is fine.
+        let _ = self.events.send(event);
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: Storage> Storage for ObservableStorage<S> {
+    fn get_credential_store(&self, connection_id: &Id) -> Result<Option<Box<dyn CredentialStore>>> {
+        self.inner.get_credential_store(connection_id)
+    }
+
+    fn get_account_config(&self, account_id: &Id) -> Result<Option<AccountConfig>> {
+        self.inner.get_account_config(account_id)
+    }
+
+    async fn list_connections(&self) -> Result<Vec<Connection>> {
+        self.inner.list_connections().await
+    }
+
+    async fn get_connection(&self, id: &Id) -> Result<Option<Connection>> {
+        self.inner.get_connection(id).await
+    }
+
+    async fn save_connection(&self, conn: &Connection) -> Result<()> {
+        self.inner.save_connection(conn).await?;
+        self.emit(StorageEvent::ConnectionSaved {
+            connection_id: conn.id().clone(),
+        });
+        Ok(())
+    }
+
+    async fn delete_connection(&self, id: &Id) -> Result<bool> {
+        let deleted = self.inner.delete_connection(id).await?;
+        if deleted {
+            self.emit(StorageEvent::ConnectionDeleted {
+                connection_id: id.clone(),
+            });
+        }
+        Ok(deleted)
+    }
+
+    async fn save_connection_config(&self, id: &Id, config: &ConnectionConfig) -> Result<()> {
+        self.inner.save_connection_config(id, config).await?;
+        self.emit(StorageEvent::ConnectionSaved {
+            connection_id: id.clone(),
+        });
+        Ok(())
+    }
+
+    async fn list_accounts(&self) -> Result<Vec<Account>> {
+        self.inner.list_accounts().await
+    }
+
+    async fn get_account(&self, id: &Id) -> Result<Option<Account>> {
+        self.inner.get_account(id).await
+    }
+
+    async fn save_account(&self, account: &Account) -> Result<()> {
+        self.inner.save_account(account).await?;
+        self.emit(StorageEvent::AccountSaved {
+            account_id: account.id.clone(),
+        });
+        Ok(())
+    }
+
+    async fn delete_account(&self, id: &Id) -> Result<bool> {
+        let deleted = self.inner.delete_account(id).await?;
+        if deleted {
+            self.emit(StorageEvent::AccountDeleted {
+                account_id: id.clone(),
+            });
+        }
+        Ok(deleted)
+    }
+
+    async fn save_account_config(&self, id: &Id, config: &AccountConfig) -> Result<()> {
+        self.inner.save_account_config(id, config).await?;
+        self.emit(StorageEvent::AccountSaved {
+            account_id: id.clone(),
+        });
+        Ok(())
+    }
+
+    async fn get_balance_snapshots(&self, account_id: &Id) -> Result<Vec<BalanceSnapshot>> {
+        self.inner.get_balance_snapshots(account_id).await
+    }
+
+    async fn append_balance_snapshot(
+        &self,
+        account_id: &Id,
+        snapshot: &BalanceSnapshot,
+    ) -> Result<()> {
+        self.inner
+            .append_balance_snapshot(account_id, snapshot)
+            .await?;
+        self.emit(StorageEvent::BalanceAppended {
+            account_id: account_id.clone(),
+        });
+        Ok(())
+    }
+
+    async fn get_latest_balance_snapshot(
+        &self,
+        account_id: &Id,
+    ) -> Result<Option<BalanceSnapshot>> {
+        self.inner.get_latest_balance_snapshot(account_id).await
+    }
+
+    async fn get_latest_balances(&self) -> Result<Vec<(Id, BalanceSnapshot)>> {
+        self.inner.get_latest_balances().await
+    }
+
+    async fn get_latest_balances_for_connection(
+        &self,
+        connection_id: &Id,
+    ) -> Result<Vec<(Id, BalanceSnapshot)>> {
+        self.inner
+            .get_latest_balances_for_connection(connection_id)
+            .await
+    }
+
+    async fn get_transactions(&self, account_id: &Id) -> Result<Vec<Transaction>> {
+        self.inner.get_transactions(account_id).await
+    }
+
+    async fn get_transactions_raw(&self, account_id: &Id) -> Result<Vec<Transaction>> {
+        self.inner.get_transactions_raw(account_id).await
+    }
+
+    async fn get_transactions_in_range(
+        &self,
+        account_id: &Id,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Transaction>> {
+        self.inner
+            .get_transactions_in_range(account_id, start, end)
+            .await
+    }
+
+    async fn stream_transactions(
+        &self,
+        account_id: &Id,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+        order: TransactionOrder,
+    ) -> Result<TransactionStream<'_>> {
+        self.inner
+            .stream_transactions(account_id, start, end, order)
+            .await
+    }
+
+    async fn find_transactions(
+        &self,
+        account_id: &Id,
+        prefixes: &[String],
+    ) -> Result<Vec<Transaction>> {
+        self.inner.find_transactions(account_id, prefixes).await
+    }
+
+    async fn append_transactions(&self, account_id: &Id, txns: &[Transaction]) -> Result<()> {
+        self.inner.append_transactions(account_id, txns).await?;
+        self.emit(StorageEvent::TransactionsAppended {
+            account_id: account_id.clone(),
+            transaction_ids: txns.iter().map(|txn| txn.id.clone()).collect(),
+        });
+        Ok(())
+    }
+
+    async fn get_transaction_annotation_patches(
+        &self,
+        account_id: &Id,
+    ) -> Result<Vec<TransactionAnnotationPatch>> {
+        self.inner
+            .get_transaction_annotation_patches(account_id)
+            .await
+    }
+
+    async fn append_transaction_annotation_patches(
+        &self,
+        account_id: &Id,
+        patches: &[TransactionAnnotationPatch],
+    ) -> Result<()> {
+        self.inner
+            .append_transaction_annotation_patches(account_id, patches)
+            .await?;
+        self.emit(StorageEvent::AnnotationsPatched {
+            account_id: account_id.clone(),
+            transaction_ids: patches
+                .iter()
+                .map(|patch| patch.transaction_id.clone())
+                .collect(),
+        });
+        Ok(())
+    }
+
+    async fn get_proposed_transaction_edits(&self) -> Result<Vec<ProposedTransactionEdit>> {
+        self.inner.get_proposed_transaction_edits().await
+    }
+
+    async fn append_proposed_transaction_edits(
+        &self,
+        edits: &[ProposedTransactionEdit],
+    ) -> Result<()> {
+        self.inner.append_proposed_transaction_edits(edits).await?;
+        self.emit(StorageEvent::ProposedEditsChanged);
+        Ok(())
+    }
+
+    async fn get_sync_runs(&self, connection_id: &Id) -> Result<Vec<SyncRun>> {
+        self.inner.get_sync_runs(connection_id).await
+    }
+
+    async fn append_sync_run(&self, run: &SyncRun) -> Result<()> {
+        self.inner.append_sync_run(run).await?;
+        self.emit(StorageEvent::SyncRecorded {
+            connection_id: run.connection_id.clone(),
+        });
+        Ok(())
+    }
+
+    async fn get_sync_batches(&self, connection_id: &Id) -> Result<Vec<SyncBatch>> {
+        self.inner.get_sync_batches(connection_id).await
+    }
+
+    async fn append_sync_batch(&self, batch: &SyncBatch) -> Result<()> {
+        self.inner.append_sync_batch(batch).await?;
+        self.emit(StorageEvent::SyncRecorded {
+            connection_id: batch.connection_id.clone(),
+        });
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: CompactionStorage> CompactionStorage for ObservableStorage<S> {
+    /// Compaction doesn't change what reads return, so it isn't reported.
+    async fn recompact_all_jsonl(&self) -> Result<JsonlCompactionStats> {
+        self.inner.recompact_all_jsonl().await
+    }
+
+    async fn remove_sync_batch_rows(
+        &self,
+        account_id: &Id,
+        batch_id: &Id,
+    ) -> Result<SyncBatchRowsRemoved> {
+        let removed = self
+            .inner
+            .remove_sync_batch_rows(account_id, batch_id)
+            .await?;
+        if removed != SyncBatchRowsRemoved::default() {
+            self.emit(StorageEvent::AccountHistoryRewritten {
+                account_id: account_id.clone(),
+            });
+        }
+        Ok(removed)
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: AccountHistoryStorage> AccountHistoryStorage for ObservableStorage<S> {
+    async fn merge_account_history(&self, from: &Id, into: &Id) -> Result<AccountHistoryMerged> {
+        let merged = self.inner.merge_account_history(from, into).await?;
+        for account_id in [from, into] {
+            self.emit(StorageEvent::AccountHistoryRewritten {
+                account_id: account_id.clone(),
+            });
+        }
+        Ok(merged)
+    }
+}
+
+/// Map a changed path under `data_dir` to the event it implies. Dot files and
+/// dirs (`.git`, the lock file, the index cache, temp files of atomic writes)
+/// and symlink farms are skipped. `removed` is whether the path is gone.
+pub fn classify_data_dir_path(data_dir: &Path, path: &Path, removed: bool) -> Option<StorageEvent> {
+    let relative = path.strip_prefix(data_dir).ok()?;
+    let mut parts: Vec<&str> = relative
+        .components()
+        .map(|component| match component {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    if parts.is_empty() || parts.iter().any(|part| part.starts_with('.')) {
+        return None;
+    }
+    // Encrypted data dirs store `<name>.age`; classify by the plaintext name.
+    if let Some(last) = parts.last_mut() {
+        if let Some(name) = last.strip_suffix(".age").filter(|name| name.contains('.')) {
+            *last = name;
+        }
+    }
+
+    let id = |value: &str| Id::from_string_checked(value).ok();
+    let event = match parts.as_slice() {
+        ["connections", "by-name", ..] | ["connections", _, "accounts", ..] => return None,
+        ["connections", connection_id] if removed => StorageEvent::ConnectionDeleted {
+            connection_id: id(connection_id)?,
+        },
+        ["connections", connection_id, "connection.json" | "connection.toml"] => {
+            StorageEvent::ConnectionSaved {
+                connection_id: id(connection_id)?,
+            }
+        }
+        ["connections", connection_id, "sync_runs.jsonl" | "sync_batches.jsonl"] => {
+            StorageEvent::SyncRecorded {
+                connection_id: id(connection_id)?,
+            }
+        }
+        ["accounts", account_id] if removed => StorageEvent::AccountDeleted {
+            account_id: id(account_id)?,
+        },
+        ["accounts", account_id, "account.json" | "account_config.toml"] => {
+            StorageEvent::AccountSaved {
+                account_id: id(account_id)?,
+            }
+        }
+        ["accounts", account_id, "balances.jsonl"] => StorageEvent::BalanceAppended {
+            account_id: id(account_id)?,
+        },
+        ["accounts", account_id, "transactions.jsonl"] => StorageEvent::TransactionsAppended {
+            account_id: id(account_id)?,
+            transaction_ids: Vec::new(),
+        },
+        ["accounts", account_id, "transaction_annotations.jsonl"] => {
+            StorageEvent::AnnotationsPatched {
+                account_id: id(account_id)?,
+                transaction_ids: Vec::new(),
+            }
+        }
+        ["proposed_transaction_edits.jsonl"] => StorageEvent::ProposedEditsChanged,
+        // Directory creation is followed by events for the files in it.
+        ["connections", _] | ["accounts", _] | ["connections"] | ["accounts"] => return None,
+        _ => StorageEvent::FileChanged {
+            path: relative.to_path_buf(),
+        },
+    };
+    Some(event)
+}
+
+/// `Some(removed)` for notifications that can mean data changed, `None` for
+/// ones that can't (access, metadata-only "other" events).
+#[cfg(feature = "watch")]
+fn fs_event_change(kind: &notify::EventKind) -> Option<bool> {
+    use notify::EventKind;
+
+    match kind {
+        EventKind::Remove(_) => Some(true),
+        EventKind::Any | EventKind::Create(_) | EventKind::Modify(_) => Some(false),
+        EventKind::Access(_) | EventKind::Other => None,
+    }
+}
+
+/// Keeps a [`watch_data_dir`] feed running until dropped.
+#[cfg(feature = "watch")]
+pub struct DataDirWatcher {
+    _watcher: notify::RecommendedWatcher,
+}
+
+/// Report writes to `data_dir` by other processes into `events`.
+#[cfg(feature = "watch")]
+pub fn watch_data_dir(data_dir: &Path, events: StorageEventSender) -> Result<DataDirWatcher> {
+    use anyhow::Context;
+    use notify::{RecursiveMode, Watcher};
+
+    let root = data_dir.to_path_buf();
+    let mut watcher =
+        notify::recommended_watcher(move |result: notify::Result<notify::Event>| match result {
+            Ok(event) => {
+                let Some(removed) = fs_event_change(&event.kind) else {
+                    return;
+                };
+                for path in &event.paths {
+                    if let Some(event) = classify_data_dir_path(&root, path, removed) {
+                        let _ = events.send(event);
+                    }
+                }
+            }
+            Err(err) => {
+                tracing::warn!(error = %err, "data directory watch event failed");
+            }
+        })
+        .context("Failed to create data directory watcher")?;
+    watcher
+        .watch(data_dir, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", data_dir.display()))?;
+    Ok(DataDirWatcher { _watcher: watcher })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Asset;
+    use crate::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn writes_are_broadcast_with_ids() -> Result<()> {
+        let storage = ObservableStorage::new(MemoryStorage::new());
+        let mut events = storage.subscribe();
+        let account_id = Id::from_string("acct-1");
+        let txn = Transaction::new("-3", Asset::currency("USD"), "Coffee")
+            .with_id(Id::from_string("tx-1"));
+
+        storage.append_transactions(&account_id, &[txn]).await?;
+        storage
+            .append_balance_snapshot(&account_id, &BalanceSnapshot::now(Vec::new()))
+            .await?;
+        // Reads don't emit anything.
+        storage.get_transactions(&account_id).await?;
+
+        assert_eq!(
+            events.try_recv()?,
+            StorageEvent::TransactionsAppended {
+                account_id: account_id.clone(),
+                transaction_ids: vec![Id::from_string("tx-1")],
+            }
+        );
+        assert_eq!(
+            events.try_recv()?,
+            StorageEvent::BalanceAppended { account_id }
+        );
+        assert!(events.try_recv().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn classifies_data_dir_paths() {
+        let root = Path::new("/data");
+        let classify =
+            |path: &str, removed| classify_data_dir_path(root, &root.join(path), removed);
+
+        assert_eq!(
+            classify("accounts/acct-1/transactions.jsonl", false),
+            Some(StorageEvent::TransactionsAppended {
+                account_id: Id::from_string("acct-1"),
+                transaction_ids: Vec::new(),
+            })
+        );
+        assert_eq!(
+            classify("accounts/acct-1/balances.jsonl.age", false),
+            Some(StorageEvent::BalanceAppended {
+                account_id: Id::from_string("acct-1"),
+            })
+        );
+        assert_eq!(
+            classify("connections/conn-1", true),
+            Some(StorageEvent::ConnectionDeleted {
+                connection_id: Id::from_string("conn-1"),
+            })
+        );
+        assert_eq!(
+            classify("prices/equity/AAPL/2026.jsonl", false),
+            Some(StorageEvent::FileChanged {
+                path: PathBuf::from("prices/equity/AAPL/2026.jsonl"),
+            })
+        );
+        assert_eq!(classify(".git/index", false), None);
+        assert_eq!(
+            classify("accounts/acct-1/.balances.jsonl.1.2.tmp", false),
+            None
+        );
+        assert_eq!(classify("connections/by-name/Bank", false), None);
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn fs_event_filter_keeps_mutations_only() {
+        use notify::event::{AccessKind, CreateKind, ModifyKind, RemoveKind};
+        use notify::EventKind;
+
+        assert_eq!(fs_event_change(&EventKind::Any), Some(false));
+        assert_eq!(
+            fs_event_change(&EventKind::Create(CreateKind::Any)),
+            Some(false)
+        );
+        assert_eq!(
+            fs_event_change(&EventKind::Modify(ModifyKind::Any)),
+            Some(false)
+        );
+        assert_eq!(
+            fs_event_change(&EventKind::Remove(RemoveKind::Any)),
+            Some(true)
+        );
+        assert_eq!(fs_event_change(&EventKind::Access(AccessKind::Any)), None);
+    }
+}
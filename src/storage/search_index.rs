@@ -0,0 +1,336 @@
+//! Sidecar inverted index over an account's transaction and annotation logs.
+//!
+//! Maps every word of a transaction's descriptions, merchant metadata, notes,
+//! categories and tags, from all of its versions and annotation patches, to
+//! the ids of the transactions it appears in. A lookup gives a superset of
+//! the matches, which callers check against the real data. Like the
+//! transaction index it is only a cache: it grows as the logs are appended
+//! to and is rebuilt when either log no longer starts with what was indexed.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::ops::Bound;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::atomic::write_atomic_sync;
+use super::transaction_index::{hash, read_at};
+use crate::models::{Transaction, TransactionAnnotationPatch};
+
+const INDEX_VERSION: u32 = 1;
+
+/// The lowercased alphanumeric words of `text`: what the index stores and
+/// what search terms are matched against.
+pub(crate) fn search_words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Offset, length and hash of an indexed line.
+type LineMark = (u64, u64, u64);
+
+/// How much of one log the index covers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LogCursor {
+    len: u64,
+    /// The first and last indexed lines, to notice rewrites.
+    first: Option<LineMark>,
+    last: Option<LineMark>,
+}
+
+impl LogCursor {
+    fn is_prefix_of(&self, log: Option<&mut (File, u64)>) -> Result<bool> {
+        let Some((log, log_len)) = log else {
+            return Ok(self.len == 0);
+        };
+        if self.len > *log_len {
+            return Ok(false);
+        }
+        for (offset, len, line_hash) in self.first.iter().chain(&self.last) {
+            if hash(&read_at(log, *offset, *len)?) != *line_hash {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Parse the lines appended since the cursor, moving it to the end.
+    fn read_new<T: DeserializeOwned>(
+        &mut self,
+        log: Option<&mut (File, u64)>,
+        log_path: &Path,
+    ) -> Result<Vec<T>> {
+        let Some((log, log_len)) = log else {
+            return Ok(Vec::new());
+        };
+        if self.len >= *log_len {
+            return Ok(Vec::new());
+        }
+        let start = self.len;
+        let buf = read_at(log, start, *log_len - start)
+            .with_context(|| format!("Failed to read {}", log_path.display()))?;
+
+        let mut rows = Vec::new();
+        let mut pos = 0usize;
+        while pos < buf.len() {
+            let end = buf[pos..]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(buf.len(), |newline| pos + newline);
+            let line = &buf[pos..end];
+            let offset = start + pos as u64;
+            pos = end + 1;
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+
+            rows.push(serde_json::from_slice(line).with_context(|| {
+                format!(
+                    "Failed to parse JSONL line at byte {offset} of {} (run `keepbook doctor`)",
+                    log_path.display()
+                )
+            })?);
+            let mark = (offset, line.len() as u64, hash(line));
+            self.first.get_or_insert(mark);
+            self.last = Some(mark);
+        }
+        self.len = *log_len;
+        Ok(rows)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct SearchIndex {
+    version: u32,
+    transactions: LogCursor,
+    annotations: LogCursor,
+    /// Transaction ids, referred to by position in `words`.
+    ids: Vec<String>,
+    /// Each word and the sorted positions in `ids` of the transactions it
+    /// appears in.
+    words: BTreeMap<String, Vec<u32>>,
+    #[serde(skip)]
+    positions: HashMap<String, u32>,
+}
+
+impl SearchIndex {
+    fn empty() -> Self {
+        Self {
+            version: INDEX_VERSION,
+            ..Self::default()
+        }
+    }
+
+    /// Load the index for an account's logs, bringing it up to date with
+    /// them. Failing to save the updated index is not an error; it is just
+    /// rebuilt next time.
+    pub(crate) fn load(
+        index_path: &Path,
+        transactions_path: &Path,
+        annotations_path: &Path,
+    ) -> Result<Self> {
+        let mut transactions_log = open_log(transactions_path)?;
+        let mut annotations_log = open_log(annotations_path)?;
+
+        let saved = std::fs::read(index_path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Self>(&bytes).ok())
+            .filter(|index| index.version == INDEX_VERSION);
+        let mut index = match saved {
+            Some(index)
+                if index.transactions.is_prefix_of(transactions_log.as_mut())?
+                    && index.annotations.is_prefix_of(annotations_log.as_mut())? =>
+            {
+                index
+            }
+            _ => Self::empty(),
+        };
+        index.positions = index
+            .ids
+            .iter()
+            .enumerate()
+            .map(|(position, id)| (id.clone(), position as u32))
+            .collect();
+
+        let (indexed_transactions, indexed_annotations) =
+            (index.transactions.len, index.annotations.len);
+        let txns: Vec<Transaction> = index
+            .transactions
+            .read_new(transactions_log.as_mut(), transactions_path)?;
+        for txn in txns {
+            let txn = txn.backfill_standardized_metadata();
+            let metadata = txn.standardized_metadata.as_ref();
+            let texts = [
+                Some(txn.description.as_str()),
+                metadata.and_then(|md| md.merchant_name.as_deref()),
+                metadata.and_then(|md| md.merchant_category_label.as_deref()),
+            ];
+            index.add(txn.id.as_str(), texts.into_iter().flatten());
+        }
+        let patches: Vec<TransactionAnnotationPatch> = index
+            .annotations
+            .read_new(annotations_log.as_mut(), annotations_path)?;
+        for patch in patches {
+            let mut texts: Vec<&str> = [
+                &patch.description,
+                &patch.note,
+                &patch.category,
+                &patch.subcategory,
+            ]
+            .into_iter()
+            .filter_map(|field| field.as_ref().and_then(|value| value.as_deref()))
+            .collect();
+            if let Some(Some(tags)) = &patch.tags {
+                texts.extend(tags.iter().map(String::as_str));
+            }
+            index.add(patch.transaction_id.as_str(), texts);
+        }
+
+        if index.transactions.len != indexed_transactions
+            || index.annotations.len != indexed_annotations
+        {
+            if let Err(error) = index.save(index_path) {
+                tracing::debug!(
+                    "Failed to save search index {}: {error:#}",
+                    index_path.display()
+                );
+            }
+        }
+        Ok(index)
+    }
+
+    fn add<'a>(&mut self, id: &str, texts: impl IntoIterator<Item = &'a str>) {
+        let position = match self.positions.get(id) {
+            Some(position) => *position,
+            None => {
+                let position = self.ids.len() as u32;
+                self.ids.push(id.to_string());
+                self.positions.insert(id.to_string(), position);
+                position
+            }
+        };
+        for text in texts {
+            for word in search_words(text) {
+                let postings = self.words.entry(word).or_default();
+                if let Err(at) = postings.binary_search(&position) {
+                    postings.insert(at, position);
+                }
+            }
+        }
+    }
+
+    fn save(&self, index_path: &Path) -> Result<()> {
+        if let Some(parent) = index_path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        write_atomic_sync(index_path, &serde_json::to_vec(self)?)
+    }
+
+    /// Ids of the transactions that have, for every one of `prefixes`, a
+    /// word starting with it.
+    pub(crate) fn candidates(&self, prefixes: &[String]) -> HashSet<String> {
+        let mut found: Option<HashSet<u32>> = None;
+        for prefix in prefixes {
+            let with_prefix: HashSet<u32> = self
+                .words
+                .range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded))
+                .take_while(|(word, _)| word.starts_with(prefix.as_str()))
+                .flat_map(|(_, postings)| postings.iter().copied())
+                .collect();
+            found = Some(match found {
+                Some(found) => found.intersection(&with_prefix).copied().collect(),
+                None => with_prefix,
+            });
+        }
+        match found {
+            Some(found) => found
+                .into_iter()
+                .map(|position| self.ids[position as usize].clone())
+                .collect(),
+            None => self.ids.iter().cloned().collect(),
+        }
+    }
+}
+
+fn open_log(path: &Path) -> Result<Option<(File, u64)>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to open {}", path.display())),
+    };
+    let len = file
+        .metadata()
+        .with_context(|| format!("Failed to stat {}", path.display()))?
+        .len();
+    Ok(Some((file, len)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Asset, Id};
+
+    fn txn(id: &str, description: &str) -> String {
+        let txn = Transaction::new("-1", Asset::currency("USD"), description)
+            .with_id(Id::from_string(id));
+        serde_json::to_string(&txn).unwrap() + "\n"
+    }
+
+    fn tag(id: &str, tags: &[&str]) -> String {
+        let patch = TransactionAnnotationPatch {
+            transaction_id: Id::from_string(id),
+            timestamp: chrono::Utc::now(),
+            description: None,
+            note: None,
+            category: None,
+            subcategory: None,
+            tags: Some(Some(tags.iter().map(|tag| tag.to_string()).collect())),
+            effective_date: None,
+        };
+        serde_json::to_string(&patch).unwrap() + "\n"
+    }
+
+    fn ids(index: &SearchIndex, prefixes: &[&str]) -> Vec<String> {
+        let prefixes: Vec<String> = prefixes.iter().map(|p| p.to_string()).collect();
+        let mut ids: Vec<String> = index.candidates(&prefixes).into_iter().collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn indexes_both_logs_incrementally() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let transactions = dir.path().join("transactions.jsonl");
+        let annotations = dir.path().join("transaction_annotations.jsonl");
+        let index_path = dir.path().join("search.json");
+
+        std::fs::write(
+            &transactions,
+            txn("a", "STARBUCKS #123") + &txn("b", "Whole Foods"),
+        )?;
+        let index = SearchIndex::load(&index_path, &transactions, &annotations)?;
+        assert_eq!(ids(&index, &["star"]), vec!["a"]);
+        assert!(ids(&index, &["travel"]).is_empty());
+        assert!(index_path.exists());
+
+        std::fs::write(&annotations, tag("a", &["travel"]))?;
+        let mut content = std::fs::read_to_string(&transactions)?;
+        content.push_str(&txn("c", "Starbucks Reserve"));
+        std::fs::write(&transactions, &content)?;
+        let index = SearchIndex::load(&index_path, &transactions, &annotations)?;
+        assert_eq!(ids(&index, &["starbucks"]), vec!["a", "c"]);
+        assert_eq!(ids(&index, &["starbucks", "travel"]), vec!["a"]);
+
+        // Rewriting a log drops what was indexed from it.
+        std::fs::write(&transactions, txn("d", "Blue Bottle"))?;
+        let index = SearchIndex::load(&index_path, &transactions, &annotations)?;
+        assert!(ids(&index, &["whole"]).is_empty());
+        assert_eq!(ids(&index, &["blue"]), vec!["d"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,285 @@
+//! Sidecar index over an account's `transactions.jsonl`.
+//!
+//! For every line the index records its byte range, timestamp and dedupe
+//! keys, so a date-range query can dedupe and filter the history without
+//! parsing it, then read just the lines it needs. The index is only a cache:
+//! it lives outside the tracked data, grows incrementally as the log is
+//! appended to, and is rebuilt from scratch whenever the log no longer starts
+//! with what was indexed (recompaction, a rewrite by `doctor --fix`, ...).
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::atomic::write_atomic_sync;
+use super::{dedupe_last_write_wins_by, timestamp_in_range, transaction_dedupe_keys};
+use crate::models::Transaction;
+
+const INDEX_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    #[serde(rename = "o")]
+    offset: u64,
+    #[serde(rename = "n")]
+    len: u64,
+    #[serde(rename = "t")]
+    timestamp: DateTime<Utc>,
+    #[serde(rename = "k")]
+    keys: Vec<String>,
+}
+
+/// Where one transaction version lives in the log.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LineRef {
+    offset: u64,
+    len: u64,
+    pub(crate) timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct TransactionIndex {
+    version: u32,
+    /// Bytes of the log covered by `entries`.
+    indexed_len: u64,
+    /// Hashes of the first and last indexed lines, to notice rewrites.
+    first_hash: u64,
+    last_hash: u64,
+    entries: Vec<IndexEntry>,
+}
+
+impl TransactionIndex {
+    fn empty() -> Self {
+        Self {
+            version: INDEX_VERSION,
+            ..Self::default()
+        }
+    }
+
+    /// Load the index for `log_path`, bringing it up to date with the log.
+    /// Failing to save the updated index is not an error; it is just rebuilt
+    /// next time.
+    pub(crate) fn load(index_path: &Path, log_path: &Path) -> Result<Self> {
+        let mut log = match File::open(log_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::empty()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to open {}", log_path.display()))
+            }
+        };
+        let log_len = log
+            .metadata()
+            .with_context(|| format!("Failed to stat {}", log_path.display()))?
+            .len();
+
+        let saved = std::fs::read(index_path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Self>(&bytes).ok())
+            .filter(|index| index.version == INDEX_VERSION);
+        let mut index = match saved {
+            Some(index) if index.is_prefix_of(&mut log, log_len)? => index,
+            _ => Self::empty(),
+        };
+
+        if index.indexed_len < log_len {
+            index.extend(&mut log, log_len, log_path)?;
+            if let Err(error) = index.save(index_path) {
+                tracing::debug!(
+                    "Failed to save transaction index {}: {error:#}",
+                    index_path.display()
+                );
+            }
+        }
+        Ok(index)
+    }
+
+    /// Whether the log still starts with the indexed bytes.
+    fn is_prefix_of(&self, log: &mut File, log_len: u64) -> Result<bool> {
+        if self.indexed_len > log_len {
+            return Ok(false);
+        }
+        let (Some(first), Some(last)) = (self.entries.first(), self.entries.last()) else {
+            return Ok(self.indexed_len == 0);
+        };
+        Ok(
+            hash(&read_at(log, first.offset, first.len)?) == self.first_hash
+                && hash(&read_at(log, last.offset, last.len)?) == self.last_hash,
+        )
+    }
+
+    /// Index the log from `indexed_len` to `log_len`.
+    fn extend(&mut self, log: &mut File, log_len: u64, log_path: &Path) -> Result<()> {
+        let start = self.indexed_len;
+        let buf = read_at(log, start, log_len - start)
+            .with_context(|| format!("Failed to read {}", log_path.display()))?;
+
+        let mut pos = 0usize;
+        while pos < buf.len() {
+            let end = buf[pos..]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(buf.len(), |newline| pos + newline);
+            let line = &buf[pos..end];
+            let offset = start + pos as u64;
+            pos = end + 1;
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+
+            let txn: Transaction = serde_json::from_slice(line).with_context(|| {
+                format!(
+                    "Failed to parse JSONL line at byte {offset} of {} (run `keepbook doctor`)",
+                    log_path.display()
+                )
+            })?;
+            if self.entries.is_empty() {
+                self.first_hash = hash(line);
+            }
+            self.last_hash = hash(line);
+            self.entries.push(IndexEntry {
+                offset,
+                len: line.len() as u64,
+                timestamp: txn.timestamp,
+                keys: transaction_dedupe_keys(&txn),
+            });
+        }
+        self.indexed_len = log_len;
+        Ok(())
+    }
+
+    fn save(&self, index_path: &Path) -> Result<()> {
+        if let Some(parent) = index_path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        write_atomic_sync(index_path, &serde_json::to_vec(self)?)
+    }
+
+    /// The latest version of each transaction, if its timestamp is in
+    /// `[start, end)`, oldest first.
+    pub(crate) fn in_range(
+        &self,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Vec<LineRef> {
+        self.latest(|entry| timestamp_in_range(entry.timestamp, start, end))
+    }
+
+    /// The latest version of each transaction whose id is in `ids`, oldest
+    /// first.
+    pub(crate) fn latest_with_ids(&self, ids: &HashSet<String>) -> Vec<LineRef> {
+        self.latest(|entry| {
+            entry
+                .keys
+                .iter()
+                .filter_map(|key| key.strip_prefix("id:"))
+                .any(|id| ids.contains(id))
+        })
+    }
+
+    fn latest(&self, keep: impl Fn(&IndexEntry) -> bool) -> Vec<LineRef> {
+        let mut refs: Vec<LineRef> =
+            dedupe_last_write_wins_by(self.entries.iter().collect(), |entry| entry.keys.clone())
+                .into_iter()
+                .filter(|entry| keep(entry))
+                .map(|entry| LineRef {
+                    offset: entry.offset,
+                    len: entry.len,
+                    timestamp: entry.timestamp,
+                })
+                .collect();
+        refs.sort_by_key(|line| line.timestamp);
+        refs
+    }
+}
+
+/// Read and parse the referenced lines, in the order given.
+pub(crate) fn read_lines(log_path: &Path, refs: &[LineRef]) -> Result<Vec<Transaction>> {
+    if refs.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut log =
+        File::open(log_path).with_context(|| format!("Failed to open {}", log_path.display()))?;
+
+    // Read front to back, then put the results back in the requested order.
+    let mut by_offset: Vec<usize> = (0..refs.len()).collect();
+    by_offset.sort_by_key(|&i| refs[i].offset);
+    let mut txns: Vec<Option<Transaction>> = vec![None; refs.len()];
+    for i in by_offset {
+        let line = read_at(&mut log, refs[i].offset, refs[i].len)?;
+        let txn: Transaction = serde_json::from_slice(&line).with_context(|| {
+            format!(
+                "Failed to parse JSONL line at byte {} of {} (run `keepbook doctor`)",
+                refs[i].offset,
+                log_path.display()
+            )
+        })?;
+        txns[i] = Some(txn.backfill_standardized_metadata());
+    }
+    Ok(txns.into_iter().flatten().collect())
+}
+
+pub(super) fn read_at(file: &mut File, offset: u64, len: u64) -> Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buf = Vec::with_capacity(len as usize);
+    file.take(len).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// FNV-1a: stable across builds, unlike `DefaultHasher`.
+pub(super) fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Asset, Id};
+    use chrono::TimeZone;
+
+    fn txn(id: &str, day: u32, description: &str) -> String {
+        let txn = Transaction::new("-1", Asset::currency("USD"), description)
+            .with_id(Id::from_string(id))
+            .with_timestamp(Utc.with_ymd_and_hms(2024, 1, day, 12, 0, 0).unwrap());
+        serde_json::to_string(&txn).unwrap() + "\n"
+    }
+
+    #[test]
+    fn extends_incrementally_and_rebuilds_after_rewrite() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log = dir.path().join("transactions.jsonl");
+        let index_path = dir.path().join("index.json");
+        let jan = |day| Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap();
+
+        std::fs::write(&log, txn("a", 1, "first") + &txn("b", 5, "second"))?;
+        let index = TransactionIndex::load(&index_path, &log)?;
+        assert_eq!(index.entries.len(), 2);
+        assert!(index_path.exists());
+
+        // A later version of `a` moves it out of the queried range.
+        let mut content = std::fs::read_to_string(&log)?;
+        content.push_str(&txn("a", 20, "moved"));
+        std::fs::write(&log, &content)?;
+        let index = TransactionIndex::load(&index_path, &log)?;
+        assert_eq!(index.entries.len(), 3);
+        let refs = index.in_range(Some(jan(1)), Some(jan(10)));
+        let found = read_lines(&log, &refs)?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].description, "second");
+
+        // A rewrite that isn't an append invalidates the saved index.
+        std::fs::write(&log, txn("c", 2, "rewritten"))?;
+        let index = TransactionIndex::load(&index_path, &log)?;
+        let found = read_lines(&log, &index.in_range(None, None))?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].description, "rewritten");
+        Ok(())
+    }
+}
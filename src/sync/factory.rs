@@ -7,7 +7,8 @@ use crate::storage::Storage;
 
 use super::Synchronizer;
 use crate::sync::synchronizers::{
-    ChaseSynchronizer, CoinbaseSynchronizer, PlaidSynchronizer, SchwabSynchronizer,
+    ChaseSynchronizer, CoinbaseSynchronizer, ExecSynchronizer, PlaidSynchronizer,
+    SchwabSynchronizer, SimpleFinSynchronizer, EXEC_PREFIX,
 };
 
 #[async_trait::async_trait]
@@ -49,6 +50,13 @@ impl SynchronizerFactory for DefaultSynchronizerFactory {
             ));
         }
 
+        if connection.config.synchronizer.starts_with(EXEC_PREFIX) {
+            return Ok(Box::new(
+                ExecSynchronizer::from_connection(connection, storage, self.data_dir.as_deref())
+                    .await?,
+            ));
+        }
+
         match connection.config.synchronizer.as_str() {
             "chase" => {
                 // Chase uses ephemeral cache directories for browser profiles/downloads.
@@ -67,6 +75,9 @@ impl SynchronizerFactory for DefaultSynchronizerFactory {
             "plaid" => Ok(Box::new(
                 PlaidSynchronizer::from_connection(connection, storage).await?,
             )),
+            "simplefin" => Ok(Box::new(
+                SimpleFinSynchronizer::from_connection(connection, storage).await?,
+            )),
             other => Err(anyhow!("Unknown synchronizer: {other}")),
         }
     }
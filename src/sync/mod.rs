@@ -1,22 +1,33 @@
 pub mod chase;
 mod factory;
 mod orchestrator;
+pub mod plugin;
+mod preview;
 mod prices;
+mod revert;
 pub mod schwab;
 mod service;
 pub mod synchronizers;
 
 pub use factory::{create_synchronizer, DefaultSynchronizerFactory, SynchronizerFactory};
 pub use orchestrator::{PriceRefreshResult, SyncOrchestrator, SyncWithPricesResult};
+pub use preview::{
+    AccountChange, BalanceChange, BalancePreview, EntityRef, FieldChange, SyncPreview,
+    TransactionAdded, TransactionChange,
+};
 pub use prices::store_sync_prices;
+pub use revert::{revert_sync_batch, SyncRevertResult};
 pub use service::{
     AuthPrompter, AutoCommitter, FixedAuthPrompter, GitAutoCommitter, NoopAutoCommitter,
-    SyncContext, SyncOutcome, SyncService,
+    SyncConcurrency, SyncContext, SyncOutcome, SyncService,
 };
 
 use crate::clock::{Clock, SystemClock};
 use crate::market_data::PricePoint;
-use crate::models::{Account, AssetBalance, BalanceSnapshot, Connection, Id, Transaction};
+use crate::models::{
+    Account, AssetBalance, BalanceSnapshot, Connection, Id, SyncBatch, SyncBatchAccountChange,
+    Transaction,
+};
 use crate::storage::Storage;
 use anyhow::Result;
 use std::collections::HashSet;
@@ -66,6 +77,18 @@ impl SyncedAssetBalance {
     }
 }
 
+/// What [`SyncResult::save_with_stats`] added or changed in storage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncSaveStats {
+    /// Batch stamped on the written rows; `None` if nothing was written.
+    pub batch_id: Option<Id>,
+    pub accounts_added: usize,
+    pub accounts_changed: usize,
+    pub balances_recorded: usize,
+    pub transactions_added: usize,
+    pub transactions_changed: usize,
+}
+
 /// Result of a sync operation.
 #[derive(Debug)]
 pub struct SyncResult {
@@ -82,6 +105,20 @@ impl SyncResult {
     }
 
     pub async fn save_with_clock(&self, storage: &dyn Storage, clock: &dyn Clock) -> Result<()> {
+        self.save_with_stats(storage, clock).await.map(|_| ())
+    }
+
+    /// Save this sync result and report what actually changed in storage.
+    ///
+    /// Appended balance snapshots and transactions are stamped with a new sync
+    /// batch id, and the batch is journaled so it can be reverted.
+    pub async fn save_with_stats(
+        &self,
+        storage: &dyn Storage,
+        clock: &dyn Clock,
+    ) -> Result<SyncSaveStats> {
+        let mut stats = SyncSaveStats::default();
+        let mut batch = SyncBatch::new(Id::new(), self.connection.id().clone(), clock.now());
         let synced_account_ids: HashSet<Id> = self
             .accounts
             .iter()
@@ -89,6 +126,21 @@ impl SyncResult {
             .collect();
 
         for account in &self.accounts {
+            let existing = storage.get_account(&account.id).await?;
+            match &existing {
+                None => stats.accounts_added += 1,
+                Some(existing) => {
+                    if !account_unchanged(existing, account) {
+                        stats.accounts_changed += 1;
+                    }
+                }
+            }
+            if existing.as_ref() != Some(account) {
+                batch.account_changes.push(SyncBatchAccountChange {
+                    before: existing,
+                    after: account.clone(),
+                });
+            }
             storage.save_account(account).await?;
         }
 
@@ -101,10 +153,16 @@ impl SyncResult {
                 inactive_account.active = false;
                 storage.save_account(&inactive_account).await?;
 
-                let snapshot = BalanceSnapshot::now_with(clock, Vec::new());
+                let snapshot =
+                    BalanceSnapshot::now_with(clock, Vec::new()).with_sync_batch(batch.id.clone());
                 storage
                     .append_balance_snapshot(&inactive_account.id, &snapshot)
                     .await?;
+                batch.account_ids.push(inactive_account.id.clone());
+                batch.account_changes.push(SyncBatchAccountChange {
+                    before: Some(account),
+                    after: inactive_account,
+                });
             }
         }
 
@@ -116,10 +174,15 @@ impl SyncResult {
                     .iter()
                     .map(|sb| sb.asset_balance.clone())
                     .collect();
-                let snapshot = BalanceSnapshot::now_with(clock, asset_balances);
+                let snapshot = BalanceSnapshot::now_with(clock, asset_balances)
+                    .with_sync_batch(batch.id.clone());
                 storage
                     .append_balance_snapshot(account_id, &snapshot)
                     .await?;
+                stats.balances_recorded += 1;
+                if !batch.account_ids.contains(account_id) {
+                    batch.account_ids.push(account_id.clone());
+                }
             }
         }
 
@@ -135,44 +198,72 @@ impl SyncResult {
                 let existing_by_id: std::collections::HashMap<Id, Transaction> =
                     existing.into_iter().map(|t| (t.id.clone(), t)).collect();
 
-                // Collapse duplicates within this batch: last write wins, preserve first-seen order.
-                let mut candidate_txns: Vec<Transaction> = Vec::new();
-                let mut idx_by_id: std::collections::HashMap<Id, usize> =
-                    std::collections::HashMap::new();
-                for txn in txns {
-                    if let Some(idx) = idx_by_id.get(&txn.id).copied() {
-                        candidate_txns[idx] = txn.clone();
-                    } else {
-                        idx_by_id.insert(txn.id.clone(), candidate_txns.len());
-                        candidate_txns.push(txn.clone());
-                    }
-                }
-
                 let mut to_append: Vec<Transaction> = Vec::new();
-                for txn in candidate_txns {
+                for txn in collapse_transaction_batch(txns) {
                     if let Some(existing) = existing_by_id.get(&txn.id) {
-                        let unchanged = existing.timestamp == txn.timestamp
-                            && existing.amount == txn.amount
-                            && existing.asset == txn.asset
-                            && existing.description == txn.description
-                            && existing.status == txn.status
-                            && existing.synchronizer_data == txn.synchronizer_data
-                            && existing.standardized_metadata == txn.standardized_metadata;
-                        if unchanged {
+                        if transaction_unchanged(existing, &txn) {
                             continue;
                         }
+                        stats.transactions_changed += 1;
+                    } else {
+                        stats.transactions_added += 1;
                     }
-                    to_append.push(txn);
+                    to_append.push(Transaction {
+                        sync_batch: Some(batch.id.clone()),
+                        ..txn
+                    });
                 }
 
                 if !to_append.is_empty() {
                     storage.append_transactions(account_id, &to_append).await?;
+                    if !batch.account_ids.contains(account_id) {
+                        batch.account_ids.push(account_id.clone());
+                    }
                 }
             }
         }
 
-        Ok(())
+        if !batch.is_empty() {
+            storage.append_sync_batch(&batch).await?;
+            stats.batch_id = Some(batch.id);
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Whether saving `account` over `existing` would change anything.
+fn account_unchanged(existing: &Account, account: &Account) -> bool {
+    existing.name == account.name
+        && existing.tags == account.tags
+        && existing.active == account.active
+        && existing.synchronizer_data == account.synchronizer_data
+}
+
+/// Whether `txn` is identical to the stored version `existing`.
+fn transaction_unchanged(existing: &Transaction, txn: &Transaction) -> bool {
+    existing.timestamp == txn.timestamp
+        && existing.amount == txn.amount
+        && existing.asset == txn.asset
+        && existing.description == txn.description
+        && existing.status == txn.status
+        && existing.synchronizer_data == txn.synchronizer_data
+        && existing.standardized_metadata == txn.standardized_metadata
+}
+
+/// Collapse duplicates within a synced batch: last write wins, preserve first-seen order.
+fn collapse_transaction_batch(txns: &[Transaction]) -> Vec<Transaction> {
+    let mut candidate_txns: Vec<Transaction> = Vec::new();
+    let mut idx_by_id: std::collections::HashMap<Id, usize> = std::collections::HashMap::new();
+    for txn in txns {
+        if let Some(idx) = idx_by_id.get(&txn.id).copied() {
+            candidate_txns[idx] = txn.clone();
+        } else {
+            idx_by_id.insert(txn.id.clone(), candidate_txns.len());
+            candidate_txns.push(txn.clone());
+        }
     }
+    candidate_txns
 }
 
 /// Trait for synchronizers - fetches data from external sources.
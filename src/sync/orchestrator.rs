@@ -5,13 +5,15 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use chrono::NaiveDate;
+use tokio::sync::{Mutex, MutexGuard};
 
 use crate::clock::{Clock, SystemClock};
 use crate::market_data::MarketDataService;
 use crate::models::{Asset, Connection, Id};
+use crate::reconcile::Discrepancy;
 use crate::storage::Storage;
 
-use super::{SyncOptions, SyncResult, Synchronizer};
+use super::{SyncOptions, SyncResult, SyncSaveStats, Synchronizer};
 
 /// Coordinates sync + price fetching operations.
 pub struct SyncOrchestrator {
@@ -19,14 +21,19 @@ pub struct SyncOrchestrator {
     market_data: MarketDataService,
     reporting_currency: String,
     clock: Arc<dyn Clock>,
+    writes: Mutex<()>,
 }
 
 /// Result of a sync operation that also stores and refreshes prices.
 #[derive(Debug)]
 pub struct SyncWithPricesResult {
     pub result: SyncResult,
+    pub saved: SyncSaveStats,
     pub stored_prices: usize,
     pub refresh: PriceRefreshResult,
+    /// Balance/transaction mismatches found after saving, per account (only
+    /// when reconciling after sync is enabled).
+    pub reconcile_warnings: Vec<(Id, Discrepancy)>,
 }
 
 /// Result of a price refresh operation.
@@ -48,6 +55,7 @@ impl SyncOrchestrator {
             market_data,
             reporting_currency,
             clock: Arc::new(SystemClock),
+            writes: Mutex::new(()),
         }
     }
 
@@ -59,6 +67,12 @@ impl SyncOrchestrator {
     pub fn reporting_currency(&self) -> &str {
         &self.reporting_currency
     }
+
+    /// Hold this while writing a sync's results so concurrent syncs never
+    /// interleave their storage writes.
+    pub async fn lock_writes(&self) -> MutexGuard<'_, ()> {
+        self.writes.lock().await
+    }
 }
 
 impl SyncOrchestrator {
@@ -433,8 +447,9 @@ impl SyncOrchestrator {
             .await?;
 
         // 2. Save sync results (this stores balances)
-        result
-            .save_with_clock(self.storage.as_ref(), self.clock.as_ref())
+        let writes = self.lock_writes().await;
+        let saved = result
+            .save_with_stats(self.storage.as_ref(), self.clock.as_ref())
             .await?;
 
         // 3. Store any prices the synchronizer provided
@@ -447,6 +462,7 @@ impl SyncOrchestrator {
                 }
             }
         }
+        drop(writes);
 
         // 4. Collect assets that need prices
         let assets: HashSet<Asset> = result
@@ -461,8 +477,10 @@ impl SyncOrchestrator {
 
         Ok(SyncWithPricesResult {
             result,
+            saved,
             stored_prices,
             refresh,
+            reconcile_warnings: Vec::new(),
         })
     }
 }
@@ -0,0 +1,347 @@
+//! JSON-over-stdio protocol for external synchronizer plugins.
+//!
+//! A connection configured with `synchronizer = "exec:/path/to/plugin"` is
+//! synced by running that executable. Messages are single-line JSON objects
+//! tagged by `type`:
+//!
+//! 1. keepbook writes a [`HostMessage::Sync`] request to the plugin's stdin.
+//! 2. The plugin may write [`PluginMessage::CredentialRequest`] lines; keepbook
+//!    answers each with a [`HostMessage::Credential`] line.
+//! 3. The plugin may write [`PluginMessage::Log`] lines at any time.
+//! 4. The plugin finishes with one [`PluginMessage::Result`] or
+//!    [`PluginMessage::Error`] line and exits.
+//!
+//! Plugin stderr is passed through untouched. Account and transaction ids are
+//! the plugin's own stable identifiers; keepbook derives its ids from them.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Asset, Connection, ConnectionConfig, TransactionStandardizedMetadata};
+use crate::models::{LastSync, TransactionStatus};
+use crate::storage::{MemoryStorage, Storage};
+use crate::sync::synchronizers::ExecSynchronizer;
+use crate::sync::{SyncOptions, Synchronizer, TransactionSyncMode};
+
+/// Protocol version spoken by this keepbook build.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Messages keepbook writes to the plugin's stdin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HostMessage {
+    Sync(SyncRequest),
+    Credential { key: String, value: Option<String> },
+}
+
+/// Messages the plugin writes to stdout.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PluginMessage {
+    CredentialRequest {
+        key: String,
+    },
+    Log {
+        #[serde(default)]
+        level: Option<String>,
+        message: String,
+    },
+    Result(SyncResponse),
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncRequest {
+    pub protocol_version: u32,
+    pub connection: PluginConnection,
+    /// Accounts previously returned by the plugin for this connection.
+    #[serde(default)]
+    pub accounts: Vec<PluginAccount>,
+    pub options: PluginSyncOptions,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginConnection {
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_sync: Option<LastSync>,
+    /// State returned by the plugin on its previous run.
+    #[serde(default)]
+    pub synchronizer_data: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginTransactionMode {
+    Auto,
+    Full,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginSyncOptions {
+    pub transactions: PluginTransactionMode,
+}
+
+impl From<&SyncOptions> for PluginSyncOptions {
+    fn from(options: &SyncOptions) -> Self {
+        Self {
+            transactions: match options.transactions {
+                TransactionSyncMode::Auto => PluginTransactionMode::Auto,
+                TransactionSyncMode::Full => PluginTransactionMode::Full,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncResponse {
+    pub protocol_version: u32,
+    pub accounts: Vec<PluginAccount>,
+    #[serde(default)]
+    pub balances: Vec<PluginAccountBalances>,
+    #[serde(default)]
+    pub transactions: Vec<PluginAccountTransactions>,
+    /// New connection state; stored and sent back on the next run.
+    #[serde(default)]
+    pub synchronizer_data: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginAccount {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default = "default_true")]
+    pub active: bool,
+    #[serde(default)]
+    pub synchronizer_data: serde_json::Value,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginAccountBalances {
+    pub account_id: String,
+    pub balances: Vec<PluginBalance>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginBalance {
+    pub asset: Asset,
+    pub amount: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_basis: Option<String>,
+    /// Unit price reported by the institution, fed into the price store.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<PluginPrice>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginPrice {
+    pub price: String,
+    pub quote_currency: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub as_of_date: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginAccountTransactions {
+    pub account_id: String,
+    pub transactions: Vec<PluginTransaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginTransaction {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub amount: String,
+    pub asset: Asset,
+    pub description: String,
+    #[serde(default = "default_status")]
+    pub status: TransactionStatus,
+    #[serde(default)]
+    pub synchronizer_data: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub standardized_metadata: Option<TransactionStandardizedMetadata>,
+}
+
+fn default_status() -> TransactionStatus {
+    TransactionStatus::Posted
+}
+
+/// Outcome of a single conformance check.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConformanceCheck {
+    pub name: String,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Result of [`check_plugin_conformance`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ConformanceReport {
+    pub plugin: String,
+    pub checks: Vec<ConformanceCheck>,
+}
+
+impl ConformanceReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    fn check(&mut self, name: &str, result: std::result::Result<(), String>) {
+        self.checks.push(ConformanceCheck {
+            name: name.to_string(),
+            passed: result.is_ok(),
+            detail: result.err(),
+        });
+    }
+}
+
+/// Run a plugin through the protocol against scratch in-memory storage and
+/// check the behaviour keepbook relies on: a well-formed result, parseable
+/// amounts, stable ids across runs, and round-tripped connection state.
+pub async fn check_plugin_conformance(
+    command: &Path,
+    credentials: HashMap<String, String>,
+) -> Result<ConformanceReport> {
+    let mut report = ConformanceReport {
+        plugin: command.display().to_string(),
+        checks: Vec::new(),
+    };
+    let storage = MemoryStorage::new();
+    let mut connection = Connection::new(ConnectionConfig {
+        name: "Conformance".to_string(),
+        synchronizer: format!("exec:{}", command.display()),
+        credentials: None,
+        balance_staleness: None,
+    });
+    storage.save_connection(&connection).await?;
+    let synchronizer =
+        ExecSynchronizer::new(command.to_path_buf()).with_static_credentials(credentials);
+
+    let first = match synchronizer.sync(&mut connection, &storage).await {
+        Ok(result) => {
+            report.check("first sync succeeds", Ok(()));
+            result
+        }
+        Err(e) => {
+            report.check("first sync succeeds", Err(format!("{e:#}")));
+            return Ok(report);
+        }
+    };
+
+    report.check("returns at least one account", {
+        if first.accounts.is_empty() {
+            Err("no accounts returned".to_string())
+        } else {
+            Ok(())
+        }
+    });
+
+    let amounts = first
+        .balances
+        .iter()
+        .flat_map(|(_, balances)| balances.iter().map(|b| &b.asset_balance.amount))
+        .chain(
+            first
+                .transactions
+                .iter()
+                .flat_map(|(_, txns)| txns.iter().map(|t| &t.amount)),
+        );
+    let bad_amounts: Vec<&String> = amounts
+        .filter(|amount| Decimal::from_str(amount.trim()).is_err())
+        .collect();
+    report.check(
+        "amounts are decimal strings",
+        if bad_amounts.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("unparseable amounts: {bad_amounts:?}"))
+        },
+    );
+
+    if let Err(e) = first.save(&storage).await {
+        report.check("result saves to storage", Err(format!("{e:#}")));
+        return Ok(report);
+    }
+    report.check("result saves to storage", Ok(()));
+
+    let first_state = connection.state.synchronizer_data.clone();
+    let second = match synchronizer.sync(&mut connection, &storage).await {
+        Ok(result) => result,
+        Err(e) => {
+            report.check("second sync succeeds", Err(format!("{e:#}")));
+            return Ok(report);
+        }
+    };
+    report.check("second sync succeeds", Ok(()));
+
+    let first_ids: HashSet<_> = first.accounts.iter().map(|a| a.id.clone()).collect();
+    let second_ids: HashSet<_> = second.accounts.iter().map(|a| a.id.clone()).collect();
+    report.check(
+        "account ids are stable across runs",
+        if first_ids == second_ids {
+            Ok(())
+        } else {
+            Err("account ids changed between runs".to_string())
+        },
+    );
+
+    let first_txn_ids: HashSet<_> = first
+        .transactions
+        .iter()
+        .flat_map(|(_, txns)| txns.iter().map(|t| t.id.clone()))
+        .collect();
+    let second_txn_ids: HashSet<_> = second
+        .transactions
+        .iter()
+        .flat_map(|(_, txns)| txns.iter().map(|t| t.id.clone()))
+        .collect();
+    report.check(
+        "transaction ids are stable across runs",
+        if second_txn_ids.is_empty() || !first_txn_ids.is_disjoint(&second_txn_ids) {
+            Ok(())
+        } else {
+            Err("no transaction id from the first run was returned again".to_string())
+        },
+    );
+
+    report.check(
+        "connection state is persisted",
+        if first_state.is_null() || !connection.state.synchronizer_data.is_null() {
+            Ok(())
+        } else {
+            Err("synchronizer_data was dropped on the second run".to_string())
+        },
+    );
+
+    let full = SyncOptions {
+        transactions: TransactionSyncMode::Full,
+    };
+    report.check(
+        "full transaction sync succeeds",
+        synchronizer
+            .sync_with_options(&mut connection, &storage, &full)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("{e:#}")),
+    );
+
+    Ok(report)
+}
@@ -0,0 +1,372 @@
+//! Dry-run view of what saving a `SyncResult` would write.
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use super::{account_unchanged, collapse_transaction_batch, transaction_unchanged, SyncResult};
+use crate::models::{Account, Asset, AssetBalance, Id, Transaction};
+use crate::storage::Storage;
+
+/// Everything `SyncResult::save_with_stats` would write, computed without
+/// touching storage.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncPreview {
+    pub connection: EntityRef,
+    pub accounts_added: Vec<Account>,
+    pub accounts_changed: Vec<AccountChange>,
+    /// Active accounts the sync didn't return; saving marks them inactive and
+    /// records an empty balance snapshot.
+    pub accounts_deactivated: Vec<EntityRef>,
+    pub balances: Vec<BalancePreview>,
+    pub transactions_added: Vec<TransactionAdded>,
+    pub transactions_updated: Vec<TransactionChange>,
+}
+
+/// An id and display name.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityRef {
+    pub id: Id,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountChange {
+    pub id: Id,
+    pub name: String,
+    pub changes: Vec<FieldChange>,
+}
+
+/// A new balance snapshot for one account.
+#[derive(Debug, Clone, Serialize)]
+pub struct BalancePreview {
+    pub account_id: Id,
+    pub account_name: String,
+    pub balances: Vec<BalanceChange>,
+}
+
+/// One asset in a new snapshot, compared with the latest stored snapshot.
+#[derive(Debug, Clone, Serialize)]
+pub struct BalanceChange {
+    pub asset: Asset,
+    /// `None` when the asset is absent from the new snapshot.
+    pub amount: Option<String>,
+    pub previous: Option<String>,
+    /// `amount - previous`, treating a missing side as zero. `None` if either
+    /// amount isn't a decimal.
+    pub delta: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionAdded {
+    pub account_id: Id,
+    pub transaction: Transaction,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionChange {
+    pub account_id: Id,
+    pub id: Id,
+    pub description: String,
+    pub changes: Vec<FieldChange>,
+}
+
+impl SyncResult {
+    /// Compute what `save_with_stats` would write, without writing anything.
+    pub async fn preview(&self, storage: &dyn Storage) -> Result<SyncPreview> {
+        let connection_id = self.connection.id();
+        let mut preview = SyncPreview {
+            connection: EntityRef {
+                id: connection_id.clone(),
+                name: self.connection.config.name.clone(),
+            },
+            accounts_added: Vec::new(),
+            accounts_changed: Vec::new(),
+            accounts_deactivated: Vec::new(),
+            balances: Vec::new(),
+            transactions_added: Vec::new(),
+            transactions_updated: Vec::new(),
+        };
+
+        let mut account_names: HashMap<Id, String> = HashMap::new();
+        for account in &self.accounts {
+            account_names.insert(account.id.clone(), account.name.clone());
+            match storage.get_account(&account.id).await? {
+                None => preview.accounts_added.push(account.clone()),
+                Some(existing) if !account_unchanged(&existing, account) => {
+                    preview.accounts_changed.push(AccountChange {
+                        id: account.id.clone(),
+                        name: account.name.clone(),
+                        changes: field_changes(
+                            &existing,
+                            account,
+                            &["id", "connection_id", "created_at"],
+                        )?,
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+
+        let synced_account_ids: HashSet<&Id> =
+            self.accounts.iter().map(|account| &account.id).collect();
+        for account in storage.list_accounts().await? {
+            if account.connection_id == *connection_id
+                && account.active
+                && !synced_account_ids.contains(&account.id)
+            {
+                let balances = balance_changes(storage, &account.id, &[]).await?;
+                if !balances.is_empty() {
+                    preview.balances.push(BalancePreview {
+                        account_id: account.id.clone(),
+                        account_name: account.name.clone(),
+                        balances,
+                    });
+                }
+                preview.accounts_deactivated.push(EntityRef {
+                    id: account.id,
+                    name: account.name,
+                });
+            }
+        }
+
+        for (account_id, synced_balances) in &self.balances {
+            if synced_balances.is_empty() {
+                continue;
+            }
+            let asset_balances: Vec<AssetBalance> = synced_balances
+                .iter()
+                .map(|sb| sb.asset_balance.clone())
+                .collect();
+            preview.balances.push(BalancePreview {
+                account_id: account_id.clone(),
+                account_name: account_name(storage, &account_names, account_id).await?,
+                balances: balance_changes(storage, account_id, &asset_balances).await?,
+            });
+        }
+
+        for (account_id, txns) in &self.transactions {
+            if txns.is_empty() {
+                continue;
+            }
+            let existing_by_id: HashMap<Id, Transaction> = storage
+                .get_transactions(account_id)
+                .await?
+                .into_iter()
+                .map(|t| (t.id.clone(), t))
+                .collect();
+
+            for txn in collapse_transaction_batch(txns) {
+                match existing_by_id.get(&txn.id) {
+                    None => preview.transactions_added.push(TransactionAdded {
+                        account_id: account_id.clone(),
+                        transaction: txn,
+                    }),
+                    Some(existing) if !transaction_unchanged(existing, &txn) => {
+                        preview.transactions_updated.push(TransactionChange {
+                            account_id: account_id.clone(),
+                            id: txn.id.clone(),
+                            description: txn.description.clone(),
+                            changes: field_changes(existing, &txn, &["id", "sync_batch"])?,
+                        });
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        Ok(preview)
+    }
+}
+
+async fn account_name(
+    storage: &dyn Storage,
+    synced: &HashMap<Id, String>,
+    account_id: &Id,
+) -> Result<String> {
+    if let Some(name) = synced.get(account_id) {
+        return Ok(name.clone());
+    }
+    Ok(storage
+        .get_account(account_id)
+        .await?
+        .map(|account| account.name)
+        .unwrap_or_else(|| account_id.to_string()))
+}
+
+/// Compare a new snapshot's balances with the latest stored snapshot. Assets
+/// only present in the stored snapshot are reported with no amount.
+async fn balance_changes(
+    storage: &dyn Storage,
+    account_id: &Id,
+    balances: &[AssetBalance],
+) -> Result<Vec<BalanceChange>> {
+    let previous: Vec<AssetBalance> = storage
+        .get_latest_balance_snapshot(account_id)
+        .await?
+        .map(|snapshot| snapshot.balances)
+        .unwrap_or_default();
+
+    let mut changes: Vec<BalanceChange> = balances
+        .iter()
+        .map(|balance| {
+            let before = previous.iter().find(|p| p.asset == balance.asset);
+            balance_change(
+                balance.asset.clone(),
+                Some(&balance.amount),
+                before.map(|b| &b.amount),
+            )
+        })
+        .collect();
+    for before in &previous {
+        if !balances.iter().any(|b| b.asset == before.asset) {
+            changes.push(balance_change(
+                before.asset.clone(),
+                None,
+                Some(&before.amount),
+            ));
+        }
+    }
+    Ok(changes)
+}
+
+fn balance_change(
+    asset: Asset,
+    amount: Option<&String>,
+    previous: Option<&String>,
+) -> BalanceChange {
+    let parse = |value: Option<&String>| match value {
+        Some(value) => Decimal::from_str(value).ok(),
+        None => Some(Decimal::ZERO),
+    };
+    let delta = match (parse(amount), parse(previous)) {
+        (Some(after), Some(before)) => Some((after - before).normalize().to_string()),
+        _ => None,
+    };
+    BalanceChange {
+        asset,
+        amount: amount.cloned(),
+        previous: previous.cloned(),
+        delta,
+    }
+}
+
+/// Top-level serialized fields that differ between `before` and `after`.
+fn field_changes<T: Serialize>(before: &T, after: &T, ignore: &[&str]) -> Result<Vec<FieldChange>> {
+    let before = serde_json::to_value(before)?;
+    let after = serde_json::to_value(after)?;
+    let empty = serde_json::Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+
+    let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    Ok(fields
+        .into_iter()
+        .filter(|field| !ignore.contains(&field.as_str()))
+        .filter_map(|field| {
+            let old = before.get(field).cloned().unwrap_or_default();
+            let new = after.get(field).cloned().unwrap_or_default();
+            (old != new).then(|| FieldChange {
+                field: field.clone(),
+                before: old,
+                after: new,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BalanceSnapshot, Connection, ConnectionConfig, TransactionStatus};
+    use crate::storage::MemoryStorage;
+    use crate::sync::SyncedAssetBalance;
+
+    #[tokio::test]
+    async fn preview_reports_changes_without_writing() -> Result<()> {
+        let storage = MemoryStorage::new();
+        let connection = Connection::new(ConnectionConfig {
+            name: "Bank".to_string(),
+            synchronizer: "test".to_string(),
+            credentials: None,
+            balance_staleness: None,
+        });
+        storage.save_connection(&connection).await?;
+
+        let checking = Account::new("Checking", connection.id().clone());
+        let closed = Account::new("Old Savings", connection.id().clone());
+        storage.save_account(&checking).await?;
+        storage.save_account(&closed).await?;
+        storage
+            .append_balance_snapshot(
+                &checking.id,
+                &BalanceSnapshot::now(vec![AssetBalance::new(Asset::currency("USD"), "100.50")]),
+            )
+            .await?;
+        let pending = Transaction::new("-5", Asset::currency("USD"), "Coffee")
+            .with_status(TransactionStatus::Pending);
+        storage
+            .append_transactions(&checking.id, std::slice::from_ref(&pending))
+            .await?;
+
+        let mut renamed = checking.clone();
+        renamed.name = "Everyday Checking".to_string();
+        let posted = pending.clone().with_status(TransactionStatus::Posted);
+        let added = Transaction::new("-12", Asset::currency("USD"), "Lunch");
+        let result = SyncResult {
+            connection: connection.clone(),
+            accounts: vec![renamed],
+            balances: vec![(
+                checking.id.clone(),
+                vec![SyncedAssetBalance::new(AssetBalance::new(
+                    Asset::currency("USD"),
+                    "83.50",
+                ))],
+            )],
+            transactions: vec![(checking.id.clone(), vec![posted, added.clone()])],
+        };
+
+        let preview = result.preview(&storage).await?;
+
+        assert!(preview.accounts_added.is_empty());
+        assert_eq!(preview.accounts_changed.len(), 1);
+        assert_eq!(
+            preview.accounts_changed[0].changes,
+            vec![FieldChange {
+                field: "name".to_string(),
+                before: "Checking".into(),
+                after: "Everyday Checking".into(),
+            }]
+        );
+        assert_eq!(preview.accounts_deactivated.len(), 1);
+        assert_eq!(preview.accounts_deactivated[0].id, closed.id);
+
+        let balances = &preview.balances[0].balances[0];
+        assert_eq!(balances.previous.as_deref(), Some("100.50"));
+        assert_eq!(balances.delta.as_deref(), Some("-17"));
+
+        assert_eq!(preview.transactions_added.len(), 1);
+        assert_eq!(preview.transactions_added[0].transaction.id, added.id);
+        assert_eq!(preview.transactions_updated.len(), 1);
+        assert_eq!(preview.transactions_updated[0].changes[0].field, "status");
+
+        // Nothing was written.
+        assert!(storage.get_account(&closed.id).await?.unwrap().active);
+        assert_eq!(storage.get_transactions(&checking.id).await?.len(), 1);
+        assert_eq!(storage.get_balance_snapshots(&checking.id).await?.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+//! Backing out a saved sync batch.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::clock::Clock;
+use crate::models::{latest_sync_batches, Id, SyncBatch};
+use crate::storage::{CompactionStorage, Storage};
+
+/// What [`revert_sync_batch`] undid.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncRevertResult {
+    pub batch_id: Id,
+    pub connection_id: Id,
+    pub balance_snapshots_removed: usize,
+    pub transactions_removed: usize,
+    /// Accounts put back to their state before the batch.
+    pub accounts_restored: Vec<Id>,
+    /// Accounts the batch created, now empty and deleted.
+    pub accounts_removed: Vec<Id>,
+    /// Accounts changed again after the batch (or created by it and since
+    /// given data by a later batch); left as they are.
+    pub accounts_skipped: Vec<Id>,
+}
+
+async fn find_sync_batch<S: Storage + ?Sized>(
+    storage: &S,
+    batch_id: &Id,
+) -> Result<Option<SyncBatch>> {
+    for connection in storage.list_connections().await? {
+        let batches = latest_sync_batches(storage.get_sync_batches(connection.id()).await?);
+        if let Some(batch) = batches.into_iter().find(|batch| batch.id == *batch_id) {
+            return Ok(Some(batch));
+        }
+    }
+    Ok(None)
+}
+
+/// Remove the balance snapshots and transaction versions stamped with
+/// `batch_id` and restore the account states it changed.
+///
+/// Removing a transaction version makes the previous version current again.
+/// Recompaction drops previous versions, so it unstamps the versions that
+/// replaced them and a revert leaves those as they are. Synchronizer cursors
+/// aren't rewound; a `--transactions full` sync refetches anything still
+/// missing.
+pub async fn revert_sync_batch<S>(
+    storage: &S,
+    batch_id: &Id,
+    clock: &dyn Clock,
+) -> Result<SyncRevertResult>
+where
+    S: Storage + CompactionStorage + ?Sized,
+{
+    let mut batch = find_sync_batch(storage, batch_id)
+        .await?
+        .with_context(|| format!("Sync batch not found: {batch_id}"))?;
+    if let Some(at) = batch.reverted_at {
+        anyhow::bail!(
+            "Sync batch {batch_id} was already reverted at {}",
+            at.to_rfc3339()
+        );
+    }
+
+    let mut result = SyncRevertResult {
+        batch_id: batch.id.clone(),
+        connection_id: batch.connection_id.clone(),
+        balance_snapshots_removed: 0,
+        transactions_removed: 0,
+        accounts_restored: Vec::new(),
+        accounts_removed: Vec::new(),
+        accounts_skipped: Vec::new(),
+    };
+
+    for account_id in &batch.account_ids {
+        let removed = storage.remove_sync_batch_rows(account_id, batch_id).await?;
+        result.balance_snapshots_removed += removed.balance_snapshots;
+        result.transactions_removed += removed.transactions;
+    }
+
+    for change in &batch.account_changes {
+        let account_id = &change.after.id;
+        let current = storage.get_account(account_id).await?;
+        if current.as_ref() != Some(&change.after) {
+            result.accounts_skipped.push(account_id.clone());
+            continue;
+        }
+        match &change.before {
+            Some(before) => {
+                storage.save_account(before).await?;
+                result.accounts_restored.push(account_id.clone());
+            }
+            None => {
+                let empty = storage.get_transactions_raw(account_id).await?.is_empty()
+                    && storage.get_balance_snapshots(account_id).await?.is_empty();
+                if !empty {
+                    result.accounts_skipped.push(account_id.clone());
+                    continue;
+                }
+                storage.delete_account(account_id).await?;
+                result.accounts_removed.push(account_id.clone());
+            }
+        }
+    }
+
+    if !result.accounts_removed.is_empty() {
+        if let Some(mut connection) = storage.get_connection(&batch.connection_id).await? {
+            connection
+                .state
+                .account_ids
+                .retain(|id| !result.accounts_removed.contains(id));
+            storage.save_connection(&connection).await?;
+        }
+    }
+
+    batch.reverted_at = Some(clock.now());
+    storage.append_sync_batch(&batch).await?;
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use crate::models::{
+        Account, Asset, AssetBalance, BalanceSnapshot, Connection, ConnectionConfig, Transaction,
+        TransactionStatus,
+    };
+    use crate::storage::{JsonFileStorage, MemoryStorage};
+    use crate::sync::{SyncResult, SyncedAssetBalance};
+
+    #[tokio::test]
+    async fn revert_restores_state_before_batch() -> Result<()> {
+        let storage = MemoryStorage::new();
+        let mut connection = Connection::new(ConnectionConfig {
+            name: "Bank".to_string(),
+            synchronizer: "test".to_string(),
+            credentials: None,
+            balance_staleness: None,
+        });
+        let checking = Account::new("Checking", connection.id().clone());
+        connection.state.account_ids.push(checking.id.clone());
+        storage.save_connection(&connection).await?;
+        storage.save_account(&checking).await?;
+        storage
+            .append_balance_snapshot(
+                &checking.id,
+                &BalanceSnapshot::now(vec![AssetBalance::new(Asset::currency("USD"), "100")]),
+            )
+            .await?;
+        let pending = Transaction::new("-5", Asset::currency("USD"), "Coffee")
+            .with_status(TransactionStatus::Pending);
+        storage
+            .append_transactions(&checking.id, std::slice::from_ref(&pending))
+            .await?;
+
+        // A bad sync: renames the account, adds a duplicate account and
+        // transaction, and posts the pending one.
+        let mut renamed = checking.clone();
+        renamed.name = "CHECKING ...1234".to_string();
+        let duplicate = Account::new("Checking (2)", connection.id().clone());
+        let mut synced_connection = connection.clone();
+        synced_connection
+            .state
+            .account_ids
+            .push(duplicate.id.clone());
+        let result = SyncResult {
+            connection: synced_connection,
+            accounts: vec![renamed, duplicate.clone()],
+            balances: vec![
+                (
+                    checking.id.clone(),
+                    vec![SyncedAssetBalance::new(AssetBalance::new(
+                        Asset::currency("USD"),
+                        "95",
+                    ))],
+                ),
+                (
+                    duplicate.id.clone(),
+                    vec![SyncedAssetBalance::new(AssetBalance::new(
+                        Asset::currency("USD"),
+                        "95",
+                    ))],
+                ),
+            ],
+            transactions: vec![(
+                checking.id.clone(),
+                vec![
+                    pending.clone().with_status(TransactionStatus::Posted),
+                    Transaction::new("-5", Asset::currency("USD"), "Coffee"),
+                ],
+            )],
+        };
+        let stats = result.save_with_stats(&storage, &SystemClock).await?;
+        let batch_id = stats.batch_id.expect("batch recorded");
+        assert_eq!(storage.get_transactions(&checking.id).await?.len(), 2);
+
+        let reverted = revert_sync_batch(&storage, &batch_id, &SystemClock).await?;
+
+        assert_eq!(reverted.balance_snapshots_removed, 2);
+        assert_eq!(reverted.transactions_removed, 2);
+        assert_eq!(reverted.accounts_restored, vec![checking.id.clone()]);
+        assert_eq!(reverted.accounts_removed, vec![duplicate.id.clone()]);
+
+        assert_eq!(
+            storage.get_account(&checking.id).await?,
+            Some(checking.clone())
+        );
+        assert!(storage.get_account(&duplicate.id).await?.is_none());
+        let txns = storage.get_transactions(&checking.id).await?;
+        assert_eq!(txns.len(), 1);
+        assert_eq!(txns[0].status, TransactionStatus::Pending);
+        let latest = storage
+            .get_latest_balance_snapshot(&checking.id)
+            .await?
+            .unwrap();
+        assert_eq!(latest.balances[0].amount, "100");
+        let connection = storage.get_connection(connection.id()).await?.unwrap();
+        assert_eq!(connection.state.account_ids, vec![checking.id.clone()]);
+
+        let err = revert_sync_batch(&storage, &batch_id, &SystemClock)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("already reverted"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn revert_after_recompaction_keeps_compacted_transactions() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let storage = JsonFileStorage::new(dir.path());
+        let mut connection = Connection::new(ConnectionConfig {
+            name: "Bank".to_string(),
+            synchronizer: "test".to_string(),
+            credentials: None,
+            balance_staleness: None,
+        });
+        let checking = Account::new("Checking", connection.id().clone());
+        connection.state.account_ids.push(checking.id.clone());
+        storage
+            .save_connection_config(connection.id(), &connection.config)
+            .await?;
+        storage.save_connection(&connection).await?;
+        storage.save_account(&checking).await?;
+        let pending = Transaction::new("-5", Asset::currency("USD"), "Coffee")
+            .with_status(TransactionStatus::Pending);
+        storage
+            .append_transactions(&checking.id, std::slice::from_ref(&pending))
+            .await?;
+
+        // Posts the pending transaction and adds a new one.
+        let added = Transaction::new("-7", Asset::currency("USD"), "Lunch");
+        let result = SyncResult {
+            connection: connection.clone(),
+            accounts: vec![checking.clone()],
+            balances: Vec::new(),
+            transactions: vec![(
+                checking.id.clone(),
+                vec![
+                    pending.clone().with_status(TransactionStatus::Posted),
+                    added.clone(),
+                ],
+            )],
+        };
+        let stats = result.save_with_stats(&storage, &SystemClock).await?;
+        let batch_id = stats.batch_id.expect("batch recorded");
+
+        // Compaction drops the pending version, leaving the posted one as the
+        // only copy.
+        storage.recompact_all_jsonl().await?;
+        let reverted = revert_sync_batch(&storage, &batch_id, &SystemClock).await?;
+
+        assert_eq!(reverted.transactions_removed, 1);
+        let txns = storage.get_transactions(&checking.id).await?;
+        assert_eq!(txns.len(), 1);
+        assert_eq!(txns[0].id, pending.id);
+        assert_eq!(txns[0].status, TransactionStatus::Posted);
+        Ok(())
+    }
+}
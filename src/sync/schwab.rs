@@ -128,6 +128,9 @@ impl TransactionHistoryTimeFrame {
     }
 }
 
+/// How long a captured Schwab session is expected to stay valid.
+pub const SESSION_LIFETIME: chrono::Duration = chrono::Duration::hours(24);
+
 const TRANSACTION_HISTORY_MAX_PAGES: usize = 20;
 const TRANSACTION_HISTORY_INIT_PATH: &str =
     "/api/is.TransactionHistoryWeb/TransactionHistoryInterface/TransactionHistory/init";
@@ -560,8 +563,10 @@ pub fn parse_exported_session(json: &str) -> Result<SessionData> {
         cookies: exported.cookies,
         cookie_jar: Vec::new(),
         captured_at: Some(chrono::Utc::now().timestamp()),
+        expires_at: None,
         data: HashMap::new(),
-    })
+    }
+    .with_lifetime(SESSION_LIFETIME))
 }
 
 #[derive(Debug)]
@@ -1,19 +1,23 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::clock::{Clock, SystemClock};
 use crate::config::RefreshConfig;
 use crate::git::{try_auto_commit, AutoCommitOutcome};
 use crate::market_data::MarketDataService;
-use crate::models::Connection;
+use crate::models::{Connection, Id, SyncRun, SyncRunOutcome};
+use crate::reconcile::{reconcile_latest, Discrepancy};
 use crate::staleness::{check_balance_staleness_at, resolve_balance_staleness};
 use crate::storage::{find_account, find_connection, Storage};
 use anyhow::{Context, Result};
+use futures::stream::{self, StreamExt};
+use tokio::sync::{Mutex, Semaphore};
 
-use super::SyncOptions;
 use super::{
     AuthStatus, InteractiveAuth, PriceRefreshResult, SyncOrchestrator, SyncWithPricesResult,
 };
 use super::{DefaultSynchronizerFactory, SynchronizerFactory};
+use super::{SyncOptions, SyncPreview, SyncResult};
 
 pub trait AuthPrompter: Send + Sync {
     fn confirm_login(&self, prompt: &str) -> Result<bool>;
@@ -95,6 +99,38 @@ impl AutoCommitter for GitAutoCommitter {
     }
 }
 
+/// Limits on how many connections `sync_all` runs at once.
+///
+/// Synchronizers that need interactive (browser) auth always run one at a
+/// time regardless of these limits.
+#[derive(Debug, Clone)]
+pub struct SyncConcurrency {
+    /// Maximum connections syncing at the same time.
+    pub max: usize,
+    /// Lower limits for particular synchronizers, keyed by synchronizer name.
+    pub per_synchronizer: HashMap<String, usize>,
+}
+
+impl SyncConcurrency {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            per_synchronizer: HashMap::new(),
+        }
+    }
+
+    pub fn with_limit(mut self, synchronizer: impl Into<String>, limit: usize) -> Self {
+        self.per_synchronizer.insert(synchronizer.into(), limit);
+        self
+    }
+}
+
+impl Default for SyncConcurrency {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
 pub struct SyncContext {
     pub storage: Arc<dyn Storage>,
     pub market_data: MarketDataService,
@@ -103,6 +139,8 @@ pub struct SyncContext {
     pub auto_committer: Arc<dyn AutoCommitter>,
     pub synchronizer_factory: Arc<dyn SynchronizerFactory>,
     pub clock: Arc<dyn Clock>,
+    pub concurrency: SyncConcurrency,
+    pub reconcile_after_sync: bool,
 }
 
 impl SyncContext {
@@ -119,6 +157,8 @@ impl SyncContext {
             auto_committer: Arc::new(NoopAutoCommitter),
             synchronizer_factory: Arc::new(DefaultSynchronizerFactory::new(None)),
             clock: Arc::new(SystemClock),
+            concurrency: SyncConcurrency::default(),
+            reconcile_after_sync: false,
         }
     }
 
@@ -143,6 +183,16 @@ impl SyncContext {
         self.clock = clock;
         self
     }
+
+    pub fn with_concurrency(mut self, concurrency: SyncConcurrency) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    pub fn with_reconcile_after_sync(mut self, enabled: bool) -> Self {
+        self.reconcile_after_sync = enabled;
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -173,6 +223,11 @@ pub struct SyncService {
     auto_committer: Arc<dyn AutoCommitter>,
     factory: Arc<dyn SynchronizerFactory>,
     clock: Arc<dyn Clock>,
+    concurrency: SyncConcurrency,
+    reconcile_after_sync: bool,
+    /// Serializes browser-based synchronizers, which share a browser profile
+    /// and may prompt on the terminal.
+    interactive: Mutex<()>,
 }
 
 impl SyncService {
@@ -192,6 +247,9 @@ impl SyncService {
             auto_committer: context.auto_committer,
             factory: context.synchronizer_factory,
             clock: context.clock,
+            concurrency: context.concurrency,
+            reconcile_after_sync: context.reconcile_after_sync,
+            interactive: Mutex::new(()),
         }
     }
 
@@ -250,21 +308,7 @@ impl SyncService {
 
     pub async fn sync_all_with_options(&self, options: &SyncOptions) -> Result<Vec<SyncOutcome>> {
         let connections = self.storage.list_connections().await?;
-        let mut results = Vec::with_capacity(connections.len());
-
-        for connection in connections {
-            let id_or_name = connection.id().to_string();
-            match self
-                .sync_connection_internal(connection.clone(), &id_or_name, options)
-                .await
-            {
-                Ok(outcome) => results.push(outcome),
-                Err(err) => results.push(SyncOutcome::Failed {
-                    connection,
-                    error: format!("{err:#}"),
-                }),
-            }
-        }
+        let results = self.sync_many(connections, options).await;
 
         self.auto_committer.maybe_commit("sync all");
 
@@ -282,35 +326,73 @@ impl SyncService {
         options: &SyncOptions,
     ) -> Result<Vec<SyncOutcome>> {
         let connections = self.storage.list_connections().await?;
-        let mut results = Vec::with_capacity(connections.len());
+        let mut results: Vec<Option<SyncOutcome>> = Vec::with_capacity(connections.len());
+        let mut stale = Vec::new();
 
         for connection in connections {
             let threshold = resolve_balance_staleness(None, &connection, refresh);
             let check = check_balance_staleness_at(&connection, threshold, self.clock.now());
 
-            if !check.is_stale {
-                results.push(SyncOutcome::SkippedNotStale { connection });
-                continue;
-            }
-
-            let id_or_name = connection.id().to_string();
-            match self
-                .sync_connection_internal(connection.clone(), &id_or_name, options)
-                .await
-            {
-                Ok(outcome) => results.push(outcome),
-                Err(err) => results.push(SyncOutcome::Failed {
-                    connection,
-                    error: format!("{err:#}"),
-                }),
+            if check.is_stale {
+                results.push(None);
+                stale.push(connection);
+            } else {
+                results.push(Some(SyncOutcome::SkippedNotStale { connection }));
             }
         }
 
+        // Slot synced outcomes back into their original positions.
+        let mut synced = self.sync_many(stale, options).await.into_iter();
+        let results = results
+            .into_iter()
+            .filter_map(|slot| slot.or_else(|| synced.next()))
+            .collect();
+
         self.auto_committer.maybe_commit("sync all");
 
         Ok(results)
     }
 
+    /// Sync `connections` with bounded concurrency. Outcomes are returned in
+    /// the same order as `connections`.
+    async fn sync_many(
+        &self,
+        connections: Vec<Connection>,
+        options: &SyncOptions,
+    ) -> Vec<SyncOutcome> {
+        let limits: HashMap<&str, Semaphore> = self
+            .concurrency
+            .per_synchronizer
+            .iter()
+            .map(|(name, limit)| (name.as_str(), Semaphore::new((*limit).max(1))))
+            .collect();
+
+        stream::iter(connections)
+            .map(|connection| {
+                let limits = &limits;
+                async move {
+                    let _permit = match limits.get(connection.config.synchronizer.as_str()) {
+                        Some(semaphore) => Some(semaphore.acquire().await),
+                        None => None,
+                    };
+                    let id_or_name = connection.id().to_string();
+                    match self
+                        .sync_connection_internal(connection.clone(), &id_or_name, options)
+                        .await
+                    {
+                        Ok(outcome) => outcome,
+                        Err(err) => SyncOutcome::Failed {
+                            connection,
+                            error: format!("{err:#}"),
+                        },
+                    }
+                }
+            })
+            .buffered(self.concurrency.max.max(1))
+            .collect()
+            .await
+    }
+
     /// Refresh prices only (no balance sync), for all accounts in storage.
     pub async fn sync_prices_all(&self, force: bool) -> Result<PriceRefreshResult> {
         let date = self.clock.today();
@@ -418,9 +500,48 @@ impl SyncService {
         Ok(connection)
     }
 
+    /// Run a connection's synchronizer and report what saving its result
+    /// would change, without writing to storage or fetching prices.
+    pub async fn preview_connection_with_options(
+        &self,
+        id_or_name: &str,
+        options: &SyncOptions,
+    ) -> Result<SyncPreview> {
+        let mut connection = find_connection(self.storage.as_ref(), id_or_name)
+            .await?
+            .context(format!("Connection not found: {id_or_name}"))?;
+        if connection.config.synchronizer == "manual" {
+            anyhow::bail!("Connection {id_or_name} is manual and has nothing to sync");
+        }
+
+        let mut synchronizer = self
+            .factory
+            .create(&connection, self.storage.as_ref())
+            .await?;
+        let _interactive_guard = match synchronizer.interactive() {
+            Some(interactive) => {
+                let guard = self.interactive.lock().await;
+                if let Some(SyncOutcome::AuthRequired { error, .. }) = self
+                    .ensure_interactive_auth(&connection, interactive)
+                    .await?
+                {
+                    anyhow::bail!("Authentication required: {error}");
+                }
+                Some(guard)
+            }
+            None => None,
+        };
+
+        let result = synchronizer
+            .sync_with_options(&mut connection, self.storage.as_ref(), options)
+            .await?;
+        result.preview(self.storage.as_ref()).await
+    }
+
+    /// Run a sync and append the attempt to the connection's run journal.
     async fn sync_connection_internal(
         &self,
-        mut connection: Connection,
+        connection: Connection,
         action_label: &str,
         options: &SyncOptions,
     ) -> Result<SyncOutcome> {
@@ -428,31 +549,112 @@ impl SyncService {
             return Ok(SyncOutcome::SkippedManual { connection });
         }
 
+        let connection_id = connection.id().clone();
+        let started_at = self.clock.now();
+        let outcome = self
+            .run_connection_sync(connection, action_label, options)
+            .await;
+        let finished_at = self.clock.now();
+
+        let mut run = SyncRun::new(
+            connection_id,
+            started_at,
+            finished_at,
+            SyncRunOutcome::Failed,
+        );
+        match &outcome {
+            Ok(SyncOutcome::Synced { report }) => {
+                run.outcome = SyncRunOutcome::Synced;
+                run.batch_id = report.saved.batch_id.clone();
+                run.accounts_added = report.saved.accounts_added;
+                run.accounts_changed = report.saved.accounts_changed;
+                run.balances_recorded = report.saved.balances_recorded;
+                run.transactions_added = report.saved.transactions_added;
+                run.transactions_changed = report.saved.transactions_changed;
+                run.prices_refreshed = report.stored_prices + report.refresh.fetched;
+            }
+            Ok(SyncOutcome::AuthRequired { error, .. }) => {
+                run.outcome = SyncRunOutcome::AuthRequired;
+                run.errors = vec![error.clone()];
+            }
+            Ok(SyncOutcome::Failed { error, .. }) => run.errors = vec![error.clone()],
+            Ok(SyncOutcome::SkippedManual { .. } | SyncOutcome::SkippedNotStale { .. }) => {
+                return outcome;
+            }
+            Err(err) => run.errors = vec![format!("{err:#}")],
+        }
+
+        // The journal is diagnostic; never fail a sync because it can't be written.
+        if let Err(err) = self.storage.append_sync_run(&run).await {
+            tracing::warn!(error = %err, "Failed to record sync run");
+        }
+
+        outcome
+    }
+
+    async fn run_connection_sync(
+        &self,
+        mut connection: Connection,
+        action_label: &str,
+        options: &SyncOptions,
+    ) -> Result<SyncOutcome> {
         let mut synchronizer = self
             .factory
             .create(&connection, self.storage.as_ref())
             .await?;
 
-        if let Some(interactive) = synchronizer.interactive() {
-            if let Some(outcome) = self
-                .ensure_interactive_auth(&connection, interactive)
-                .await?
-            {
-                return Ok(outcome);
+        let _interactive_guard = match synchronizer.interactive() {
+            Some(interactive) => {
+                let guard = self.interactive.lock().await;
+                if let Some(outcome) = self
+                    .ensure_interactive_auth(&connection, interactive)
+                    .await?
+                {
+                    return Ok(outcome);
+                }
+                Some(guard)
             }
-        }
+            None => None,
+        };
 
-        let report = self
+        let mut report = self
             .orchestrator
             .sync_with_prices(synchronizer.as_ref(), &mut connection, false, options)
             .await?;
+        if self.reconcile_after_sync {
+            report.reconcile_warnings = self.reconcile_synced_accounts(&report.result).await;
+        }
 
+        let _writes = self.orchestrator.lock_writes().await;
         self.auto_committer
             .maybe_commit(&format!("sync connection {action_label}"));
 
         Ok(SyncOutcome::Synced { report })
     }
 
+    /// Check the newest balance change of each account the sync recorded a
+    /// balance for. Failures are logged rather than failing the sync.
+    async fn reconcile_synced_accounts(&self, result: &SyncResult) -> Vec<(Id, Discrepancy)> {
+        let mut warnings = Vec::new();
+        for (account_id, balances) in &result.balances {
+            if balances.is_empty() {
+                continue;
+            }
+            match reconcile_latest(self.storage.as_ref(), account_id).await {
+                Ok(reconciliation) => warnings.extend(
+                    reconciliation
+                        .discrepancies
+                        .into_iter()
+                        .map(|discrepancy| (account_id.clone(), discrepancy)),
+                ),
+                Err(err) => {
+                    tracing::warn!(account = %account_id, error = %err, "Failed to reconcile account")
+                }
+            }
+        }
+        warnings
+    }
+
     async fn ensure_interactive_auth(
         &self,
         connection: &Connection,
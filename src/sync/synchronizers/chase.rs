@@ -31,6 +31,9 @@ use crate::sync::{
     TransactionSyncMode,
 };
 
+/// How long a captured Chase session is expected to stay valid.
+const SESSION_LIFETIME: chrono::Duration = chrono::Duration::days(7);
+
 /// Chase synchronizer using API-based data fetching.
 pub struct ChaseSynchronizer {
     connection_id: Id,
@@ -673,17 +676,21 @@ impl InteractiveAuth for ChaseSynchronizer {
                     return Ok(AuthStatus::Missing);
                 }
 
-                if let Some(captured_at) = session.captured_at {
-                    let age_secs = Utc::now().timestamp() - captured_at;
-                    if age_secs > 7 * 24 * 60 * 60 {
-                        return Ok(AuthStatus::Expired {
-                            reason: format!("Session is {} hours old", age_secs / 3600),
-                        });
-                    }
+                // Sessions saved before expiry was recorded get the default lifetime.
+                let session = match session.expires_at {
+                    Some(_) => session,
+                    None => session.with_lifetime(SESSION_LIFETIME),
+                };
+                let now = Utc::now().timestamp();
+                if session.is_expired(now) {
+                    let age_hours = session.captured_at.map_or(0, |at| (now - at) / 3600);
+                    return Ok(AuthStatus::Expired {
+                        reason: format!("Session is {age_hours} hours old"),
+                    });
                 }
 
                 // Probe the Chase API to verify the session is actually valid.
-                // Sessions can be revoked server-side before the 7-day age limit.
+                // Sessions can be revoked server-side before they expire.
                 match ChaseClient::new(session) {
                     Ok(client) => match client.test_auth().await {
                         Ok(()) => Ok(AuthStatus::Valid),
@@ -987,6 +994,7 @@ fn chase_activity_to_transaction(
             status,
             synchronizer_data: Value::Object(synchronizer_data),
             standardized_metadata: None,
+            sync_batch: None,
         }
         .backfill_standardized_metadata(),
     )
@@ -1612,8 +1620,10 @@ async fn session_from_page(page: &chromiumoxide::Page) -> Result<SessionData> {
         cookies: cookie_map,
         cookie_jar,
         captured_at: Some(Utc::now().timestamp()),
+        expires_at: None,
         data: HashMap::new(),
-    })
+    }
+    .with_lifetime(SESSION_LIFETIME))
 }
 
 async fn wait_for_valid_api_session(page: &chromiumoxide::Page) -> Result<SessionData> {
@@ -0,0 +1,371 @@
+//! External-process synchronizer.
+//!
+//! Runs the executable named by `synchronizer = "exec:/path/to/plugin"` and
+//! talks to it with the protocol in [`crate::sync::plugin`].
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use secrecy::ExposeSecret;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+
+use crate::credentials::CredentialStore;
+use crate::market_data::{AssetId, PriceKind, PricePoint};
+use crate::models::{
+    Account, AssetBalance, Connection, ConnectionStatus, Id, LastSync, SyncStatus, Transaction,
+};
+use crate::storage::Storage;
+use crate::sync::plugin::{
+    HostMessage, PluginAccount, PluginConnection, PluginMessage, PluginSyncOptions, SyncRequest,
+    SyncResponse, PROTOCOL_VERSION,
+};
+use crate::sync::{SyncOptions, SyncResult, SyncedAssetBalance, Synchronizer};
+
+/// Prefix of `synchronizer` values handled by [`ExecSynchronizer`].
+pub const EXEC_PREFIX: &str = "exec:";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+enum Credentials {
+    None,
+    Store(Box<dyn CredentialStore>),
+    Static(HashMap<String, String>),
+}
+
+/// Synchronizer backed by an external plugin executable.
+pub struct ExecSynchronizer {
+    command: PathBuf,
+    name: String,
+    credentials: Credentials,
+    timeout: Duration,
+}
+
+impl ExecSynchronizer {
+    pub fn new(command: PathBuf) -> Self {
+        let name = format!("{EXEC_PREFIX}{}", command.display());
+        Self {
+            command,
+            name,
+            credentials: Credentials::None,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Answer credential requests from the connection's credential store.
+    pub fn with_credential_store(mut self, store: Box<dyn CredentialStore>) -> Self {
+        self.credentials = Credentials::Store(store);
+        self
+    }
+
+    /// Answer credential requests from a fixed map (useful for tests).
+    pub fn with_static_credentials(mut self, credentials: HashMap<String, String>) -> Self {
+        self.credentials = Credentials::Static(credentials);
+        self
+    }
+
+    /// Kill the plugin if a sync takes longer than `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Create a synchronizer from an `exec:` connection. Relative plugin paths
+    /// are resolved against `data_dir` when one is given.
+    pub async fn from_connection<S: Storage + ?Sized>(
+        connection: &Connection,
+        storage: &S,
+        data_dir: Option<&Path>,
+    ) -> Result<Self> {
+        let raw = connection
+            .config
+            .synchronizer
+            .strip_prefix(EXEC_PREFIX)
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .with_context(|| {
+                format!(
+                    "Invalid exec synchronizer {:?}; expected exec:/path/to/plugin",
+                    connection.config.synchronizer
+                )
+            })?;
+        let mut command = PathBuf::from(raw);
+        if command.is_relative() {
+            if let Some(dir) = data_dir {
+                command = dir.join(command);
+            }
+        }
+
+        let mut synchronizer = Self::new(command);
+        if let Some(store) = storage.get_credential_store(connection.id())? {
+            synchronizer = synchronizer.with_credential_store(store);
+        }
+        Ok(synchronizer)
+    }
+
+    async fn credential(&self, key: &str) -> Result<Option<String>> {
+        match &self.credentials {
+            Credentials::None => Ok(None),
+            Credentials::Store(store) => Ok(store
+                .get(key)
+                .await?
+                .map(|secret| secret.expose_secret().to_string())),
+            Credentials::Static(values) => Ok(values.get(key).cloned()),
+        }
+    }
+
+    async fn send(stdin: &mut ChildStdin, message: &HostMessage) -> Result<()> {
+        let mut line = serde_json::to_string(message).context("Failed to encode plugin message")?;
+        line.push('\n');
+        stdin
+            .write_all(line.as_bytes())
+            .await
+            .context("Failed to write to plugin stdin")?;
+        stdin.flush().await?;
+        Ok(())
+    }
+
+    /// Drive one protocol exchange and return the plugin's result.
+    async fn exchange(&self, child: &mut Child, request: &SyncRequest) -> Result<SyncResponse> {
+        let mut stdin = child.stdin.take().context("Plugin stdin unavailable")?;
+        let stdout = child.stdout.take().context("Plugin stdout unavailable")?;
+        let mut lines = BufReader::new(stdout).lines();
+
+        Self::send(&mut stdin, &HostMessage::Sync(request.clone())).await?;
+
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message: PluginMessage = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid message from plugin: {line}"))?;
+            match message {
+                PluginMessage::CredentialRequest { key } => {
+                    let value = self.credential(&key).await?;
+                    Self::send(&mut stdin, &HostMessage::Credential { key, value }).await?;
+                }
+                PluginMessage::Log { level, message } => match level.as_deref() {
+                    Some("error") => tracing::error!(plugin = %self.name, "{message}"),
+                    Some("warn") => tracing::warn!(plugin = %self.name, "{message}"),
+                    Some("debug") => tracing::debug!(plugin = %self.name, "{message}"),
+                    _ => tracing::info!(plugin = %self.name, "{message}"),
+                },
+                PluginMessage::Error { message } => bail!("Plugin reported an error: {message}"),
+                PluginMessage::Result(response) => return Ok(response),
+            }
+        }
+
+        bail!("Plugin exited without sending a result")
+    }
+
+    async fn run(&self, request: &SyncRequest) -> Result<SyncResponse> {
+        let mut child = Command::new(&self.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start plugin {}", self.command.display()))?;
+
+        // The wait is under the timeout too: a plugin may close stdout and
+        // keep running.
+        let finish = async {
+            let response = self.exchange(&mut child, request).await?;
+            let status = child.wait().await?;
+            if !status.success() {
+                bail!("Plugin {} exited with {status}", self.command.display());
+            }
+            Ok(response)
+        };
+        let response = match tokio::time::timeout(self.timeout, finish).await {
+            Ok(response) => response?,
+            Err(_) => bail!(
+                "Plugin {} timed out after {}s",
+                self.command.display(),
+                self.timeout.as_secs()
+            ),
+        };
+
+        if response.protocol_version != PROTOCOL_VERSION {
+            bail!(
+                "Plugin speaks protocol version {}, keepbook supports {PROTOCOL_VERSION}",
+                response.protocol_version
+            );
+        }
+        Ok(response)
+    }
+
+    fn account_id(connection: &Connection, plugin_id: &str) -> Id {
+        Id::from_external(&format!("exec:{}:{plugin_id}", connection.id()))
+    }
+
+    async fn sync_internal<S: Storage + ?Sized>(
+        &self,
+        connection: &mut Connection,
+        storage: &S,
+        options: &SyncOptions,
+    ) -> Result<SyncResult> {
+        let existing: HashMap<Id, Account> = storage
+            .list_accounts()
+            .await?
+            .into_iter()
+            .filter(|a| a.connection_id == *connection.id())
+            .map(|a| (a.id.clone(), a))
+            .collect();
+
+        // Keepbook stores the plugin's account id next to its opaque data so
+        // it can hand both back on the next run.
+        let known_accounts = existing
+            .values()
+            .filter_map(|account| {
+                let data = &account.synchronizer_data;
+                Some(PluginAccount {
+                    id: data.get("plugin_account_id")?.as_str()?.to_string(),
+                    name: account.name.clone(),
+                    tags: account.tags.clone(),
+                    active: account.active,
+                    synchronizer_data: data.get("data").cloned().unwrap_or_default(),
+                })
+            })
+            .collect();
+
+        let request = SyncRequest {
+            protocol_version: PROTOCOL_VERSION,
+            connection: PluginConnection {
+                id: connection.id().to_string(),
+                name: connection.config.name.clone(),
+                last_sync: connection.state.last_sync.clone(),
+                synchronizer_data: connection.state.synchronizer_data.clone(),
+            },
+            accounts: known_accounts,
+            options: PluginSyncOptions::from(options),
+        };
+        let response = self.run(&request).await?;
+
+        let mut accounts = Vec::new();
+        let mut ids_by_plugin_id: HashMap<String, Id> = HashMap::new();
+        for plugin_account in response.accounts {
+            let id = Self::account_id(connection, &plugin_account.id);
+            if ids_by_plugin_id
+                .insert(plugin_account.id.clone(), id.clone())
+                .is_some()
+            {
+                bail!("Plugin returned account {:?} twice", plugin_account.id);
+            }
+            let created_at = existing
+                .get(&id)
+                .map(|a| a.created_at)
+                .unwrap_or_else(Utc::now);
+            accounts.push(Account {
+                id,
+                name: plugin_account.name,
+                connection_id: connection.id().clone(),
+                tags: plugin_account.tags,
+                created_at,
+                active: plugin_account.active,
+                synchronizer_data: serde_json::json!({
+                    "plugin_account_id": plugin_account.id,
+                    "data": plugin_account.synchronizer_data,
+                }),
+            });
+        }
+
+        let resolve = |plugin_id: &str| -> Result<Id> {
+            ids_by_plugin_id
+                .get(plugin_id)
+                .cloned()
+                .with_context(|| format!("Plugin referenced unknown account {plugin_id:?}"))
+        };
+
+        let now = Utc::now();
+        let mut balances = Vec::new();
+        for entry in response.balances {
+            let account_id = resolve(&entry.account_id)?;
+            let synced = entry
+                .balances
+                .into_iter()
+                .map(|balance| {
+                    let price = balance.price.map(|price| PricePoint {
+                        asset_id: AssetId::from_asset(&balance.asset),
+                        as_of_date: price.as_of_date.unwrap_or_else(|| now.date_naive()),
+                        timestamp: price.timestamp.unwrap_or(now),
+                        price: price.price,
+                        quote_currency: price.quote_currency,
+                        kind: PriceKind::Quote,
+                        source: self.name.clone(),
+                    });
+                    let mut asset_balance = AssetBalance::new(balance.asset, balance.amount);
+                    asset_balance.cost_basis = balance.cost_basis;
+                    let synced = SyncedAssetBalance::new(asset_balance);
+                    match price {
+                        Some(price) => synced.with_price(price),
+                        None => synced,
+                    }
+                })
+                .collect();
+            balances.push((account_id, synced));
+        }
+
+        let mut transactions = Vec::new();
+        for entry in response.transactions {
+            let account_id = resolve(&entry.account_id)?;
+            let txns = entry
+                .transactions
+                .into_iter()
+                .map(|txn| {
+                    let mut transaction = Transaction::new(txn.amount, txn.asset, txn.description)
+                        .with_id(Id::from_external(&format!("exec:{account_id}:{}", txn.id)))
+                        .with_timestamp(txn.timestamp)
+                        .with_status(txn.status)
+                        .with_synchronizer_data(txn.synchronizer_data);
+                    if let Some(metadata) = txn.standardized_metadata {
+                        transaction = transaction.with_standardized_metadata(metadata);
+                    }
+                    transaction
+                })
+                .collect();
+            transactions.push((account_id, txns));
+        }
+
+        connection.state.account_ids = accounts.iter().map(|a| a.id.clone()).collect();
+        connection.state.synchronizer_data = response.synchronizer_data;
+        connection.state.last_sync = Some(LastSync {
+            at: Utc::now(),
+            status: SyncStatus::Success,
+            error: None,
+        });
+        connection.state.status = ConnectionStatus::Active;
+
+        Ok(SyncResult {
+            connection: connection.clone(),
+            accounts,
+            balances,
+            transactions,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Synchronizer for ExecSynchronizer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn sync(&self, connection: &mut Connection, storage: &dyn Storage) -> Result<SyncResult> {
+        self.sync_internal(connection, storage, &SyncOptions::default())
+            .await
+    }
+
+    async fn sync_with_options(
+        &self,
+        connection: &mut Connection,
+        storage: &dyn Storage,
+        options: &SyncOptions,
+    ) -> Result<SyncResult> {
+        self.sync_internal(connection, storage, options).await
+    }
+}
@@ -2,10 +2,14 @@
 
 mod chase;
 mod coinbase;
+mod exec;
 mod plaid;
 mod schwab;
+mod simplefin;
 
 pub use chase::ChaseSynchronizer;
 pub use coinbase::CoinbaseSynchronizer;
+pub use exec::{ExecSynchronizer, EXEC_PREFIX};
 pub use plaid::PlaidSynchronizer;
 pub use schwab::SchwabSynchronizer;
+pub use simplefin::{claim_setup_token, SimpleFinSynchronizer};
@@ -21,7 +21,7 @@ use crate::models::{
 use crate::storage::Storage;
 use crate::sync::schwab::{
     parse_banking_transactions_rows, parse_brokerage_transactions_rows, Position, SchwabClient,
-    TransactionHistoryTimeFrame,
+    TransactionHistoryTimeFrame, SESSION_LIFETIME,
 };
 use crate::sync::{
     AuthStatus, InteractiveAuth, SyncOptions, SyncResult, SyncedAssetBalance, Synchronizer,
@@ -447,14 +447,17 @@ impl InteractiveAuth for SchwabSynchronizer {
                     return Ok(AuthStatus::Missing);
                 }
 
-                // Check age - sessions older than 24 hours are likely expired
-                if let Some(captured_at) = session.captured_at {
-                    let age_secs = Utc::now().timestamp() - captured_at;
-                    if age_secs > 24 * 60 * 60 {
-                        return Ok(AuthStatus::Expired {
-                            reason: format!("Session is {} hours old", age_secs / 3600),
-                        });
-                    }
+                // Sessions saved before expiry was recorded get the default lifetime.
+                let session = match session.expires_at {
+                    Some(_) => session,
+                    None => session.with_lifetime(SESSION_LIFETIME),
+                };
+                let now = Utc::now().timestamp();
+                if session.is_expired(now) {
+                    let age_hours = session.captured_at.map_or(0, |at| (now - at) / 3600);
+                    return Ok(AuthStatus::Expired {
+                        reason: format!("Session is {age_hours} hours old"),
+                    });
                 }
 
                 // Try a simple API call to verify
@@ -574,10 +577,12 @@ impl InteractiveAuth for SchwabSynchronizer {
             cookies: cookie_map,
             cookie_jar: Vec::new(),
             captured_at: Some(Utc::now().timestamp()),
+            expires_at: None,
             data: api_base
                 .map(|base| [("api_base".to_string(), base)].into())
                 .unwrap_or_default(),
-        };
+        }
+        .with_lifetime(SESSION_LIFETIME);
 
         // Save to cache
         self.session_cache.set(&self.session_key(), &session)?;
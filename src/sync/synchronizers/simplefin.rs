@@ -0,0 +1,526 @@
+//! SimpleFIN Bridge synchronizer.
+//!
+//! Credentials hold either an `access-url` or a one-time `setup-token`. A
+//! setup token is claimed on first use and the resulting access URL is
+//! written back through the credential store. Each account remembers the
+//! newest posted transaction it has seen, so later syncs only ask the bridge
+//! for recent history.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use reqwest::{Client, Url};
+use rust_decimal::Decimal;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+use crate::credentials::CredentialStore;
+use crate::market_data::{AssetId, PriceKind, PricePoint};
+use crate::models::{
+    is_iso_currency_code, Account, Asset, AssetBalance, Connection, ConnectionStatus, Id, LastSync,
+    SyncStatus, Transaction, TransactionStatus,
+};
+use crate::storage::Storage;
+use crate::sync::{SyncOptions, SyncResult, SyncedAssetBalance, Synchronizer, TransactionSyncMode};
+
+const KEY_ACCESS_URL: [&str; 2] = ["access-url", "access_url"];
+const KEY_SETUP_TOKEN: [&str; 2] = ["setup-token", "setup_token"];
+
+/// Transactions can post a few days after the newest one we have seen, so
+/// incremental syncs re-request a short overlap.
+const CURSOR_OVERLAP_DAYS: i64 = 5;
+
+/// SimpleFIN Bridge synchronizer.
+pub struct SimpleFinSynchronizer {
+    access_url: SecretString,
+    client: Client,
+}
+
+impl SimpleFinSynchronizer {
+    /// Create a synchronizer for an already-claimed access URL.
+    pub fn new(access_url: SecretString) -> Self {
+        Self {
+            access_url,
+            client: Client::new(),
+        }
+    }
+
+    /// Load the access URL from a credential store, claiming a setup token if
+    /// no access URL has been stored yet.
+    pub async fn from_credentials(store: &dyn CredentialStore) -> Result<Self> {
+        if let Some(access_url) = get_optional_secret(store, &KEY_ACCESS_URL).await? {
+            return Ok(Self::new(access_url));
+        }
+
+        let setup_token = get_optional_secret(store, &KEY_SETUP_TOKEN)
+            .await?
+            .with_context(|| {
+                format!(
+                    "Missing SimpleFIN credentials (expected one of: {}, {})",
+                    KEY_ACCESS_URL.join(", "),
+                    KEY_SETUP_TOKEN.join(", ")
+                )
+            })?;
+        // Setup tokens are single-use, so refuse to claim one we cannot keep.
+        if !store.supports_write() {
+            anyhow::bail!(
+                "SimpleFIN setup token found but the credential store is read-only; \
+                 claim it manually and store the access URL as {}",
+                KEY_ACCESS_URL[0]
+            );
+        }
+
+        let client = Client::new();
+        let access_url = claim_setup_token(&client, setup_token.expose_secret()).await?;
+        let access_url = SecretString::new(access_url.into());
+        store
+            .set(KEY_ACCESS_URL[0], access_url.clone())
+            .await
+            .context("Failed to store claimed SimpleFIN access URL")?;
+        Ok(Self { access_url, client })
+    }
+
+    /// Create from a Keepbook connection.
+    pub async fn from_connection<S: Storage + ?Sized>(
+        connection: &Connection,
+        storage: &S,
+    ) -> Result<Self> {
+        let credential_store = storage
+            .get_credential_store(connection.id())?
+            .context("No credentials configured for this connection")?;
+        Self::from_credentials(credential_store.as_ref()).await
+    }
+
+    async fn get_accounts(&self, start_date: Option<DateTime<Utc>>) -> Result<SimpleFinResponse> {
+        let mut url = Url::parse(self.access_url.expose_secret().trim())
+            .context("Invalid SimpleFIN access URL")?;
+        let username = url.username().to_string();
+        let password = url.password().map(str::to_string);
+        // reqwest does not send userinfo on its own; move it to a header.
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+        let base = url.as_str().trim_end_matches('/').to_string();
+
+        let mut query = vec![("pending", "1".to_string())];
+        if let Some(start) = start_date {
+            query.push(("start-date", start.timestamp().to_string()));
+        }
+
+        let mut request = self.client.get(format!("{base}/accounts")).query(&query);
+        if !username.is_empty() {
+            request = request.basic_auth(username, password);
+        }
+        let response = request
+            .send()
+            .await
+            .context("SimpleFIN HTTP request failed")?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .context("Failed to read SimpleFIN response body")?;
+        if !status.is_success() {
+            anyhow::bail!("SimpleFIN request failed ({status}): {body}");
+        }
+
+        serde_json::from_str(&body).context("Failed to parse SimpleFIN JSON response")
+    }
+
+    async fn sync_internal<S: Storage + ?Sized>(
+        &self,
+        connection: &mut Connection,
+        storage: &S,
+        options: &SyncOptions,
+    ) -> Result<SyncResult> {
+        let existing_by_id: HashMap<Id, Account> = storage
+            .list_accounts()
+            .await?
+            .into_iter()
+            .filter(|a| a.connection_id == *connection.id())
+            .map(|a| (a.id.clone(), a))
+            .collect();
+
+        // Every known account needs a cursor for an incremental request;
+        // otherwise fetch everything the bridge will give us.
+        let start_date = match options.transactions {
+            TransactionSyncMode::Full => None,
+            TransactionSyncMode::Auto if existing_by_id.is_empty() => None,
+            TransactionSyncMode::Auto => existing_by_id
+                .values()
+                .map(account_cursor)
+                .collect::<Option<Vec<i64>>>()
+                .and_then(|cursors| cursors.into_iter().min())
+                .and_then(|min| DateTime::from_timestamp(min, 0))
+                .map(|at| at - Duration::days(CURSOR_OVERLAP_DAYS)),
+        };
+
+        let response = self.get_accounts(start_date).await?;
+        for error in &response.errors {
+            tracing::warn!(error = %error, "SimpleFIN bridge reported an error");
+        }
+
+        let now = Utc::now();
+        let mut accounts = Vec::new();
+        let mut balances = Vec::new();
+        let mut transactions = Vec::new();
+
+        for sf_account in response.accounts {
+            let account_id = Id::from_external(&format!("simplefin:{}", sf_account.id));
+            let existing = existing_by_id.get(&account_id);
+            let currency = account_currency(&sf_account.currency);
+            if currency.is_none() {
+                tracing::warn!(
+                    simplefin_account_id = %sf_account.id,
+                    unit = %sf_account.currency,
+                    "Skipping balances and transactions of SimpleFIN account in a non-currency unit",
+                );
+            }
+
+            let balance_at = DateTime::from_timestamp(sf_account.balance_date, 0)
+                .filter(|_| sf_account.balance_date > 0)
+                .unwrap_or(now);
+            // Accounts without posted transactions are caught up as of the
+            // balance date.
+            let previous_cursor = existing.and_then(account_cursor);
+            let cursor = sf_account
+                .transactions
+                .iter()
+                .filter(|tx| !tx.pending && tx.posted > 0)
+                .map(|tx| tx.posted)
+                .chain(previous_cursor)
+                .max()
+                .unwrap_or(balance_at.timestamp());
+
+            let mut tags = vec!["simplefin".to_string()];
+            if !sf_account.holdings.is_empty() {
+                tags.push("investment".to_string());
+            }
+
+            accounts.push(Account {
+                id: account_id.clone(),
+                name: sf_account.name.clone(),
+                connection_id: connection.id().clone(),
+                tags,
+                created_at: existing.map(|a| a.created_at).unwrap_or(now),
+                active: true,
+                synchronizer_data: serde_json::json!({
+                    "simplefin_account_id": sf_account.id,
+                    "org": sf_account.org.as_ref().and_then(|org| org.name.clone()),
+                    "cursor": cursor,
+                }),
+            });
+
+            let Some(currency) = currency else {
+                continue;
+            };
+            balances.push((
+                account_id.clone(),
+                account_balances(&sf_account, &currency, balance_at)?,
+            ));
+
+            let txns: Vec<Transaction> = sf_account
+                .transactions
+                .iter()
+                .map(|tx| simplefin_transaction_to_keepbook(tx, &sf_account.id, &currency))
+                .collect();
+            if !txns.is_empty() {
+                transactions.push((account_id, txns));
+            }
+        }
+
+        connection.state.account_ids = accounts.iter().map(|a| a.id.clone()).collect();
+        connection.state.last_sync = Some(LastSync {
+            at: Utc::now(),
+            status: SyncStatus::Success,
+            error: None,
+        });
+        connection.state.status = ConnectionStatus::Active;
+
+        Ok(SyncResult {
+            connection: connection.clone(),
+            accounts,
+            balances,
+            transactions,
+        })
+    }
+
+    /// Sync with storage access for account lookups.
+    pub async fn sync_with_storage<S: Storage + ?Sized>(
+        &self,
+        connection: &mut Connection,
+        storage: &S,
+    ) -> Result<SyncResult> {
+        self.sync_internal(connection, storage, &SyncOptions::default())
+            .await
+    }
+}
+
+/// Exchange a SimpleFIN setup token for an access URL.
+///
+/// The token is the base64-encoded claim URL; POSTing to it returns the access
+/// URL once. Claiming a token a second time fails.
+pub async fn claim_setup_token(client: &Client, setup_token: &str) -> Result<String> {
+    let decoded = STANDARD
+        .decode(setup_token.trim())
+        .context("SimpleFIN setup token is not valid base64")?;
+    let claim_url =
+        String::from_utf8(decoded).context("SimpleFIN setup token is not a valid claim URL")?;
+
+    let response = client
+        .post(claim_url.trim())
+        .header("Content-Length", "0")
+        .send()
+        .await
+        .context("SimpleFIN claim request failed")?;
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .context("Failed to read SimpleFIN claim response")?;
+    if !status.is_success() {
+        anyhow::bail!("SimpleFIN setup token claim failed ({status}): {body}");
+    }
+
+    let access_url = body.trim().to_string();
+    Url::parse(&access_url).context("SimpleFIN claim returned an invalid access URL")?;
+    Ok(access_url)
+}
+
+fn account_cursor(account: &Account) -> Option<i64> {
+    account.synchronizer_data.get("cursor")?.as_i64()
+}
+
+/// The ISO 4217 code of a SimpleFIN currency. Other units (e.g. loyalty
+/// points) are reported as a URL describing the unit and give `None`.
+fn account_currency(raw: &str) -> Option<String> {
+    is_iso_currency_code(raw).then(|| raw.trim().to_ascii_uppercase())
+}
+
+fn parse_decimal(value: &str, what: &str) -> Result<Decimal> {
+    Decimal::from_str(value.trim())
+        .with_context(|| format!("Invalid SimpleFIN {what} amount: {value:?}"))
+}
+
+fn account_balances(
+    account: &SimpleFinAccount,
+    currency: &str,
+    balance_at: DateTime<Utc>,
+) -> Result<Vec<SyncedAssetBalance>> {
+    let balance = parse_decimal(&account.balance, "balance")?;
+    let mut synced = Vec::new();
+    let mut holdings_value = Decimal::ZERO;
+
+    for holding in &account.holdings {
+        let symbol = holding.symbol.trim();
+        if symbol.is_empty() {
+            // Keepbook has no asset to hold it under, so its value stays in
+            // the cash remainder below rather than disappearing.
+            tracing::warn!(
+                simplefin_holding_id = %holding.id,
+                "Counting SimpleFIN holding without a symbol as cash",
+            );
+            continue;
+        }
+        let shares = parse_decimal(&holding.shares, "shares")?;
+        let market_value = parse_decimal(&holding.market_value, "market value")?;
+        holdings_value += market_value;
+
+        let asset = Asset::equity(symbol);
+        let mut asset_balance = AssetBalance::new(asset.clone(), shares.normalize().to_string());
+        asset_balance.cost_basis = holding
+            .cost_basis
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string);
+
+        let mut entry = SyncedAssetBalance::new(asset_balance);
+        let quote_currency = match holding.currency.as_deref() {
+            Some(raw) => account_currency(raw),
+            None => Some(currency.to_string()),
+        };
+        if let Some(quote_currency) = quote_currency.filter(|_| !shares.is_zero()) {
+            entry = entry.with_price(PricePoint {
+                asset_id: AssetId::from_asset(&asset),
+                as_of_date: balance_at.date_naive(),
+                timestamp: balance_at,
+                price: (market_value / shares).round_dp(8).normalize().to_string(),
+                quote_currency,
+                kind: PriceKind::Quote,
+                source: "simplefin".to_string(),
+            });
+        }
+        synced.push(entry);
+    }
+
+    // The account balance includes holdings; whatever is left is cash.
+    let cash = balance - holdings_value;
+    synced.push(SyncedAssetBalance::new(AssetBalance::new(
+        Asset::currency(currency),
+        cash.normalize().to_string(),
+    )));
+    Ok(synced)
+}
+
+fn simplefin_transaction_to_keepbook(
+    tx: &SimpleFinTransaction,
+    account_id: &str,
+    currency: &str,
+) -> Transaction {
+    let timestamp = [tx.transacted_at, Some(tx.posted)]
+        .into_iter()
+        .flatten()
+        .filter(|secs| *secs > 0)
+        .find_map(|secs| DateTime::from_timestamp(secs, 0))
+        .unwrap_or_else(Utc::now);
+
+    Transaction::new(tx.amount.trim(), Asset::currency(currency), &tx.description)
+        .with_id(Id::from_external(&format!(
+            "simplefin:tx:{account_id}:{}",
+            tx.id
+        )))
+        .with_timestamp(timestamp)
+        .with_status(if tx.pending {
+            TransactionStatus::Pending
+        } else {
+            TransactionStatus::Posted
+        })
+        .with_synchronizer_data(serde_json::json!({
+            "simplefin_transaction_id": tx.id,
+            "posted": tx.posted,
+            "pending": tx.pending,
+        }))
+}
+
+#[async_trait::async_trait]
+impl Synchronizer for SimpleFinSynchronizer {
+    fn name(&self) -> &str {
+        "simplefin"
+    }
+
+    async fn sync(&self, connection: &mut Connection, storage: &dyn Storage) -> Result<SyncResult> {
+        self.sync_internal(connection, storage, &SyncOptions::default())
+            .await
+    }
+
+    async fn sync_with_options(
+        &self,
+        connection: &mut Connection,
+        storage: &dyn Storage,
+        options: &SyncOptions,
+    ) -> Result<SyncResult> {
+        self.sync_internal(connection, storage, options).await
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SimpleFinResponse {
+    #[serde(default)]
+    errors: Vec<String>,
+    #[serde(default)]
+    accounts: Vec<SimpleFinAccount>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SimpleFinOrg {
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SimpleFinAccount {
+    id: String,
+    name: String,
+    currency: String,
+    balance: String,
+    #[serde(rename = "balance-date", default)]
+    balance_date: i64,
+    org: Option<SimpleFinOrg>,
+    #[serde(default)]
+    transactions: Vec<SimpleFinTransaction>,
+    #[serde(default)]
+    holdings: Vec<SimpleFinHolding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SimpleFinTransaction {
+    id: String,
+    #[serde(default)]
+    posted: i64,
+    amount: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    transacted_at: Option<i64>,
+    #[serde(default)]
+    pending: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct SimpleFinHolding {
+    id: String,
+    #[serde(default)]
+    symbol: String,
+    shares: String,
+    market_value: String,
+    #[serde(default)]
+    cost_basis: Option<String>,
+    #[serde(default)]
+    currency: Option<String>,
+}
+
+async fn get_optional_secret(
+    store: &dyn CredentialStore,
+    keys: &[&str],
+) -> Result<Option<SecretString>> {
+    for key in keys {
+        if let Some(value) = store.get(key).await? {
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn account_currency_rejects_custom_units() {
+        assert_eq!(account_currency("usd").as_deref(), Some("USD"));
+        assert_eq!(account_currency("https://example.com/points"), None);
+        assert_eq!(account_currency("PTS"), None);
+    }
+
+    #[test]
+    fn holdings_split_balance_into_positions_and_cash() -> Result<()> {
+        let account: SimpleFinAccount = serde_json::from_value(serde_json::json!({
+            "id": "brk",
+            "name": "Brokerage",
+            "currency": "USD",
+            "balance": "1100.00",
+            "balance-date": 1767225600,
+            "holdings": [
+                { "id": "h1", "symbol": "VTI", "shares": "4", "market_value": "1000.00",
+                  "cost_basis": "800.00" },
+                { "id": "h2", "symbol": "", "shares": "1", "market_value": "5" }
+            ]
+        }))?;
+        let at = DateTime::from_timestamp(account.balance_date, 0).unwrap();
+
+        let balances = account_balances(&account, "USD", at)?;
+
+        assert_eq!(balances.len(), 2);
+        let vti = &balances[0];
+        assert_eq!(vti.asset_balance.asset, Asset::equity("VTI"));
+        assert_eq!(vti.asset_balance.amount, "4");
+        assert_eq!(vti.asset_balance.cost_basis.as_deref(), Some("800.00"));
+        assert_eq!(vti.price.as_ref().map(|p| p.price.as_str()), Some("250"));
+        // The symbol-less holding stays in cash so the total still matches.
+        assert_eq!(balances[1].asset_balance.amount, "100");
+        Ok(())
+    }
+}
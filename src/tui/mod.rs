@@ -5,7 +5,7 @@ use std::io::{self, BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, NaiveDate, Utc};
@@ -27,11 +27,18 @@ use serde::{Deserialize, Serialize};
 use crate::app::{self, HistoryPoint, TransactionOutput};
 use crate::config::ResolvedConfig;
 use crate::format::{currency_symbol, format_base_currency_display};
-use crate::storage::Storage;
+use crate::storage::{
+    prepare_for_write, storage_event_channel, watch_data_dir, DataDirLock, LockMode, Storage,
+    StorageEvent, DEFAULT_LOCK_TIMEOUT,
+};
+use tokio::sync::broadcast::{self, error::TryRecvError};
 
 const LOAD_START_DATE: &str = "1900-01-01";
 const LOAD_END_DATE: &str = "9999-12-31";
 const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// Quiet period after a data-dir change before reloading, so a sync writing
+/// many files reloads once.
+const DATA_CHANGE_DEBOUNCE: Duration = Duration::from_millis(500);
 const CATEGORY_RULES_FILE: &str = "transaction_category_rules.jsonl";
 const OPENAI_REGEX_SUGGESTION_MODEL_ENV: &str = "KEEPBOOK_REGEX_LLM_MODEL";
 const OPENAI_REGEX_SUGGESTION_MODEL_DEFAULT: &str = "gpt-4o-mini";
@@ -410,6 +417,9 @@ impl NetWorthInterval {
 pub struct TuiOptions {
     pub start_view: TuiView,
     pub net_worth_interval: NetWorthInterval,
+    /// How long each load or write waits for other keepbook processes to
+    /// release the data dir. The lock is only held for that load or write.
+    pub lock_timeout: Duration,
 }
 
 impl Default for TuiOptions {
@@ -417,6 +427,7 @@ impl Default for TuiOptions {
         Self {
             start_view: TuiView::Transactions,
             net_worth_interval: NetWorthInterval::Daily,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
         }
     }
 }
@@ -546,6 +557,41 @@ struct RegexModalState {
     used_llm_suggestion: bool,
 }
 
+/// Views to reload after data-dir changes, once they settle.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct PendingReload {
+    transactions: bool,
+    net_worth: bool,
+}
+
+impl PendingReload {
+    fn add(&mut self, event: &StorageEvent) {
+        match event {
+            StorageEvent::TransactionsAppended { .. }
+            | StorageEvent::AnnotationsPatched { .. }
+            | StorageEvent::AccountHistoryRewritten { .. }
+            | StorageEvent::AccountSaved { .. }
+            | StorageEvent::AccountDeleted { .. }
+            | StorageEvent::ConnectionSaved { .. }
+            | StorageEvent::ConnectionDeleted { .. } => {
+                self.transactions = true;
+                self.net_worth = true;
+            }
+            StorageEvent::BalanceAppended { .. } => self.net_worth = true,
+            StorageEvent::FileChanged { path } if path == Path::new(CATEGORY_RULES_FILE) => {
+                self.transactions = true;
+            }
+            // Prices, FX rates, ...
+            StorageEvent::FileChanged { .. } => self.net_worth = true,
+            StorageEvent::ProposedEditsChanged | StorageEvent::SyncRecorded { .. } => {}
+        }
+    }
+
+    fn any(&self) -> bool {
+        self.transactions || self.net_worth
+    }
+}
+
 #[derive(Debug, Clone)]
 enum ModalState {
     Category(CategoryModalState),
@@ -570,6 +616,7 @@ struct AppState {
     net_worth_last_refresh_utc: DateTime<Utc>,
     status_message: Option<String>,
     modal: Option<ModalState>,
+    lock_timeout: Duration,
 }
 
 impl AppState {
@@ -599,6 +646,7 @@ impl AppState {
             net_worth_last_refresh_utc: now,
             status_message: None,
             modal: None,
+            lock_timeout: options.lock_timeout,
         };
         state.recompute_visible_transactions();
         state.recompute_visible_net_worth();
@@ -663,9 +711,12 @@ pub async fn run_tui(
     options: TuiOptions,
 ) -> Result<()> {
     let include_ignored = false;
+    let lock =
+        DataDirLock::acquire(&config.data_dir, LockMode::Shared, options.lock_timeout).await?;
     let transactions = load_transactions(storage.as_ref(), config, include_ignored).await?;
     let rules_path = category_rules_path(&config.data_dir);
     let (category_matcher, rule_warning) = load_transaction_category_rules(&rules_path)?;
+    drop(lock);
     let mut app_state = AppState::new(
         transactions,
         category_matcher,
@@ -683,6 +734,17 @@ pub async fn run_tui(
     let mut net_worth_table_state = TableState::default();
     net_worth_table_state.select(Some(0));
 
+    let data_events = storage_event_channel();
+    let _watcher = match watch_data_dir(&config.data_dir, data_events.clone()) {
+        Ok(watcher) => Some(watcher),
+        Err(error) => {
+            app_state.status_message = Some(format!(
+                "Not watching for data changes (press r to reload): {error}"
+            ));
+            None
+        }
+    };
+
     let mut terminal = enter_terminal()?;
     let result = run_event_loop(
         &mut terminal,
@@ -691,17 +753,33 @@ pub async fn run_tui(
         &mut net_worth_table_state,
         storage,
         config,
+        data_events.subscribe(),
     )
     .await;
     leave_terminal(&mut terminal)?;
     result
 }
 
+/// Take the data-dir lock for a write, refusing data dirs written by a newer
+/// keepbook format.
+async fn lock_for_write(app_state: &AppState, config: &ResolvedConfig) -> Result<DataDirLock> {
+    let lock = DataDirLock::acquire(
+        &config.data_dir,
+        LockMode::Exclusive,
+        app_state.lock_timeout,
+    )
+    .await?;
+    prepare_for_write(&config.data_dir)?;
+    Ok(lock)
+}
+
 async fn refresh_transactions_and_rules(
     app_state: &mut AppState,
     storage: &dyn Storage,
     config: &ResolvedConfig,
 ) -> Result<()> {
+    let _lock =
+        DataDirLock::acquire(&config.data_dir, LockMode::Shared, app_state.lock_timeout).await?;
     app_state.all_transactions =
         load_transactions(storage, config, app_state.include_ignored).await?;
     app_state.transaction_last_refresh_utc = Utc::now();
@@ -715,6 +793,28 @@ async fn refresh_transactions_and_rules(
     Ok(())
 }
 
+/// Reload what changed on disk, keeping the selected transaction selected.
+async fn reload_after_data_change(
+    app_state: &mut AppState,
+    tx_table_state: &mut TableState,
+    storage: Arc<dyn Storage>,
+    config: &ResolvedConfig,
+    reload: PendingReload,
+) -> Result<()> {
+    if reload.transactions {
+        let selected = selected_transaction(app_state, tx_table_state)
+            .map(|tx| (tx.account_id.clone(), tx.id.clone()));
+        refresh_transactions_and_rules(app_state, storage.as_ref(), config).await?;
+        if let Some((account_id, transaction_id)) = selected {
+            select_transaction_by_id(app_state, tx_table_state, &account_id, &transaction_id);
+        }
+    }
+    if reload.net_worth && app_state.net_worth_loaded {
+        refresh_net_worth(app_state, storage, config).await;
+    }
+    Ok(())
+}
+
 fn selected_transaction<'a>(
     app_state: &'a AppState,
     tx_table_state: &TableState,
@@ -993,6 +1093,7 @@ async fn handle_category_modal_key(
                     } else {
                         Some(chosen_category)
                     };
+                    let lock = lock_for_write(app_state, config).await?;
                     app::set_transaction_annotation(
                         storage.as_ref(),
                         config,
@@ -1013,6 +1114,7 @@ async fn handle_category_modal_key(
                         false,
                     )
                     .await?;
+                    drop(lock);
                     refresh_transactions_and_rules(app_state, storage.as_ref(), config).await?;
                     select_transaction_by_id(
                         app_state,
@@ -1082,6 +1184,7 @@ async fn handle_category_modal_key(
 async fn handle_regex_modal_key(
     app_state: &mut AppState,
     tx_table_state: &mut TableState,
+    config: &ResolvedConfig,
     mut modal: RegexModalState,
     key: KeyCode,
 ) -> Result<Option<ModalState>> {
@@ -1109,9 +1212,11 @@ async fn handle_regex_modal_key(
                 status: None,
                 amount: None,
             };
+            let lock = lock_for_write(app_state, config).await?;
             append_transaction_category_rule(&app_state.category_rules_path, &rule)?;
             let (matcher, warning) =
                 load_transaction_category_rules(&app_state.category_rules_path)?;
+            drop(lock);
             app_state.category_matcher = matcher;
             if let Some(message) = warning {
                 app_state.status_message = Some(message);
@@ -1158,7 +1263,7 @@ async fn handle_modal_key(
                 .await?
         }
         ModalState::Regex(modal) => {
-            handle_regex_modal_key(app_state, tx_table_state, modal, key).await?
+            handle_regex_modal_key(app_state, tx_table_state, config, modal, key).await?
         }
     };
     app_state.modal = next_modal;
@@ -1172,8 +1277,36 @@ async fn run_event_loop(
     net_worth_table_state: &mut TableState,
     storage: Arc<dyn Storage>,
     config: &ResolvedConfig,
+    mut data_events: broadcast::Receiver<StorageEvent>,
 ) -> Result<()> {
+    let mut pending_reload = PendingReload::default();
+    let mut last_data_event = Instant::now();
     loop {
+        loop {
+            match data_events.try_recv() {
+                Ok(event) => pending_reload.add(&event),
+                Err(TryRecvError::Lagged(_)) => {
+                    pending_reload.transactions = true;
+                    pending_reload.net_worth = true;
+                }
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            }
+            last_data_event = Instant::now();
+        }
+        if pending_reload.any()
+            && app_state.modal.is_none()
+            && last_data_event.elapsed() >= DATA_CHANGE_DEBOUNCE
+        {
+            reload_after_data_change(
+                app_state,
+                tx_table_state,
+                storage.clone(),
+                config,
+                std::mem::take(&mut pending_reload),
+            )
+            .await?;
+        }
+
         let active_table_state =
             active_table_state_mut(app_state.active_view, tx_table_state, net_worth_table_state);
         clamp_selection(app_state.visible_row_count(), active_table_state);
@@ -1715,7 +1848,9 @@ async fn load_net_worth(
     storage: Arc<dyn Storage>,
     config: &ResolvedConfig,
     interval: NetWorthInterval,
+    lock_timeout: Duration,
 ) -> Result<Vec<HistoryPoint>> {
+    let _lock = DataDirLock::acquire(&config.data_dir, LockMode::Shared, lock_timeout).await?;
     let output = app::portfolio_history(
         storage,
         config,
@@ -1735,7 +1870,13 @@ async fn refresh_net_worth(
     config: &ResolvedConfig,
 ) {
     let refreshed_at = Utc::now();
-    let result = load_net_worth(storage, config, app_state.net_worth_interval).await;
+    let result = load_net_worth(
+        storage,
+        config,
+        app_state.net_worth_interval,
+        app_state.lock_timeout,
+    )
+    .await;
     app_state.net_worth_loaded = true;
     app_state.net_worth_last_refresh_utc = refreshed_at;
 
@@ -2166,6 +2307,39 @@ mod tests {
     use serde_json::json;
     use std::path::PathBuf;
 
+    #[test]
+    fn pending_reload_targets_affected_views() {
+        let account_id = crate::models::Id::from_string("acct-1");
+        let mut reload = PendingReload::default();
+        reload.add(&StorageEvent::SyncRecorded {
+            connection_id: account_id.clone(),
+        });
+        assert!(!reload.any());
+
+        reload.add(&StorageEvent::BalanceAppended {
+            account_id: account_id.clone(),
+        });
+        assert_eq!(
+            reload,
+            PendingReload {
+                transactions: false,
+                net_worth: true,
+            }
+        );
+
+        let mut reload = PendingReload::default();
+        reload.add(&StorageEvent::FileChanged {
+            path: PathBuf::from(CATEGORY_RULES_FILE),
+        });
+        assert_eq!(
+            reload,
+            PendingReload {
+                transactions: true,
+                net_worth: false,
+            }
+        );
+    }
+
     fn tx(id: &str, timestamp: &str, amount: &str) -> TransactionOutput {
         TransactionOutput {
             id: id.to_string(),
@@ -2196,9 +2370,60 @@ mod tests {
             ignore: IgnoreConfig::default(),
             ai: crate::config::AiConfig::default(),
             git: GitConfig::default(),
+            profiles: Default::default(),
         }
     }
 
+    #[tokio::test]
+    async fn reload_waits_for_a_writer_holding_the_data_dir() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut config = test_config();
+        config.data_dir = dir.path().to_path_buf();
+        let storage = crate::storage::MemoryStorage::new();
+        let mut state = AppState::new(
+            Vec::new(),
+            TransactionCategoryMatcher::default(),
+            dir.path().join(CATEGORY_RULES_FILE),
+            false,
+            TuiOptions {
+                lock_timeout: Duration::ZERO,
+                ..TuiOptions::default()
+            },
+        );
+
+        let writer = DataDirLock::try_acquire(dir.path(), LockMode::Exclusive)?.expect("lock");
+        let err = refresh_transactions_and_rules(&mut state, &storage, &config)
+            .await
+            .unwrap_err();
+        assert!(err.is::<crate::storage::DataDirBusy>(), "{err:#}");
+
+        drop(writer);
+        refresh_transactions_and_rules(&mut state, &storage, &config).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn writes_refuse_a_newer_data_dir_format() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut config = test_config();
+        config.data_dir = dir.path().to_path_buf();
+        let state = AppState::new(
+            Vec::new(),
+            TransactionCategoryMatcher::default(),
+            dir.path().join(CATEGORY_RULES_FILE),
+            false,
+            TuiOptions::default(),
+        );
+
+        crate::storage::write_format_version(
+            dir.path(),
+            crate::storage::CURRENT_FORMAT_VERSION + 1,
+        )?;
+        let err = lock_for_write(&state, &config).await.unwrap_err();
+        assert!(err.is::<crate::storage::NewerFormatVersion>(), "{err:#}");
+        Ok(())
+    }
+
     #[test]
     fn compare_by_amount_handles_numeric_values() {
         let a = tx("a", "2026-01-01T00:00:00+00:00", "12");
@@ -0,0 +1,226 @@
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use keepbook::app::{close_account, merge_accounts, move_account, remove_account};
+use keepbook::config::{
+    DisplayConfig, GitConfig, IgnoreConfig, RefreshConfig, ResolvedConfig, SpendingConfig,
+    TrayConfig,
+};
+use keepbook::models::{
+    Account, Asset, AssetBalance, BalanceSnapshot, Connection, ConnectionConfig, Id, Transaction,
+    TransactionAnnotationPatch,
+};
+use keepbook::storage::{JsonFileStorage, Storage};
+use tempfile::TempDir;
+
+fn resolved_config(data_dir: &Path) -> ResolvedConfig {
+    ResolvedConfig {
+        data_dir: data_dir.to_path_buf(),
+        reporting_currency: "USD".to_string(),
+        display: DisplayConfig::default(),
+        refresh: RefreshConfig::default(),
+        history: keepbook::config::HistoryConfig::default(),
+        tray: TrayConfig::default(),
+        spending: SpendingConfig::default(),
+        portfolio: keepbook::config::PortfolioConfig::default(),
+        ignore: IgnoreConfig::default(),
+        ai: keepbook::config::AiConfig::default(),
+        git: GitConfig::default(),
+        profiles: Default::default(),
+    }
+}
+
+async fn add_connection(storage: &JsonFileStorage, name: &str) -> Result<Connection> {
+    let connection = Connection::new(ConnectionConfig {
+        name: name.to_string(),
+        synchronizer: "manual".to_string(),
+        credentials: None,
+        balance_staleness: None,
+    });
+    storage
+        .save_connection_config(connection.id(), &connection.config)
+        .await?;
+    storage.save_connection(&connection).await?;
+    Ok(connection)
+}
+
+async fn add_account(
+    storage: &JsonFileStorage,
+    connection: &mut Connection,
+    name: &str,
+) -> Result<Account> {
+    let account = Account::new(name, connection.id().clone());
+    storage.save_account(&account).await?;
+    connection.state.account_ids.push(account.id.clone());
+    storage.save_connection(connection).await?;
+    Ok(account)
+}
+
+fn txn(id: &str, day: u32, description: &str) -> Transaction {
+    Transaction::new("-20", Asset::currency("USD"), description)
+        .with_id(Id::from_string(id))
+        .with_timestamp(Utc.with_ymd_and_hms(2026, 1, day, 12, 0, 0).unwrap())
+}
+
+fn usd(day: u32, amount: &str) -> BalanceSnapshot {
+    BalanceSnapshot::new(
+        Utc.with_ymd_and_hms(2026, 1, day, 0, 0, 0).unwrap(),
+        vec![AssetBalance::new(Asset::currency("USD"), amount)],
+    )
+}
+
+#[tokio::test]
+async fn merge_moves_history_and_keeps_newer_versions() -> Result<()> {
+    let dir = TempDir::new()?;
+    let storage = JsonFileStorage::new(dir.path());
+    let config = resolved_config(dir.path());
+    let mut connection = add_connection(&storage, "Bank").await?;
+    let old = add_account(&storage, &mut connection, "Checking (old)").await?;
+    let new = add_account(&storage, &mut connection, "Checking").await?;
+
+    storage
+        .append_transactions(&old.id, &[txn("t1", 1, "rent"), txn("t2", 5, "pending")])
+        .await?;
+    storage
+        .append_balance_snapshot(&old.id, &usd(1, "100"))
+        .await?;
+    storage
+        .append_transaction_annotation_patches(
+            &old.id,
+            &[TransactionAnnotationPatch {
+                transaction_id: Id::from_string("t1"),
+                timestamp: Utc.with_ymd_and_hms(2026, 1, 2, 0, 0, 0).unwrap(),
+                description: None,
+                note: None,
+                category: Some(Some("Housing".to_string())),
+                subcategory: None,
+                tags: None,
+                effective_date: None,
+            }],
+        )
+        .await?;
+    // The new account re-reported t2 after it posted.
+    storage
+        .append_transactions(&new.id, &[txn("t2", 5, "posted"), txn("t3", 9, "coffee")])
+        .await?;
+    storage
+        .append_balance_snapshot(&new.id, &usd(9, "80"))
+        .await?;
+
+    merge_accounts(&storage, &config, old.id.as_str(), new.id.as_str()).await?;
+
+    assert!(storage.get_account(&old.id).await?.is_none());
+    let connection = storage.get_connection(connection.id()).await?.unwrap();
+    assert_eq!(connection.state.account_ids, vec![new.id.clone()]);
+
+    let txns = storage.get_transactions(&new.id).await?;
+    let descriptions: Vec<&str> = txns.iter().map(|t| t.description.as_str()).collect();
+    assert_eq!(descriptions, vec!["rent", "posted", "coffee"]);
+
+    let snapshots = storage.get_balance_snapshots(&new.id).await?;
+    let amounts: Vec<&str> = snapshots
+        .iter()
+        .map(|s| s.balances[0].amount.as_str())
+        .collect();
+    assert_eq!(amounts, vec!["100", "80"]);
+
+    let patches = storage.get_transaction_annotation_patches(&new.id).await?;
+    assert_eq!(patches.len(), 1);
+    assert_eq!(patches[0].transaction_id, Id::from_string("t1"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn merge_into_itself_is_refused() -> Result<()> {
+    let dir = TempDir::new()?;
+    let storage = JsonFileStorage::new(dir.path());
+    let config = resolved_config(dir.path());
+    let mut connection = add_connection(&storage, "Bank").await?;
+    let account = add_account(&storage, &mut connection, "Checking").await?;
+    storage
+        .append_transactions(&account.id, &[txn("t1", 1, "rent")])
+        .await?;
+
+    let err = merge_accounts(&storage, &config, "Checking", account.id.as_str())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("into itself"));
+    assert_eq!(storage.get_transactions(&account.id).await?.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn move_account_relinks_both_connections() -> Result<()> {
+    let dir = TempDir::new()?;
+    let storage = JsonFileStorage::new(dir.path());
+    let config = resolved_config(dir.path());
+    let mut bank_a = add_connection(&storage, "Bank A").await?;
+    let bank_b = add_connection(&storage, "Bank B").await?;
+    let account = add_account(&storage, &mut bank_a, "Savings").await?;
+
+    move_account(&storage, &config, "Savings", "Bank B").await?;
+
+    let moved = storage.get_account(&account.id).await?.unwrap();
+    assert_eq!(moved.connection_id, *bank_b.id());
+    let bank_a = storage.get_connection(bank_a.id()).await?.unwrap();
+    assert!(bank_a.state.account_ids.is_empty());
+    let bank_b = storage.get_connection(bank_b.id()).await?.unwrap();
+    assert_eq!(bank_b.state.account_ids, vec![account.id.clone()]);
+    assert!(dir
+        .path()
+        .join("connections")
+        .join(bank_b.id().to_string())
+        .join("accounts/Savings")
+        .exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn close_account_records_zero_and_deactivates() -> Result<()> {
+    let dir = TempDir::new()?;
+    let storage = JsonFileStorage::new(dir.path());
+    let config = resolved_config(dir.path());
+    let mut connection = add_connection(&storage, "Bank").await?;
+    let account = add_account(&storage, &mut connection, "Old Card").await?;
+    storage
+        .append_balance_snapshot(&account.id, &usd(1, "-42.10"))
+        .await?;
+
+    close_account(&storage, &config, "Old Card").await?;
+
+    let account = storage.get_account(&account.id).await?.unwrap();
+    assert!(!account.active);
+    let latest = storage
+        .get_latest_balance_snapshot(&account.id)
+        .await?
+        .unwrap();
+    assert_eq!(latest.balances.len(), 1);
+    assert_eq!(latest.balances[0].amount, "0");
+    Ok(())
+}
+
+#[tokio::test]
+async fn remove_account_deletes_data_and_unlinks_connection() -> Result<()> {
+    let dir = TempDir::new()?;
+    let storage = JsonFileStorage::new(dir.path());
+    let config = resolved_config(dir.path());
+    let mut connection = add_connection(&storage, "Bank").await?;
+    let removed = add_account(&storage, &mut connection, "Checking").await?;
+    let kept = add_account(&storage, &mut connection, "Savings").await?;
+    storage
+        .append_transactions(&removed.id, &[txn("t1", 1, "rent")])
+        .await?;
+
+    remove_account(&storage, &config, removed.id.as_str()).await?;
+
+    assert!(storage.get_account(&removed.id).await?.is_none());
+    assert!(!dir
+        .path()
+        .join("accounts")
+        .join(removed.id.to_string())
+        .exists());
+    let connection = storage.get_connection(connection.id()).await?.unwrap();
+    assert_eq!(connection.state.account_ids, vec![kept.id]);
+    Ok(())
+}
@@ -222,6 +222,7 @@ async fn seed_storage(storage: &dyn Storage, seed: &Seed) -> Result<()> {
             status: parse_transaction_status(&t.status)?,
             synchronizer_data: serde_json::Value::Null,
             standardized_metadata: None,
+            sync_batch: None,
         };
         storage.append_transactions(&account_id, &[tx]).await?;
     }
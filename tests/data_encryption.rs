@@ -0,0 +1,219 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use keepbook::app::{disable_encryption, enable_encryption, encryption_textconv};
+use keepbook::config::{
+    DisplayConfig, GitConfig, IgnoreConfig, RefreshConfig, ResolvedConfig, SpendingConfig,
+    TrayConfig,
+};
+use keepbook::models::{Account, Asset, Connection, ConnectionConfig, Id, Transaction};
+use keepbook::storage::{prepare_for_write, JsonFileStorage, Storage, ENCRYPTION_CONFIG_FILE};
+use secrecy::ExposeSecret;
+use tempfile::TempDir;
+
+/// Public key of the age identity every test in this file decrypts with.
+/// The identity is set process-wide through the environment, so it's
+/// generated once and shared.
+fn local_recipient() -> &'static str {
+    static RECIPIENT: OnceLock<(TempDir, String)> = OnceLock::new();
+    &RECIPIENT
+        .get_or_init(|| {
+            let dir = TempDir::new().expect("temp dir");
+            let identity = age::x25519::Identity::generate();
+            let path = dir.path().join("identity.txt");
+            std::fs::write(&path, identity.to_string().expose_secret()).expect("identity");
+            std::env::set_var("KEEPBOOK_CREDENTIALS_AGE_IDENTITY_PATH", &path);
+            (dir, identity.to_public().to_string())
+        })
+        .1
+}
+
+fn resolved_config(data_dir: &Path) -> ResolvedConfig {
+    ResolvedConfig {
+        data_dir: data_dir.to_path_buf(),
+        reporting_currency: "USD".to_string(),
+        display: DisplayConfig::default(),
+        refresh: RefreshConfig::default(),
+        history: keepbook::config::HistoryConfig::default(),
+        tray: TrayConfig::default(),
+        spending: SpendingConfig::default(),
+        portfolio: keepbook::config::PortfolioConfig::default(),
+        ignore: IgnoreConfig::default(),
+        ai: keepbook::config::AiConfig::default(),
+        git: GitConfig::default(),
+        profiles: Default::default(),
+    }
+}
+
+fn txn(id: &str, day: u32, description: &str) -> Transaction {
+    Transaction::new("-20", Asset::currency("USD"), description)
+        .with_id(Id::from_string(id))
+        .with_timestamp(Utc.with_ymd_and_hms(2026, 1, day, 12, 0, 0).unwrap())
+}
+
+async fn seed(data_dir: &Path) -> Result<Account> {
+    prepare_for_write(data_dir)?;
+    let storage = JsonFileStorage::new(data_dir);
+    let mut connection = Connection::new(ConnectionConfig {
+        name: "Bank".to_string(),
+        synchronizer: "manual".to_string(),
+        credentials: None,
+        balance_staleness: None,
+    });
+    storage
+        .save_connection_config(connection.id(), &connection.config)
+        .await?;
+    let account = Account::new("Checking", connection.id().clone());
+    storage.save_account(&account).await?;
+    connection.state.account_ids.push(account.id.clone());
+    storage.save_connection(&connection).await?;
+    storage
+        .append_transactions(&account.id, &[txn("t1", 1, "rent")])
+        .await?;
+    Ok(account)
+}
+
+#[tokio::test]
+async fn encrypted_data_dir_round_trips() -> Result<()> {
+    let dir = TempDir::new()?;
+    let config = resolved_config(dir.path());
+    let account = seed(dir.path()).await?;
+
+    enable_encryption(&config, vec![local_recipient().to_string()], None).await?;
+
+    let tx_path = dir
+        .path()
+        .join("accounts")
+        .join(account.id.to_string())
+        .join("transactions.jsonl");
+    let encrypted_tx_path = PathBuf::from(format!("{}.age", tx_path.display()));
+    assert!(!tx_path.exists());
+    assert!(!String::from_utf8_lossy(&std::fs::read(&encrypted_tx_path)?).contains("rent"));
+    let gitattributes = std::fs::read_to_string(dir.path().join(".gitattributes"))?;
+    assert!(gitattributes.contains("*.jsonl.age diff=keepbook-age"));
+
+    let storage = JsonFileStorage::new(dir.path());
+    assert!(storage.is_encrypted());
+    assert_eq!(storage.list_accounts().await?.len(), 1);
+    assert_eq!(storage.list_connections().await?.len(), 1);
+    storage
+        .append_transactions(&account.id, &[txn("t2", 9, "coffee")])
+        .await?;
+    assert!(!tx_path.exists());
+    let descriptions: Vec<String> = storage
+        .get_transactions_in_range(
+            &account.id,
+            Some(Utc.with_ymd_and_hms(2026, 1, 5, 0, 0, 0).unwrap()),
+            None,
+        )
+        .await?
+        .into_iter()
+        .map(|t| t.description)
+        .collect();
+    assert_eq!(descriptions, vec!["coffee"]);
+
+    let diff_text = String::from_utf8(encryption_textconv(&encrypted_tx_path)?)?;
+    assert_eq!(diff_text.lines().count(), 2);
+    assert!(diff_text.contains("rent") && diff_text.contains("coffee"));
+
+    disable_encryption(&config).await?;
+    assert!(!dir.path().join(ENCRYPTION_CONFIG_FILE).exists());
+    assert!(!encrypted_tx_path.exists());
+    let storage = JsonFileStorage::new(dir.path());
+    assert!(!storage.is_encrypted());
+    assert_eq!(storage.get_transactions(&account.id).await?.len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn enable_removes_the_plaintext_index_cache() -> Result<()> {
+    let dir = TempDir::new()?;
+    let config = resolved_config(dir.path());
+    let account = seed(dir.path()).await?;
+    let storage = JsonFileStorage::new(dir.path());
+    let found = storage
+        .find_transactions(&account.id, &["rent".to_string()])
+        .await?;
+    assert_eq!(found.len(), 1);
+    let cache_dir = JsonFileStorage::local_cache_dir_for(dir.path());
+    assert!(cache_dir.join("search").exists());
+
+    enable_encryption(&config, vec![local_recipient().to_string()], None).await?;
+
+    assert!(!cache_dir.exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn enable_refuses_recipients_without_a_local_identity() -> Result<()> {
+    local_recipient();
+    let dir = TempDir::new()?;
+    let config = resolved_config(dir.path());
+    let account = seed(dir.path()).await?;
+    let stranger = age::x25519::Identity::generate().to_public().to_string();
+
+    let err = enable_encryption(&config, vec![stranger], None)
+        .await
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("local identity"));
+    assert!(!dir.path().join(ENCRYPTION_CONFIG_FILE).exists());
+    let storage = JsonFileStorage::new(dir.path());
+    assert_eq!(storage.get_transactions(&account.id).await?.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_encryption_config_fails_closed() -> Result<()> {
+    let dir = TempDir::new()?;
+    std::fs::write(dir.path().join(ENCRYPTION_CONFIG_FILE), "recipients = []\n")?;
+    let storage = JsonFileStorage::new(dir.path());
+
+    let account = Account::new("Checking", Id::from_string("conn-1"));
+    let err = storage.save_account(&account).await.unwrap_err();
+    assert!(format!("{err:#}").contains("no recipients"));
+    assert!(!dir
+        .path()
+        .join("accounts")
+        .join(account.id.to_string())
+        .join("account.json")
+        .exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn long_lived_storage_follows_encryption_changes() -> Result<()> {
+    let dir = TempDir::new()?;
+    let config = resolved_config(dir.path());
+    let account = seed(dir.path()).await?;
+    // Created before enabling, like the server's or sync daemon's storage.
+    let storage = JsonFileStorage::new(dir.path());
+    assert!(!storage.is_encrypted());
+
+    enable_encryption(&config, vec![local_recipient().to_string()], None).await?;
+    storage
+        .append_transactions(&account.id, &[txn("t2", 9, "coffee")])
+        .await?;
+    let tx_path = dir
+        .path()
+        .join("accounts")
+        .join(account.id.to_string())
+        .join("transactions.jsonl");
+    assert!(!tx_path.exists());
+    assert_eq!(storage.get_transactions(&account.id).await?.len(), 2);
+
+    // A plaintext copy next to the encrypted file is never written past.
+    std::fs::write(&tx_path, "")?;
+    let err = storage
+        .append_transactions(&account.id, &[txn("t3", 10, "lunch")])
+        .await
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("also exists"));
+    std::fs::remove_file(&tx_path)?;
+
+    disable_encryption(&config).await?;
+    assert!(!storage.is_encrypted());
+    assert_eq!(storage.get_transactions(&account.id).await?.len(), 2);
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use keepbook::models::{Asset, Connection, ConnectionConfig};
+use keepbook::storage::{MemoryStorage, Storage};
+use keepbook::sync::plugin::check_plugin_conformance;
+use keepbook::sync::synchronizers::ExecSynchronizer;
+use keepbook::sync::{
+    DefaultSynchronizerFactory, SyncOptions, Synchronizer, SynchronizerFactory, TransactionSyncMode,
+};
+
+fn reference_plugin() -> PathBuf {
+    PathBuf::from(env!("CARGO_BIN_EXE_keepbook-plugin-reference"))
+}
+
+fn credentials() -> HashMap<String, String> {
+    HashMap::from([("api-key".to_string(), "test-key".to_string())])
+}
+
+fn exec_connection(plugin: &std::path::Path) -> Connection {
+    Connection::new(ConnectionConfig {
+        name: "Reference".to_string(),
+        synchronizer: format!("exec:{}", plugin.display()),
+        credentials: None,
+        balance_staleness: None,
+    })
+}
+
+#[tokio::test]
+async fn reference_plugin_passes_conformance() -> Result<()> {
+    let report = check_plugin_conformance(&reference_plugin(), credentials()).await?;
+    assert!(report.passed(), "{report:#?}");
+    assert!(report.checks.len() >= 8);
+    Ok(())
+}
+
+#[tokio::test]
+async fn exec_sync_maps_plugin_result_into_storage() -> Result<()> {
+    let storage = MemoryStorage::new();
+    let mut connection = exec_connection(&reference_plugin());
+    storage.save_connection(&connection).await?;
+    let synchronizer =
+        ExecSynchronizer::new(reference_plugin()).with_static_credentials(credentials());
+
+    let result = synchronizer
+        .sync_with_options(
+            &mut connection,
+            &storage,
+            &SyncOptions {
+                transactions: TransactionSyncMode::Full,
+            },
+        )
+        .await?;
+    result.save(&storage).await?;
+
+    assert_eq!(result.accounts.len(), 2);
+    assert_eq!(
+        connection.state.synchronizer_data,
+        serde_json::json!({ "sync_count": 1 })
+    );
+    let vti = result
+        .balances
+        .iter()
+        .flat_map(|(_, balances)| balances.iter())
+        .find(|b| b.asset_balance.asset == Asset::equity("VTI"))
+        .expect("VTI balance");
+    assert_eq!(vti.asset_balance.cost_basis.as_deref(), Some("2000.00"));
+    assert_eq!(vti.price.as_ref().map(|p| p.price.as_str()), Some("250.00"));
+
+    let checking = result
+        .accounts
+        .iter()
+        .find(|a| a.name == "Reference Checking")
+        .expect("checking account");
+    assert_eq!(storage.get_transactions(&checking.id).await?.len(), 3);
+
+    // The stored connection state is handed back on the next run.
+    let second = synchronizer.sync(&mut connection, &storage).await?;
+    assert_eq!(
+        second.connection.state.synchronizer_data,
+        serde_json::json!({ "sync_count": 2 })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn factory_routes_exec_connections_and_surfaces_plugin_errors() -> Result<()> {
+    let storage = MemoryStorage::new();
+    let mut connection = exec_connection(&reference_plugin());
+    storage.save_connection(&connection).await?;
+
+    let synchronizer = DefaultSynchronizerFactory::new(None)
+        .create(&connection, &storage)
+        .await?;
+    assert!(synchronizer.name().starts_with("exec:"));
+
+    // No credential store is configured, so the plugin's request goes unanswered.
+    let err = synchronizer
+        .sync(&mut connection, &storage)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("missing api-key"), "{err:#}");
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn plugin_that_keeps_running_after_its_result_times_out() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, Instant};
+
+    let dir = tempfile::tempdir()?;
+    let plugin = dir.path().join("lingering-plugin");
+    // Sends a result, closes stdout, and never exits on its own.
+    std::fs::write(
+        &plugin,
+        "#!/bin/sh\nread request\necho '{\"type\":\"result\",\"protocol_version\":1,\"accounts\":[]}'\nexec >&-\nsleep 30\n",
+    )?;
+    std::fs::set_permissions(&plugin, std::fs::Permissions::from_mode(0o755))?;
+
+    let storage = MemoryStorage::new();
+    let mut connection = exec_connection(&plugin);
+    storage.save_connection(&connection).await?;
+    let synchronizer = ExecSynchronizer::new(plugin).with_timeout(Duration::from_millis(500));
+
+    let started = Instant::now();
+    let err = synchronizer
+        .sync(&mut connection, &storage)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("timed out"), "{err:#}");
+    assert!(started.elapsed() < Duration::from_secs(10));
+    Ok(())
+}
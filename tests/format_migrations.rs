@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use anyhow::Result;
+use keepbook::models::Id;
+use keepbook::storage::{
+    format_version, migrate, prepare_for_write, read_format_version, write_format_version,
+    JsonFileStorage, NewerFormatVersion, Storage, CURRENT_FORMAT_VERSION, MIGRATIONS,
+};
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+fn fixture(name: &str) -> Result<tempfile::TempDir> {
+    let dir = tempfile::tempdir()?;
+    let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/data_dirs")
+        .join(name);
+    copy_dir(&source, dir.path())?;
+    Ok(dir)
+}
+
+fn snapshot(dir: &Path) -> Result<Vec<(String, String)>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(snapshot(&path)?);
+        } else {
+            files.push((path.display().to_string(), std::fs::read_to_string(&path)?));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[tokio::test]
+async fn migrates_unversioned_data_dir_to_current() -> Result<()> {
+    let dir = fixture("v0")?;
+    assert_eq!(format_version(dir.path())?, 0);
+
+    let report = migrate(dir.path()).await?;
+    assert_eq!(report.from_version, 0);
+    assert_eq!(report.to_version, CURRENT_FORMAT_VERSION);
+    let applied: Vec<u32> = report.applied.iter().map(|m| m.version).collect();
+    assert_eq!(applied, vec![1, 2]);
+    assert_eq!(
+        read_format_version(dir.path())?,
+        Some(CURRENT_FORMAT_VERSION)
+    );
+
+    let connection_dir = dir.path().join("connections/conn-legacy");
+    assert!(!connection_dir.join("credentials.toml").exists());
+    let config = std::fs::read_to_string(connection_dir.join("connection.toml"))?;
+    assert!(config.starts_with("# Hand-written config"));
+    assert!(config.contains("[credentials]"));
+
+    let storage = JsonFileStorage::new(dir.path());
+    assert!(storage
+        .get_credential_store(&Id::from_string("conn-legacy"))?
+        .is_some());
+    let raw =
+        std::fs::read_to_string(dir.path().join("accounts/acct-checking/transactions.jsonl"))?;
+    assert!(raw.contains("\"merchant_name\":\"Coffee Shop\""));
+    assert_eq!(
+        storage
+            .get_transactions(&Id::from_string("acct-checking"))
+            .await?
+            .len(),
+        2
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn migrations_are_idempotent() -> Result<()> {
+    let dir = fixture("v0")?;
+    migrate(dir.path()).await?;
+    let after_first = snapshot(dir.path())?;
+
+    // Rerunning from an older recorded version (e.g. after an interrupted
+    // migrate) must not change anything already migrated.
+    write_format_version(dir.path(), 0)?;
+    let report = migrate(dir.path()).await?;
+    assert_eq!(report.applied.len(), MIGRATIONS.len());
+    assert_eq!(snapshot(dir.path())?, after_first);
+
+    let report = migrate(dir.path()).await?;
+    assert!(report.applied.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn newer_format_is_refused() -> Result<()> {
+    let dir = fixture("v0")?;
+    write_format_version(dir.path(), CURRENT_FORMAT_VERSION + 1)?;
+
+    let err = prepare_for_write(dir.path()).unwrap_err();
+    let newer = err
+        .downcast_ref::<NewerFormatVersion>()
+        .expect("NewerFormatVersion");
+    assert_eq!(newer.found, CURRENT_FORMAT_VERSION + 1);
+
+    assert!(migrate(dir.path()).await.is_err());
+    assert!(dir
+        .path()
+        .join("connections/conn-legacy/credentials.toml")
+        .exists());
+    Ok(())
+}
@@ -56,3 +56,39 @@ async fn frankfurter_cross_rate_uses_eur_rates() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn frankfurter_range_uses_one_time_series_request() -> Result<()> {
+    let server = MockServer::start().await;
+    let provider = FrankfurterRateSource::new().with_base_url(server.uri());
+
+    // Friday to Monday: the weekend has no fixing.
+    let body = r#"{
+        "amount": 1.0,
+        "base": "EUR",
+        "start_date": "2024-01-12",
+        "end_date": "2024-01-15",
+        "rates": {
+            "2024-01-12": { "USD": 1.2, "GBP": 0.8 },
+            "2024-01-15": { "USD": 1.25, "GBP": 0.75 }
+        }
+    }"#;
+
+    Mock::given(method("GET"))
+        .and(path("/2024-01-12..2024-01-15"))
+        .and(query_param("from", "EUR"))
+        .and(query_param("to", "GBP"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/json"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let start = NaiveDate::from_ymd_opt(2024, 1, 12).unwrap();
+    let end = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+    let rates = provider.fetch_closes("EUR", "GBP", start, end).await?;
+
+    let dates: Vec<NaiveDate> = rates.iter().map(|fx| fx.as_of_date).collect();
+    assert_eq!(dates, vec![start, end]);
+    assert_eq!(rates[1].rate, "0.75");
+    Ok(())
+}
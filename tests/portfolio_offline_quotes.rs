@@ -39,6 +39,7 @@ async fn portfolio_snapshot_offline_uses_cached_quote() -> Result<()> {
         ignore: IgnoreConfig::default(),
         ai: keepbook::config::AiConfig::default(),
         git: GitConfig::default(),
+        profiles: Default::default(),
     };
 
     let connection = Connection::new(ConnectionConfig {
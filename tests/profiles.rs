@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use keepbook::app::{list_accounts, list_balances, lock_profile_data_dirs, profile_storage};
+use keepbook::config::{
+    DisplayConfig, GitConfig, IgnoreConfig, ProfileConfig, RefreshConfig, ResolvedConfig,
+    SpendingConfig, TrayConfig,
+};
+use keepbook::models::{
+    Account, Asset, AssetBalance, BalanceSnapshot, Connection, ConnectionConfig,
+};
+use keepbook::storage::{DataDirLock, JsonFileStorage, LockMode, Storage};
+use tempfile::TempDir;
+
+fn resolved_config(data_dir: &Path, profiles: BTreeMap<String, ProfileConfig>) -> ResolvedConfig {
+    ResolvedConfig {
+        data_dir: data_dir.to_path_buf(),
+        reporting_currency: "USD".to_string(),
+        display: DisplayConfig::default(),
+        refresh: RefreshConfig::default(),
+        history: keepbook::config::HistoryConfig::default(),
+        tray: TrayConfig::default(),
+        spending: SpendingConfig::default(),
+        portfolio: keepbook::config::PortfolioConfig::default(),
+        ignore: IgnoreConfig::default(),
+        ai: keepbook::config::AiConfig::default(),
+        git: GitConfig::default(),
+        profiles,
+    }
+}
+
+/// A data dir with one account holding `amount` USD.
+async fn data_dir_with_account(dir: &Path, account_name: &str, amount: &str) -> Result<Account> {
+    let storage = JsonFileStorage::new(dir);
+    let mut connection = Connection::new(ConnectionConfig {
+        name: "Bank".to_string(),
+        synchronizer: "manual".to_string(),
+        credentials: None,
+        balance_staleness: None,
+    });
+    storage
+        .save_connection_config(connection.id(), &connection.config)
+        .await?;
+    let account = Account::new(account_name, connection.id().clone());
+    storage.save_account(&account).await?;
+    connection.state.account_ids.push(account.id.clone());
+    storage.save_connection(&connection).await?;
+    storage
+        .append_balance_snapshot(
+            &account.id,
+            &BalanceSnapshot::new(
+                Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap(),
+                vec![AssetBalance::new(Asset::currency("USD"), amount)],
+            ),
+        )
+        .await?;
+    Ok(account)
+}
+
+#[tokio::test]
+async fn combined_view_namespaces_and_splits_joint_accounts() -> Result<()> {
+    let main = TempDir::new()?;
+    let alice_dir = TempDir::new()?;
+    let bob_dir = TempDir::new()?;
+    let alice_joint = data_dir_with_account(alice_dir.path(), "Joint Checking", "1000").await?;
+    let bob_savings = data_dir_with_account(bob_dir.path(), "Savings", "250").await?;
+
+    let profiles = BTreeMap::from([
+        (
+            "alice".to_string(),
+            ProfileConfig {
+                data_dir: alice_dir.path().to_path_buf(),
+                ownership: HashMap::from([("Joint Checking".to_string(), 50.0)]),
+            },
+        ),
+        (
+            "bob".to_string(),
+            ProfileConfig {
+                data_dir: bob_dir.path().to_path_buf(),
+                ownership: HashMap::new(),
+            },
+        ),
+    ]);
+    let config = resolved_config(main.path(), profiles);
+
+    let storage = profile_storage(&config, "all")?;
+    let mut account_ids: Vec<String> = list_accounts(&storage)
+        .await?
+        .into_iter()
+        .map(|account| account.id)
+        .collect();
+    account_ids.sort();
+    let mut expected = vec![
+        format!("alice:{}", alice_joint.id),
+        format!("bob:{}", bob_savings.id),
+    ];
+    expected.sort();
+    assert_eq!(account_ids, expected);
+
+    let balances = list_balances(&storage, &config).await?;
+    let amount_of = |id: &str| {
+        balances
+            .iter()
+            .find(|balance| balance.account_id == id)
+            .map(|balance| balance.amount.clone())
+    };
+    assert_eq!(
+        amount_of(&format!("alice:{}", alice_joint.id)).as_deref(),
+        Some("500")
+    );
+    assert_eq!(
+        amount_of(&format!("bob:{}", bob_savings.id)).as_deref(),
+        Some("250")
+    );
+
+    let bob_only = profile_storage(&config, "bob")?;
+    assert_eq!(list_accounts(&bob_only).await?.len(), 1);
+    assert!(profile_storage(&config, "carol").is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn profile_locks_skip_the_main_data_dir_and_repeats() -> Result<()> {
+    let main = TempDir::new()?;
+    let shared = TempDir::new()?;
+    let profile = |data_dir: &Path| ProfileConfig {
+        data_dir: data_dir.to_path_buf(),
+        ownership: HashMap::new(),
+    };
+    let profiles = BTreeMap::from([
+        ("alice".to_string(), profile(&main.path().join("."))),
+        ("bob".to_string(), profile(shared.path())),
+        ("carol".to_string(), profile(shared.path())),
+    ]);
+    let config = resolved_config(main.path(), profiles);
+
+    // As with `--git-merge-master`, the main data dir is locked exclusively.
+    let _main_lock = DataDirLock::acquire(
+        main.path(),
+        LockMode::Exclusive,
+        std::time::Duration::from_secs(1),
+    )
+    .await?;
+    let locks =
+        lock_profile_data_dirs(&config, "all", std::time::Duration::from_millis(200)).await?;
+    assert_eq!(locks.len(), 1);
+    assert!(DataDirLock::try_acquire(shared.path(), LockMode::Exclusive)?.is_none());
+    Ok(())
+}
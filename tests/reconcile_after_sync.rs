@@ -0,0 +1,125 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::Utc;
+use keepbook::market_data::{MarketDataService, NullMarketDataStore};
+use keepbook::models::{
+    Account, Asset, AssetBalance, Connection, ConnectionConfig, Id, Transaction,
+};
+use keepbook::storage::{MemoryStorage, Storage};
+use keepbook::sync::{
+    SyncContext, SyncOutcome, SyncResult, SyncService, SyncedAssetBalance, Synchronizer,
+    SynchronizerFactory,
+};
+
+/// Reports a balance that drops by 50 per sync but only a 20 purchase, so
+/// every window after the first is 30 short.
+struct LeakySynchronizer {
+    calls: Arc<AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl Synchronizer for LeakySynchronizer {
+    fn name(&self) -> &str {
+        "leaky"
+    }
+
+    async fn sync(
+        &self,
+        connection: &mut Connection,
+        _storage: &dyn Storage,
+    ) -> Result<SyncResult> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        let account_id = Id::from_external("leaky:checking");
+        let account = Account {
+            id: account_id.clone(),
+            name: "Checking".to_string(),
+            connection_id: connection.id().clone(),
+            tags: Vec::new(),
+            created_at: Utc::now(),
+            active: true,
+            synchronizer_data: serde_json::Value::Null,
+        };
+        let balance = 1000 - 50 * call as i64;
+        let purchase = Transaction::new("-20", Asset::currency("USD"), format!("Purchase {call}"))
+            .with_id(Id::from_external(&format!("leaky:tx:{call}")))
+            .with_timestamp(Utc::now());
+        Ok(SyncResult {
+            connection: connection.clone(),
+            accounts: vec![account],
+            balances: vec![(
+                account_id.clone(),
+                vec![SyncedAssetBalance::new(AssetBalance::new(
+                    Asset::currency("USD"),
+                    balance.to_string(),
+                ))],
+            )],
+            transactions: vec![(account_id, vec![purchase])],
+        })
+    }
+}
+
+struct LeakyFactory {
+    calls: Arc<AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl SynchronizerFactory for LeakyFactory {
+    async fn create(
+        &self,
+        _connection: &Connection,
+        _storage: &dyn Storage,
+    ) -> Result<Box<dyn Synchronizer>> {
+        Ok(Box::new(LeakySynchronizer {
+            calls: self.calls.clone(),
+        }))
+    }
+}
+
+#[tokio::test]
+async fn sync_reports_reconcile_warnings_when_enabled() -> Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let connection = Connection::new(ConnectionConfig {
+        name: "Leaky Bank".to_string(),
+        synchronizer: "leaky".to_string(),
+        credentials: None,
+        balance_staleness: None,
+    });
+    storage.save_connection(&connection).await?;
+
+    let market_data = MarketDataService::new(Arc::new(NullMarketDataStore), None);
+    let context = SyncContext::new(
+        storage.clone() as Arc<dyn Storage>,
+        market_data,
+        "USD".to_string(),
+    )
+    .with_factory(Arc::new(LeakyFactory {
+        calls: Arc::new(AtomicUsize::new(0)),
+    }))
+    .with_reconcile_after_sync(true);
+    let service = SyncService::new(context);
+    let id = connection.id().to_string();
+
+    let SyncOutcome::Synced { report } = service.sync_connection(&id).await? else {
+        panic!("expected first sync to succeed");
+    };
+    assert!(report.reconcile_warnings.is_empty());
+    // Keep the next purchase strictly after the first snapshot.
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+    let SyncOutcome::Synced { report } = service.sync_connection(&id).await? else {
+        panic!("expected second sync to succeed");
+    };
+    assert_eq!(report.reconcile_warnings.len(), 1);
+    let (account_id, discrepancy) = &report.reconcile_warnings[0];
+    assert_eq!(*account_id, Id::from_external("leaky:checking"));
+    assert_eq!(discrepancy.balance_delta, "-50");
+    assert_eq!(discrepancy.transactions_total, "-20");
+    assert_eq!(discrepancy.difference, "-30");
+
+    let output = keepbook::app::reconcile(storage.as_ref(), Some("Checking")).await?;
+    assert_eq!(output[0].windows_checked, 1);
+    assert_eq!(output[0].discrepancies.len(), 1);
+    Ok(())
+}
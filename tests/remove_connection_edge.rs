@@ -23,6 +23,7 @@ fn resolved_config(data_dir: &Path) -> ResolvedConfig {
         ignore: IgnoreConfig::default(),
         ai: keepbook::config::AiConfig::default(),
         git: GitConfig::default(),
+        profiles: Default::default(),
     }
 }
 
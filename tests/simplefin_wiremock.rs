@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use keepbook::credentials::CredentialStore;
+use keepbook::models::{Asset, Connection, ConnectionConfig, TransactionStatus};
+use keepbook::storage::{MemoryStorage, Storage};
+use keepbook::sync::synchronizers::SimpleFinSynchronizer;
+use keepbook::sync::{SyncOptions, Synchronizer, TransactionSyncMode};
+use secrecy::{ExposeSecret, SecretString};
+use wiremock::matchers::{basic_auth, method, path, query_param, query_param_is_missing};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[derive(Default)]
+struct MapCredentialStore {
+    values: Mutex<HashMap<String, String>>,
+}
+
+impl MapCredentialStore {
+    fn with(key: &str, value: &str) -> Self {
+        let store = Self::default();
+        store
+            .values
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        store
+    }
+}
+
+#[async_trait]
+impl CredentialStore for MapCredentialStore {
+    async fn get(&self, key: &str) -> Result<Option<SecretString>> {
+        Ok(self
+            .values
+            .lock()
+            .unwrap()
+            .get(key)
+            .map(|v| SecretString::new(v.clone().into())))
+    }
+
+    async fn set(&self, key: &str, value: SecretString) -> Result<()> {
+        self.values
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.expose_secret().to_string());
+        Ok(())
+    }
+}
+
+fn access_url(server: &MockServer) -> String {
+    server.uri().replacen("http://", "http://demo:secret@", 1) + "/simplefin"
+}
+
+fn simplefin_connection() -> Connection {
+    Connection::new(ConnectionConfig {
+        name: "SimpleFIN".to_string(),
+        synchronizer: "simplefin".to_string(),
+        credentials: None,
+        balance_staleness: None,
+    })
+}
+
+#[tokio::test]
+async fn setup_token_is_claimed_and_access_url_stored() -> Result<()> {
+    let server = MockServer::start().await;
+    let access = access_url(&server);
+    Mock::given(method("POST"))
+        .and(path("/simplefin/claim/abc123"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(access.clone()))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let token = STANDARD.encode(format!("{}/simplefin/claim/abc123", server.uri()));
+    let store = MapCredentialStore::with("setup-token", &token);
+
+    SimpleFinSynchronizer::from_credentials(&store).await?;
+
+    let stored = store.get("access-url").await?.expect("access url stored");
+    assert_eq!(stored.expose_secret(), access);
+
+    // Subsequent loads use the stored URL instead of claiming again.
+    SimpleFinSynchronizer::from_credentials(&store).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn sync_maps_accounts_holdings_and_advances_cursor() -> Result<()> {
+    let server = MockServer::start().await;
+    let body = serde_json::json!({
+        "errors": ["Connection to First Bank may need attention"],
+        "accounts": [
+            {
+                "org": { "domain": "firstbank.example", "name": "First Bank" },
+                "id": "chk-1",
+                "name": "Checking",
+                "currency": "USD",
+                "balance": "1500.25",
+                "balance-date": 1767312000,
+                "transactions": [
+                    { "id": "t1", "posted": 1767139200, "amount": "-12.34",
+                      "description": "Coffee", "transacted_at": 1767135600 },
+                    { "id": "t2", "posted": 1767225600, "amount": "2000.00",
+                      "description": "Payroll" },
+                    { "id": "t3", "posted": 0, "amount": "-5.00",
+                      "description": "Card hold", "pending": true }
+                ]
+            },
+            {
+                "org": { "domain": "broker.example", "name": "Broker" },
+                "id": "brk-1",
+                "name": "Brokerage",
+                "currency": "USD",
+                "balance": "3100.00",
+                "balance-date": 1767312000,
+                "transactions": [],
+                "holdings": [
+                    { "id": "h1", "created": 1700000000, "currency": "USD",
+                      "cost_basis": "2400.00", "description": "Vanguard Total Stock",
+                      "market_value": "3000.00", "purchase_price": "200.00",
+                      "shares": "12", "symbol": "VTI" }
+                ]
+            }
+        ]
+    });
+
+    Mock::given(method("GET"))
+        .and(path("/simplefin/accounts"))
+        .and(basic_auth("demo", "secret"))
+        .and(query_param("pending", "1"))
+        .and(query_param_is_missing("start-date"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body.clone()))
+        .expect(2)
+        .mount(&server)
+        .await;
+    // Incremental request: oldest account cursor (1767225600) minus five days.
+    Mock::given(method("GET"))
+        .and(path("/simplefin/accounts"))
+        .and(query_param("start-date", "1766793600"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "errors": [],
+            "accounts": [{
+                "id": "chk-1", "name": "Checking", "currency": "USD",
+                "balance": "1490.25", "balance-date": 1767398400,
+                "transactions": [
+                    { "id": "t3", "posted": 1767398400, "amount": "-5.00",
+                      "description": "Card hold" }
+                ]
+            }, {
+                "id": "brk-1", "name": "Brokerage", "currency": "USD",
+                "balance": "3100.00", "balance-date": 1767398400
+            }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let storage = MemoryStorage::new();
+    let mut connection = simplefin_connection();
+    storage.save_connection(&connection).await?;
+    let synchronizer = SimpleFinSynchronizer::new(SecretString::new(access_url(&server).into()));
+
+    let result = synchronizer.sync(&mut connection, &storage).await?;
+    result.save(&storage).await?;
+
+    assert_eq!(result.accounts.len(), 2);
+    assert_eq!(connection.state.account_ids.len(), 2);
+    let checking = result
+        .accounts
+        .iter()
+        .find(|a| a.name == "Checking")
+        .expect("checking");
+    assert_eq!(checking.synchronizer_data["cursor"], 1767225600);
+    assert_eq!(checking.synchronizer_data["org"], "First Bank");
+
+    let txns = storage.get_transactions(&checking.id).await?;
+    assert_eq!(txns.len(), 3);
+    let hold = txns
+        .iter()
+        .find(|t| t.description == "Card hold")
+        .expect("pending");
+    assert_eq!(hold.status, TransactionStatus::Pending);
+
+    let brokerage_balances = result
+        .balances
+        .iter()
+        .find(|(id, _)| *id != checking.id)
+        .map(|(_, b)| b)
+        .expect("brokerage balances");
+    let vti = brokerage_balances
+        .iter()
+        .find(|b| b.asset_balance.asset == Asset::equity("VTI"))
+        .expect("VTI");
+    assert_eq!(vti.asset_balance.amount, "12");
+    assert_eq!(vti.asset_balance.cost_basis.as_deref(), Some("2400.00"));
+    assert_eq!(vti.price.as_ref().map(|p| p.price.as_str()), Some("250"));
+    let cash = brokerage_balances
+        .iter()
+        .find(|b| b.asset_balance.asset == Asset::currency("USD"))
+        .expect("cash");
+    assert_eq!(cash.asset_balance.amount, "100");
+
+    // Both accounts now have cursors, so the next run is incremental.
+    let result = synchronizer.sync(&mut connection, &storage).await?;
+    result.save(&storage).await?;
+
+    let txns = storage.get_transactions(&checking.id).await?;
+    let hold = txns
+        .iter()
+        .find(|t| t.description == "Card hold")
+        .expect("posted hold");
+    assert_eq!(hold.status, TransactionStatus::Posted);
+    let checking = storage
+        .get_account(&checking.id)
+        .await?
+        .expect("checking saved");
+    assert_eq!(checking.synchronizer_data["cursor"], 1767398400);
+
+    // A full sync ignores the cursors.
+    let full = SyncOptions {
+        transactions: TransactionSyncMode::Full,
+    };
+    synchronizer
+        .sync_with_options(&mut connection, &storage, &full)
+        .await?;
+    Ok(())
+}
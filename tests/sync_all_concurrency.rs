@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use keepbook::market_data::{MarketDataService, NullMarketDataStore};
+use keepbook::models::{Connection, ConnectionConfig};
+use keepbook::storage::{MemoryStorage, Storage};
+use keepbook::sync::{
+    AuthStatus, InteractiveAuth, SyncConcurrency, SyncContext, SyncOutcome, SyncResult,
+    SyncService, Synchronizer, SynchronizerFactory,
+};
+
+/// Tracks how many syncs run at once, overall and per synchronizer.
+#[derive(Default)]
+struct InFlight {
+    total: AtomicUsize,
+    max_total: AtomicUsize,
+    by_name: Mutex<HashMap<String, (usize, usize)>>,
+}
+
+impl InFlight {
+    fn enter(&self, name: &str) {
+        let now = self.total.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_total.fetch_max(now, Ordering::SeqCst);
+        let mut by_name = self.by_name.lock().unwrap();
+        let (current, max) = by_name.entry(name.to_string()).or_default();
+        *current += 1;
+        *max = (*max).max(*current);
+    }
+
+    fn exit(&self, name: &str) {
+        self.total.fetch_sub(1, Ordering::SeqCst);
+        self.by_name.lock().unwrap().get_mut(name).unwrap().0 -= 1;
+    }
+
+    fn max_for(&self, name: &str) -> usize {
+        self.by_name.lock().unwrap()[name].1
+    }
+}
+
+struct SlowSynchronizer {
+    name: String,
+    in_flight: Arc<InFlight>,
+}
+
+#[async_trait::async_trait]
+impl Synchronizer for SlowSynchronizer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn sync(
+        &self,
+        connection: &mut Connection,
+        _storage: &dyn Storage,
+    ) -> Result<SyncResult> {
+        self.in_flight.enter(&self.name);
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        self.in_flight.exit(&self.name);
+        if connection.config.name.ends_with("broken") {
+            anyhow::bail!("upstream unavailable");
+        }
+        Ok(SyncResult {
+            connection: connection.clone(),
+            accounts: Vec::new(),
+            balances: Vec::new(),
+            transactions: Vec::new(),
+        })
+    }
+
+    fn interactive(&mut self) -> Option<&mut dyn InteractiveAuth> {
+        if self.name == "browser" {
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl InteractiveAuth for SlowSynchronizer {
+    async fn check_auth(&self) -> Result<AuthStatus> {
+        Ok(AuthStatus::Valid)
+    }
+
+    async fn login(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct SlowFactory {
+    in_flight: Arc<InFlight>,
+}
+
+#[async_trait::async_trait]
+impl SynchronizerFactory for SlowFactory {
+    async fn create(
+        &self,
+        connection: &Connection,
+        _storage: &dyn Storage,
+    ) -> Result<Box<dyn Synchronizer>> {
+        Ok(Box::new(SlowSynchronizer {
+            name: connection.config.synchronizer.clone(),
+            in_flight: self.in_flight.clone(),
+        }))
+    }
+}
+
+#[tokio::test]
+async fn sync_all_runs_connections_concurrently_within_limits() -> Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let connections = [
+        ("api-1", "api"),
+        ("browser-1", "browser"),
+        ("api-2", "api"),
+        ("limited-1", "limited"),
+        ("api-broken", "api"),
+        ("browser-2", "browser"),
+        ("limited-2", "limited"),
+        ("api-3", "api"),
+    ];
+    for (name, synchronizer) in connections {
+        let connection = Connection::new(ConnectionConfig {
+            name: name.to_string(),
+            synchronizer: synchronizer.to_string(),
+            credentials: None,
+            balance_staleness: None,
+        });
+        storage.save_connection(&connection).await?;
+    }
+    let expected_order: Vec<String> = storage
+        .list_connections()
+        .await?
+        .into_iter()
+        .map(|c| c.config.name)
+        .collect();
+
+    let in_flight = Arc::new(InFlight::default());
+    let market_data = MarketDataService::new(Arc::new(NullMarketDataStore), None);
+    let context = SyncContext::new(
+        storage.clone() as Arc<dyn Storage>,
+        market_data,
+        "USD".to_string(),
+    )
+    .with_factory(Arc::new(SlowFactory {
+        in_flight: in_flight.clone(),
+    }))
+    .with_concurrency(SyncConcurrency::new(3).with_limit("limited", 1));
+    let service = SyncService::new(context);
+
+    let outcomes = service.sync_all().await?;
+
+    let names: Vec<String> = outcomes
+        .iter()
+        .map(|outcome| match outcome {
+            SyncOutcome::Synced { report } => report.result.connection.config.name.clone(),
+            SyncOutcome::Failed { connection, error } => {
+                assert!(error.contains("upstream unavailable"));
+                connection.config.name.clone()
+            }
+            other => panic!("unexpected outcome: {other:?}"),
+        })
+        .collect();
+    assert_eq!(names, expected_order);
+
+    let max_total = in_flight.max_total.load(Ordering::SeqCst);
+    assert!(max_total > 1, "expected concurrent syncs, got {max_total}");
+    assert!(max_total <= 3, "concurrency limit exceeded: {max_total}");
+    assert_eq!(in_flight.max_for("browser"), 1);
+    assert_eq!(in_flight.max_for("limited"), 1);
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use keepbook::market_data::{MarketDataService, NullMarketDataStore};
+use keepbook::models::{
+    Account, Asset, AssetBalance, Connection, ConnectionConfig, Id, SyncRunOutcome, Transaction,
+};
+use keepbook::storage::{JsonFileStorage, Storage};
+use keepbook::sync::{
+    SyncContext, SyncOutcome, SyncResult, SyncService, SyncedAssetBalance, Synchronizer,
+    SynchronizerFactory,
+};
+use tempfile::TempDir;
+
+/// Fails the first `failures` syncs, then returns one account with a balance
+/// and `calls` transactions.
+struct FlakySynchronizer {
+    calls: Arc<AtomicUsize>,
+    failures: usize,
+}
+
+#[async_trait::async_trait]
+impl Synchronizer for FlakySynchronizer {
+    fn name(&self) -> &str {
+        "flaky"
+    }
+
+    async fn sync(
+        &self,
+        connection: &mut Connection,
+        _storage: &dyn Storage,
+    ) -> Result<SyncResult> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        if call <= self.failures {
+            return Err(anyhow::anyhow!("HTTP 503")).context("Bank login failed");
+        }
+
+        let account_id = Id::from_external("flaky:checking");
+        let account = Account {
+            id: account_id.clone(),
+            name: "Checking".to_string(),
+            connection_id: connection.id().clone(),
+            tags: Vec::new(),
+            created_at: Utc::now(),
+            active: true,
+            synchronizer_data: serde_json::Value::Null,
+        };
+        let transactions = (1..=call)
+            .map(|i| {
+                Transaction::new("-1", Asset::currency("USD"), format!("Purchase {i}"))
+                    .with_id(Id::from_external(&format!("flaky:tx:{i}")))
+            })
+            .collect();
+        Ok(SyncResult {
+            connection: connection.clone(),
+            accounts: vec![account],
+            balances: vec![(
+                account_id.clone(),
+                vec![SyncedAssetBalance::new(AssetBalance::new(
+                    Asset::currency("USD"),
+                    "100",
+                ))],
+            )],
+            transactions: vec![(account_id, transactions)],
+        })
+    }
+}
+
+struct FlakyFactory {
+    calls: Arc<AtomicUsize>,
+    failures: usize,
+}
+
+#[async_trait::async_trait]
+impl SynchronizerFactory for FlakyFactory {
+    async fn create(
+        &self,
+        _connection: &Connection,
+        _storage: &dyn Storage,
+    ) -> Result<Box<dyn Synchronizer>> {
+        Ok(Box::new(FlakySynchronizer {
+            calls: self.calls.clone(),
+            failures: self.failures,
+        }))
+    }
+}
+
+async fn setup(
+    dir: &TempDir,
+    failures: usize,
+) -> Result<(JsonFileStorage, Connection, SyncService)> {
+    let storage = JsonFileStorage::new(dir.path());
+    let connection = Connection::new(ConnectionConfig {
+        name: "Flaky Bank".to_string(),
+        synchronizer: "flaky".to_string(),
+        credentials: None,
+        balance_staleness: None,
+    });
+    storage
+        .save_connection_config(connection.id(), &connection.config)
+        .await?;
+    storage.save_connection(&connection).await?;
+
+    let market_data = MarketDataService::new(Arc::new(NullMarketDataStore), None);
+    let context = SyncContext::new(
+        Arc::new(storage.clone()) as Arc<dyn Storage>,
+        market_data,
+        "USD".to_string(),
+    )
+    .with_factory(Arc::new(FlakyFactory {
+        calls: Arc::new(AtomicUsize::new(0)),
+        failures,
+    }));
+    Ok((storage, connection, SyncService::new(context)))
+}
+
+#[tokio::test]
+async fn sync_runs_are_journaled_per_connection() -> Result<()> {
+    let dir = TempDir::new()?;
+    let (storage, connection, service) = setup(&dir, 2).await?;
+    let id = connection.id().to_string();
+
+    assert!(service.sync_connection(&id).await.is_err());
+    let outcomes = service.sync_all().await?;
+    assert!(matches!(outcomes[0], SyncOutcome::Failed { .. }));
+
+    let connections = keepbook::app::list_connections(&storage).await?;
+    assert_eq!(connections[0].failure_streak, 2);
+    assert_eq!(
+        connections[0].last_error.as_deref(),
+        Some("Bank login failed: HTTP 503")
+    );
+
+    assert!(matches!(
+        service.sync_connection(&id).await?,
+        SyncOutcome::Synced { .. }
+    ));
+    assert!(matches!(
+        service.sync_connection(&id).await?,
+        SyncOutcome::Synced { .. }
+    ));
+
+    let runs = storage.get_sync_runs(connection.id()).await?;
+    assert_eq!(runs.len(), 4);
+    assert!(dir
+        .path()
+        .join("connections")
+        .join(&id)
+        .join("sync_runs.jsonl")
+        .exists());
+
+    assert_eq!(runs[0].outcome, SyncRunOutcome::Failed);
+    assert_eq!(runs[0].errors, vec!["Bank login failed: HTTP 503"]);
+
+    let first_success = &runs[2];
+    assert_eq!(first_success.outcome, SyncRunOutcome::Synced);
+    assert_eq!(first_success.accounts_added, 1);
+    assert_eq!(first_success.balances_recorded, 1);
+    assert_eq!(first_success.transactions_added, 3);
+    assert!(first_success.batch_id.is_some());
+
+    // The second success only adds the one new transaction.
+    let second_success = &runs[3];
+    assert_eq!(second_success.accounts_added, 0);
+    assert_eq!(second_success.accounts_changed, 0);
+    assert_eq!(second_success.transactions_added, 1);
+    assert!(second_success.finished_at >= second_success.started_at);
+
+    let connections = keepbook::app::list_connections(&storage).await?;
+    assert_eq!(connections[0].failure_streak, 0);
+
+    let history = keepbook::app::sync_history(&storage, Some("Flaky Bank"), Some(3)).await?;
+    assert_eq!(history.len(), 3);
+    assert_eq!(history[0].transactions_added, 1);
+    assert_eq!(history[2].outcome, SyncRunOutcome::Failed);
+    assert_eq!(history[0].connection_name, "Flaky Bank");
+    Ok(())
+}
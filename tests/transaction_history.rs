@@ -0,0 +1,214 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use keepbook::app::{list_transaction_history, list_transactions};
+use keepbook::config::{
+    DisplayConfig, GitConfig, IgnoreConfig, RefreshConfig, ResolvedConfig, SpendingConfig,
+    TrayConfig,
+};
+use keepbook::models::{
+    Account, Asset, Connection, ConnectionConfig, Id, SyncBatch, Transaction,
+    TransactionAnnotationPatch,
+};
+use keepbook::storage::{AsOfStorage, MemoryStorage, Storage};
+
+fn resolved_config(data_dir: &Path) -> ResolvedConfig {
+    ResolvedConfig {
+        data_dir: data_dir.to_path_buf(),
+        reporting_currency: "USD".to_string(),
+        display: DisplayConfig::default(),
+        refresh: RefreshConfig::default(),
+        history: keepbook::config::HistoryConfig::default(),
+        tray: TrayConfig::default(),
+        spending: SpendingConfig::default(),
+        portfolio: keepbook::config::PortfolioConfig::default(),
+        ignore: IgnoreConfig::default(),
+        ai: keepbook::config::AiConfig::default(),
+        git: GitConfig::default(),
+        profiles: Default::default(),
+    }
+}
+
+fn at(day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 3, day, 12, 0, 0).unwrap()
+}
+
+fn category_patch(day: u32, category: &str) -> TransactionAnnotationPatch {
+    TransactionAnnotationPatch {
+        transaction_id: Id::from_string("t1"),
+        timestamp: at(day),
+        description: None,
+        note: None,
+        category: Some(Some(category.to_string())),
+        subcategory: None,
+        tags: None,
+        effective_date: None,
+    }
+}
+
+/// A pending version on day 2, a "Dining" patch on day 3, the posted version
+/// from a sync on day 5 and a "Coffee" patch on day 6.
+async fn seed() -> Result<(Arc<MemoryStorage>, Account)> {
+    let storage = Arc::new(MemoryStorage::new());
+    let connection = Connection::new(ConnectionConfig {
+        name: "Bank".to_string(),
+        synchronizer: "manual".to_string(),
+        credentials: None,
+        balance_staleness: None,
+    });
+    storage.save_connection(&connection).await?;
+    let account = Account::new_with(
+        Id::from_string("acct"),
+        at(1),
+        "Checking",
+        connection.id().clone(),
+    );
+    storage.save_account(&account).await?;
+
+    let batch = SyncBatch::new(Id::from_string("b1"), connection.id().clone(), at(5));
+    storage.append_sync_batch(&batch).await?;
+
+    let pending = Transaction::new("-5", Asset::currency("USD"), "SQ *BLUE BOTTLE")
+        .with_id(Id::from_string("t1"))
+        .with_timestamp(at(2));
+    let mut posted = pending.clone();
+    posted.description = "Blue Bottle Coffee".to_string();
+    posted.sync_batch = Some(batch.id.clone());
+    let other = Transaction::new("-40", Asset::currency("USD"), "Groceries")
+        .with_id(Id::from_string("t2"))
+        .with_timestamp(at(2));
+    storage
+        .append_transactions(&account.id, &[pending, other])
+        .await?;
+    storage
+        .append_transaction_annotation_patches(&account.id, &[category_patch(3, "Dining")])
+        .await?;
+    storage.append_transactions(&account.id, &[posted]).await?;
+    storage
+        .append_transaction_annotation_patches(&account.id, &[category_patch(6, "Coffee")])
+        .await?;
+    Ok((storage, account))
+}
+
+#[tokio::test]
+async fn history_merges_versions_and_patches_chronologically() -> Result<()> {
+    let (storage, _) = seed().await?;
+
+    let history = list_transaction_history(storage.as_ref(), "t1", None).await?;
+    assert_eq!(history.len(), 1);
+    let json = serde_json::to_value(&history[0].entries)?;
+    let entries = json.as_array().unwrap();
+    let kinds: Vec<&str> = entries
+        .iter()
+        .map(|entry| entry["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        kinds,
+        vec!["version", "annotation_patch", "version", "annotation_patch"]
+    );
+    assert_eq!(entries[0]["description"], "SQ *BLUE BOTTLE");
+    assert!(entries[0].get("recorded_at").is_none());
+    assert_eq!(entries[2]["description"], "Blue Bottle Coffee");
+    assert_eq!(entries[2]["recorded_at"], at(5).to_rfc3339());
+    assert_eq!(entries[3]["patch"]["category"], "Coffee");
+    assert_eq!(entries[3]["annotation"]["category"], "Coffee");
+
+    assert!(list_transaction_history(storage.as_ref(), "missing", None)
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn as_of_reproduces_an_earlier_listing() -> Result<()> {
+    let (storage, _) = seed().await?;
+    let config = resolved_config(Path::new("/nonexistent"));
+    let list_at = |day: u32| {
+        let storage = AsOfStorage::new(storage.clone(), at(day));
+        let config = &config;
+        async move {
+            list_transactions(
+                &storage,
+                Some("2026-03-01".to_string()),
+                Some("2026-03-31".to_string()),
+                false,
+                false,
+                config,
+            )
+            .await
+        }
+    };
+
+    let before_sync = list_at(4).await?;
+    let coffee = before_sync.iter().find(|tx| tx.id == "t1").unwrap();
+    assert_eq!(coffee.description, "SQ *BLUE BOTTLE");
+    assert_eq!(coffee.category.as_deref(), Some("Dining"));
+    assert_eq!(before_sync.len(), 2);
+
+    let after_sync = list_at(5).await?;
+    let coffee = after_sync.iter().find(|tx| tx.id == "t1").unwrap();
+    assert_eq!(coffee.description, "Blue Bottle Coffee");
+    assert_eq!(coffee.category.as_deref(), Some("Dining"));
+
+    let before_anything = list_at(1).await?;
+    assert!(before_anything.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn history_dates_versions_like_as_of_after_recompaction() -> Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let connection = Connection::new(ConnectionConfig {
+        name: "Bank".to_string(),
+        synchronizer: "manual".to_string(),
+        credentials: None,
+        balance_staleness: None,
+    });
+    storage.save_connection(&connection).await?;
+    let account = Account::new_with(
+        Id::from_string("acct"),
+        at(1),
+        "Checking",
+        connection.id().clone(),
+    );
+    storage.save_account(&account).await?;
+    let early = SyncBatch::new(Id::from_string("b1"), connection.id().clone(), at(3));
+    let late = SyncBatch::new(Id::from_string("b2"), connection.id().clone(), at(6));
+    storage.append_sync_batch(&early).await?;
+    storage.append_sync_batch(&late).await?;
+
+    // Sorted by timestamp, as `sync recompact` leaves it: the late batch's
+    // row comes before the early batch's version of t1.
+    let mut backdated = Transaction::new("-40", Asset::currency("USD"), "Groceries")
+        .with_id(Id::from_string("t2"))
+        .with_timestamp(at(1));
+    backdated.sync_batch = Some(late.id.clone());
+    let mut coffee = Transaction::new("-5", Asset::currency("USD"), "Coffee")
+        .with_id(Id::from_string("t1"))
+        .with_timestamp(at(2));
+    coffee.sync_batch = Some(early.id.clone());
+    storage
+        .append_transactions(&account.id, &[backdated, coffee])
+        .await?;
+    storage
+        .append_transaction_annotation_patches(&account.id, &[category_patch(4, "Dining")])
+        .await?;
+
+    let as_of = AsOfStorage::new(storage.clone(), at(4));
+    let visible = as_of.get_transactions_raw(&account.id).await?;
+    assert_eq!(visible.len(), 1);
+    assert_eq!(visible[0].id, Id::from_string("t1"));
+
+    let history = list_transaction_history(storage.as_ref(), "t1", None).await?;
+    let json = serde_json::to_value(&history[0].entries)?;
+    let kinds: Vec<&str> = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, vec!["version", "annotation_patch"]);
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use keepbook::app::search_transactions;
+use keepbook::config::{
+    DisplayConfig, GitConfig, IgnoreConfig, RefreshConfig, ResolvedConfig, SpendingConfig,
+    TrayConfig,
+};
+use keepbook::models::{
+    Account, Asset, Connection, ConnectionConfig, Id, Transaction, TransactionAnnotationPatch,
+    TransactionStandardizedMetadata, TransactionStatus,
+};
+use keepbook::storage::{JsonFileStorage, Storage};
+use tempfile::TempDir;
+
+fn resolved_config(data_dir: &Path) -> ResolvedConfig {
+    ResolvedConfig {
+        data_dir: data_dir.to_path_buf(),
+        reporting_currency: "USD".to_string(),
+        display: DisplayConfig::default(),
+        refresh: RefreshConfig::default(),
+        history: keepbook::config::HistoryConfig::default(),
+        tray: TrayConfig::default(),
+        spending: SpendingConfig::default(),
+        portfolio: keepbook::config::PortfolioConfig::default(),
+        ignore: IgnoreConfig::default(),
+        ai: keepbook::config::AiConfig::default(),
+        git: GitConfig::default(),
+        profiles: Default::default(),
+    }
+}
+
+fn txn(id: &str, month: u32, amount: &str, description: &str, merchant: &str) -> Transaction {
+    Transaction::new(amount, Asset::currency("USD"), description)
+        .with_id(Id::from_string(id))
+        .with_timestamp(Utc.with_ymd_and_hms(2026, month, 10, 12, 0, 0).unwrap())
+        .with_standardized_metadata(TransactionStandardizedMetadata {
+            merchant_name: Some(merchant.to_string()),
+            ..Default::default()
+        })
+}
+
+fn annotate(id: &str, category: &str, tags: &[&str]) -> TransactionAnnotationPatch {
+    TransactionAnnotationPatch {
+        transaction_id: Id::from_string(id),
+        timestamp: Utc::now(),
+        description: None,
+        note: Some(Some("client dinner".to_string())),
+        category: Some(Some(category.to_string())),
+        subcategory: None,
+        tags: Some(Some(tags.iter().map(|tag| tag.to_string()).collect())),
+        effective_date: None,
+    }
+}
+
+async fn add_account(storage: &JsonFileStorage, name: &str) -> Result<Account> {
+    let mut connection = Connection::new(ConnectionConfig {
+        name: format!("{name} Bank"),
+        synchronizer: "manual".to_string(),
+        credentials: None,
+        balance_staleness: None,
+    });
+    storage
+        .save_connection_config(connection.id(), &connection.config)
+        .await?;
+    let account = Account::new(name, connection.id().clone());
+    storage.save_account(&account).await?;
+    connection.state.account_ids.push(account.id.clone());
+    storage.save_connection(&connection).await?;
+    Ok(account)
+}
+
+async fn ids(
+    storage: &JsonFileStorage,
+    config: &ResolvedConfig,
+    query: &str,
+) -> Result<Vec<String>> {
+    Ok(search_transactions(storage, query, false, true, config)
+        .await?
+        .into_iter()
+        .map(|tx| tx.id)
+        .collect())
+}
+
+#[tokio::test]
+async fn search_filters_on_text_annotations_and_amounts() -> Result<()> {
+    let dir = TempDir::new()?;
+    let config = resolved_config(dir.path());
+    let storage = JsonFileStorage::new(dir.path());
+
+    let sapphire = add_account(&storage, "Chase Sapphire").await?;
+    let checking = add_account(&storage, "Checking").await?;
+    storage
+        .append_transactions(
+            &sapphire.id,
+            &[
+                txn("s1", 1, "-32.50", "SQ *STARBUCKS 0042", "Starbucks"),
+                txn("s2", 2, "-4.75", "STARBUCKS STORE 99", "Starbucks"),
+                txn("s3", 2, "-60", "Delta Air Lines", "Delta"),
+                txn("s0", 1, "-25", "Starbucks Reserve", "Starbucks")
+                    .with_timestamp(Utc.with_ymd_and_hms(2025, 12, 30, 12, 0, 0).unwrap()),
+            ],
+        )
+        .await?;
+    storage
+        .append_transactions(
+            &checking.id,
+            &[txn("c1", 1, "-40", "Starbucks", "Starbucks")
+                .with_status(TransactionStatus::Pending)],
+        )
+        .await?;
+    storage
+        .append_transaction_annotation_patches(
+            &sapphire.id,
+            &[
+                annotate("s1", "Dining", &["travel"]),
+                annotate("s0", "Dining", &["travel"]),
+                annotate("s3", "Travel", &["work"]),
+            ],
+        )
+        .await?;
+
+    assert_eq!(
+        ids(
+            &storage,
+            &config,
+            r#"merchant:starbucks amount:<-20 category:dining tag:travel after:2026-01-01 account:"Sapphire""#
+        )
+        .await?,
+        vec!["s1"]
+    );
+    // Bare words search annotations too; results are oldest first.
+    assert_eq!(
+        ids(&storage, &config, "client").await?,
+        vec!["s0", "s1", "s3"]
+    );
+    assert_eq!(
+        ids(&storage, &config, "starbucks -tag:travel").await?,
+        vec!["c1", "s2"]
+    );
+    assert_eq!(ids(&storage, &config, "status:pending").await?, vec!["c1"]);
+    assert_eq!(
+        ids(&storage, &config, "connection:checking amount:>=-40").await?,
+        vec!["c1"]
+    );
+    assert!(ids(&storage, &config, "vendor:acme").await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn search_index_follows_appends() -> Result<()> {
+    let dir = TempDir::new()?;
+    let config = resolved_config(dir.path());
+    let storage = JsonFileStorage::new(dir.path());
+    let account = add_account(&storage, "Checking").await?;
+    storage
+        .append_transactions(
+            &account.id,
+            &[txn("a", 1, "-5", "Blue Bottle Coffee", "Blue Bottle")],
+        )
+        .await?;
+
+    assert_eq!(ids(&storage, &config, "blue").await?, vec!["a"]);
+    let index_path = dir
+        .path()
+        .join(".keepbook-cache")
+        .join("search")
+        .join(format!("{}.json", account.id));
+    assert!(index_path.exists());
+
+    // Appends (as a sync makes) extend the existing index in place.
+    let mut posted = txn("a", 1, "-5", "BLUE BOTTLE COFFEE SF", "Blue Bottle");
+    posted.status = TransactionStatus::Posted;
+    storage
+        .append_transactions(
+            &account.id,
+            &[posted, txn("b", 2, "-7", "Sightglass Coffee", "Sightglass")],
+        )
+        .await?;
+    assert!(std::fs::read_to_string(&index_path)?.contains("sightglass"));
+    assert_eq!(ids(&storage, &config, "coffee").await?, vec!["a", "b"]);
+    assert_eq!(ids(&storage, &config, "sf").await?, vec!["a"]);
+
+    storage
+        .append_transaction_annotation_patches(&account.id, &[annotate("b", "Dining", &[])])
+        .await?;
+    assert!(std::fs::read_to_string(&index_path)?.contains("dining"));
+    assert_eq!(ids(&storage, &config, "category:dining").await?, vec!["b"]);
+
+    // A stale index is only a cache.
+    std::fs::write(&index_path, "not json")?;
+    assert_eq!(ids(&storage, &config, "sightglass").await?, vec!["b"]);
+    Ok(())
+}
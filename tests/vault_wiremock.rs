@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use keepbook::credentials::{CredentialStore, VaultAuth, VaultConfig, VaultCredentialStore};
+use secrecy::{ExposeSecret, SecretString};
+use serde_json::json;
+use wiremock::matchers::{body_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn vault_config(server: &MockServer, auth: VaultAuth) -> VaultConfig {
+    let mut fields = HashMap::new();
+    fields.insert("key_name".to_string(), "key-name".to_string());
+    VaultConfig {
+        address: Some(server.uri()),
+        mount: "kv".to_string(),
+        path: "finance/coinbase".to_string(),
+        auth,
+        fields,
+    }
+}
+
+fn kv_response(data: serde_json::Value, version: u64) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "data": {
+            "data": data,
+            "metadata": { "version": version }
+        }
+    }))
+}
+
+#[tokio::test]
+async fn reads_mapped_field_with_token_file() -> Result<()> {
+    let server = MockServer::start().await;
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("vault-token"), "s.file-token\n")?;
+
+    Mock::given(method("GET"))
+        .and(path("/v1/kv/data/finance/coinbase"))
+        .and(header("X-Vault-Token", "s.file-token"))
+        .respond_with(kv_response(
+            json!({ "key-name": "organizations/abc", "private-key": "pem" }),
+            3,
+        ))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let store = VaultCredentialStore::with_base_dir(
+        vault_config(
+            &server,
+            VaultAuth::Token {
+                token_file: Some("vault-token".to_string()),
+            },
+        ),
+        dir.path(),
+    );
+
+    let key_name = store.get("key_name").await?.expect("key_name");
+    assert_eq!(key_name.expose_secret(), "organizations/abc");
+    assert!(store.get("missing").await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn approle_login_then_write_uses_check_and_set() -> Result<()> {
+    let server = MockServer::start().await;
+    std::env::set_var("KEEPBOOK_TEST_VAULT_ROLE_ID", "role-1");
+    std::env::set_var("KEEPBOOK_TEST_VAULT_SECRET_ID", "secret-1");
+
+    Mock::given(method("POST"))
+        .and(path("/v1/auth/approle/login"))
+        .and(body_json(
+            json!({ "role_id": "role-1", "secret_id": "secret-1" }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "auth": { "client_token": "s.approle-token" }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/kv/data/finance/coinbase"))
+        .and(header("X-Vault-Token", "s.approle-token"))
+        .respond_with(kv_response(json!({ "private-key": "pem" }), 7))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/kv/data/finance/coinbase"))
+        .and(header("X-Vault-Token", "s.approle-token"))
+        .and(body_json(json!({
+            "options": { "cas": 7 },
+            "data": { "private-key": "pem", "key-name": "organizations/new" }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": { "version": 8 }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let store = VaultCredentialStore::new(vault_config(
+        &server,
+        VaultAuth::AppRole {
+            mount: "approle".to_string(),
+            role_id_env: "KEEPBOOK_TEST_VAULT_ROLE_ID".to_string(),
+            secret_id_env: "KEEPBOOK_TEST_VAULT_SECRET_ID".to_string(),
+        },
+    ));
+
+    store
+        .set("key_name", SecretString::from("organizations/new"))
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn write_to_missing_secret_creates_it() -> Result<()> {
+    let server = MockServer::start().await;
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("vault-token"), "s.file-token")?;
+
+    Mock::given(method("GET"))
+        .and(path("/v1/kv/data/finance/coinbase"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({ "errors": [] })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/kv/data/finance/coinbase"))
+        .and(body_json(json!({
+            "options": { "cas": 0 },
+            "data": { "token": "abc" }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let store = VaultCredentialStore::with_base_dir(
+        vault_config(
+            &server,
+            VaultAuth::Token {
+                token_file: Some("vault-token".to_string()),
+            },
+        ),
+        dir.path(),
+    );
+
+    assert!(store.get("token").await?.is_none());
+    store.set("token", SecretString::from("abc")).await?;
+    Ok(())
+}